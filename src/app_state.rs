@@ -1,24 +1,102 @@
-use crate::{auth::CredentialsStore, storage::StorageBackend};
-use std::sync::Arc;
+use crate::{
+    auth::CredentialsStore,
+    bucket_settings::BucketSettingsStore,
+    object_lock::ObjectLockStore,
+    storage::StorageBackend,
+    types::{CorsConfiguration, LifecycleConfiguration, error::S3Error},
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// A bucket exposed to clients, backed by its own storage (usually a MultiBackend)
+#[derive(Clone)]
+pub struct VirtualBucket {
+    pub name: String,
+    pub storage: Arc<dyn StorageBackend>,
+    /// Reported as the bucket's creation date in ListBuckets (when the proxy started serving it)
+    pub creation_date: DateTime<Utc>,
+    /// Lifecycle rules applied by the proxy's lifecycle worker, saved to the bucket settings store
+    pub lifecycle: Arc<RwLock<Option<LifecycleConfiguration>>>,
+    /// CORS rules for browser requests, saved to the bucket settings store
+    pub cors: Arc<RwLock<Option<CorsConfiguration>>>,
+}
+
+impl VirtualBucket {
+    pub fn new(name: String, storage: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            name,
+            storage,
+            creation_date: Utc::now(),
+            lifecycle: Arc::new(RwLock::new(None)),
+            cors: Arc::new(RwLock::new(None)),
+        }
+    }
+}
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
-    pub storage: Arc<dyn StorageBackend>,
+    /// Virtual buckets keyed by name
+    pub buckets: Arc<HashMap<String, VirtualBucket>>,
     pub credentials: CredentialsStore,
-    pub bucket_name: String,
+    /// Region clients must sign their requests for
+    pub region: String,
+    /// Base domain for virtual-hosted-style requests (`{bucket}.{base_domain}`)
+    pub base_domain: Option<String>,
+    /// Retention and legal holds of objects in all virtual buckets
+    pub object_locks: ObjectLockStore,
+    /// Where changes to the buckets' lifecycle and CORS rules are persisted
+    pub bucket_settings: BucketSettingsStore,
 }
 
 impl AppState {
     pub fn new(
-        storage: Arc<dyn StorageBackend>,
+        buckets: Vec<VirtualBucket>,
         credentials: CredentialsStore,
-        bucket_name: String,
+        region: String,
+        base_domain: Option<String>,
     ) -> Self {
+        let buckets = buckets
+            .into_iter()
+            .map(|bucket| (bucket.name.clone(), bucket))
+            .collect();
+
         Self {
-            storage,
+            buckets: Arc::new(buckets),
             credentials,
-            bucket_name,
+            region,
+            base_domain,
+            object_locks: ObjectLockStore::in_memory(),
+            bucket_settings: BucketSettingsStore::in_memory(),
         }
     }
+
+    /// Use `object_locks` instead of the default in-memory store
+    pub fn with_object_lock_store(mut self, object_locks: ObjectLockStore) -> Self {
+        self.object_locks = object_locks;
+        self
+    }
+
+    /// Use `bucket_settings` instead of the default in-memory store
+    pub fn with_bucket_settings_store(mut self, bucket_settings: BucketSettingsStore) -> Self {
+        self.bucket_settings = bucket_settings;
+        self
+    }
+
+    /// Persist the buckets' settings after one of them was changed
+    pub async fn save_bucket_settings(&self) -> Result<(), S3Error> {
+        self.bucket_settings.save(&self.buckets).await
+    }
+
+    /// Look up a virtual bucket by name
+    /// Returns NoSuchBucket if the proxy doesn't serve a bucket with that name
+    pub fn bucket(&self, name: &str) -> Result<&VirtualBucket, S3Error> {
+        self.buckets.get(name).ok_or(S3Error::NoSuchBucket)
+    }
+
+    /// Get the storage backing a virtual bucket
+    pub fn storage(&self, bucket: &str) -> Result<&Arc<dyn StorageBackend>, S3Error> {
+        self.bucket(bucket).map(|bucket| &bucket.storage)
+    }
 }
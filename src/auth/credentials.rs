@@ -1,11 +1,13 @@
 use crate::types::Credentials;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// Credentials store - maps access key ID to credentials
 #[derive(Clone)]
 pub struct CredentialsStore {
     credentials: Arc<HashMap<String, Credentials>>,
+    /// Buckets each access key ID is limited to, keys missing here can access every bucket
+    bucket_scopes: Arc<HashMap<String, Arc<HashSet<String>>>>,
 }
 
 impl CredentialsStore {
@@ -13,12 +15,37 @@ impl CredentialsStore {
     pub fn new(credentials: HashMap<String, Credentials>) -> Self {
         Self {
             credentials: Arc::new(credentials),
+            bucket_scopes: Arc::new(HashMap::new()),
         }
     }
 
+    /// Limit access key IDs to the given buckets
+    pub fn with_bucket_scopes(mut self, scopes: HashMap<String, HashSet<String>>) -> Self {
+        self.bucket_scopes = Arc::new(
+            scopes
+                .into_iter()
+                .map(|(access_key_id, buckets)| (access_key_id, Arc::new(buckets)))
+                .collect(),
+        );
+        self
+    }
+
     /// Get credentials for a given access key ID
     /// Returns None if the access key ID is not found
     pub fn get(&self, access_key_id: &str) -> Option<&Credentials> {
         self.credentials.get(access_key_id)
     }
+
+    /// Buckets an access key ID is limited to
+    /// Returns None if it can access every bucket
+    pub fn allowed_buckets(&self, access_key_id: &str) -> Option<Arc<HashSet<String>>> {
+        self.bucket_scopes.get(access_key_id).cloned()
+    }
+
+    /// Whether an access key ID can access a bucket
+    pub fn can_access(&self, access_key_id: &str, bucket: &str) -> bool {
+        self.bucket_scopes
+            .get(access_key_id)
+            .is_none_or(|buckets| buckets.contains(bucket))
+    }
 }
@@ -1,6 +1,9 @@
 mod credentials;
 mod middleware;
+mod post_policy;
 mod signature;
 
 pub use credentials::CredentialsStore;
 pub use middleware::auth_middleware;
+pub use post_policy::PostPolicy;
+pub use signature::verify_post_signature;
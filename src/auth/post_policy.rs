@@ -0,0 +1,251 @@
+use crate::types::error::S3Error;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Form fields that are never covered by policy conditions
+const UNCHECKED_FIELDS: [&str; 3] = ["policy", "x-amz-signature", "file"];
+
+/// A single condition of a POST policy document
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    /// The field must have exactly this value
+    Eq { field: String, value: String },
+    /// The field must start with this prefix
+    StartsWith { field: String, prefix: String },
+    /// The size of the uploaded file must be within this range (inclusive)
+    ContentLengthRange { min: u64, max: u64 },
+}
+
+/// Policy document of a browser POST upload
+///
+/// The policy is a base64-encoded JSON document listing the conditions the form fields must
+/// satisfy. Every form field (apart from the policy, the signature, the file and `x-ignore-*`
+/// fields) must be covered by a condition.
+#[derive(Debug)]
+pub struct PostPolicy {
+    expiration: DateTime<Utc>,
+    conditions: Vec<Condition>,
+}
+
+fn policy_error(message: &str) -> S3Error {
+    tracing::info!("POST policy rejected: {}", message);
+    S3Error::AccessDenied
+}
+
+impl PostPolicy {
+    /// Decode and parse a base64-encoded policy document
+    pub fn parse(policy: &str) -> Result<Self, S3Error> {
+        let malformed =
+            || S3Error::InvalidRequest("Invalid Policy: Invalid JSON or encoding.".to_string());
+
+        let document = BASE64.decode(policy.trim()).map_err(|_| malformed())?;
+        let document: Value = serde_json::from_slice(&document).map_err(|_| malformed())?;
+
+        let expiration = document
+            .get("expiration")
+            .and_then(Value::as_str)
+            .and_then(|expiration| DateTime::parse_from_rfc3339(expiration).ok())
+            .ok_or_else(malformed)?
+            .with_timezone(&Utc);
+
+        let conditions = document
+            .get("conditions")
+            .and_then(Value::as_array)
+            .ok_or_else(malformed)?
+            .iter()
+            .map(|condition| Self::parse_condition(condition).ok_or_else(malformed))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            expiration,
+            conditions,
+        })
+    }
+
+    fn parse_condition(condition: &Value) -> Option<Condition> {
+        // Field name of a condition, written `$name` in array conditions
+        let field_name = |value: &Value| {
+            value
+                .as_str()?
+                .strip_prefix('$')
+                .map(|name| name.to_ascii_lowercase())
+        };
+        // Numbers may be sent as JSON numbers or strings
+        let number = |value: &Value| {
+            value
+                .as_u64()
+                .or_else(|| value.as_str().and_then(|value| value.parse().ok()))
+        };
+
+        match condition {
+            Value::Object(map) if map.len() == 1 => {
+                let (field, value) = map.iter().next()?;
+                Some(Condition::Eq {
+                    field: field.to_ascii_lowercase(),
+                    value: value.as_str()?.to_string(),
+                })
+            }
+            Value::Array(items) if items.len() == 3 => {
+                match items[0].as_str()?.to_ascii_lowercase().as_str() {
+                    "eq" => Some(Condition::Eq {
+                        field: field_name(&items[1])?,
+                        value: items[2].as_str()?.to_string(),
+                    }),
+                    "starts-with" => Some(Condition::StartsWith {
+                        field: field_name(&items[1])?,
+                        prefix: items[2].as_str()?.to_string(),
+                    }),
+                    "content-length-range" => Some(Condition::ContentLengthRange {
+                        min: number(&items[1])?,
+                        max: number(&items[2])?,
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Check the form fields against the policy
+    ///
+    /// `fields` are keyed by lowercase field name. The bucket is checked separately from the
+    /// form fields since it comes from the request path.
+    pub fn check(&self, bucket: &str, fields: &HashMap<String, String>) -> Result<(), S3Error> {
+        if self.expiration <= Utc::now() {
+            return Err(policy_error("Invalid according to Policy: Policy expired."));
+        }
+
+        let value_of = |field: &str| {
+            if field == "bucket" {
+                Some(bucket)
+            } else {
+                fields.get(field).map(String::as_str)
+            }
+        };
+
+        for condition in &self.conditions {
+            let satisfied = match condition {
+                Condition::Eq { field, value } => value_of(field) == Some(value.as_str()),
+                Condition::StartsWith { field, prefix } => value_of(field)
+                    .unwrap_or_default()
+                    .starts_with(prefix.as_str()),
+                Condition::ContentLengthRange { .. } => true,
+            };
+            if !satisfied {
+                return Err(policy_error(&format!(
+                    "Invalid according to Policy: Policy Condition failed: {:?}",
+                    condition
+                )));
+            }
+        }
+
+        for field in fields.keys() {
+            let covered = self.conditions.iter().any(|condition| match condition {
+                Condition::Eq { field: name, .. } | Condition::StartsWith { field: name, .. } => {
+                    name == field
+                }
+                Condition::ContentLengthRange { .. } => false,
+            });
+            if !covered
+                && !UNCHECKED_FIELDS.contains(&field.as_str())
+                && !field.starts_with("x-ignore-")
+            {
+                return Err(policy_error(&format!(
+                    "Invalid according to Policy: Extra input fields: {}",
+                    field
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allowed size range of the uploaded file, if the policy restricts it
+    pub fn content_length_range(&self) -> Option<(u64, u64)> {
+        self.conditions
+            .iter()
+            .find_map(|condition| match condition {
+                Condition::ContentLengthRange { min, max } => Some((*min, *max)),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(document: &str) -> String {
+        BASE64.encode(document)
+    }
+
+    fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_check_policy() {
+        let policy = PostPolicy::parse(&encode(
+            r#"{
+                "expiration": "2100-01-01T00:00:00.000Z",
+                "conditions": [
+                    {"bucket": "uploads"},
+                    ["starts-with", "$key", "user/"],
+                    ["eq", "$Content-Type", "image/png"],
+                    ["content-length-range", 1, "1024"]
+                ]
+            }"#,
+        ))
+        .unwrap();
+
+        assert_eq!(policy.content_length_range(), Some((1, 1024)));
+
+        let valid = fields(&[
+            ("key", "user/cat.png"),
+            ("content-type", "image/png"),
+            ("policy", "..."),
+            ("x-ignore-tracking", "1"),
+        ]);
+        assert!(policy.check("uploads", &valid).is_ok());
+        assert!(policy.check("other", &valid).is_err());
+
+        let wrong_prefix = fields(&[("key", "admin/cat.png"), ("content-type", "image/png")]);
+        assert!(policy.check("uploads", &wrong_prefix).is_err());
+
+        let extra_field = fields(&[
+            ("key", "user/cat.png"),
+            ("content-type", "image/png"),
+            ("acl", "public-read"),
+        ]);
+        assert!(policy.check("uploads", &extra_field).is_err());
+    }
+
+    #[test]
+    fn test_expired_policy() {
+        let policy = PostPolicy::parse(&encode(
+            r#"{"expiration": "2000-01-01T00:00:00Z", "conditions": []}"#,
+        ))
+        .unwrap();
+        assert!(matches!(
+            policy.check("bucket", &HashMap::new()),
+            Err(S3Error::AccessDenied)
+        ));
+    }
+
+    #[test]
+    fn test_malformed_policy() {
+        assert!(PostPolicy::parse("not base64!").is_err());
+        assert!(PostPolicy::parse(&encode(r#"{"conditions": []}"#)).is_err());
+        assert!(
+            PostPolicy::parse(&encode(
+                r#"{"expiration": "2100-01-01T00:00:00Z", "conditions": [["in", "$key", "a"]]}"#
+            ))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Bucket settings set through the S3 API and kept by the proxy
+//!
+//! Lifecycle rules and CORS rules are applied by the proxy rather than by the backends, so the
+//! proxy keeps them too. The live settings are held by each [`VirtualBucket`]; a [`BucketSettingsStore`]
+//! with a path rewrites them to a JSON file after every change and restores them at startup.
+
+use crate::{
+    app_state::VirtualBucket,
+    types::{CorsConfiguration, LifecycleConfiguration, error::S3Error},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Settings of one bucket, as written to the store file
+#[derive(Default, Serialize, Deserialize)]
+struct BucketSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifecycle: Option<LifecycleConfiguration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cors: Option<CorsConfiguration>,
+}
+
+impl BucketSettings {
+    fn of(bucket: &VirtualBucket) -> Self {
+        Self {
+            lifecycle: bucket.lifecycle.read().unwrap().clone(),
+            cors: bucket.cors.read().unwrap().clone(),
+        }
+    }
+
+    fn apply_to(self, bucket: &VirtualBucket) {
+        *bucket.lifecycle.write().unwrap() = self.lifecycle;
+        *bucket.cors.write().unwrap() = self.cors;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lifecycle.is_none() && self.cors.is_none()
+    }
+}
+
+/// Where the settings of all virtual buckets are persisted, if anywhere
+#[derive(Clone)]
+pub struct BucketSettingsStore {
+    path: Option<PathBuf>,
+    /// Held while writing, so that an older snapshot can't replace a newer one
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl BucketSettingsStore {
+    /// Create a store that is lost when the proxy stops
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            write_lock: Arc::default(),
+        }
+    }
+
+    /// Open a store persisted to `path`, restoring its settings into `buckets` if the file exists
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        buckets: &[VirtualBucket],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            let settings: BTreeMap<String, BucketSettings> =
+                serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            for (name, settings) in settings {
+                match buckets.iter().find(|bucket| bucket.name == name) {
+                    Some(bucket) => settings.apply_to(bucket),
+                    None => {
+                        tracing::warn!("Ignoring stored settings of unknown bucket '{}'", name);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            path: Some(path),
+            write_lock: Arc::default(),
+        })
+    }
+
+    /// Write the current settings of `buckets` to the store file
+    ///
+    /// Goes through a temporary file so a crash can't truncate the store.
+    pub async fn save(&self, buckets: &HashMap<String, VirtualBucket>) -> Result<(), S3Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let _guard = self.write_lock.lock().await;
+        let settings: BTreeMap<&str, BucketSettings> = buckets
+            .values()
+            .map(|bucket| (bucket.name.as_str(), BucketSettings::of(bucket)))
+            .filter(|(_, settings)| !settings.is_empty())
+            .collect();
+        let content = serde_json::to_string_pretty(&settings).map_err(|e| {
+            S3Error::InternalError(format!("Failed to serialize bucket settings: {}", e))
+        })?;
+
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+        let result = async {
+            tokio::fs::write(&tmp_path, content).await?;
+            tokio::fs::rename(&tmp_path, path).await
+        };
+        result.await.map_err(|e| {
+            tracing::error!("Failed to persist bucket settings to {:?}: {}", path, e);
+            S3Error::InternalError(format!("Failed to persist bucket settings: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{InMemoryStorage, StorageBackend};
+    use crate::types::{CorsRule, LifecycleExpiration, LifecycleRule};
+
+    fn bucket(name: &str) -> VirtualBucket {
+        let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+        VirtualBucket::new(name.to_string(), storage)
+    }
+
+    #[tokio::test]
+    async fn test_settings_persist_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bucket-settings.json");
+
+        let logs = bucket("logs");
+        let store = BucketSettingsStore::open(&path, std::slice::from_ref(&logs)).unwrap();
+        *logs.lifecycle.write().unwrap() = Some(LifecycleConfiguration {
+            rules: vec![LifecycleRule {
+                id: Some("expire-logs".to_string()),
+                status: "Enabled".to_string(),
+                filter: None,
+                prefix: Some("logs/".to_string()),
+                expiration: Some(LifecycleExpiration {
+                    days: Some(30),
+                    date: None,
+                }),
+                abort_incomplete_multipart_upload: None,
+            }],
+        });
+        *logs.cors.write().unwrap() = Some(CorsConfiguration {
+            rules: vec![CorsRule {
+                id: None,
+                allowed_origins: vec!["https://example.com".to_string()],
+                allowed_methods: vec!["GET".to_string()],
+                allowed_headers: Vec::new(),
+                expose_headers: Vec::new(),
+                max_age_seconds: Some(600),
+            }],
+        });
+        let buckets = HashMap::from([("logs".to_string(), logs)]);
+        store.save(&buckets).await.unwrap();
+
+        let reopened = bucket("logs");
+        BucketSettingsStore::open(&path, std::slice::from_ref(&reopened)).unwrap();
+        let lifecycle = reopened.lifecycle.read().unwrap().clone().unwrap();
+        assert_eq!(lifecycle.rules[0].id.as_deref(), Some("expire-logs"));
+        assert_eq!(
+            lifecycle.rules[0].expiration.as_ref().unwrap().days,
+            Some(30)
+        );
+        let cors = reopened.cors.read().unwrap().clone().unwrap();
+        assert_eq!(cors.rules[0].allowed_origins, vec!["https://example.com"]);
+    }
+}
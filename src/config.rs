@@ -1,7 +1,9 @@
+use crate::storage::{AzureCredentials, FaultOperation, FaultRates};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -25,17 +27,107 @@ pub enum WriteMode {
     MultiSync,
 }
 
+/// What a backend is used for within its bucket
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BackendRole {
+    /// Read from and written to
+    #[default]
+    ReadWrite,
+    /// Read from but never written to, such as a legacy source
+    ReadOnly,
+    /// Written to but never read from, such as an archive or backup target
+    WriteOnly,
+}
+
+impl BackendRole {
+    pub fn can_read(self) -> bool {
+        self != BackendRole::WriteOnly
+    }
+
+    pub fn can_write(self) -> bool {
+        self != BackendRole::ReadOnly
+    }
+}
+
+/// Bucket name exposed to clients when the configuration doesn't name one
+pub const DEFAULT_VIRTUAL_BUCKET: &str = "mybucket";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub virtual_bucket: Option<String>,
+    /// Multiple virtual buckets, each with its own backends (replaces the top-level
+    /// `virtualBucket`/`backends`; top-level read/write modes act as defaults)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub virtual_buckets: Vec<VirtualBucketConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_domain: Option<String>,
+    /// File where retention and legal holds are persisted (kept in memory if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_lock_store_path: Option<String>,
+    /// File where bucket settings set through the S3 API are persisted (kept in memory if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_settings_store_path: Option<String>,
+    /// Directory where each bucket's version ID map is persisted (kept in memory if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_map_dir: Option<String>,
+    /// How often the lifecycle worker applies lifecycle rules (defaults to an hour)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle_interval_seconds: Option<u64>,
+    /// Client credentials accepted besides the ones given on the command line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credentials: Vec<CredentialConfig>,
     pub read_mode: ReadMode,
     pub write_mode: WriteMode,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub primary_backend_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub use_latency_based_primary_backend: Option<bool>,
+    #[serde(default)]
+    pub backends: Vec<BackendConfig>,
+}
+
+/// Client credential, optionally limited to some of the virtual buckets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialConfig {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Buckets the credential can access and list, every bucket if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buckets: Option<Vec<String>>,
+}
+
+/// A virtual bucket exposed by the proxy, backed by its own set of backends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualBucketConfig {
+    pub name: String,
+    /// Overrides the top-level `readMode` for this bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_mode: Option<ReadMode>,
+    /// Overrides the top-level `writeMode` for this bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_mode: Option<WriteMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_backend_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub use_latency_based_primary_backend: Option<bool>,
+    pub backends: Vec<BackendConfig>,
+}
+
+/// Fully resolved configuration of a single virtual bucket (defaults applied)
+#[derive(Debug, Clone)]
+pub struct BucketConfig {
+    pub name: String,
+    pub read_mode: ReadMode,
+    pub write_mode: WriteMode,
+    pub primary_backend_name: Option<String>,
+    pub use_latency_based_primary_backend: Option<bool>,
     pub backends: Vec<BackendConfig>,
 }
 
@@ -44,11 +136,27 @@ pub struct Config {
 pub enum BackendConfig {
     S3(S3BackendConfig),
     Memory(MemoryBackendConfig),
+    Filesystem(FilesystemBackendConfig),
+    Azure(AzureBackendConfig),
+    Gcs(GcsBackendConfig),
+    Sftp(SftpBackendConfig),
+    Webdav(WebdavBackendConfig),
+    Sqlite(SqliteBackendConfig),
+    /// Read-only HTTP(S) file server, never written to
+    Http(HttpBackendConfig),
+    /// Encrypts objects in the proxy before they reach the wrapped backend
+    Encrypted(EncryptedBackendConfig),
+    /// Compresses objects in the proxy before they reach the wrapped backend
+    Compressed(CompressedBackendConfig),
+    /// Injects faults into the operations of the wrapped backend, for testing
+    Fault(FaultBackendConfig),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3BackendConfig {
     pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
     pub region: String,
     pub bucket: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -59,11 +167,243 @@ pub struct S3BackendConfig {
     pub access_key_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secret_access_key: Option<String>,
+    /// Encryption the backend applies to every object the proxy writes to it
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub server_side_encryption: Option<ServerSideEncryptionConfig>,
+}
+
+/// Server-side encryption requested from an S3 backend on writes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ServerSideEncryptionConfig {
+    pub mode: SseMode,
+    /// KMS key to encrypt with in `aws:kms` mode (the bucket's default key if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kms_key_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SseMode {
+    /// SSE-S3: keys managed by the backend
+    #[serde(rename = "AES256")]
+    Aes256,
+    /// SSE-KMS: keys managed by the key management service
+    #[serde(rename = "aws:kms")]
+    AwsKms,
+}
+
+impl SseMode {
+    /// Value of the `x-amz-server-side-encryption` header for this mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SseMode::Aes256 => "AES256",
+            SseMode::AwsKms => "aws:kms",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryBackendConfig {
     pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    /// Total size of the stored objects, beyond which the least recently used are evicted
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_bytes: Option<u64>,
+    /// Number of keys, beyond which the least recently used are evicted
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_objects: Option<usize>,
+    /// File the objects are loaded from on startup and saved to on shutdown
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub snapshot_path: Option<PathBuf>,
+    /// How often to also save the snapshot while running (only on shutdown if unset)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub snapshot_interval_seconds: Option<u64>,
+}
+
+/// Backend storing objects as files under a local directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesystemBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    /// Directory holding the objects, which must already exist
+    pub root: PathBuf,
+}
+
+/// Azure Blob Storage container, authorized with either the account key or a SAS token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    pub account: String,
+    pub container: String,
+    /// Blob service URL (`https://<account>.blob.core.windows.net` if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Base64-encoded storage account key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_key: Option<String>,
+    /// Shared access signature granting access to the container
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sas_token: Option<String>,
+}
+
+impl AzureBackendConfig {
+    /// Resolve the credentials, exactly one of `account_key` and `sas_token` must be set
+    pub fn credentials(&self) -> Result<AzureCredentials, String> {
+        match (&self.account_key, &self.sas_token) {
+            (Some(account_key), None) => BASE64
+                .decode(account_key)
+                .map(AzureCredentials::SharedKey)
+                .map_err(|_| "account_key must be base64-encoded".to_string()),
+            (None, Some(sas_token)) => Ok(AzureCredentials::Sas(
+                sas_token.trim_start_matches('?').to_string(),
+            )),
+            _ => Err("exactly one of account_key and sas_token must be set".to_string()),
+        }
+    }
+}
+
+/// Google Cloud Storage bucket, accessed through the JSON API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    pub bucket: String,
+    /// API URL (`https://storage.googleapis.com` if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Service account JSON key file (requests are not authorized if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_file: Option<PathBuf>,
+}
+
+/// Directory on an SFTP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    pub host: String,
+    #[serde(default = "default_sftp_port")]
+    pub port: u16,
+    pub username: String,
+    /// Remote directory holding the objects, relative to the login directory unless absolute
+    pub root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// OpenSSH private key file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key_file: Option<PathBuf>,
+    /// Passphrase the private key file is encrypted with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key_passphrase: Option<String>,
+    /// Known hosts file holding the server's host key (`~/.ssh/known_hosts` if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub known_hosts_file: Option<PathBuf>,
+}
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
+/// WebDAV collection, such as a Nextcloud folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebdavBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    /// URL of the collection holding the objects
+    pub url: String,
+    /// Username for basic authentication (requests are not authorized if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// SQLite database file holding the objects, suited to many small objects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqliteBackendConfig {
+    pub name: String,
+    #[serde(default)]
+    pub role: BackendRole,
+    /// Database file, created if it doesn't exist
+    pub path: PathBuf,
+}
+
+/// Static HTTP(S) file server the objects are read from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpBackendConfig {
+    pub name: String,
+    /// Base URL keys are resolved against
+    pub url: String,
+}
+
+/// Backend whose objects are encrypted by the proxy, named after the backend it wraps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedBackendConfig {
+    /// Base64-encoded 256-bit key wrapping the per-object data keys
+    pub master_key: String,
+    pub backend: Box<BackendConfig>,
+}
+
+impl EncryptedBackendConfig {
+    /// Decode the master key
+    pub fn master_key(&self) -> Result<[u8; 32], String> {
+        BASE64
+            .decode(&self.master_key)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| "master_key must be a base64-encoded 256-bit key".to_string())
+    }
+}
+
+/// Backend whose objects are compressed by the proxy, named after the backend it wraps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressedBackendConfig {
+    /// zstd compression level, from 1 (fastest) to 22 (smallest)
+    #[serde(default = "default_compression_level")]
+    pub level: i32,
+    /// Content types to compress, either exact (`application/json`) or by type (`text/*`)
+    #[serde(default = "default_compressed_content_types")]
+    pub content_types: Vec<String>,
+    pub backend: Box<BackendConfig>,
+}
+
+fn default_compression_level() -> i32 {
+    3
+}
+
+fn default_compressed_content_types() -> Vec<String> {
+    [
+        "text/*",
+        "application/json",
+        "application/x-ndjson",
+        "application/xml",
+        "application/javascript",
+        "application/x-yaml",
+        "application/csv",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Backend failing on purpose, named after the backend it wraps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultBackendConfig {
+    /// Seed of the random faults, to make test runs reproducible
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Faults of the operations not listed in `operations`
+    #[serde(default)]
+    pub default: FaultRates,
+    #[serde(default)]
+    pub operations: HashMap<FaultOperation, FaultRates>,
+    pub backend: Box<BackendConfig>,
 }
 
 impl BackendConfig {
@@ -71,6 +411,161 @@ impl BackendConfig {
         match self {
             BackendConfig::S3(s3) => &s3.name,
             BackendConfig::Memory(mem) => &mem.name,
+            BackendConfig::Filesystem(fs) => &fs.name,
+            BackendConfig::Azure(azure) => &azure.name,
+            BackendConfig::Gcs(gcs) => &gcs.name,
+            BackendConfig::Sftp(sftp) => &sftp.name,
+            BackendConfig::Webdav(webdav) => &webdav.name,
+            BackendConfig::Sqlite(sqlite) => &sqlite.name,
+            BackendConfig::Http(http) => &http.name,
+            BackendConfig::Encrypted(encrypted) => encrypted.backend.name(),
+            BackendConfig::Compressed(compressed) => compressed.backend.name(),
+            BackendConfig::Fault(fault) => fault.backend.name(),
+        }
+    }
+
+    /// What the backend is used for, wrappers take the role of the backend they wrap
+    pub fn role(&self) -> BackendRole {
+        match self {
+            BackendConfig::S3(s3) => s3.role,
+            BackendConfig::Memory(mem) => mem.role,
+            BackendConfig::Filesystem(fs) => fs.role,
+            BackendConfig::Azure(azure) => azure.role,
+            BackendConfig::Gcs(gcs) => gcs.role,
+            BackendConfig::Sftp(sftp) => sftp.role,
+            BackendConfig::Webdav(webdav) => webdav.role,
+            BackendConfig::Sqlite(sqlite) => sqlite.role,
+            // Static servers can't be written to
+            BackendConfig::Http(_) => BackendRole::ReadOnly,
+            BackendConfig::Encrypted(encrypted) => encrypted.backend.role(),
+            BackendConfig::Compressed(compressed) => compressed.backend.role(),
+            BackendConfig::Fault(fault) => fault.backend.role(),
+        }
+    }
+
+    /// Whether the backend keeps the user metadata of objects, which wrappers can rely on
+    fn stores_user_metadata(&self) -> bool {
+        match self {
+            BackendConfig::S3(_)
+            | BackendConfig::Memory(_)
+            | BackendConfig::Filesystem(_)
+            | BackendConfig::Azure(_)
+            | BackendConfig::Gcs(_)
+            | BackendConfig::Sqlite(_) => true,
+            BackendConfig::Sftp(_) | BackendConfig::Webdav(_) | BackendConfig::Http(_) => false,
+            BackendConfig::Encrypted(encrypted) => encrypted.backend.stores_user_metadata(),
+            BackendConfig::Compressed(compressed) => compressed.backend.stores_user_metadata(),
+            BackendConfig::Fault(fault) => fault.backend.stores_user_metadata(),
+        }
+    }
+
+    /// Check the settings of a single backend
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            BackendConfig::S3(s3) => {
+                // A KMS key only means something in aws:kms mode
+                if s3
+                    .server_side_encryption
+                    .as_ref()
+                    .is_some_and(|sse| sse.kms_key_id.is_some() && sse.mode != SseMode::AwsKms)
+                {
+                    return Err(format!(
+                        "Backend '{}' sets kms_key_id without the aws:kms encryption mode",
+                        s3.name
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+            BackendConfig::Memory(mem) => {
+                if mem.snapshot_interval_seconds.is_some() && mem.snapshot_path.is_none() {
+                    return Err(format!(
+                        "Backend '{}' sets snapshot_interval_seconds without snapshot_path",
+                        mem.name
+                    )
+                    .into());
+                }
+                if mem.snapshot_interval_seconds == Some(0) {
+                    return Err(format!(
+                        "Backend '{}' sets snapshot_interval_seconds to 0",
+                        mem.name
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+            BackendConfig::Filesystem(_)
+            | BackendConfig::Gcs(_)
+            | BackendConfig::Sqlite(_)
+            | BackendConfig::Http(_) => Ok(()),
+            BackendConfig::Azure(azure) => {
+                azure
+                    .credentials()
+                    .map_err(|e| format!("Backend '{}': {}", azure.name, e))?;
+                Ok(())
+            }
+            BackendConfig::Sftp(sftp) => {
+                if sftp.password.is_some() == sftp.private_key_file.is_some() {
+                    return Err(format!(
+                        "Backend '{}': exactly one of password and private_key_file must be set",
+                        sftp.name
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+            BackendConfig::Webdav(webdav) => {
+                if webdav.username.is_some() != webdav.password.is_some() {
+                    return Err(format!(
+                        "Backend '{}': username and password must be set together",
+                        webdav.name
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+            BackendConfig::Encrypted(encrypted) => {
+                encrypted
+                    .master_key()
+                    .map_err(|e| format!("Backend '{}': {}", self.name(), e))?;
+                encrypted.backend.validate()
+            }
+            BackendConfig::Compressed(compressed) => {
+                if !(1..=22).contains(&compressed.level) {
+                    return Err(format!(
+                        "Backend '{}' sets compression level {}, expected 1 to 22",
+                        self.name(),
+                        compressed.level
+                    )
+                    .into());
+                }
+                // Without the metadata marking them, compressed bodies would be served as is
+                if !compressed.backend.stores_user_metadata() {
+                    return Err(format!(
+                        "Backend '{}' can't be compressed, it doesn't store user metadata",
+                        self.name()
+                    )
+                    .into());
+                }
+                compressed.backend.validate()
+            }
+            BackendConfig::Fault(fault) => {
+                fault
+                    .default
+                    .validate()
+                    .map_err(|e| format!("Backend '{}': {}", self.name(), e))?;
+                for (operation, rates) in &fault.operations {
+                    rates.validate().map_err(|e| {
+                        format!(
+                            "Backend '{}', operation {:?}: {}",
+                            self.name(),
+                            operation,
+                            e
+                        )
+                    })?;
+                }
+                fault.backend.validate()
+            }
         }
     }
 }
@@ -121,7 +616,103 @@ impl Config {
         Ok(config)
     }
 
+    /// Resolve the virtual buckets served by the proxy
+    ///
+    /// Returns the `virtualBuckets` list with top-level read/write modes applied as defaults,
+    /// or a single bucket built from the top-level `virtualBucket`/`backends` fields.
+    pub fn buckets(&self) -> Vec<BucketConfig> {
+        if self.virtual_buckets.is_empty() {
+            return vec![BucketConfig {
+                name: self
+                    .virtual_bucket
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_VIRTUAL_BUCKET.to_string()),
+                read_mode: self.read_mode.clone(),
+                write_mode: self.write_mode.clone(),
+                primary_backend_name: self.primary_backend_name.clone(),
+                use_latency_based_primary_backend: self.use_latency_based_primary_backend,
+                backends: self.backends.clone(),
+            }];
+        }
+
+        self.virtual_buckets
+            .iter()
+            .map(|bucket| BucketConfig {
+                name: bucket.name.clone(),
+                read_mode: bucket
+                    .read_mode
+                    .clone()
+                    .unwrap_or_else(|| self.read_mode.clone()),
+                write_mode: bucket
+                    .write_mode
+                    .clone()
+                    .unwrap_or_else(|| self.write_mode.clone()),
+                primary_backend_name: bucket.primary_backend_name.clone(),
+                use_latency_based_primary_backend: bucket.use_latency_based_primary_backend,
+                backends: bucket.backends.clone(),
+            })
+            .collect()
+    }
+
     pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // virtualBuckets replaces the single-bucket fields, mixing them is ambiguous
+        if !self.virtual_buckets.is_empty()
+            && (self.virtual_bucket.is_some()
+                || !self.backends.is_empty()
+                || self.primary_backend_name.is_some()
+                || self.use_latency_based_primary_backend.is_some())
+        {
+            return Err(
+                "Cannot combine virtualBuckets with top-level virtualBucket, backends, \
+                 primaryBackendName or useLatencyBasedPrimaryBackend"
+                    .into(),
+            );
+        }
+
+        // Check that all virtual bucket names are unique
+        let mut seen_buckets = HashSet::new();
+        for bucket in &self.virtual_buckets {
+            if !seen_buckets.insert(bucket.name.as_str()) {
+                return Err(format!("Duplicate virtual bucket name: {}", bucket.name).into());
+            }
+        }
+
+        if self.lifecycle_interval_seconds == Some(0) {
+            return Err("lifecycleIntervalSeconds must be greater than 0".into());
+        }
+
+        let buckets = self.buckets();
+        for bucket in &buckets {
+            bucket.validate()?;
+        }
+
+        // Credentials must be unique and limited to buckets the proxy serves
+        let mut seen_access_keys = HashSet::new();
+        for credential in &self.credentials {
+            if !seen_access_keys.insert(credential.access_key_id.as_str()) {
+                return Err(format!(
+                    "Duplicate credential access key ID: {}",
+                    credential.access_key_id
+                )
+                .into());
+            }
+            for name in credential.buckets.iter().flatten() {
+                if !buckets.iter().any(|bucket| &bucket.name == name) {
+                    return Err(format!(
+                        "Credential '{}' refers to unknown bucket '{}'",
+                        credential.access_key_id, name
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl BucketConfig {
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
         // Check that all backend names are unique
         let mut seen_names = HashSet::new();
         for backend in &self.backends {
@@ -131,6 +722,10 @@ impl Config {
             }
         }
 
+        for backend in &self.backends {
+            backend.validate()?;
+        }
+
         // Check that primaryBackendName and useLatencyBasedPrimaryBackend are mutually exclusive
         if self.primary_backend_name.is_some()
             && self.use_latency_based_primary_backend == Some(true)
@@ -142,74 +737,495 @@ impl Config {
 
         // Check that primaryBackendName, if specified, exists in backends
         if let Some(primary_name) = &self.primary_backend_name {
-            let exists = self.backends.iter().any(|b| b.name() == primary_name);
-            if !exists {
+            let Some(primary) = self.backends.iter().find(|b| b.name() == primary_name) else {
                 return Err(format!(
                     "Primary backend name '{}' not found in backends list",
                     primary_name
                 )
                 .into());
+            };
+            // The primary is read from and written to first
+            if primary.role() != BackendRole::ReadWrite {
+                return Err(format!(
+                    "Primary backend '{}' must have the readWrite role",
+                    primary_name
+                )
+                .into());
+            }
+        }
+
+        // Check that the roles leave a backend to read from, and one to be the primary
+        if !self.backends.iter().any(|b| b.role().can_read()) {
+            return Err(format!("Bucket '{}' has no backend to read from", self.name).into());
+        }
+        if self.backends.len() > 1
+            && !self
+                .backends
+                .iter()
+                .any(|b| b.role() == BackendRole::ReadWrite)
+        {
+            return Err(format!(
+                "Bucket '{}' needs a backend with the readWrite role as the primary",
+                self.name
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_parse_s3_backend_minimal() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "s3",
+                    "name": "aws-s3",
+                    "region": "us-east-1",
+                    "bucket": "my-bucket"
+                }
+            ],
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.backends.len(), 1);
+        assert_eq!(config.read_mode, ReadMode::PrimaryFallback);
+        assert_eq!(config.write_mode, WriteMode::AsyncReplication);
+        assert!(config.primary_backend_name.is_none());
+
+        match &config.backends[0] {
+            BackendConfig::S3(s3_config) => {
+                assert_eq!(s3_config.name, "aws-s3");
+                assert_eq!(s3_config.region, "us-east-1");
+                assert_eq!(s3_config.bucket, "my-bucket");
+                assert_eq!(s3_config.force_path_style, false);
+                assert!(s3_config.endpoint.is_none());
+                assert!(s3_config.access_key_id.is_none());
+                assert!(s3_config.secret_access_key.is_none());
+            }
+            _ => panic!("Expected S3 backend"),
+        }
+    }
+
+    #[test]
+    fn test_parse_s3_backend_full() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "s3",
+                    "name": "minio",
+                    "region": "us-east-1",
+                    "bucket": "my-bucket",
+                    "endpoint": "http://localhost:9000",
+                    "force_path_style": true,
+                    "access_key_id": "minioadmin",
+                    "secret_access_key": "minioadmin"
+                }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.read_mode, ReadMode::PrimaryOnly);
+        assert_eq!(config.write_mode, WriteMode::MultiSync);
+
+        match &config.backends[0] {
+            BackendConfig::S3(s3_config) => {
+                assert_eq!(s3_config.name, "minio");
+                assert_eq!(
+                    s3_config.endpoint,
+                    Some("http://localhost:9000".to_string())
+                );
+                assert_eq!(s3_config.force_path_style, true);
+                assert_eq!(s3_config.access_key_id, Some("minioadmin".to_string()));
+                assert_eq!(s3_config.secret_access_key, Some("minioadmin".to_string()));
+            }
+            _ => panic!("Expected S3 backend"),
+        }
+    }
+
+    #[test]
+    fn test_parse_s3_backend_server_side_encryption() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "s3",
+                    "name": "aws",
+                    "region": "us-east-1",
+                    "bucket": "my-bucket",
+                    "server_side_encryption": {
+                        "mode": "aws:kms",
+                        "kms_key_id": "alias/replicat4"
+                    }
+                }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::S3(s3_config) => {
+                assert_eq!(
+                    s3_config.server_side_encryption,
+                    Some(ServerSideEncryptionConfig {
+                        mode: SseMode::AwsKms,
+                        kms_key_id: Some("alias/replicat4".to_string()),
+                    })
+                );
+            }
+            _ => panic!("Expected S3 backend"),
+        }
+
+        // A KMS key without aws:kms mode is a mistake
+        let json = json.replace("aws:kms", "AES256");
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_encrypted_backend() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "encrypted",
+                    "master_key": "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+                    "backend": {
+                        "type": "memory",
+                        "name": "untrusted"
+                    }
+                }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.backends[0].name(), "untrusted");
+        match &config.backends[0] {
+            BackendConfig::Encrypted(encrypted) => {
+                assert_eq!(encrypted.master_key().unwrap(), [1u8; 32]);
+            }
+            _ => panic!("Expected encrypted backend"),
+        }
+
+        // The master key must be 256 bits
+        let json = json.replace("AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=", "AQEB");
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_azure_backend() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "azure",
+                    "name": "azurite",
+                    "account": "devstoreaccount1",
+                    "container": "replicat4",
+                    "endpoint": "http://127.0.0.1:10000/devstoreaccount1",
+                    "account_key": "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw=="
+                }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::Azure(azure) => {
+                assert_eq!(azure.name, "azurite");
+                assert!(matches!(
+                    azure.credentials(),
+                    Ok(AzureCredentials::SharedKey(key)) if key.len() == 64
+                ));
+            }
+            _ => panic!("Expected Azure backend"),
+        }
+
+        // Either credential works, but not both
+        let sas = json.replace(
+            r#""account_key": "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==""#,
+            r#""sas_token": "?sv=2021-12-02&sig=abc""#,
+        );
+        let config: Config = serde_json::from_str(&sas).unwrap();
+        assert!(config.validate().is_ok());
+        let both = json.replace(
+            r#""account_key""#,
+            r#""sas_token": "sv=2021-12-02&sig=abc", "account_key""#,
+        );
+        let config: Config = serde_json::from_str(&both).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_gcs_backend() {
+        let yaml = r#"
+backends:
+  - type: gcs
+    name: gcs-europe
+    bucket: replicat4-eu
+    credentials_file: /etc/replicat4/gcs-key.json
+  - type: gcs
+    name: fake-gcs
+    bucket: replicat4
+    endpoint: http://localhost:4443
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        match (&config.backends[0], &config.backends[1]) {
+            (BackendConfig::Gcs(gcs), BackendConfig::Gcs(fake)) => {
+                assert_eq!(gcs.bucket, "replicat4-eu");
+                assert_eq!(
+                    gcs.credentials_file,
+                    Some(PathBuf::from("/etc/replicat4/gcs-key.json"))
+                );
+                assert_eq!(gcs.endpoint, None);
+                assert_eq!(fake.endpoint.as_deref(), Some("http://localhost:4443"));
+                assert_eq!(fake.credentials_file, None);
+            }
+            _ => panic!("Expected GCS backends"),
+        }
+    }
+
+    #[test]
+    fn test_parse_sftp_backend() {
+        let yaml = r#"
+backends:
+  - type: sftp
+    name: partner
+    host: sftp.partner.example.com
+    username: replicat4
+    root: /upload/replicat4
+    private_key_file: /etc/replicat4/id_ed25519
+    known_hosts_file: /etc/replicat4/known_hosts
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::Sftp(sftp) => {
+                assert_eq!(sftp.port, 22);
+                assert_eq!(sftp.root, "/upload/replicat4");
+                assert_eq!(
+                    sftp.private_key_file,
+                    Some(PathBuf::from("/etc/replicat4/id_ed25519"))
+                );
+                assert_eq!(sftp.password, None);
+            }
+            _ => panic!("Expected an SFTP backend"),
+        }
+
+        // Exactly one authentication method must be configured
+        let both = yaml.replace("    root:", "    password: secret\n    root:");
+        let config: Config = serde_yml::from_str(&both).unwrap();
+        assert!(config.validate().is_err());
+        let neither = yaml.replace("    private_key_file: /etc/replicat4/id_ed25519\n", "");
+        let config: Config = serde_yml::from_str(&neither).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_webdav_backend() {
+        let yaml = r#"
+backends:
+  - type: webdav
+    name: nextcloud
+    url: https://cloud.example.com/remote.php/dav/files/replicat4/backup
+    username: replicat4
+    password: app-password
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::Webdav(webdav) => {
+                assert_eq!(
+                    webdav.url,
+                    "https://cloud.example.com/remote.php/dav/files/replicat4/backup"
+                );
+                assert_eq!(webdav.username.as_deref(), Some("replicat4"));
+            }
+            _ => panic!("Expected a WebDAV backend"),
+        }
+
+        let without_password = yaml.replace("    password: app-password\n", "");
+        let config: Config = serde_yml::from_str(&without_password).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_sqlite_backend() {
+        let yaml = r#"
+backends:
+  - type: sqlite
+    name: small-objects
+    path: /var/lib/replicat4/objects.db
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::Sqlite(sqlite) => {
+                assert_eq!(sqlite.name, "small-objects");
+                assert_eq!(sqlite.path, PathBuf::from("/var/lib/replicat4/objects.db"));
+            }
+            _ => panic!("Expected a SQLite backend"),
+        }
+    }
+
+    #[test]
+    fn test_parse_http_backend() {
+        let yaml = r#"
+backends:
+  - type: s3
+    name: aws
+    region: us-east-1
+    bucket: my-bucket
+  - type: http
+    name: legacy
+    url: https://files.example.com/static/
+primaryBackendName: aws
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[1] {
+            BackendConfig::Http(http) => {
+                assert_eq!(http.url, "https://files.example.com/static/");
             }
+            _ => panic!("Expected an HTTP backend"),
         }
+        assert_eq!(config.backends[1].role(), BackendRole::ReadOnly);
 
-        Ok(())
+        // The primary receives every write
+        let read_only_primary =
+            yaml.replace("primaryBackendName: aws", "primaryBackendName: legacy");
+        let config: Config = serde_yml::from_str(&read_only_primary).unwrap();
+        assert!(config.validate().is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+    #[test]
+    fn test_parse_backend_roles() {
+        let yaml = r#"
+backends:
+  - type: s3
+    name: aws
+    region: us-east-1
+    bucket: my-bucket
+  - type: filesystem
+    name: legacy
+    root: /srv/legacy
+    role: readOnly
+  - type: compressed
+    backend:
+      type: s3
+      name: archive
+      region: us-east-1
+      bucket: my-archive
+      role: writeOnly
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let roles: Vec<_> = config.backends.iter().map(BackendConfig::role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                BackendRole::ReadWrite,
+                BackendRole::ReadOnly,
+                BackendRole::WriteOnly
+            ]
+        );
+
+        // The primary is read from and written to first
+        let archive_primary = format!("{}primaryBackendName: archive\n", yaml);
+        let config: Config = serde_yml::from_str(&archive_primary).unwrap();
+        assert!(config.validate().is_err());
+
+        // Some backend must be able to act as the primary
+        let no_read_write = yaml.replace(
+            "    bucket: my-bucket\n",
+            "    bucket: my-bucket\n    role: readOnly\n",
+        );
+        let config: Config = serde_yml::from_str(&no_read_write).unwrap();
+        assert!(config.validate().is_err());
+    }
 
     #[test]
-    fn test_parse_s3_backend_minimal() {
+    fn test_parse_compressed_backend() {
         let json = r#"{
             "backends": [
                 {
-                    "type": "s3",
-                    "name": "aws-s3",
-                    "region": "us-east-1",
-                    "bucket": "my-bucket"
+                    "type": "compressed",
+                    "content_types": ["text/*"],
+                    "backend": {
+                        "type": "memory",
+                        "name": "archive"
+                    }
                 }
             ],
-            "readMode": "PRIMARY_FALLBACK",
-            "writeMode": "ASYNC_REPLICATION"
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
         }"#;
 
         let config: Config = serde_json::from_str(json).unwrap();
-        assert_eq!(config.backends.len(), 1);
-        assert_eq!(config.read_mode, ReadMode::PrimaryFallback);
-        assert_eq!(config.write_mode, WriteMode::AsyncReplication);
-        assert!(config.primary_backend_name.is_none());
-
+        assert!(config.validate().is_ok());
+        assert_eq!(config.backends[0].name(), "archive");
         match &config.backends[0] {
-            BackendConfig::S3(s3_config) => {
-                assert_eq!(s3_config.name, "aws-s3");
-                assert_eq!(s3_config.region, "us-east-1");
-                assert_eq!(s3_config.bucket, "my-bucket");
-                assert_eq!(s3_config.force_path_style, false);
-                assert!(s3_config.endpoint.is_none());
-                assert!(s3_config.access_key_id.is_none());
-                assert!(s3_config.secret_access_key.is_none());
+            BackendConfig::Compressed(compressed) => {
+                assert_eq!(compressed.level, 3);
+                assert_eq!(compressed.content_types, vec!["text/*".to_string()]);
             }
-            _ => panic!("Expected S3 backend"),
+            _ => panic!("Expected compressed backend"),
         }
+
+        let json = json.replace(
+            r#""type": "compressed","#,
+            r#""type": "compressed", "level": 30,"#,
+        );
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_parse_s3_backend_full() {
+    fn test_validate_compressed_backend_needs_user_metadata() {
         let json = r#"{
             "backends": [
                 {
-                    "type": "s3",
-                    "name": "minio",
-                    "region": "us-east-1",
-                    "bucket": "my-bucket",
-                    "endpoint": "http://localhost:9000",
-                    "force_path_style": true,
-                    "access_key_id": "minioadmin",
-                    "secret_access_key": "minioadmin"
+                    "type": "compressed",
+                    "backend": {
+                        "type": "webdav",
+                        "name": "dav",
+                        "url": "https://dav.example.com/objects/"
+                    }
                 }
             ],
             "readMode": "PRIMARY_ONLY",
@@ -217,22 +1233,71 @@ mod tests {
         }"#;
 
         let config: Config = serde_json::from_str(json).unwrap();
-        assert_eq!(config.read_mode, ReadMode::PrimaryOnly);
-        assert_eq!(config.write_mode, WriteMode::MultiSync);
+        let result = config.validate();
+        assert!(result.unwrap_err().to_string().contains("user metadata"));
+
+        // Wrappers in between don't make the metadata stick
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "compressed",
+                    "backend": {
+                        "type": "fault",
+                        "backend": {
+                            "type": "sftp",
+                            "name": "files",
+                            "host": "sftp.example.com",
+                            "username": "replicat4",
+                            "root": "/data",
+                            "password": "secret"
+                        }
+                    }
+                }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_fault_backend() {
+        let yaml = r#"
+backends:
+  - type: fault
+    seed: 42
+    default:
+      latency: 0.5
+      latency_ms: 200
+    operations:
+      get_object:
+        truncate: 0.1
+        stream_error: 0.1
+    backend:
+      type: memory
+      name: flaky
+readMode: PRIMARY_FALLBACK
+writeMode: MULTI_SYNC
+"#;
 
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.backends[0].name(), "flaky");
         match &config.backends[0] {
-            BackendConfig::S3(s3_config) => {
-                assert_eq!(s3_config.name, "minio");
-                assert_eq!(
-                    s3_config.endpoint,
-                    Some("http://localhost:9000".to_string())
-                );
-                assert_eq!(s3_config.force_path_style, true);
-                assert_eq!(s3_config.access_key_id, Some("minioadmin".to_string()));
-                assert_eq!(s3_config.secret_access_key, Some("minioadmin".to_string()));
+            BackendConfig::Fault(fault) => {
+                assert_eq!(fault.seed, Some(42));
+                assert_eq!(fault.default.latency_ms, 200);
+                let get = &fault.operations[&FaultOperation::GetObject];
+                assert_eq!(get.truncate, 0.1);
+                assert_eq!(get.error, 0.0);
             }
-            _ => panic!("Expected S3 backend"),
+            _ => panic!("Expected fault backend"),
         }
+
+        let yaml = yaml.replace("truncate: 0.1", "truncate: 2");
+        let config: Config = serde_yml::from_str(&yaml).unwrap();
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -259,6 +1324,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_memory_backend_with_limits() {
+        let json = r#"{
+            "backends": [
+                {
+                    "type": "memory",
+                    "name": "cache",
+                    "max_bytes": 1073741824,
+                    "max_objects": 10000,
+                    "snapshot_path": "/var/lib/replicat4/cache.snapshot",
+                    "snapshot_interval_seconds": 300
+                }
+            ],
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        match &config.backends[0] {
+            BackendConfig::Memory(mem_config) => {
+                assert_eq!(mem_config.max_bytes, Some(1 << 30));
+                assert_eq!(mem_config.max_objects, Some(10000));
+                assert_eq!(mem_config.snapshot_interval_seconds, Some(300));
+            }
+            _ => panic!("Expected Memory backend"),
+        }
+
+        // The interval needs a file to save to
+        let json = json.replace(
+            r#""snapshot_path": "/var/lib/replicat4/cache.snapshot","#,
+            "",
+        );
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_filesystem_backend() {
+        let yaml = r#"
+backends:
+  - type: filesystem
+    name: nas
+    root: /mnt/nas/replicat4
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        match &config.backends[0] {
+            BackendConfig::Filesystem(fs_config) => {
+                assert_eq!(fs_config.name, "nas");
+                assert_eq!(fs_config.root, PathBuf::from("/mnt/nas/replicat4"));
+            }
+            _ => panic!("Expected Filesystem backend"),
+        }
+    }
+
     #[test]
     fn test_parse_multiple_backends() {
         let json = r#"{
@@ -409,14 +1532,24 @@ mod tests {
     fn test_serialize_s3_backend() {
         let config = Config {
             virtual_bucket: None,
+            virtual_buckets: vec![],
+            region: None,
+            base_domain: None,
+            object_lock_store_path: None,
+            bucket_settings_store_path: None,
+            lifecycle_interval_seconds: None,
+            version_map_dir: None,
+            credentials: Vec::new(),
             backends: vec![BackendConfig::S3(S3BackendConfig {
                 name: "test".to_string(),
+                role: BackendRole::default(),
                 region: "us-east-1".to_string(),
                 bucket: "my-bucket".to_string(),
                 endpoint: None,
                 force_path_style: false,
                 access_key_id: None,
                 secret_access_key: None,
+                server_side_encryption: None,
             })],
             read_mode: ReadMode::PrimaryFallback,
             write_mode: WriteMode::MultiSync,
@@ -443,14 +1576,24 @@ mod tests {
     fn test_serialize_skip_none_fields() {
         let config = Config {
             virtual_bucket: None,
+            virtual_buckets: vec![],
+            region: None,
+            base_domain: None,
+            object_lock_store_path: None,
+            bucket_settings_store_path: None,
+            lifecycle_interval_seconds: None,
+            version_map_dir: None,
+            credentials: Vec::new(),
             backends: vec![BackendConfig::S3(S3BackendConfig {
                 name: "test".to_string(),
+                role: BackendRole::default(),
                 region: "us-east-1".to_string(),
                 bucket: "my-bucket".to_string(),
                 endpoint: None,
                 force_path_style: false,
                 access_key_id: None,
                 secret_access_key: None,
+                server_side_encryption: None,
             })],
             read_mode: ReadMode::PrimaryFallback,
             write_mode: WriteMode::AsyncReplication,
@@ -462,6 +1605,11 @@ mod tests {
 
         // Optional None fields should not appear in JSON
         assert!(!json.contains("virtualBucket"));
+        assert!(!json.contains("virtualBuckets"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value.get("region").is_none());
+        assert!(!json.contains("baseDomain"));
+        assert!(!json.contains("objectLockStorePath"));
         assert!(!json.contains("endpoint"));
         assert!(!json.contains("access_key_id"));
         assert!(!json.contains("secret_access_key"));
@@ -564,17 +1712,24 @@ mod tests {
     fn test_backend_config_name() {
         let s3_backend = BackendConfig::S3(S3BackendConfig {
             name: "s3-test".to_string(),
+            role: BackendRole::default(),
             region: "us-east-1".to_string(),
             bucket: "bucket".to_string(),
             endpoint: None,
             force_path_style: false,
             access_key_id: None,
             secret_access_key: None,
+            server_side_encryption: None,
         });
         assert_eq!(s3_backend.name(), "s3-test");
 
         let mem_backend = BackendConfig::Memory(MemoryBackendConfig {
             name: "mem-test".to_string(),
+            role: BackendRole::default(),
+            max_bytes: None,
+            max_objects: None,
+            snapshot_path: None,
+            snapshot_interval_seconds: None,
         });
         assert_eq!(mem_backend.name(), "mem-test");
     }
@@ -830,4 +1985,223 @@ primaryBackendName: aws
             _ => panic!("Expected S3 backends"),
         }
     }
+
+    #[test]
+    fn test_parse_virtual_buckets() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "virtualBuckets": [
+                {
+                    "name": "logs",
+                    "writeMode": "MULTI_SYNC",
+                    "primaryBackendName": "logs-b",
+                    "backends": [
+                        { "type": "memory", "name": "logs-a" },
+                        { "type": "memory", "name": "logs-b" }
+                    ]
+                },
+                {
+                    "name": "media",
+                    "readMode": "BEST_EFFORT",
+                    "backends": [
+                        { "type": "memory", "name": "media-a" }
+                    ]
+                }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.backends.is_empty());
+
+        let buckets = config.buckets();
+        assert_eq!(buckets.len(), 2);
+
+        assert_eq!(buckets[0].name, "logs");
+        assert_eq!(buckets[0].read_mode, ReadMode::PrimaryFallback);
+        assert_eq!(buckets[0].write_mode, WriteMode::MultiSync);
+        assert_eq!(buckets[0].primary_backend_name, Some("logs-b".to_string()));
+        assert_eq!(buckets[0].backends.len(), 2);
+
+        assert_eq!(buckets[1].name, "media");
+        assert_eq!(buckets[1].read_mode, ReadMode::BestEffort);
+        assert_eq!(buckets[1].write_mode, WriteMode::AsyncReplication);
+        assert_eq!(buckets[1].backends.len(), 1);
+    }
+
+    #[test]
+    fn test_buckets_single_bucket_defaults() {
+        let json = r#"{
+            "backends": [
+                { "type": "memory", "name": "test" }
+            ],
+            "readMode": "PRIMARY_ONLY",
+            "writeMode": "MULTI_SYNC"
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let buckets = config.buckets();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].name, DEFAULT_VIRTUAL_BUCKET);
+        assert_eq!(buckets[0].read_mode, ReadMode::PrimaryOnly);
+        assert_eq!(buckets[0].write_mode, WriteMode::MultiSync);
+        assert_eq!(buckets[0].backends.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_duplicate_virtual_bucket_names() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "virtualBuckets": [
+                { "name": "data", "backends": [{ "type": "memory", "name": "a" }] },
+                { "name": "data", "backends": [{ "type": "memory", "name": "b" }] }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let result = config.validate();
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Duplicate virtual bucket name")
+        );
+    }
+
+    #[test]
+    fn test_validate_virtual_buckets_with_top_level_backends() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "backends": [{ "type": "memory", "name": "top" }],
+            "virtualBuckets": [
+                { "name": "data", "backends": [{ "type": "memory", "name": "a" }] }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let result = config.validate();
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Cannot combine virtualBuckets")
+        );
+    }
+
+    #[test]
+    fn test_validate_virtual_bucket_primary_backend_exists() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "virtualBuckets": [
+                {
+                    "name": "data",
+                    "primaryBackendName": "missing",
+                    "backends": [{ "type": "memory", "name": "a" }]
+                }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let result = config.validate();
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("not found in backends list")
+        );
+    }
+
+    #[test]
+    fn test_validate_credential_buckets() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "virtualBuckets": [
+                { "name": "photos", "backends": [{ "type": "memory", "name": "a" }] },
+                { "name": "logs", "backends": [{ "type": "memory", "name": "b" }] }
+            ],
+            "credentials": [
+                {
+                    "accessKeyId": "PHOTOS",
+                    "secretAccessKey": "photos-secret",
+                    "buckets": ["photos"]
+                },
+                { "accessKeyId": "ADMIN", "secretAccessKey": "admin-secret" }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.credentials[0].buckets,
+            Some(vec!["photos".to_string()])
+        );
+        assert_eq!(config.credentials[1].buckets, None);
+
+        let unknown_bucket = json.replace(r#"["photos"]"#, r#"["videos"]"#);
+        let config: Config = serde_json::from_str(&unknown_bucket).unwrap();
+        let result = config.validate();
+        assert!(result.unwrap_err().to_string().contains("unknown bucket"));
+
+        let duplicate = json.replace(r#""ADMIN""#, r#""PHOTOS""#);
+        let config: Config = serde_json::from_str(&duplicate).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_lifecycle_interval() {
+        let json = r#"{
+            "readMode": "PRIMARY_FALLBACK",
+            "writeMode": "ASYNC_REPLICATION",
+            "lifecycleIntervalSeconds": 60,
+            "backends": [{ "type": "memory", "name": "a" }]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.lifecycle_interval_seconds, Some(60));
+
+        // A zero interval would make the lifecycle worker panic
+        let zero = json.replace("60", "0");
+        let config: Config = serde_json::from_str(&zero).unwrap();
+        let result = config.validate();
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("lifecycleIntervalSeconds")
+        );
+    }
+
+    #[test]
+    fn test_parse_virtual_buckets_yaml() {
+        let yaml = r#"
+readMode: PRIMARY_FALLBACK
+writeMode: ASYNC_REPLICATION
+virtualBuckets:
+  - name: logs
+    readMode: ALL_CONSISTENT
+    backends:
+      - type: memory
+        name: logs-a
+  - name: media
+    backends:
+      - type: memory
+        name: media-a
+"#;
+
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let buckets = config.buckets();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].read_mode, ReadMode::AllConsistent);
+        assert_eq!(buckets[1].read_mode, ReadMode::PrimaryFallback);
+    }
 }
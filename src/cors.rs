@@ -0,0 +1,300 @@
+//! CORS support for browser clients
+//!
+//! Each virtual bucket can have a CORS configuration. Preflight `OPTIONS` requests are
+//! answered without authentication by the preflight handler; every other request carrying an
+//! `Origin` header gets `Access-Control-*` headers from the first matching rule.
+
+use crate::{
+    app_state::AppState,
+    types::{CorsConfiguration, CorsRule, error::S3Error},
+};
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, Method, header},
+    middleware::Next,
+    response::Response,
+};
+
+/// Maximum number of rules in a CORS configuration
+const MAX_RULES: usize = 100;
+
+/// Methods a CORS rule may allow
+const SUPPORTED_METHODS: [&str; 5] = ["GET", "PUT", "HEAD", "POST", "DELETE"];
+
+/// Check a CORS configuration before accepting it
+pub fn validate_configuration(configuration: &CorsConfiguration) -> Result<(), S3Error> {
+    if configuration.rules.is_empty() || configuration.rules.len() > MAX_RULES {
+        return Err(S3Error::MalformedXML);
+    }
+
+    for rule in &configuration.rules {
+        if rule.allowed_origins.is_empty() || rule.allowed_methods.is_empty() {
+            return Err(S3Error::MalformedXML);
+        }
+        if let Some(method) = rule
+            .allowed_methods
+            .iter()
+            .find(|method| !SUPPORTED_METHODS.contains(&method.as_str()))
+        {
+            return Err(S3Error::InvalidRequest(format!(
+                "Found unsupported HTTP method in CORS config. Unsupported method is {}",
+                method
+            )));
+        }
+        for pattern in rule.allowed_origins.iter().chain(&rule.allowed_headers) {
+            if pattern.matches('*').count() > 1 {
+                return Err(S3Error::InvalidRequest(format!(
+                    "AllowedOrigin/AllowedHeader \"{}\" can not have more than one wildcard.",
+                    pattern
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Match a value against a pattern containing at most one `*` wildcard
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            value.len() >= prefix.len() + suffix.len()
+                && value.starts_with(prefix)
+                && value.ends_with(suffix)
+        }
+        None => pattern == value,
+    }
+}
+
+impl CorsRule {
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|pattern| wildcard_match(pattern, origin))
+    }
+
+    fn allows_method(&self, method: &str) -> bool {
+        self.allowed_methods.iter().any(|allowed| allowed == method)
+    }
+
+    fn allows_header(&self, header: &str) -> bool {
+        let header = header.to_ascii_lowercase();
+        self.allowed_headers
+            .iter()
+            .any(|pattern| wildcard_match(&pattern.to_ascii_lowercase(), &header))
+    }
+
+    /// Headers answering a request from `origin` that matched this rule
+    fn response_headers(&self, origin: &str) -> Vec<(HeaderName, String)> {
+        let any_origin = self.allowed_origins.iter().any(|pattern| pattern == "*");
+        let mut headers = vec![
+            (
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                if any_origin { "*" } else { origin }.to_string(),
+            ),
+            (
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                self.allowed_methods.join(", "),
+            ),
+            (header::VARY, "Origin".to_string()),
+        ];
+        if !any_origin {
+            headers.push((header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true".to_string()));
+        }
+        if !self.expose_headers.is_empty() {
+            headers.push((
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                self.expose_headers.join(", "),
+            ));
+        }
+        if let Some(max_age) = self.max_age_seconds {
+            headers.push((header::ACCESS_CONTROL_MAX_AGE, max_age.to_string()));
+        }
+        headers
+    }
+}
+
+impl CorsConfiguration {
+    /// Find the first rule allowing `method` from `origin` with all `request_headers`
+    fn find_rule(&self, origin: &str, method: &str, request_headers: &[&str]) -> Option<&CorsRule> {
+        self.rules.iter().find(|rule| {
+            rule.allows_origin(origin)
+                && rule.allows_method(method)
+                && request_headers
+                    .iter()
+                    .all(|header| rule.allows_header(header))
+        })
+    }
+}
+
+/// Answer a preflight request for `bucket`
+/// Returns the headers to send back, or AccessForbidden if no rule allows the request
+pub fn preflight_headers(
+    app_state: &AppState,
+    bucket: &str,
+    headers: &HeaderMap,
+) -> Result<Vec<(HeaderName, String)>, S3Error> {
+    let header = |name: HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(origin), Some(method)) = (
+        header(header::ORIGIN),
+        header(header::ACCESS_CONTROL_REQUEST_METHOD),
+    ) else {
+        return Err(S3Error::InvalidRequest(
+            "Insufficient information. Origin request header needed.".to_string(),
+        ));
+    };
+    let request_headers: Vec<&str> = header(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let configuration = app_state.bucket(bucket)?.cors.read().unwrap().clone();
+    let configuration = configuration.ok_or_else(|| {
+        S3Error::AccessForbidden("CORSResponse: CORS is not enabled for this bucket.".to_string())
+    })?;
+    let rule = configuration
+        .find_rule(origin, method, &request_headers)
+        .ok_or_else(|| {
+            S3Error::AccessForbidden(
+                "CORSResponse: This CORS request is not allowed. This is usually because the \
+                 evaluation of Origin, request method / Access-Control-Request-Method or \
+                 Access-Control-Request-Headers are not whitelisted by the resource's CORS spec."
+                    .to_string(),
+            )
+        })?;
+
+    let mut response_headers = rule.response_headers(origin);
+    if !request_headers.is_empty() {
+        response_headers.push((
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            request_headers.join(", "),
+        ));
+    }
+    Ok(response_headers)
+}
+
+/// Add `Access-Control-*` headers to responses for cross-origin requests
+///
+/// Preflight requests are left to the preflight handler.
+pub async fn cors_middleware(app_state: AppState, request: Request, next: Next) -> Response {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let Some(origin) = origin.filter(|_| request.method() != Method::OPTIONS) else {
+        return next.run(request).await;
+    };
+
+    let bucket = request
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let configuration = app_state
+        .bucket(bucket)
+        .ok()
+        .and_then(|bucket| bucket.cors.read().unwrap().clone());
+    let cors_headers = configuration.and_then(|configuration| {
+        configuration
+            .find_rule(&origin, request.method().as_str(), &[])
+            .map(|rule| rule.response_headers(&origin))
+    });
+
+    let mut response = next.run(request).await;
+    for (name, value) in cors_headers.into_iter().flatten() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(origins: &[&str], methods: &[&str], headers: &[&str]) -> CorsRule {
+        CorsRule {
+            id: None,
+            allowed_origins: origins.iter().map(|s| s.to_string()).collect(),
+            allowed_methods: methods.iter().map(|s| s.to_string()).collect(),
+            allowed_headers: headers.iter().map(|s| s.to_string()).collect(),
+            expose_headers: vec![],
+            max_age_seconds: None,
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "https://example.com"));
+        assert!(wildcard_match(
+            "https://*.example.com",
+            "https://app.example.com"
+        ));
+        assert!(!wildcard_match(
+            "https://*.example.com",
+            "https://example.com"
+        ));
+        assert!(!wildcard_match(
+            "https://example.com",
+            "https://example.org"
+        ));
+    }
+
+    #[test]
+    fn test_find_rule() {
+        let configuration = CorsConfiguration {
+            rules: vec![
+                rule(&["https://app.example.com"], &["PUT"], &["content-*"]),
+                rule(&["*"], &["GET"], &[]),
+            ],
+        };
+
+        assert!(
+            configuration
+                .find_rule("https://app.example.com", "PUT", &["Content-Type"])
+                .is_some()
+        );
+        assert!(
+            configuration
+                .find_rule("https://app.example.com", "PUT", &["x-amz-date"])
+                .is_none()
+        );
+        assert!(
+            configuration
+                .find_rule("https://other.com", "PUT", &[])
+                .is_none()
+        );
+        assert!(
+            configuration
+                .find_rule("https://other.com", "GET", &[])
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_validate_configuration() {
+        let valid = CorsConfiguration {
+            rules: vec![rule(&["*"], &["GET", "PUT"], &["*"])],
+        };
+        assert!(validate_configuration(&valid).is_ok());
+
+        let bad_method = CorsConfiguration {
+            rules: vec![rule(&["*"], &["PATCH"], &[])],
+        };
+        assert!(validate_configuration(&bad_method).is_err());
+
+        let two_wildcards = CorsConfiguration {
+            rules: vec![rule(&["https://*.*.com"], &["GET"], &[])],
+        };
+        assert!(validate_configuration(&two_wildcards).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+/// DELETE /{bucket}?cors - Remove the CORS configuration of a bucket
+pub async fn delete_bucket_cors(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let virtual_bucket = app_state.bucket(&bucket)?;
+    tracing::info!("DELETE bucket CORS: bucket={}", bucket);
+
+    *virtual_bucket.cors.write().unwrap() = None;
+    app_state.save_bucket_settings().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
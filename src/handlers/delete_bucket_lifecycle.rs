@@ -0,0 +1,25 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+/// DELETE /{bucket}?lifecycle - Remove the lifecycle configuration of a bucket
+pub async fn delete_bucket_lifecycle(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let virtual_bucket = app_state.bucket(&bucket)?;
+    tracing::info!("DELETE bucket lifecycle: bucket={}", bucket);
+
+    *virtual_bucket.lifecycle.write().unwrap() = None;
+    app_state.save_bucket_settings().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
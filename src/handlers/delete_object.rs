@@ -1,26 +1,65 @@
+use super::{
+    get_object::ObjectVersionQuery, object_lock::check_object_lock, set_version_id_header,
+};
 use crate::{
     app_state::AppState,
     types::{AuthContext, error::S3Error},
 };
 use axum::{
     Extension,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
 
-/// DELETE /{bucket_name}/{key} - Delete an object
+/// DELETE /{bucket}/{key} - Delete an object
 pub async fn delete_object(
-    Path(key): Path<String>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
-) -> Result<impl IntoResponse, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
-    tracing::info!("DELETE object: bucket={}, key={}", bucket, key);
+    headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "DELETE object: bucket={}, key={}, version_id={:?}",
+        bucket,
+        key,
+        params.version_id
+    );
+
+    let _key_guard = app_state.object_locks.lock_key(&bucket, &key).await;
+    let locked_version_id = check_object_lock(
+        &app_state,
+        &bucket,
+        &key,
+        params.version_id.as_deref(),
+        &headers,
+    )
+    .await?;
 
-    storage.delete_object(&key).await?;
+    let output = storage
+        .delete_object(&key, params.version_id.as_deref())
+        .await?;
+
+    // The version is gone unless the delete only added a delete marker on top of it
+    if let Some(version_id) = locked_version_id
+        && (params.version_id.is_some() || !output.delete_marker)
+    {
+        app_state
+            .object_locks
+            .remove(&bucket, &key, &version_id)
+            .await?;
+    }
 
     // S3 returns 204 No Content on successful delete
-    Ok(StatusCode::NO_CONTENT)
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    set_version_id_header(&mut response, output.version_id.as_deref());
+    if output.delete_marker {
+        response
+            .headers_mut()
+            .insert("x-amz-delete-marker", HeaderValue::from_static("true"));
+    }
+
+    Ok(response)
 }
@@ -0,0 +1,33 @@
+use super::get_object::ObjectVersionQuery;
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+/// DELETE /{bucket}/{key}?tagging - Remove all tags from an object
+pub async fn delete_object_tagging(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "DELETE object tagging: bucket={}, key={}, version_id={:?}",
+        bucket,
+        key,
+        params.version_id
+    );
+
+    storage
+        .delete_object_tagging(&key, params.version_id.as_deref())
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
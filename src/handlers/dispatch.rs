@@ -0,0 +1,137 @@
+//! Dispatch requests that share a route to the handler for their query subresource
+//!
+//! S3 multiplexes operations on the same path through query parameters (`GET /{bucket}`
+//! lists objects, `GET /{bucket}?location` returns the bucket's region, ...). axum routes on
+//! paths only, so these handlers pick the operation and forward the whole request to it.
+
+use super::{
+    delete_bucket_cors, delete_bucket_lifecycle, delete_object, delete_object_tagging,
+    get_bucket_cors, get_bucket_lifecycle, get_bucket_location, get_bucket_versioning, get_object,
+    get_object_attributes, get_object_legal_hold, get_object_retention, get_object_tagging,
+    list_object_versions, list_objects, put_bucket_cors, put_bucket_lifecycle,
+    put_bucket_versioning, put_object, put_object_legal_hold, put_object_retention,
+    put_object_tagging,
+};
+use crate::{app_state::AppState, types::error::S3Error};
+use axum::{
+    extract::{Request, State},
+    handler::Handler,
+    http::Uri,
+    response::{IntoResponse, Response},
+};
+
+/// GET /{bucket}
+pub async fn get_bucket(State(app_state): State<AppState>, request: Request) -> Response {
+    let uri = request.uri();
+    if has_subresource(uri, "location") {
+        get_bucket_location.call(request, app_state).await
+    } else if has_subresource(uri, "versioning") {
+        get_bucket_versioning.call(request, app_state).await
+    } else if has_subresource(uri, "versions") {
+        list_object_versions.call(request, app_state).await
+    } else if has_subresource(uri, "lifecycle") {
+        get_bucket_lifecycle.call(request, app_state).await
+    } else if has_subresource(uri, "cors") {
+        get_bucket_cors.call(request, app_state).await
+    } else {
+        list_objects.call(request, app_state).await
+    }
+}
+
+/// PUT /{bucket}
+pub async fn put_bucket(State(app_state): State<AppState>, request: Request) -> Response {
+    let uri = request.uri();
+    if has_subresource(uri, "versioning") {
+        put_bucket_versioning.call(request, app_state).await
+    } else if has_subresource(uri, "lifecycle") {
+        put_bucket_lifecycle.call(request, app_state).await
+    } else if has_subresource(uri, "cors") {
+        put_bucket_cors.call(request, app_state).await
+    } else {
+        // Buckets are defined in the configuration, they can't be created through the API
+        S3Error::NotImplemented("CreateBucket is not supported by this proxy".to_string())
+            .into_response()
+    }
+}
+
+/// DELETE /{bucket}
+pub async fn delete_bucket(State(app_state): State<AppState>, request: Request) -> Response {
+    let uri = request.uri();
+    if has_subresource(uri, "lifecycle") {
+        delete_bucket_lifecycle.call(request, app_state).await
+    } else if has_subresource(uri, "cors") {
+        delete_bucket_cors.call(request, app_state).await
+    } else {
+        // Buckets are defined in the configuration, they can't be deleted through the API
+        S3Error::NotImplemented("DeleteBucket is not supported by this proxy".to_string())
+            .into_response()
+    }
+}
+
+/// GET /{bucket}/{key}
+pub async fn get_key(State(app_state): State<AppState>, request: Request) -> Response {
+    let uri = request.uri();
+    if has_subresource(uri, "tagging") {
+        get_object_tagging.call(request, app_state).await
+    } else if has_subresource(uri, "retention") {
+        get_object_retention.call(request, app_state).await
+    } else if has_subresource(uri, "legal-hold") {
+        get_object_legal_hold.call(request, app_state).await
+    } else if has_subresource(uri, "attributes") {
+        get_object_attributes.call(request, app_state).await
+    } else {
+        get_object.call(request, app_state).await
+    }
+}
+
+/// PUT /{bucket}/{key}
+pub async fn put_key(State(app_state): State<AppState>, request: Request) -> Response {
+    let uri = request.uri();
+    if has_subresource(uri, "tagging") {
+        put_object_tagging.call(request, app_state).await
+    } else if has_subresource(uri, "retention") {
+        put_object_retention.call(request, app_state).await
+    } else if has_subresource(uri, "legal-hold") {
+        put_object_legal_hold.call(request, app_state).await
+    } else {
+        put_object.call(request, app_state).await
+    }
+}
+
+/// DELETE /{bucket}/{key}
+pub async fn delete_key(State(app_state): State<AppState>, request: Request) -> Response {
+    if has_subresource(request.uri(), "tagging") {
+        delete_object_tagging.call(request, app_state).await
+    } else {
+        delete_object.call(request, app_state).await
+    }
+}
+
+/// Whether the query string contains `name`, with or without a value
+fn has_subresource(uri: &Uri, name: &str) -> bool {
+    uri.query().is_some_and(|query| {
+        query
+            .split('&')
+            .any(|param| param.split('=').next() == Some(name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_subresource() {
+        let uri: Uri = "/bucket?location".parse().unwrap();
+        assert!(has_subresource(&uri, "location"));
+
+        let uri: Uri = "/bucket?list-type=2&location=".parse().unwrap();
+        assert!(has_subresource(&uri, "location"));
+
+        let uri: Uri = "/bucket?list-type=2&prefix=location".parse().unwrap();
+        assert!(!has_subresource(&uri, "location"));
+
+        let uri: Uri = "/bucket".parse().unwrap();
+        assert!(!has_subresource(&uri, "location"));
+    }
+}
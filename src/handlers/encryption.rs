@@ -0,0 +1,132 @@
+//! Customer-provided encryption keys (SSE-C) shared by the handlers that read and write objects
+
+use crate::{storage::SseCustomerKey, types::error::S3Error};
+use axum::{
+    http::{HeaderMap, HeaderValue},
+    response::Response,
+};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use md5::{Digest, Md5};
+
+const ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
+const KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
+const KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-md5";
+
+/// Parse and check the SSE-C headers of a request, None if the request sends no key
+pub(super) fn parse_sse_customer_headers(
+    headers: &HeaderMap,
+) -> Result<Option<SseCustomerKey>, S3Error> {
+    parse_sse_customer_key(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+}
+
+/// Parse and check an SSE-C key from headers or, for browser uploads, form fields
+///
+/// `header` looks up a value by its lowercase header name.
+pub(super) fn parse_sse_customer_key<'a>(
+    header: impl Fn(&str) -> Option<&'a str>,
+) -> Result<Option<SseCustomerKey>, S3Error> {
+    let (algorithm, key, key_md5) = match (
+        header(ALGORITHM_HEADER),
+        header(KEY_HEADER),
+        header(KEY_MD5_HEADER),
+    ) {
+        (None, None, None) => return Ok(None),
+        (Some(algorithm), Some(key), Some(key_md5)) => (algorithm, key, key_md5),
+        _ => {
+            return Err(S3Error::InvalidArgument(
+                "Requests specifying Server Side Encryption with Customer provided keys must provide the algorithm, the key and the key MD5"
+                    .to_string(),
+            ));
+        }
+    };
+
+    if algorithm != "AES256" {
+        return Err(S3Error::InvalidArgument(
+            "The encryption algorithm specified is not valid".to_string(),
+        ));
+    }
+    let key_bytes = BASE64
+        .decode(key)
+        .ok()
+        .filter(|key_bytes| key_bytes.len() == 32)
+        .ok_or_else(|| {
+            S3Error::InvalidArgument(
+                "The secret key was invalid for the specified algorithm".to_string(),
+            )
+        })?;
+    if BASE64.encode(Md5::digest(&key_bytes)) != key_md5 {
+        return Err(S3Error::InvalidArgument(
+            "The calculated MD5 hash of the key did not match the hash that was provided"
+                .to_string(),
+        ));
+    }
+
+    Ok(Some(SseCustomerKey {
+        algorithm: algorithm.to_string(),
+        key: key.to_string(),
+        key_md5: key_md5.to_string(),
+    }))
+}
+
+/// Confirm the customer-provided key on the response, as S3 does
+pub(super) fn set_sse_customer_headers(
+    response: &mut Response,
+    sse_customer: Option<&SseCustomerKey>,
+) {
+    let Some(sse_customer) = sse_customer else {
+        return;
+    };
+    for (name, value) in [
+        (ALGORITHM_HEADER, &sse_customer.algorithm),
+        (KEY_MD5_HEADER, &sse_customer.key_md5),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sse_headers(key: &[u8], key_md5: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ALGORITHM_HEADER, "AES256".parse().unwrap());
+        headers.insert(KEY_HEADER, BASE64.encode(key).parse().unwrap());
+        headers.insert(KEY_MD5_HEADER, BASE64.encode(key_md5).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_parse_sse_customer_headers() {
+        let key = [7u8; 32];
+        let headers = sse_headers(&key, &Md5::digest(key));
+        let sse_customer = parse_sse_customer_headers(&headers).unwrap().unwrap();
+        assert_eq!(sse_customer.algorithm, "AES256");
+        assert_eq!(sse_customer.key, BASE64.encode(key));
+
+        assert!(
+            parse_sse_customer_headers(&HeaderMap::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_sse_customer_headers_rejects_bad_keys() {
+        // Key of the wrong length
+        let short_key = [7u8; 16];
+        let headers = sse_headers(&short_key, &Md5::digest(short_key));
+        assert!(parse_sse_customer_headers(&headers).is_err());
+
+        // MD5 of another key
+        let headers = sse_headers(&[7u8; 32], &Md5::digest([8u8; 32]));
+        assert!(parse_sse_customer_headers(&headers).is_err());
+
+        // Key without its algorithm
+        let mut headers = sse_headers(&[7u8; 32], &Md5::digest([7u8; 32]));
+        headers.remove(ALGORITHM_HEADER);
+        assert!(parse_sse_customer_headers(&headers).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET /{bucket}?lifecycle - Get the lifecycle configuration of a bucket
+pub async fn get_bucket_lifecycle(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let virtual_bucket = app_state.bucket(&bucket)?;
+    tracing::info!("GET bucket lifecycle: bucket={}", bucket);
+
+    let configuration = virtual_bucket
+        .lifecycle
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or(S3Error::NoSuchLifecycleConfiguration)?;
+
+    // Serialize to XML
+    let xml = to_xml_string(&configuration)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
@@ -0,0 +1,42 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, LocationConstraint, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET /{bucket}?location - Return the region the bucket lives in
+///
+/// All virtual buckets report the region clients sign their requests for, except that
+/// us-east-1 is reported as an empty location constraint like S3 does.
+pub async fn get_bucket_location(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    app_state.bucket(&bucket)?;
+    tracing::info!("GET bucket location: bucket={}", bucket);
+
+    let region = match app_state.region.as_str() {
+        "us-east-1" => String::new(),
+        region => region.to_string(),
+    };
+    let response = LocationConstraint { region };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
@@ -0,0 +1,40 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, VersioningConfiguration, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET /{bucket}?versioning - Get the versioning state of a bucket
+pub async fn get_bucket_versioning(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!("GET bucket versioning: bucket={}", bucket);
+
+    let status = storage.get_bucket_versioning().await?;
+
+    // A bucket that never had versioning configured has no Status element
+    let response = VersioningConfiguration {
+        status: status.map(|status| status.as_str().to_string()),
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
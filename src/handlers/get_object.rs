@@ -1,28 +1,174 @@
+use super::{
+    encryption::{parse_sse_customer_headers, set_sse_customer_headers},
+    set_version_id_header,
+};
 use crate::{
     app_state::AppState,
-    types::{AuthContext, error::S3Error},
+    storage::{ByteRange, GetObjectOptions},
+    types::{AuthContext, ObjectMetadata, error::S3Error},
 };
 use axum::{
     Extension,
     body::Body,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use futures::TryStreamExt;
+use serde::Deserialize;
+
+/// Query parameters for object operations that can target a specific version
+#[derive(Deserialize)]
+pub struct ObjectVersionQuery {
+    #[serde(rename = "versionId")]
+    pub version_id: Option<String>,
+    /// Part of a multipart object to read (GET and HEAD only)
+    #[serde(rename = "partNumber")]
+    pub part_number: Option<u32>,
+}
+
+/// `response-*` query parameters overriding the headers of a GetObject response
+///
+/// S3 only honours them on signed requests; every request reaching the handlers is
+/// authenticated (by header or presigned URL), so they always apply here.
+#[derive(Deserialize)]
+pub struct ResponseHeaderOverrides {
+    #[serde(rename = "response-content-type")]
+    content_type: Option<String>,
+    #[serde(rename = "response-content-language")]
+    content_language: Option<String>,
+    #[serde(rename = "response-expires")]
+    expires: Option<String>,
+    #[serde(rename = "response-cache-control")]
+    cache_control: Option<String>,
+    #[serde(rename = "response-content-disposition")]
+    content_disposition: Option<String>,
+    #[serde(rename = "response-content-encoding")]
+    content_encoding: Option<String>,
+}
+
+impl ResponseHeaderOverrides {
+    /// The headers to set on the response, checked before the object is read
+    fn headers(&self) -> Result<Vec<(HeaderName, HeaderValue)>, S3Error> {
+        [
+            (header::CONTENT_TYPE, &self.content_type),
+            (header::CONTENT_LANGUAGE, &self.content_language),
+            (header::EXPIRES, &self.expires),
+            (header::CACHE_CONTROL, &self.cache_control),
+            (header::CONTENT_DISPOSITION, &self.content_disposition),
+            (header::CONTENT_ENCODING, &self.content_encoding),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .map(|(name, value)| {
+            let value = HeaderValue::from_str(value).map_err(|_| {
+                S3Error::InvalidArgument(format!("Invalid value for response-{}", name))
+            })?;
+            Ok((name, value))
+        })
+        .collect()
+    }
+}
+
+/// Highest part number of a multipart object
+const MAX_PART_NUMBER: u32 = 10_000;
 
-/// GET /{bucket_name}/{key} - Get an object
+/// Check the `partNumber` query parameter of a GET or HEAD
+pub(super) fn validate_part_number(part_number: Option<u32>) -> Result<(), S3Error> {
+    match part_number {
+        Some(part_number) if !(1..=MAX_PART_NUMBER).contains(&part_number) => {
+            Err(S3Error::InvalidArgument(format!(
+                "Part number must be an integer between 1 and {}, inclusive",
+                MAX_PART_NUMBER
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Turn a GET or HEAD response into a response for a single part
+///
+/// Objects stored in a single part have part 1 only, which spans the whole object.
+pub(super) fn set_part_headers(
+    response: &mut Response,
+    metadata: &ObjectMetadata,
+    part_number: Option<u32>,
+) {
+    if part_number.is_none() {
+        return;
+    }
+
+    *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    let headers = response.headers_mut();
+    match metadata.parts_count() {
+        Some(parts_count) => {
+            headers.insert("x-amz-mp-parts-count", parts_count.into());
+        }
+        None if metadata.size > 0 => {
+            let range = format!("bytes 0-{}/{}", metadata.size - 1, metadata.size);
+            if let Ok(range) = HeaderValue::from_str(&range) {
+                headers.insert(header::CONTENT_RANGE, range);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Turn a GET response into a response for the requested byte range
+fn set_range_headers(
+    response: &mut Response,
+    metadata: &ObjectMetadata,
+    range: &ByteRange,
+) -> Result<(), S3Error> {
+    let (start, end) = range.resolve(metadata.size)?;
+    *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_LENGTH, (end - start + 1).into());
+    let content_range = format!("bytes {}-{}/{}", start, end, metadata.size);
+    if let Ok(content_range) = HeaderValue::from_str(&content_range) {
+        headers.insert(header::CONTENT_RANGE, content_range);
+    }
+    Ok(())
+}
+
+/// GET /{bucket}/{key} - Get an object
 pub async fn get_object(
-    Path(key): Path<String>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    Query(overrides): Query<ResponseHeaderOverrides>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
+    headers: HeaderMap,
 ) -> Result<Response, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
-    tracing::info!("GET object: bucket={}, key={}", bucket, key);
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "GET object: bucket={}, key={}, version_id={:?}, part_number={:?}",
+        bucket,
+        key,
+        params.version_id,
+        params.part_number
+    );
+    validate_part_number(params.part_number)?;
+    let override_headers = overrides.headers()?;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(ByteRange::parse);
+    if range.is_some() && params.part_number.is_some() {
+        return Err(S3Error::InvalidRequest(
+            "Cannot specify both Range header and partNumber query parameter".to_string(),
+        ));
+    }
+
+    let options = GetObjectOptions {
+        version_id: params.version_id,
+        part_number: params.part_number,
+        range,
+        sse_customer: parse_sse_customer_headers(&headers)?,
+    };
 
     // Retrieve object stream and metadata from storage in a single call
-    let (stream, metadata) = storage.get_object(&key).await?;
+    let (stream, metadata) = storage.get_object(&key, &options).await?;
 
     // Convert our stream to axum Body
     // The stream yields Result<Bytes, S3Error>, we need to map errors to std::io::Error for Body
@@ -30,7 +176,7 @@ pub async fn get_object(
     let body = Body::from_stream(body_stream);
 
     // Build response with S3 headers
-    Ok((
+    let mut response = (
         StatusCode::OK,
         [
             ("content-type", metadata.content_type.clone()),
@@ -40,8 +186,20 @@ pub async fn get_object(
                 metadata.last_modified.to_rfc2822().replace("+0000", "GMT"),
             ),
             ("content-length", metadata.size.to_string()),
+            ("accept-ranges", "bytes".to_string()),
         ],
         body,
     )
-        .into_response())
+        .into_response();
+    set_version_id_header(&mut response, metadata.version_id.as_deref());
+    set_part_headers(&mut response, &metadata, options.part_number);
+    if let Some(range) = &options.range {
+        set_range_headers(&mut response, &metadata, range)?;
+    }
+    set_sse_customer_headers(&mut response, options.sse_customer.as_ref());
+    for (name, value) in override_headers {
+        response.headers_mut().insert(name, value);
+    }
+
+    Ok(response)
 }
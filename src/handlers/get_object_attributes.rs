@@ -0,0 +1,137 @@
+use super::{
+    encryption::parse_sse_customer_headers, get_object::ObjectVersionQuery, set_version_id_header,
+};
+use crate::{
+    app_state::AppState,
+    storage::GetObjectOptions,
+    types::{AuthContext, GetObjectAttributesOutput, ObjectParts, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use quick_xml::se::to_string as to_xml_string;
+
+/// Attributes a client can request with the `x-amz-object-attributes` header
+const SUPPORTED_ATTRIBUTES: [&str; 5] = [
+    "ETag",
+    "Checksum",
+    "ObjectParts",
+    "StorageClass",
+    "ObjectSize",
+];
+
+/// GET /{bucket}/{key}?attributes - Get the attributes of an object without its body
+///
+/// Checksums are not tracked by the proxy, so a requested `Checksum` is left out of the
+/// response, as S3 does for objects stored without one.
+pub async fn get_object_attributes(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    let attributes = parse_attributes_header(&headers)?;
+    tracing::info!(
+        "GET object attributes: bucket={}, key={}, version_id={:?}, attributes={:?}",
+        bucket,
+        key,
+        params.version_id,
+        attributes
+    );
+
+    let options = GetObjectOptions {
+        version_id: params.version_id,
+        sse_customer: parse_sse_customer_headers(&headers)?,
+        ..Default::default()
+    };
+    let metadata = storage.head_object(&key, &options).await?;
+
+    let requested = |attribute: &str| attributes.contains(&attribute);
+    let response = GetObjectAttributesOutput {
+        etag: requested("ETag").then(|| metadata.etag.trim_matches('"').to_string()),
+        object_parts: metadata
+            .parts_count()
+            .filter(|_| requested("ObjectParts"))
+            .map(|total_parts_count| ObjectParts { total_parts_count }),
+        storage_class: requested("StorageClass").then(|| "STANDARD".to_string()),
+        object_size: requested("ObjectSize").then_some(metadata.size),
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    let mut response = (
+        StatusCode::OK,
+        [
+            ("content-type", "application/xml".to_string()),
+            (
+                "last-modified",
+                metadata.last_modified.to_rfc2822().replace("+0000", "GMT"),
+            ),
+        ],
+        xml_with_header,
+    )
+        .into_response();
+    set_version_id_header(&mut response, metadata.version_id.as_deref());
+
+    Ok(response)
+}
+
+/// Parse the comma-separated `x-amz-object-attributes` header
+fn parse_attributes_header(headers: &HeaderMap) -> Result<Vec<&'static str>, S3Error> {
+    let attributes = headers
+        .get_all("x-amz-object-attributes")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|attribute| !attribute.is_empty())
+        .map(|attribute| {
+            SUPPORTED_ATTRIBUTES
+                .into_iter()
+                .find(|supported| *supported == attribute)
+                .ok_or_else(|| {
+                    S3Error::InvalidArgument(format!("Invalid attribute name: {}", attribute))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if attributes.is_empty() {
+        return Err(S3Error::InvalidArgument(
+            "Please specify at least one valid attribute with the x-amz-object-attributes header"
+                .to_string(),
+        ));
+    }
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attributes_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-amz-object-attributes",
+            "ETag, ObjectSize".parse().unwrap(),
+        );
+        assert_eq!(
+            parse_attributes_header(&headers).unwrap(),
+            vec!["ETag", "ObjectSize"]
+        );
+
+        headers.insert("x-amz-object-attributes", "Owner".parse().unwrap());
+        assert!(parse_attributes_header(&headers).is_err());
+
+        assert!(parse_attributes_header(&HeaderMap::new()).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use super::{get_object::ObjectVersionQuery, object_lock::lock_version_id};
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, ObjectLockRetention, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::SecondsFormat;
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET /{bucket}/{key}?retention - Get the retention of an object
+pub async fn get_object_retention(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "GET object retention: bucket={}, key={}, version_id={:?}",
+        bucket,
+        key,
+        params.version_id
+    );
+
+    let version_id = lock_version_id(storage, &key, params.version_id.as_deref()).await?;
+    let retention = app_state
+        .object_locks
+        .get(&bucket, &key, &version_id)
+        .await
+        .and_then(|lock| lock.retention)
+        .ok_or(S3Error::NoSuchObjectLockConfiguration)?;
+
+    let response = ObjectLockRetention {
+        mode: Some(retention.mode.as_str().to_string()),
+        retain_until_date: Some(
+            retention
+                .retain_until_date
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        ),
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
@@ -0,0 +1,48 @@
+use super::get_object::ObjectVersionQuery;
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, TagSet, Tagging, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET /{bucket}/{key}?tagging - Get the tags of an object
+pub async fn get_object_tagging(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "GET object tagging: bucket={}, key={}, version_id={:?}",
+        bucket,
+        key,
+        params.version_id
+    );
+
+    let tags = storage
+        .get_object_tagging(&key, params.version_id.as_deref())
+        .await?;
+
+    let response = Tagging {
+        tag_set: TagSet { tags },
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
@@ -4,18 +4,18 @@ use crate::{
 };
 use axum::{
     Extension,
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 
-/// HEAD /{bucket_name} - Check if bucket exists and user has access
+/// HEAD /{bucket} - Check if bucket exists and user has access
 pub async fn head_bucket(
+    Path(bucket): Path<String>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
 ) -> Result<Response, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
+    let storage = app_state.storage(&bucket)?;
     tracing::info!("HEAD bucket: bucket={}", bucket);
 
     storage.head_bucket().await?;
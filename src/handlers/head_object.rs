@@ -1,38 +1,65 @@
+use super::{
+    encryption::{parse_sse_customer_headers, set_sse_customer_headers},
+    get_object::{ObjectVersionQuery, set_part_headers, validate_part_number},
+    set_version_id_header,
+};
 use crate::{
     app_state::AppState,
+    storage::GetObjectOptions,
     types::{AuthContext, error::S3Error},
 };
 use axum::{
     Extension,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
 
-/// HEAD /{bucket_name}/{key} - Get object metadata
+/// HEAD /{bucket}/{key} - Get object metadata
 pub async fn head_object(
-    Path(key): Path<String>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
+    headers: HeaderMap,
 ) -> Result<Response, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
-    tracing::info!("HEAD object: bucket={}, key={}", bucket, key);
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "HEAD object: bucket={}, key={}, version_id={:?}, part_number={:?}",
+        bucket,
+        key,
+        params.version_id,
+        params.part_number
+    );
+    validate_part_number(params.part_number)?;
+
+    let options = GetObjectOptions {
+        version_id: params.version_id,
+        part_number: params.part_number,
+        sse_customer: parse_sse_customer_headers(&headers)?,
+        ..Default::default()
+    };
 
-    let metadata = storage.head_object(&key).await?;
+    let metadata = storage.head_object(&key, &options).await?;
 
     // Return headers only (no body)
-    Ok((
+    let mut response = (
         StatusCode::OK,
         [
-            ("content-type", metadata.content_type),
-            ("etag", metadata.etag),
+            ("content-type", metadata.content_type.clone()),
+            ("etag", metadata.etag.clone()),
             (
                 "last-modified",
                 metadata.last_modified.to_rfc2822().replace("+0000", "GMT"),
             ),
             ("content-length", metadata.size.to_string()),
+            ("accept-ranges", "bytes".to_string()),
         ],
     )
-        .into_response())
+        .into_response();
+    set_version_id_header(&mut response, metadata.version_id.as_deref());
+    set_part_headers(&mut response, &metadata, options.part_number);
+    set_sse_customer_headers(&mut response, options.sse_customer.as_ref());
+
+    Ok(response)
 }
@@ -0,0 +1,47 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, BucketEntry, Buckets, ListAllMyBucketsResult, Owner, error::S3Error},
+};
+use axum::{Extension, extract::State, http::StatusCode, response::IntoResponse};
+use quick_xml::se::to_string as to_xml_string;
+
+/// GET / - List the virtual buckets served by the proxy
+///
+/// Lists the buckets the caller's credential can access, sorted by name like S3 does.
+pub async fn list_buckets(
+    State(app_state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    tracing::info!("LIST buckets: access_key_id={}", auth.access_key_id);
+
+    let mut buckets: Vec<BucketEntry> = app_state
+        .buckets
+        .values()
+        .filter(|bucket| auth.can_access(&bucket.name))
+        .map(|bucket| BucketEntry {
+            name: bucket.name.clone(),
+            creation_date: bucket.creation_date.to_rfc3339(),
+        })
+        .collect();
+    buckets.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let response = ListAllMyBucketsResult {
+        owner: Owner {
+            id: auth.access_key_id.clone(),
+            display_name: auth.access_key_id,
+        },
+        buckets: Buckets { bucket: buckets },
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
@@ -0,0 +1,93 @@
+use crate::{
+    app_state::AppState,
+    types::{
+        AuthContext, ListVersionsResult, NULL_VERSION_ID, S3DeleteMarker, S3ObjectVersion,
+        error::S3Error,
+    },
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::se::to_string as to_xml_string;
+use serde::Deserialize;
+
+/// Query parameters for ListObjectVersions
+#[derive(Deserialize)]
+pub struct ListObjectVersionsQuery {
+    prefix: Option<String>,
+    #[serde(rename = "max-keys")]
+    max_keys: Option<i32>,
+}
+
+/// GET /{bucket}?versions - List all versions and delete markers in a bucket
+pub async fn list_object_versions(
+    Path(bucket): Path<String>,
+    Query(params): Query<ListObjectVersionsQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+    tracing::info!(
+        "LIST object versions: bucket={}, prefix={:?}",
+        bucket,
+        params.prefix
+    );
+
+    let max_keys = params.max_keys.unwrap_or(1000).min(1000);
+    let prefix = params.prefix.as_deref();
+
+    let object_versions = storage.list_object_versions(prefix, max_keys).await?;
+
+    // Convert to S3 XML format
+    let mut versions = Vec::new();
+    let mut delete_markers = Vec::new();
+    for version in object_versions {
+        let metadata = version.metadata;
+        let version_id = metadata
+            .version_id
+            .unwrap_or_else(|| NULL_VERSION_ID.to_string());
+
+        if version.is_delete_marker {
+            delete_markers.push(S3DeleteMarker {
+                key: metadata.key,
+                version_id,
+                is_latest: version.is_latest,
+                last_modified: metadata.last_modified.to_rfc3339(),
+            });
+        } else {
+            versions.push(S3ObjectVersion {
+                key: metadata.key,
+                version_id,
+                is_latest: version.is_latest,
+                last_modified: metadata.last_modified.to_rfc3339(),
+                etag: metadata.etag,
+                size: metadata.size,
+                storage_class: "STANDARD".to_string(),
+            });
+        }
+    }
+
+    let response = ListVersionsResult {
+        name: bucket,
+        prefix: params.prefix,
+        max_keys,
+        is_truncated: false,
+        versions,
+        delete_markers,
+    };
+
+    // Serialize to XML
+    let xml = to_xml_string(&response)
+        .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+    let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        xml_with_header,
+    ))
+}
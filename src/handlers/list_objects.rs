@@ -4,7 +4,7 @@ use crate::{
 };
 use axum::{
     Extension,
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
@@ -21,14 +21,14 @@ pub struct ListObjectsQuery {
     max_keys: Option<i32>,
 }
 
-/// GET /{bucket_name}?list-type=2 - List objects in a bucket
+/// GET /{bucket}?list-type=2 - List objects in a bucket
 pub async fn list_objects(
+    Path(bucket): Path<String>,
     Query(params): Query<ListObjectsQuery>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
 ) -> Result<impl IntoResponse, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
+    let storage = app_state.storage(&bucket)?;
     tracing::info!(
         "LIST objects: bucket={}, prefix={:?}",
         bucket,
@@ -54,7 +54,7 @@ pub async fn list_objects(
         .collect();
 
     let response = ListBucketResult {
-        name: bucket.to_string(),
+        name: bucket,
         prefix: params.prefix,
         key_count: s3_objects.len() as i32,
         max_keys,
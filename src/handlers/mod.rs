@@ -1,15 +1,70 @@
+mod delete_bucket_cors;
+mod delete_bucket_lifecycle;
 mod delete_object;
+mod delete_object_tagging;
+mod dispatch;
+mod encryption;
+mod get_bucket_cors;
+mod get_bucket_lifecycle;
+mod get_bucket_location;
+mod get_bucket_versioning;
 mod get_object;
+mod get_object_attributes;
+mod get_object_legal_hold;
+mod get_object_retention;
+mod get_object_tagging;
 mod head_bucket;
 mod head_object;
+mod list_buckets;
+mod list_object_versions;
 mod list_objects;
 mod not_found;
+mod object_lock;
+mod post_object;
+mod preflight;
+mod put_bucket_cors;
+mod put_bucket_lifecycle;
+mod put_bucket_versioning;
 mod put_object;
+mod put_object_legal_hold;
+mod put_object_retention;
+mod put_object_tagging;
 
+pub use delete_bucket_cors::delete_bucket_cors;
+pub use delete_bucket_lifecycle::delete_bucket_lifecycle;
 pub use delete_object::delete_object;
+pub use delete_object_tagging::delete_object_tagging;
+pub use dispatch::{delete_bucket, delete_key, get_bucket, get_key, put_bucket, put_key};
+pub use get_bucket_cors::get_bucket_cors;
+pub use get_bucket_lifecycle::get_bucket_lifecycle;
+pub use get_bucket_location::get_bucket_location;
+pub use get_bucket_versioning::get_bucket_versioning;
 pub use get_object::get_object;
+pub use get_object_attributes::get_object_attributes;
+pub use get_object_legal_hold::get_object_legal_hold;
+pub use get_object_retention::get_object_retention;
+pub use get_object_tagging::get_object_tagging;
 pub use head_bucket::head_bucket;
 pub use head_object::head_object;
+pub use list_buckets::list_buckets;
+pub use list_object_versions::list_object_versions;
 pub use list_objects::list_objects;
 pub use not_found::not_found;
+pub use post_object::post_object;
+pub use preflight::preflight;
+pub use put_bucket_cors::put_bucket_cors;
+pub use put_bucket_lifecycle::put_bucket_lifecycle;
+pub use put_bucket_versioning::put_bucket_versioning;
 pub use put_object::put_object;
+pub use put_object_legal_hold::put_object_legal_hold;
+pub use put_object_retention::put_object_retention;
+pub use put_object_tagging::put_object_tagging;
+
+use axum::{http::HeaderValue, response::Response};
+
+/// Add the `x-amz-version-id` header for objects that have a version
+fn set_version_id_header(response: &mut Response, version_id: Option<&str>) {
+    if let Some(value) = version_id.and_then(|v| HeaderValue::from_str(v).ok()) {
+        response.headers_mut().insert("x-amz-version-id", value);
+    }
+}
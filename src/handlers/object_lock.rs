@@ -0,0 +1,156 @@
+//! Object Lock checks shared by the handlers that delete, overwrite or lock objects
+
+use crate::{
+    app_state::AppState,
+    object_lock::{ObjectLock, Retention, RetentionMode},
+    storage::{GetObjectOptions, StorageBackend},
+    types::{NULL_VERSION_ID, VersioningStatus, error::S3Error},
+};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Whether the request asks to bypass governance-mode retention
+pub(super) fn bypass_governance(headers: &HeaderMap) -> bool {
+    headers
+        .get("x-amz-bypass-governance-retention")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// Resolve the version a lock operation applies to: the given version, or the latest one
+pub(super) async fn lock_version_id(
+    storage: &Arc<dyn StorageBackend>,
+    key: &str,
+    version_id: Option<&str>,
+) -> Result<String, S3Error> {
+    let options = GetObjectOptions {
+        version_id: version_id.map(str::to_string),
+        ..Default::default()
+    };
+    let metadata = storage.head_object(key, &options).await?;
+    Ok(metadata
+        .version_id
+        .unwrap_or_else(|| NULL_VERSION_ID.to_string()))
+}
+
+/// Refuse to delete or overwrite a version that is under retention or legal hold
+///
+/// `version_id` is None for the latest version. On a bucket with versioning enabled, writes
+/// and deletes of the latest version only stack a new version or a delete marker on top of
+/// it, so they are allowed. Returns the resolved version ID when the key has lock state, so
+/// callers can drop it once the version is gone.
+///
+/// Callers hold [`ObjectLockStore::lock_key`](crate::object_lock::ObjectLockStore::lock_key)
+/// from this check until their write is done.
+pub(super) async fn check_object_lock(
+    app_state: &AppState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    headers: &HeaderMap,
+) -> Result<Option<String>, S3Error> {
+    if !app_state.object_locks.has_locks(bucket, key).await {
+        return Ok(None);
+    }
+
+    let storage = app_state.storage(bucket)?;
+    if version_id.is_none()
+        && matches!(
+            storage.get_bucket_versioning().await,
+            Ok(Some(VersioningStatus::Enabled))
+        )
+    {
+        return Ok(None);
+    }
+
+    let version_id = match lock_version_id(storage, key, version_id).await {
+        Ok(version_id) => version_id,
+        // Nothing stored under that key or version, so nothing to protect
+        Err(S3Error::NoSuchKey | S3Error::NoSuchVersion | S3Error::MethodNotAllowed) => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+
+    if let Some(lock) = app_state.object_locks.get(bucket, key, &version_id).await {
+        lock.check_removable(bypass_governance(headers))
+            .inspect_err(|_| {
+                tracing::info!(
+                    "Refusing to remove locked object: bucket={}, key={}, version_id={}",
+                    bucket,
+                    key,
+                    version_id
+                );
+            })?;
+    }
+
+    Ok(Some(version_id))
+}
+
+/// Parse a retention mode and date, as sent in the Retention XML body or PutObject headers
+pub(super) fn parse_retention(mode: &str, retain_until_date: &str) -> Result<Retention, S3Error> {
+    let mode = RetentionMode::parse(mode)
+        .ok_or_else(|| S3Error::InvalidArgument(format!("Unknown retention mode: {}", mode)))?;
+    let retain_until_date = DateTime::parse_from_rfc3339(retain_until_date)
+        .map_err(|_| S3Error::InvalidArgument("The retain until date is invalid".to_string()))?
+        .with_timezone(&Utc);
+
+    if retain_until_date <= Utc::now() {
+        return Err(S3Error::InvalidArgument(
+            "The retain until date must be in the future!".to_string(),
+        ));
+    }
+
+    Ok(Retention {
+        mode,
+        retain_until_date,
+    })
+}
+
+/// Parse a legal hold status ("ON" or "OFF")
+pub(super) fn parse_legal_hold(status: &str) -> Result<bool, S3Error> {
+    match status {
+        "ON" => Ok(true),
+        "OFF" => Ok(false),
+        _ => Err(S3Error::MalformedXML),
+    }
+}
+
+/// Parse the `x-amz-object-lock-*` headers of a PutObject request
+/// Returns None if the request doesn't lock the new object
+pub(super) fn parse_object_lock_headers(
+    headers: &HeaderMap,
+) -> Result<Option<ObjectLock>, S3Error> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let retention = match (
+        header("x-amz-object-lock-mode"),
+        header("x-amz-object-lock-retain-until-date"),
+    ) {
+        (Some(mode), Some(retain_until_date)) => Some(parse_retention(mode, retain_until_date)?),
+        (None, None) => None,
+        _ => {
+            return Err(S3Error::InvalidArgument(
+                "x-amz-object-lock-mode and x-amz-object-lock-retain-until-date must both be \
+                 supplied"
+                    .to_string(),
+            ));
+        }
+    };
+    let legal_hold = header("x-amz-object-lock-legal-hold")
+        .map(|status| {
+            parse_legal_hold(status).map_err(|_| {
+                S3Error::InvalidArgument("Invalid x-amz-object-lock-legal-hold".to_string())
+            })
+        })
+        .transpose()?;
+
+    if retention.is_none() && legal_hold.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ObjectLock {
+        retention,
+        legal_hold,
+    }))
+}
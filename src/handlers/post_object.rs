@@ -0,0 +1,230 @@
+use super::{
+    encryption::{parse_sse_customer_key, set_sse_customer_headers},
+    object_lock::check_object_lock,
+    set_version_id_header,
+};
+use crate::{
+    app_state::AppState,
+    auth::{PostPolicy, verify_post_signature},
+    storage::{ObjectStream, PutObjectOptions},
+    types::{NULL_VERSION_ID, PostResponse, error::S3Error},
+};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures::{
+    future,
+    stream::{self, StreamExt},
+};
+use multer::Multipart;
+use quick_xml::se::to_string as to_xml_string;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// POST /{bucket} - Upload an object from an HTML form (multipart/form-data)
+///
+/// The form carries its own SigV4 signature over a policy document, so the request is not
+/// authenticated by the middleware. Form fields must come before the `file` field; fields
+/// after it are ignored, as on S3.
+pub async fn post_object(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let boundary = multer::parse_boundary(content_type).map_err(|_| {
+        S3Error::InvalidRequest(
+            "Bucket POST must be of the enclosure-type multipart/form-data".to_string(),
+        )
+    })?;
+    let mut multipart = Multipart::new(body.into_data_stream(), boundary);
+
+    // Text fields, keyed by lowercase name, up to the file field
+    let mut fields = HashMap::new();
+    let file = loop {
+        let field = multipart.next_field().await.map_err(malformed_post)?;
+        let Some(field) = field else {
+            return Err(S3Error::InvalidArgument(
+                "POST requires exactly one file upload per request.".to_string(),
+            ));
+        };
+        let name = field.name().unwrap_or_default().to_ascii_lowercase();
+        if name == "file" {
+            break field;
+        }
+        let value = field.text().await.map_err(malformed_post)?;
+        fields.insert(name, value);
+    };
+
+    let field = |name: &str| fields.get(name).map(String::as_str);
+    let (Some(policy), Some(signature)) = (field("policy"), field("x-amz-signature")) else {
+        return Err(S3Error::AccessDenied);
+    };
+    let access_key_id = verify_post_signature(
+        field("x-amz-algorithm").unwrap_or_default(),
+        field("x-amz-credential").unwrap_or_default(),
+        field("x-amz-date").unwrap_or_default(),
+        policy,
+        signature,
+        &app_state.credentials,
+        &app_state.region,
+    )?;
+    if !app_state.credentials.can_access(access_key_id, &bucket) {
+        return Err(S3Error::AccessDenied);
+    }
+    let policy = PostPolicy::parse(policy)?;
+    policy.check(&bucket, &fields)?;
+
+    let key = field("key")
+        .ok_or_else(|| {
+            S3Error::InvalidArgument("Bucket POST must contain a field named 'key'.".to_string())
+        })?
+        .replace("${filename}", file.file_name().unwrap_or_default());
+    tracing::info!("POST object: bucket={}, key={}", bucket, key);
+
+    // Overwriting the latest version is refused while it is locked
+    let _key_guard = app_state.object_locks.lock_key(&bucket, &key).await;
+    let locked_version_id = check_object_lock(&app_state, &bucket, &key, None, &headers).await?;
+
+    // The backend may wrap stream errors, so size errors are also recorded here
+    let size_error = Arc::new(Mutex::new(None));
+    let (min_size, max_size) = policy.content_length_range().unwrap_or((0, u64::MAX));
+    let body = size_limited_stream(file, min_size, max_size, size_error.clone());
+
+    let options = PutObjectOptions {
+        content_type: field("content-type").map(str::to_string),
+        sse_customer: parse_sse_customer_key(field)?,
+        ..Default::default()
+    };
+    let result = storage.put_object(&key, body, &options).await;
+    if let Some(e) = size_error.lock().unwrap().take() {
+        return Err(e);
+    }
+    let output = result?;
+    let version_id = output.version_id.as_deref().unwrap_or(NULL_VERSION_ID);
+
+    // Without versioning the write replaced the previous object, and its lock with it
+    if locked_version_id.as_deref() == Some(version_id) {
+        app_state
+            .object_locks
+            .remove(&bucket, &key, version_id)
+            .await?;
+    }
+
+    let mut response = success_response(
+        &bucket,
+        &key,
+        &output.etag,
+        field("success_action_redirect"),
+        field("success_action_status"),
+    )?;
+    if let Ok(etag) = output.etag.parse() {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    set_version_id_header(&mut response, output.version_id.as_deref());
+    set_sse_customer_headers(&mut response, options.sse_customer.as_ref());
+
+    Ok(response)
+}
+
+fn malformed_post(e: multer::Error) -> S3Error {
+    S3Error::InvalidRequest(format!(
+        "The body of your POST request is not well-formed multipart/form-data: {}",
+        e
+    ))
+}
+
+/// Stream the file field, failing once it is outside the policy's content-length-range
+fn size_limited_stream(
+    file: multer::Field<'static>,
+    min_size: u64,
+    max_size: u64,
+    size_error: Arc<Mutex<Option<S3Error>>>,
+) -> ObjectStream {
+    let received = Arc::new(AtomicU64::new(0));
+
+    let chunks = file.map({
+        let received = received.clone();
+        let size_error = size_error.clone();
+        move |chunk| {
+            let chunk = chunk.map_err(malformed_post)?;
+            let size =
+                received.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+            if size > max_size {
+                *size_error.lock().unwrap() = Some(S3Error::EntityTooLarge);
+                return Err(S3Error::EntityTooLarge);
+            }
+            Ok(chunk)
+        }
+    });
+
+    // Checked once the whole file has been read, before the backend completes the write
+    let end_check = stream::once(async move {
+        if received.load(Ordering::Relaxed) < min_size {
+            *size_error.lock().unwrap() = Some(S3Error::EntityTooSmall);
+            return Some(Err(S3Error::EntityTooSmall));
+        }
+        None
+    })
+    .filter_map(future::ready);
+
+    Box::pin(chunks.chain(end_check))
+}
+
+/// Build the response requested by `success_action_redirect` or `success_action_status`
+fn success_response(
+    bucket: &str,
+    key: &str,
+    etag: &str,
+    redirect: Option<&str>,
+    status: Option<&str>,
+) -> Result<Response, S3Error> {
+    if let Some(redirect) = redirect.filter(|redirect| !redirect.is_empty()) {
+        let query = serde_urlencoded::to_string([("bucket", bucket), ("key", key), ("etag", etag)])
+            .map_err(|e| S3Error::InternalError(format!("Failed to encode redirect: {}", e)))?;
+        let separator = if redirect.contains('?') { '&' } else { '?' };
+        let location = format!("{}{}{}", redirect, separator, query);
+        return Ok((StatusCode::SEE_OTHER, [(header::LOCATION, location)]).into_response());
+    }
+
+    match status {
+        Some("200") => Ok(StatusCode::OK.into_response()),
+        Some("201") => {
+            let response = PostResponse {
+                location: format!("/{}/{}", bucket, key),
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                etag: etag.to_string(),
+            };
+
+            // Serialize to XML
+            let xml = to_xml_string(&response)
+                .map_err(|e| S3Error::InternalError(format!("Failed to serialize XML: {}", e)))?;
+
+            let xml_with_header = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml);
+
+            Ok((
+                StatusCode::CREATED,
+                [("content-type", "application/xml")],
+                xml_with_header,
+            )
+                .into_response())
+        }
+        // S3 answers 204 for a missing or unrecognized status
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
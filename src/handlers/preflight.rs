@@ -0,0 +1,29 @@
+use crate::{app_state::AppState, cors::preflight_headers, types::error::S3Error};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+
+/// OPTIONS /{bucket} and /{bucket}/{key} - Answer a CORS preflight request
+///
+/// Preflight requests are sent by browsers without credentials, so this handler runs
+/// unauthenticated.
+pub async fn preflight(
+    Path(params): Path<HashMap<String, String>>,
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let bucket = params.get("bucket").map(String::as_str).unwrap_or_default();
+    tracing::info!("OPTIONS preflight: bucket={}", bucket);
+
+    let mut response = StatusCode::OK.into_response();
+    for (name, value) in preflight_headers(&app_state, bucket, &headers)? {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,37 @@
+use crate::{
+    app_state::AppState,
+    cors::validate_configuration,
+    types::{AuthContext, CorsConfiguration, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+
+/// PUT /{bucket}?cors - Replace the CORS configuration of a bucket
+pub async fn put_bucket_cors(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let virtual_bucket = app_state.bucket(&bucket)?;
+
+    let configuration: CorsConfiguration =
+        from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    validate_configuration(&configuration)?;
+
+    tracing::info!(
+        "PUT bucket CORS: bucket={}, rules={}",
+        bucket,
+        configuration.rules.len()
+    );
+
+    *virtual_bucket.cors.write().unwrap() = Some(configuration);
+    app_state.save_bucket_settings().await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,37 @@
+use crate::{
+    app_state::AppState,
+    lifecycle::validate_configuration,
+    types::{AuthContext, LifecycleConfiguration, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+
+/// PUT /{bucket}?lifecycle - Replace the lifecycle configuration of a bucket
+pub async fn put_bucket_lifecycle(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let virtual_bucket = app_state.bucket(&bucket)?;
+
+    let configuration: LifecycleConfiguration =
+        from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    validate_configuration(&configuration)?;
+
+    tracing::info!(
+        "PUT bucket lifecycle: bucket={}, rules={}",
+        bucket,
+        configuration.rules.len()
+    );
+
+    *virtual_bucket.lifecycle.write().unwrap() = Some(configuration);
+    app_state.save_bucket_settings().await?;
+
+    Ok(StatusCode::OK)
+}
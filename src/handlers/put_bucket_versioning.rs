@@ -0,0 +1,39 @@
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, VersioningConfiguration, VersioningStatus, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+
+/// PUT /{bucket}?versioning - Enable or suspend versioning for a bucket
+pub async fn put_bucket_versioning(
+    Path(bucket): Path<String>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+
+    let configuration: VersioningConfiguration =
+        from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    let status = configuration
+        .status
+        .as_deref()
+        .and_then(VersioningStatus::parse)
+        .ok_or(S3Error::MalformedXML)?;
+
+    tracing::info!(
+        "PUT bucket versioning: bucket={}, status={:?}",
+        bucket,
+        status
+    );
+
+    storage.put_bucket_versioning(status).await?;
+
+    Ok(StatusCode::OK)
+}
@@ -1,28 +1,49 @@
+use super::{
+    encryption::{parse_sse_customer_headers, set_sse_customer_headers},
+    object_lock::{check_object_lock, parse_object_lock_headers},
+    put_object_tagging::validate_tags,
+    set_version_id_header,
+};
 use crate::{
     app_state::AppState,
-    types::{AuthContext, error::S3Error},
+    storage::PutObjectOptions,
+    types::{AuthContext, NULL_VERSION_ID, Tag, error::S3Error},
 };
 use axum::{
     Extension,
     body::Body,
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use futures::stream::StreamExt;
 
-/// PUT /{bucket_name}/{key} - Put an object
+/// PUT /{bucket}/{key} - Put an object
 pub async fn put_object(
-    Path(key): Path<String>,
+    Path((bucket, key)): Path<(String, String)>,
     State(app_state): State<AppState>,
     Extension(_auth): Extension<AuthContext>,
-    _headers: HeaderMap,
+    headers: HeaderMap,
     body: Body,
 ) -> Result<Response, S3Error> {
-    let storage = &app_state.storage;
-    let bucket = &app_state.bucket_name;
+    let storage = app_state.storage(&bucket)?;
     tracing::info!("PUT object: bucket={}, key={}", bucket, key);
 
+    let options = PutObjectOptions {
+        tags: parse_tagging_header(&headers)?,
+        content_type: headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        sse_customer: parse_sse_customer_headers(&headers)?,
+        ..Default::default()
+    };
+    let object_lock = parse_object_lock_headers(&headers)?;
+
+    // Overwriting the latest version is refused while it is locked
+    let _key_guard = app_state.object_locks.lock_key(&bucket, &key).await;
+    let locked_version_id = check_object_lock(&app_state, &bucket, &key, None, &headers).await?;
+
     // Convert axum Body to ObjectStream
     let stream = body.into_data_stream().map(|result| {
         result.map_err(|e| S3Error::InternalError(format!("Failed to read body: {}", e)))
@@ -30,8 +51,51 @@ pub async fn put_object(
     let boxed_stream = Box::pin(stream);
 
     // Store the object
-    let etag = storage.put_object(&key, boxed_stream).await?;
+    let output = storage.put_object(&key, boxed_stream, &options).await?;
+    let version_id = output.version_id.as_deref().unwrap_or(NULL_VERSION_ID);
+
+    // Without versioning the write replaced the previous object, and its lock with it
+    if locked_version_id.as_deref() == Some(version_id) {
+        app_state
+            .object_locks
+            .remove(&bucket, &key, version_id)
+            .await?;
+    }
+    if let Some(object_lock) = object_lock {
+        app_state
+            .object_locks
+            .update(&bucket, &key, version_id, |lock| {
+                *lock = object_lock;
+                Ok(())
+            })
+            .await?;
+    }
 
     // Return success with ETag
-    Ok((StatusCode::OK, [("etag", etag)]).into_response())
+    let mut response = (StatusCode::OK, [("etag", output.etag)]).into_response();
+    set_version_id_header(&mut response, output.version_id.as_deref());
+    set_sse_customer_headers(&mut response, options.sse_customer.as_ref());
+
+    Ok(response)
+}
+
+/// Parse the URL-encoded tag set of the `x-amz-tagging` header
+fn parse_tagging_header(headers: &HeaderMap) -> Result<Vec<Tag>, S3Error> {
+    let Some(value) = headers.get("x-amz-tagging") else {
+        return Ok(Vec::new());
+    };
+
+    let value = value
+        .to_str()
+        .map_err(|_| S3Error::InvalidTag("The x-amz-tagging header is invalid".to_string()))?;
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(value)
+        .map_err(|_| S3Error::InvalidTag("The x-amz-tagging header is invalid".to_string()))?;
+
+    let tags: Vec<Tag> = pairs
+        .into_iter()
+        .map(|(key, value)| Tag { key, value })
+        .collect();
+    validate_tags(&tags)?;
+
+    Ok(tags)
 }
@@ -0,0 +1,50 @@
+use super::{
+    get_object::ObjectVersionQuery,
+    object_lock::{lock_version_id, parse_legal_hold},
+};
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, ObjectLockLegalHold, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+
+/// PUT /{bucket}/{key}?legal-hold - Place or release a legal hold on an object
+pub async fn put_object_legal_hold(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+
+    let configuration: ObjectLockLegalHold =
+        from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    let legal_hold = parse_legal_hold(&configuration.status)?;
+
+    let _key_guard = app_state.object_locks.lock_key(&bucket, &key).await;
+    let version_id = lock_version_id(storage, &key, params.version_id.as_deref()).await?;
+    tracing::info!(
+        "PUT object legal hold: bucket={}, key={}, version_id={}, status={}",
+        bucket,
+        key,
+        version_id,
+        configuration.status
+    );
+
+    app_state
+        .object_locks
+        .update(&bucket, &key, &version_id, |lock| {
+            lock.legal_hold = Some(legal_hold);
+            Ok(())
+        })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
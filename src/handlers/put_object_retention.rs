@@ -0,0 +1,60 @@
+use super::{
+    get_object::ObjectVersionQuery,
+    object_lock::{bypass_governance, lock_version_id, parse_retention},
+};
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, ObjectLockRetention, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+
+/// PUT /{bucket}/{key}?retention - Set or remove the retention of an object
+///
+/// An empty Retention element removes the retention, which requires bypassing governance
+/// mode and is never allowed in compliance mode.
+pub async fn put_object_retention(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+
+    let configuration: ObjectLockRetention =
+        from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    let retention = match (&configuration.mode, &configuration.retain_until_date) {
+        (Some(mode), Some(retain_until_date)) => Some(parse_retention(mode, retain_until_date)?),
+        (None, None) => None,
+        _ => return Err(S3Error::MalformedXML),
+    };
+
+    let _key_guard = app_state.object_locks.lock_key(&bucket, &key).await;
+    let version_id = lock_version_id(storage, &key, params.version_id.as_deref()).await?;
+    tracing::info!(
+        "PUT object retention: bucket={}, key={}, version_id={}, retention={:?}",
+        bucket,
+        key,
+        version_id,
+        retention
+    );
+
+    let bypass_governance = bypass_governance(&headers);
+    app_state
+        .object_locks
+        .update(&bucket, &key, &version_id, |lock| {
+            lock.check_retention_change(retention.as_ref(), bypass_governance)?;
+            lock.retention = retention;
+            Ok(())
+        })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
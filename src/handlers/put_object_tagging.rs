@@ -0,0 +1,113 @@
+use super::get_object::ObjectVersionQuery;
+use crate::{
+    app_state::AppState,
+    types::{AuthContext, Tag, Tagging, error::S3Error},
+};
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use quick_xml::de::from_str as from_xml_str;
+use std::collections::HashSet;
+
+/// Limits S3 puts on the tag set of an object
+const MAX_TAGS: usize = 10;
+const MAX_TAG_KEY_LENGTH: usize = 128;
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+/// PUT /{bucket}/{key}?tagging - Replace the tags of an object
+pub async fn put_object_tagging(
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<ObjectVersionQuery>,
+    State(app_state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+    body: String,
+) -> Result<impl IntoResponse, S3Error> {
+    let storage = app_state.storage(&bucket)?;
+
+    let tagging: Tagging = from_xml_str(&body).map_err(|_| S3Error::MalformedXML)?;
+    validate_tags(&tagging.tag_set.tags)?;
+
+    tracing::info!(
+        "PUT object tagging: bucket={}, key={}, version_id={:?}, tags={}",
+        bucket,
+        key,
+        params.version_id,
+        tagging.tag_set.tags.len()
+    );
+
+    storage
+        .put_object_tagging(&key, params.version_id.as_deref(), tagging.tag_set.tags)
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Check a tag set against the limits S3 enforces
+pub(super) fn validate_tags(tags: &[Tag]) -> Result<(), S3Error> {
+    if tags.len() > MAX_TAGS {
+        return Err(S3Error::InvalidTag(format!(
+            "Object tags cannot be greater than {}",
+            MAX_TAGS
+        )));
+    }
+
+    let mut keys = HashSet::new();
+    for tag in tags {
+        if tag.key.is_empty() || tag.key.chars().count() > MAX_TAG_KEY_LENGTH {
+            return Err(S3Error::InvalidTag(
+                "The TagKey you have provided is invalid".to_string(),
+            ));
+        }
+        if tag.value.chars().count() > MAX_TAG_VALUE_LENGTH {
+            return Err(S3Error::InvalidTag(
+                "The TagValue you have provided is invalid".to_string(),
+            ));
+        }
+        if !keys.insert(tag.key.as_str()) {
+            return Err(S3Error::InvalidTag(
+                "Cannot provide multiple Tags with the same key".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(key: &str, value: &str) -> Tag {
+        Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_tags() {
+        assert!(validate_tags(&[tag("a", "1"), tag("b", "")]).is_ok());
+
+        let too_many: Vec<_> = (0..=MAX_TAGS).map(|i| tag(&i.to_string(), "")).collect();
+        assert!(matches!(
+            validate_tags(&too_many),
+            Err(S3Error::InvalidTag(_))
+        ));
+
+        assert!(matches!(
+            validate_tags(&[tag("a", "1"), tag("a", "2")]),
+            Err(S3Error::InvalidTag(_))
+        ));
+        assert!(matches!(
+            validate_tags(&[tag(&"k".repeat(MAX_TAG_KEY_LENGTH + 1), "")]),
+            Err(S3Error::InvalidTag(_))
+        ));
+        assert!(matches!(
+            validate_tags(&[tag("a", &"v".repeat(MAX_TAG_VALUE_LENGTH + 1))]),
+            Err(S3Error::InvalidTag(_))
+        ));
+    }
+}
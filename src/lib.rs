@@ -1,14 +1,18 @@
 // Library exports for integration tests
 pub mod app_state;
 pub mod auth;
+pub mod bucket_settings;
 pub mod config;
+pub mod cors;
 pub mod handlers;
+pub mod lifecycle;
+pub mod object_lock;
 pub mod server;
 pub mod storage;
 pub mod types;
 
 // Re-export commonly used types
-pub use app_state::AppState;
+pub use app_state::{AppState, VirtualBucket};
 pub use auth::CredentialsStore;
 pub use config::{BackendConfig, Config};
 pub use storage::{InMemoryStorage, MultiBackend, S3Backend, StorageBackend};
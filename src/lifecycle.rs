@@ -0,0 +1,470 @@
+//! Bucket lifecycle rules executed by the proxy
+//!
+//! Backends have different (or no) lifecycle features, so expiration is done by the proxy: a
+//! background worker periodically lists every virtual bucket with a lifecycle configuration
+//! and deletes expired objects through the bucket's storage, which replicates the deletes to
+//! all backends according to the write mode.
+
+use crate::{
+    app_state::{AppState, VirtualBucket},
+    types::{
+        LifecycleConfiguration, LifecycleRule, NULL_VERSION_ID, ObjectMetadata, Tag, error::S3Error,
+    },
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use tokio::task::JoinHandle;
+
+/// Maximum number of rules in a lifecycle configuration
+const MAX_RULES: usize = 1000;
+
+/// Number of objects listed at a time while applying a rule
+///
+/// Each pass pages through every object under the rule's prefix, starting each page after
+/// the last key of the previous one.
+const LIST_BATCH_SIZE: i32 = 1000;
+
+/// Check a lifecycle configuration before accepting it
+pub fn validate_configuration(configuration: &LifecycleConfiguration) -> Result<(), S3Error> {
+    if configuration.rules.is_empty() || configuration.rules.len() > MAX_RULES {
+        return Err(S3Error::MalformedXML);
+    }
+
+    let mut ids = HashSet::new();
+    for rule in &configuration.rules {
+        if let Some(id) = &rule.id
+            && !ids.insert(id.as_str())
+        {
+            return Err(S3Error::InvalidArgument(
+                "Rule ID must be unique. Found same ID for more than one rule".to_string(),
+            ));
+        }
+        if rule.status != "Enabled" && rule.status != "Disabled" {
+            return Err(S3Error::MalformedXML);
+        }
+        // Multipart uploads aren't served, so a cleanup rule would silently never run
+        if rule.abort_incomplete_multipart_upload.is_some() {
+            return Err(S3Error::NotImplemented(
+                "AbortIncompleteMultipartUpload lifecycle actions are not supported".to_string(),
+            ));
+        }
+        if rule.expiration.is_none() {
+            return Err(S3Error::InvalidRequest(
+                "At least one action needs to be specified in a rule".to_string(),
+            ));
+        }
+        if let Some(expiration) = &rule.expiration {
+            match (expiration.days, &expiration.date) {
+                (Some(0), None) => {
+                    return Err(S3Error::InvalidArgument(
+                        "'Days' for Expiration action must be a positive integer".to_string(),
+                    ));
+                }
+                (Some(_), None) => {}
+                (None, Some(date)) => {
+                    parse_date(date)?;
+                }
+                _ => return Err(S3Error::MalformedXML),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>, S3Error> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| S3Error::InvalidArgument("Invalid date in Expiration action".to_string()))
+}
+
+impl LifecycleRule {
+    fn is_enabled(&self) -> bool {
+        self.status == "Enabled"
+    }
+
+    /// Key prefix the rule applies to, from the filter or the deprecated rule-level prefix
+    fn key_prefix(&self) -> Option<&str> {
+        let filter = self.filter.as_ref();
+        filter
+            .and_then(|filter| filter.prefix.as_deref())
+            .or_else(|| filter.and_then(|filter| filter.and.as_ref()?.prefix.as_deref()))
+            .or(self.prefix.as_deref())
+            .filter(|prefix| !prefix.is_empty())
+    }
+
+    /// Tags an object must all carry for the rule to apply
+    fn required_tags(&self) -> Vec<&Tag> {
+        let Some(filter) = &self.filter else {
+            return Vec::new();
+        };
+        filter
+            .tag
+            .iter()
+            .chain(filter.and.iter().flat_map(|and| and.tags.iter()))
+            .collect()
+    }
+
+    /// Whether the rule's expiration action applies to an object at `now`
+    fn is_expired(&self, object: &ObjectMetadata, now: DateTime<Utc>) -> bool {
+        let Some(expiration) = &self.expiration else {
+            return false;
+        };
+        match (expiration.days, &expiration.date) {
+            (Some(days), _) => object.last_modified + Duration::days(days.into()) <= now,
+            (None, Some(date)) => parse_date(date).is_ok_and(|date| date <= now),
+            (None, None) => false,
+        }
+    }
+}
+
+/// Run the lifecycle rules of all virtual buckets every `interval`
+pub fn spawn_lifecycle_worker(
+    app_state: AppState,
+    interval: std::time::Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let expired = run_lifecycle(&app_state).await;
+            if expired > 0 {
+                tracing::info!("Lifecycle worker expired {} objects", expired);
+            }
+        }
+    })
+}
+
+/// Apply the lifecycle rules of all virtual buckets once
+/// Returns the number of objects that were expired
+pub async fn run_lifecycle(app_state: &AppState) -> usize {
+    let mut expired = 0;
+    for bucket in app_state.buckets.values() {
+        let configuration = bucket.lifecycle.read().unwrap().clone();
+        let Some(configuration) = configuration else {
+            continue;
+        };
+
+        for rule in configuration.rules.iter().filter(|rule| rule.is_enabled()) {
+            expired += apply_rule(app_state, bucket, rule).await;
+        }
+    }
+    expired
+}
+
+async fn apply_rule(app_state: &AppState, bucket: &VirtualBucket, rule: &LifecycleRule) -> usize {
+    let now = Utc::now();
+    let mut expired = 0;
+    let mut last_key: Option<String> = None;
+    loop {
+        let listed = match &last_key {
+            Some(key) => {
+                bucket
+                    .storage
+                    .list_objects_after(rule.key_prefix(), key, LIST_BATCH_SIZE)
+                    .await
+            }
+            None => {
+                bucket
+                    .storage
+                    .list_objects(rule.key_prefix(), LIST_BATCH_SIZE)
+                    .await
+            }
+        };
+        let objects = match listed {
+            Ok(objects) => objects,
+            Err(e) => {
+                tracing::error!(
+                    "Lifecycle: failed to list bucket {} for rule {:?}: {}",
+                    bucket.name,
+                    rule.id,
+                    e
+                );
+                return expired;
+            }
+        };
+        let Some(last) = objects.last() else {
+            return expired;
+        };
+        last_key = Some(last.key.clone());
+
+        for object in objects.iter().filter(|object| rule.is_expired(object, now)) {
+            if expire_if_tagged(app_state, bucket, rule, object).await {
+                expired += 1;
+            }
+        }
+    }
+}
+
+/// Expire an object if it has all the tags the rule requires
+async fn expire_if_tagged(
+    app_state: &AppState,
+    bucket: &VirtualBucket,
+    rule: &LifecycleRule,
+    object: &ObjectMetadata,
+) -> bool {
+    let required_tags = rule.required_tags();
+    if !required_tags.is_empty() {
+        let tags = match bucket.storage.get_object_tagging(&object.key, None).await {
+            Ok(tags) => tags,
+            Err(e) => {
+                tracing::warn!(
+                    "Lifecycle: failed to get tags of {}/{}: {}",
+                    bucket.name,
+                    object.key,
+                    e
+                );
+                return false;
+            }
+        };
+        if !required_tags.iter().all(|tag| tags.contains(tag)) {
+            return false;
+        }
+    }
+
+    expire_object(app_state, bucket, object).await
+}
+
+/// Delete an expired object unless it is under Object Lock
+async fn expire_object(
+    app_state: &AppState,
+    bucket: &VirtualBucket,
+    object: &ObjectMetadata,
+) -> bool {
+    let version_id = object.version_id.as_deref().unwrap_or(NULL_VERSION_ID);
+    let _key_guard = app_state
+        .object_locks
+        .lock_key(&bucket.name, &object.key)
+        .await;
+    if let Some(lock) = app_state
+        .object_locks
+        .get(&bucket.name, &object.key, version_id)
+        .await
+        && lock.check_removable(false).is_err()
+    {
+        tracing::debug!(
+            "Lifecycle: skipping locked object {}/{}",
+            bucket.name,
+            object.key
+        );
+        return false;
+    }
+
+    match bucket.storage.delete_object(&object.key, None).await {
+        Ok(output) => {
+            tracing::info!("Lifecycle: expired {}/{}", bucket.name, object.key);
+            if !output.delete_marker
+                && let Err(e) = app_state
+                    .object_locks
+                    .remove(&bucket.name, &object.key, version_id)
+                    .await
+            {
+                tracing::warn!("Lifecycle: failed to drop lock of {}: {}", object.key, e);
+            }
+            true
+        }
+        Err(e) => {
+            tracing::error!(
+                "Lifecycle: failed to expire {}/{}: {}",
+                bucket.name,
+                object.key,
+                e
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::CredentialsStore;
+    use crate::storage::{InMemoryStorage, PutObjectOptions, StorageBackend};
+    use crate::types::{
+        AbortIncompleteMultipartUpload, LifecycleExpiration, LifecycleRuleAndOperator,
+        LifecycleRuleFilter,
+    };
+    use bytes::Bytes;
+    use futures::stream;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn rule(prefix: &str, tags: Vec<Tag>, expiration: LifecycleExpiration) -> LifecycleRule {
+        LifecycleRule {
+            id: None,
+            status: "Enabled".to_string(),
+            filter: Some(LifecycleRuleFilter {
+                prefix: None,
+                tag: None,
+                and: Some(LifecycleRuleAndOperator {
+                    prefix: Some(prefix.to_string()),
+                    tags,
+                }),
+            }),
+            prefix: None,
+            expiration: Some(expiration),
+            abort_incomplete_multipart_upload: None,
+        }
+    }
+
+    fn expired_date() -> LifecycleExpiration {
+        LifecycleExpiration {
+            days: None,
+            date: Some("2000-01-01T00:00:00Z".to_string()),
+        }
+    }
+
+    async fn put(storage: &Arc<dyn StorageBackend>, key: &str, tags: Vec<Tag>) {
+        storage
+            .put_object(
+                key,
+                Box::pin(stream::once(async { Ok(Bytes::from("data")) })),
+                &PutObjectOptions {
+                    tags,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_validate_configuration() {
+        let valid = LifecycleConfiguration {
+            rules: vec![rule("logs/", vec![], expired_date())],
+        };
+        assert!(validate_configuration(&valid).is_ok());
+
+        let zero_days = LifecycleConfiguration {
+            rules: vec![rule(
+                "logs/",
+                vec![],
+                LifecycleExpiration {
+                    days: Some(0),
+                    date: None,
+                },
+            )],
+        };
+        assert!(validate_configuration(&zero_days).is_err());
+
+        let both = LifecycleConfiguration {
+            rules: vec![rule(
+                "logs/",
+                vec![],
+                LifecycleExpiration {
+                    days: Some(1),
+                    date: Some("2000-01-01T00:00:00Z".to_string()),
+                },
+            )],
+        };
+        assert!(validate_configuration(&both).is_err());
+
+        let mut abort_multipart = rule("logs/", vec![], expired_date());
+        abort_multipart.abort_incomplete_multipart_upload = Some(AbortIncompleteMultipartUpload {
+            days_after_initiation: 7,
+        });
+        let abort_multipart = LifecycleConfiguration {
+            rules: vec![abort_multipart],
+        };
+        assert!(matches!(
+            validate_configuration(&abort_multipart),
+            Err(S3Error::NotImplemented(_))
+        ));
+    }
+
+    #[test]
+    fn test_rule_expiration_by_days() {
+        let rule = rule(
+            "",
+            vec![],
+            LifecycleExpiration {
+                days: Some(30),
+                date: None,
+            },
+        );
+        let now = Utc::now();
+        let object = |age_days| ObjectMetadata {
+            key: "key".to_string(),
+            size: 0,
+            etag: String::new(),
+            last_modified: now - Duration::days(age_days),
+            content_type: String::new(),
+            version_id: None,
+            user_metadata: HashMap::new(),
+        };
+
+        assert!(rule.is_expired(&object(31), now));
+        assert!(!rule.is_expired(&object(29), now));
+        assert_eq!(rule.key_prefix(), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_lifecycle_expires_matching_objects() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+        let temporary = Tag {
+            key: "class".to_string(),
+            value: "temporary".to_string(),
+        };
+        put(&storage, "logs/a.log", vec![temporary.clone()]).await;
+        put(&storage, "logs/b.log", vec![]).await;
+        put(&storage, "data/c.bin", vec![temporary.clone()]).await;
+
+        let bucket = VirtualBucket::new("bucket".to_string(), storage.clone());
+        *bucket.lifecycle.write().unwrap() = Some(LifecycleConfiguration {
+            rules: vec![rule("logs/", vec![temporary], expired_date())],
+        });
+        let app_state = AppState::new(
+            vec![bucket],
+            CredentialsStore::new(HashMap::new()),
+            "us-east-1".to_string(),
+            None,
+        );
+
+        assert_eq!(run_lifecycle(&app_state).await, 1);
+
+        let keys: Vec<_> = storage
+            .list_objects(None, 1000)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        assert_eq!(keys, vec!["data/c.bin", "logs/b.log"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_lifecycle_pages_through_all_objects() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+        let temporary = Tag {
+            key: "class".to_string(),
+            value: "temporary".to_string(),
+        };
+        // A full batch of objects the rule keeps comes before the ones it expires
+        for i in 0..LIST_BATCH_SIZE {
+            put(&storage, &format!("logs/{:05}.log", i), vec![]).await;
+        }
+        for i in 0..3 {
+            put(
+                &storage,
+                &format!("logs/tmp-{}.log", i),
+                vec![temporary.clone()],
+            )
+            .await;
+        }
+
+        let bucket = VirtualBucket::new("bucket".to_string(), storage.clone());
+        *bucket.lifecycle.write().unwrap() = Some(LifecycleConfiguration {
+            rules: vec![rule("logs/", vec![temporary], expired_date())],
+        });
+        let app_state = AppState::new(
+            vec![bucket],
+            CredentialsStore::new(HashMap::new()),
+            "us-east-1".to_string(),
+            None,
+        );
+
+        assert_eq!(run_lifecycle(&app_state).await, 3);
+        let remaining = storage.list_objects(None, i32::MAX).await.unwrap();
+        assert_eq!(remaining.len(), LIST_BATCH_SIZE as usize);
+    }
+}
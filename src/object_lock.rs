@@ -0,0 +1,411 @@
+//! Object Lock (WORM) state kept by the proxy
+//!
+//! Retention and legal holds are enforced by the proxy rather than by the backends, so they
+//! apply uniformly to every replica, including backends without native Object Lock support.
+//! The state lives in an [`ObjectLockStore`], optionally persisted to a JSON file.
+
+use crate::types::error::S3Error;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Retention mode of a locked object version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RetentionMode {
+    /// Can be shortened or removed with `x-amz-bypass-governance-retention: true`
+    Governance,
+    /// Can't be shortened or removed by anyone until it expires
+    Compliance,
+}
+
+impl RetentionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionMode::Governance => "GOVERNANCE",
+            RetentionMode::Compliance => "COMPLIANCE",
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "GOVERNANCE" => Some(RetentionMode::Governance),
+            "COMPLIANCE" => Some(RetentionMode::Compliance),
+            _ => None,
+        }
+    }
+}
+
+/// Retention period of a locked object version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    pub mode: RetentionMode,
+    pub retain_until_date: DateTime<Utc>,
+}
+
+impl Retention {
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.retain_until_date > now
+    }
+}
+
+/// Lock state of a single object version
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectLock {
+    pub retention: Option<Retention>,
+    /// None if a legal hold was never set on the version
+    pub legal_hold: Option<bool>,
+}
+
+impl ObjectLock {
+    fn is_empty(&self) -> bool {
+        self.retention.is_none() && self.legal_hold.is_none()
+    }
+
+    /// Check that the version may be deleted or overwritten
+    /// Returns AccessDenied while a legal hold or an unbypassed retention is active
+    pub fn check_removable(&self, bypass_governance: bool) -> Result<(), S3Error> {
+        if self.legal_hold == Some(true) {
+            return Err(S3Error::AccessDenied);
+        }
+
+        match &self.retention {
+            Some(retention) if retention.is_active(Utc::now()) => match retention.mode {
+                RetentionMode::Governance if bypass_governance => Ok(()),
+                _ => Err(S3Error::AccessDenied),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the retention may be replaced by `retention` (None removes it)
+    ///
+    /// An active retention can only be extended, except in governance mode with the bypass
+    /// header. Compliance mode can never be downgraded to governance.
+    pub fn check_retention_change(
+        &self,
+        retention: Option<&Retention>,
+        bypass_governance: bool,
+    ) -> Result<(), S3Error> {
+        let Some(current) = self
+            .retention
+            .as_ref()
+            .filter(|current| current.is_active(Utc::now()))
+        else {
+            return Ok(());
+        };
+
+        if current.mode == RetentionMode::Governance && bypass_governance {
+            return Ok(());
+        }
+
+        match retention {
+            Some(retention)
+                if retention.retain_until_date >= current.retain_until_date
+                    && (current.mode == RetentionMode::Governance
+                        || retention.mode == RetentionMode::Compliance) =>
+            {
+                Ok(())
+            }
+            _ => Err(S3Error::AccessDenied),
+        }
+    }
+}
+
+/// A lock of one version, as written to the store file
+#[derive(Serialize, Deserialize)]
+struct ObjectLockEntry {
+    bucket: String,
+    key: String,
+    version_id: String,
+    #[serde(flatten)]
+    lock: ObjectLock,
+}
+
+type LockKey = (String, String, String);
+
+/// Mutexes of the keys being written, keyed by virtual bucket and key
+type KeyMutexes = std::sync::Mutex<HashMap<(String, String), Weak<Mutex<()>>>>;
+
+/// Lock state of all object versions, keyed by virtual bucket, key and proxy version ID
+///
+/// Objects written while versioning was never enabled use the "null" version ID. When a
+/// path is set, the whole store is rewritten to it after every change.
+#[derive(Clone)]
+pub struct ObjectLockStore {
+    locks: Arc<Mutex<HashMap<LockKey, ObjectLock>>>,
+    path: Option<PathBuf>,
+    key_mutexes: Arc<KeyMutexes>,
+}
+
+impl ObjectLockStore {
+    /// Create a store that is lost when the proxy stops
+    pub fn in_memory() -> Self {
+        Self {
+            locks: Arc::new(Mutex::new(HashMap::new())),
+            path: None,
+            key_mutexes: Arc::default(),
+        }
+    }
+
+    /// Open a store persisted to `path`, loading its content if the file exists
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref().to_path_buf();
+        let locks = if path.exists() {
+            let entries: Vec<ObjectLockEntry> =
+                serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            entries
+                .into_iter()
+                .map(|entry| ((entry.bucket, entry.key, entry.version_id), entry.lock))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            locks: Arc::new(Mutex::new(locks)),
+            path: Some(path),
+            key_mutexes: Arc::default(),
+        })
+    }
+
+    /// Wait until no other request holds the key, and hold it until the guard is dropped
+    ///
+    /// Writes and deletes hold the key from their lock check until they are done, and lock
+    /// changes hold it too, so a version can't be locked between the check and the write.
+    pub async fn lock_key(&self, bucket: &str, key: &str) -> OwnedMutexGuard<()> {
+        let mutex = {
+            let mut mutexes = self.key_mutexes.lock().unwrap();
+            // Forget the keys nobody holds anymore
+            mutexes.retain(|_, mutex| mutex.strong_count() > 0);
+            let mutex_key = (bucket.to_string(), key.to_string());
+            match mutexes.get(&mutex_key).and_then(Weak::upgrade) {
+                Some(mutex) => mutex,
+                None => {
+                    let mutex = Arc::new(Mutex::new(()));
+                    mutexes.insert(mutex_key, Arc::downgrade(&mutex));
+                    mutex
+                }
+            }
+        };
+        mutex.lock_owned().await
+    }
+
+    /// Get the lock state of a version, None if it was never locked
+    pub async fn get(&self, bucket: &str, key: &str, version_id: &str) -> Option<ObjectLock> {
+        let locks = self.locks.lock().await;
+        locks.get(&lock_key(bucket, key, version_id)).cloned()
+    }
+
+    /// Whether any version of the key has lock state, so callers can skip resolving versions
+    pub async fn has_locks(&self, bucket: &str, key: &str) -> bool {
+        let locks = self.locks.lock().await;
+        locks
+            .keys()
+            .any(|(lock_bucket, lock_key, _)| lock_bucket == bucket && lock_key == key)
+    }
+
+    /// Change the lock state of a version
+    ///
+    /// `update` can reject the change; nothing is written in that case.
+    pub async fn update<F>(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        update: F,
+    ) -> Result<(), S3Error>
+    where
+        F: FnOnce(&mut ObjectLock) -> Result<(), S3Error>,
+    {
+        let mut locks = self.locks.lock().await;
+        let lock_key = lock_key(bucket, key, version_id);
+        let mut lock = locks.get(&lock_key).cloned().unwrap_or_default();
+        update(&mut lock)?;
+
+        if lock.is_empty() {
+            locks.remove(&lock_key);
+        } else {
+            locks.insert(lock_key, lock);
+        }
+        self.persist(&locks).await
+    }
+
+    /// Forget the lock state of a version once it has been deleted or replaced
+    pub async fn remove(&self, bucket: &str, key: &str, version_id: &str) -> Result<(), S3Error> {
+        let mut locks = self.locks.lock().await;
+        if locks.remove(&lock_key(bucket, key, version_id)).is_some() {
+            self.persist(&locks).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the store to its file, through a temporary file so a crash can't truncate it
+    async fn persist(&self, locks: &HashMap<LockKey, ObjectLock>) -> Result<(), S3Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let entries: Vec<ObjectLockEntry> = locks
+            .iter()
+            .map(|((bucket, key, version_id), lock)| ObjectLockEntry {
+                bucket: bucket.clone(),
+                key: key.clone(),
+                version_id: version_id.clone(),
+                lock: lock.clone(),
+            })
+            .collect();
+        let content = serde_json::to_string_pretty(&entries).map_err(|e| {
+            S3Error::InternalError(format!("Failed to serialize object locks: {}", e))
+        })?;
+
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+        let result = async {
+            tokio::fs::write(&tmp_path, content).await?;
+            tokio::fs::rename(&tmp_path, path).await
+        };
+        result.await.map_err(|e| {
+            tracing::error!("Failed to persist object locks to {:?}: {}", path, e);
+            S3Error::InternalError(format!("Failed to persist object locks: {}", e))
+        })
+    }
+}
+
+fn lock_key(bucket: &str, key: &str, version_id: &str) -> LockKey {
+    (bucket.to_string(), key.to_string(), version_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn retention(mode: RetentionMode, days: i64) -> Retention {
+        Retention {
+            mode,
+            retain_until_date: Utc::now() + Duration::days(days),
+        }
+    }
+
+    #[test]
+    fn test_check_removable() {
+        assert!(ObjectLock::default().check_removable(false).is_ok());
+
+        let legal_hold = ObjectLock {
+            retention: None,
+            legal_hold: Some(true),
+        };
+        assert!(matches!(
+            legal_hold.check_removable(true),
+            Err(S3Error::AccessDenied)
+        ));
+
+        let governance = ObjectLock {
+            retention: Some(retention(RetentionMode::Governance, 1)),
+            legal_hold: None,
+        };
+        assert!(governance.check_removable(false).is_err());
+        assert!(governance.check_removable(true).is_ok());
+
+        let compliance = ObjectLock {
+            retention: Some(retention(RetentionMode::Compliance, 1)),
+            legal_hold: Some(false),
+        };
+        assert!(compliance.check_removable(true).is_err());
+
+        let expired = ObjectLock {
+            retention: Some(retention(RetentionMode::Compliance, -1)),
+            legal_hold: None,
+        };
+        assert!(expired.check_removable(false).is_ok());
+    }
+
+    #[test]
+    fn test_check_retention_change() {
+        let compliance = ObjectLock {
+            retention: Some(retention(RetentionMode::Compliance, 10)),
+            legal_hold: None,
+        };
+        assert!(
+            compliance
+                .check_retention_change(Some(&retention(RetentionMode::Compliance, 20)), false)
+                .is_ok()
+        );
+        assert!(
+            compliance
+                .check_retention_change(Some(&retention(RetentionMode::Compliance, 5)), true)
+                .is_err()
+        );
+        assert!(
+            compliance
+                .check_retention_change(Some(&retention(RetentionMode::Governance, 20)), true)
+                .is_err()
+        );
+        assert!(compliance.check_retention_change(None, true).is_err());
+
+        let governance = ObjectLock {
+            retention: Some(retention(RetentionMode::Governance, 10)),
+            legal_hold: None,
+        };
+        assert!(governance.check_retention_change(None, false).is_err());
+        assert!(governance.check_retention_change(None, true).is_ok());
+        assert!(
+            governance
+                .check_retention_change(Some(&retention(RetentionMode::Compliance, 10)), false)
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locks.json");
+
+        let store = ObjectLockStore::open(&path).unwrap();
+        store
+            .update("bucket", "key", "null", |lock| {
+                lock.legal_hold = Some(true);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(store.has_locks("bucket", "key").await);
+        assert!(!store.has_locks("other", "key").await);
+
+        let reopened = ObjectLockStore::open(&path).unwrap();
+        assert_eq!(
+            reopened
+                .get("bucket", "key", "null")
+                .await
+                .unwrap()
+                .legal_hold,
+            Some(true)
+        );
+
+        reopened.remove("bucket", "key", "null").await.unwrap();
+        let reopened = ObjectLockStore::open(&path).unwrap();
+        assert!(reopened.get("bucket", "key", "null").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_key_lock_serializes_requests() {
+        let store = ObjectLockStore::in_memory();
+        let guard = store.lock_key("bucket", "key").await;
+
+        // Other keys are not held up
+        drop(store.lock_key("bucket", "other").await);
+
+        let waiting = tokio::spawn({
+            let store = store.clone();
+            async move { drop(store.lock_key("bucket", "key").await) }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(guard);
+        waiting.await.unwrap();
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{app_state::AppState, auth, handlers};
+use crate::{app_state::AppState, auth, cors, handlers};
 use axum::{
     Router,
-    extract::Request,
+    extract::{OriginalUri, Request},
+    http::{Uri, header::HOST, uri::PathAndQuery},
     middleware::{self, Next},
     routing::get,
 };
@@ -11,36 +12,205 @@ use tower_http::trace::TraceLayer;
 ///
 /// This function is used by both main.rs and integration tests to ensure
 /// the same server configuration is used in both production and tests.
-pub fn create_app(app_state: AppState, bucket_name: String) -> Router {
+///
+/// Buckets are matched dynamically: handlers look the bucket name up in the app state and
+/// return NoSuchBucket for names the proxy doesn't serve.
+pub fn create_app(app_state: AppState) -> Router {
     use handlers::{
-        delete_object, get_object, head_bucket, head_object, list_objects, not_found, put_object,
+        delete_bucket, delete_key, get_bucket, get_key, head_bucket, head_object, list_buckets,
+        not_found, post_object, preflight, put_bucket, put_key,
     };
 
-    let bucket_path = format!("/{}", bucket_name);
-    let bucket_path_with_slash = format!("/{}/", bucket_name);
-    let object_path = format!("/{}/{{*key}}", bucket_name);
-
-    Router::new()
-        // Object operations: /{bucket_name}/{key}
+    let router = Router::new()
+        // Object operations: /{bucket}/{key}
         .route(
-            &object_path,
-            get(get_object)
-                .put(put_object)
-                .delete(delete_object)
-                .head(head_object),
+            "/{bucket}/{*key}",
+            get(get_key)
+                .put(put_key)
+                .delete(delete_key)
+                .head(head_object)
+                .options(preflight),
         )
-        // Bucket operations: /{bucket_name} and /{bucket_name}/
-        .route(&bucket_path, get(list_objects).head(head_bucket))
-        .route(&bucket_path_with_slash, get(list_objects).head(head_bucket))
+        // Bucket operations: /{bucket} and /{bucket}/
+        .route(
+            "/{bucket}",
+            get(get_bucket)
+                .put(put_bucket)
+                .post(post_object)
+                .delete(delete_bucket)
+                .head(head_bucket)
+                .options(preflight),
+        )
+        .route(
+            "/{bucket}/",
+            get(get_bucket)
+                .put(put_bucket)
+                .post(post_object)
+                .delete(delete_bucket)
+                .head(head_bucket)
+                .options(preflight),
+        )
+        // Service operations: /
+        .route("/", get(list_buckets))
         // Fallback for 404 Not Found
         .fallback(not_found)
         // Add shared state
         .with_state(app_state.clone())
         // Add authentication middleware (captures app_state)
-        .layer(middleware::from_fn(move |request: Request, next: Next| {
-            let state = app_state.clone();
-            async move { auth::auth_middleware(state, request, next).await }
+        .layer(middleware::from_fn({
+            let app_state = app_state.clone();
+            move |request: Request, next: Next| {
+                let state = app_state.clone();
+                async move { auth::auth_middleware(state, request, next).await }
+            }
         }))
-        // Add tracing
-        .layer(TraceLayer::new_for_http())
+        // Add CORS headers outside authentication, so browsers can read auth errors too
+        .layer(middleware::from_fn({
+            let app_state = app_state.clone();
+            move |request: Request, next: Next| {
+                let state = app_state.clone();
+                async move { cors::cors_middleware(state, request, next).await }
+            }
+        }));
+
+    // Virtual-hosted-style requests must be rewritten before routing, so the rewrite runs
+    // in an outer router whose only job is to forward everything to the path-style router
+    let router = match app_state.base_domain {
+        Some(base_domain) => Router::new()
+            .fallback_service(router)
+            .layer(middleware::map_request(move |request: Request| {
+                let base_domain = base_domain.clone();
+                async move { rewrite_virtual_hosted_request(&base_domain, request) }
+            })),
+        None => router,
+    };
+
+    // Add tracing
+    router.layer(TraceLayer::new_for_http())
+}
+
+/// Rewrite a virtual-hosted-style request (`{bucket}.{base_domain}/{key}`) into path style
+/// (`/{bucket}/{key}`) so it is served by the same handlers
+///
+/// The URI the client sent is kept as [`OriginalUri`], which SigV4 verification uses to
+/// build the canonical request. Requests whose host doesn't name a bucket are left untouched.
+fn rewrite_virtual_hosted_request(base_domain: &str, mut request: Request) -> Request {
+    let host = request
+        .headers()
+        .get(HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| request.uri().host());
+
+    let Some(bucket) = host.and_then(|host| bucket_from_host(host, base_domain)) else {
+        return request;
+    };
+
+    let original_uri = request.uri().clone();
+    let path_and_query = match original_uri.query() {
+        Some(query) => format!("/{}{}?{}", bucket, original_uri.path(), query),
+        None => format!("/{}{}", bucket, original_uri.path()),
+    };
+
+    let mut parts = original_uri.clone().into_parts();
+    parts.path_and_query = match path_and_query.parse::<PathAndQuery>() {
+        Ok(path_and_query) => Some(path_and_query),
+        Err(e) => {
+            tracing::warn!("Failed to rewrite virtual-hosted-style request: {}", e);
+            return request;
+        }
+    };
+
+    match Uri::from_parts(parts) {
+        Ok(uri) => {
+            tracing::debug!(
+                "Rewrote virtual-hosted-style request {} to {}",
+                original_uri,
+                uri
+            );
+            if request.extensions().get::<OriginalUri>().is_none() {
+                request
+                    .extensions_mut()
+                    .insert(OriginalUri(original_uri.clone()));
+            }
+            *request.uri_mut() = uri;
+        }
+        Err(e) => tracing::warn!("Failed to rewrite virtual-hosted-style request: {}", e),
+    }
+
+    request
+}
+
+/// Extract the bucket name from a `{bucket}.{base_domain}` host (port and case are ignored)
+///
+/// Returns None when the host is the base domain itself or doesn't belong to it.
+fn bucket_from_host(host: &str, base_domain: &str) -> Option<String> {
+    let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+    let base_domain = base_domain.trim_end_matches('.').to_ascii_lowercase();
+
+    host.strip_suffix(&base_domain)?
+        .strip_suffix('.')
+        .filter(|bucket| !bucket.is_empty())
+        .map(|bucket| bucket.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[test]
+    fn test_bucket_from_host() {
+        assert_eq!(
+            bucket_from_host("mybucket.s3.example.com", "s3.example.com"),
+            Some("mybucket".to_string())
+        );
+        assert_eq!(
+            bucket_from_host("MyBucket.S3.Example.com:3000", "s3.example.com"),
+            Some("mybucket".to_string())
+        );
+        assert_eq!(
+            bucket_from_host("my.dotted.bucket.s3.example.com", "s3.example.com"),
+            Some("my.dotted.bucket".to_string())
+        );
+    }
+
+    #[test]
+    fn test_bucket_from_host_not_virtual_hosted() {
+        assert_eq!(bucket_from_host("s3.example.com", "s3.example.com"), None);
+        assert_eq!(bucket_from_host("localhost:3000", "s3.example.com"), None);
+        assert_eq!(
+            bucket_from_host("evil-s3.example.com", "s3.example.com"),
+            None
+        );
+        assert_eq!(bucket_from_host(".s3.example.com", "s3.example.com"), None);
+    }
+
+    #[test]
+    fn test_rewrite_virtual_hosted_request() {
+        let request = Request::builder()
+            .uri("/photos/cat.jpg?versionId=1")
+            .header("host", "mybucket.s3.example.com")
+            .body(Body::empty())
+            .unwrap();
+
+        let request = rewrite_virtual_hosted_request("s3.example.com", request);
+
+        assert_eq!(request.uri(), "/mybucket/photos/cat.jpg?versionId=1");
+        let original = request.extensions().get::<OriginalUri>().unwrap();
+        assert_eq!(original.0, "/photos/cat.jpg?versionId=1");
+    }
+
+    #[test]
+    fn test_rewrite_path_style_request_untouched() {
+        let request = Request::builder()
+            .uri("/mybucket/photos/cat.jpg")
+            .header("host", "s3.example.com")
+            .body(Body::empty())
+            .unwrap();
+
+        let request = rewrite_virtual_hosted_request("s3.example.com", request);
+
+        assert_eq!(request.uri(), "/mybucket/photos/cat.jpg");
+        assert!(request.extensions().get::<OriginalUri>().is_none());
+    }
 }
@@ -0,0 +1,905 @@
+//! Storage backend for an Azure Blob Storage container, using the Blob REST API
+//!
+//! Requests are authorized with the storage account's shared key or with a SAS token. Uploads
+//! are sent as a single Put Blob when they fit in one block, and otherwise staged block by
+//! block and committed with Put Block List, so only one block is held in memory.
+
+use super::backend::{
+    ByteRange, DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions,
+    PutObjectOutput, StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use bytes::{Bytes, BytesMut};
+use futures::stream::StreamExt;
+use hmac::{Hmac, Mac};
+use quick_xml::de::from_str as from_xml_str;
+use quick_xml::se::to_string as to_xml_string;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Response, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+
+/// Version of the Blob REST API the requests are written for
+const API_VERSION: &str = "2021-12-02";
+/// Size of the blocks large uploads are staged in
+const BLOCK_SIZE: usize = 8 * 1024 * 1024;
+/// Largest page the List Blobs operation returns
+const MAX_LIST_RESULTS: usize = 5000;
+const META_PREFIX: &str = "x-ms-meta-";
+
+/// How requests to the storage account are authorized
+pub enum AzureCredentials {
+    /// Decoded access key of the storage account
+    SharedKey(Vec<u8>),
+    /// Shared access signature, as a query string
+    Sas(String),
+}
+
+pub struct AzureBackend {
+    client: reqwest::Client,
+    account: String,
+    /// URL of the container, e.g. `https://<account>.blob.core.windows.net/<container>`
+    container_url: Url,
+    credentials: AzureCredentials,
+}
+
+/// Response of the List Blobs operation
+#[derive(Debug, Deserialize)]
+struct EnumerationResults {
+    #[serde(rename = "Blobs", default)]
+    blobs: BlobList,
+    #[serde(rename = "NextMarker", default)]
+    next_marker: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BlobList {
+    #[serde(rename = "Blob", default)]
+    blobs: Vec<BlobItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlobItem {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Properties")]
+    properties: BlobProperties,
+    #[serde(rename = "Metadata", default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlobProperties {
+    #[serde(rename = "Last-Modified")]
+    last_modified: String,
+    #[serde(rename = "Etag")]
+    etag: String,
+    #[serde(rename = "Content-Length")]
+    content_length: u64,
+    #[serde(rename = "Content-Type", default)]
+    content_type: Option<String>,
+}
+
+/// Body of the Get Blob Tags and Set Blob Tags operations
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename = "Tags")]
+struct BlobTags {
+    #[serde(rename = "TagSet", default)]
+    tag_set: BlobTagSet,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BlobTagSet {
+    #[serde(rename = "Tag", default)]
+    tags: Vec<Tag>,
+}
+
+/// Escape a user metadata name into a valid Azure metadata name
+///
+/// Azure metadata names must be C# identifiers, so characters other than ASCII letters and
+/// (non-leading) digits are written as `_xx` in hex, and underscores as `__`.
+fn encode_metadata_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for (idx, byte) in name.bytes().enumerate() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' => encoded.push(byte as char),
+            b'0'..=b'9' if idx > 0 => encoded.push(byte as char),
+            b'_' => encoded.push_str("__"),
+            _ => encoded.push_str(&format!("_{:02x}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Reverse `encode_metadata_name`, keeping names written by other clients as they are
+fn decode_metadata_name(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] != b'_' {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        } else if bytes.get(idx + 1) == Some(&b'_') {
+            decoded.push(b'_');
+            idx += 2;
+        } else {
+            let Some(byte) = name
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            else {
+                return name.to_string();
+            };
+            decoded.push(byte);
+            idx += 3;
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| name.to_string())
+}
+
+/// ETags are quoted in headers but not in listings
+fn quote_etag(etag: &str) -> String {
+    if etag.starts_with('"') {
+        etag.to_string()
+    } else {
+        format!("\"{}\"", etag)
+    }
+}
+
+fn parse_http_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&chrono::Utc))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, S3Error> {
+    HeaderValue::from_str(value)
+        .map_err(|_| S3Error::InvalidArgument(format!("Invalid header value: {}", value)))
+}
+
+fn request_error(e: reqwest::Error) -> S3Error {
+    S3Error::InternalError(format!("Azure request failed: {}", e))
+}
+
+/// String signed for Shared Key authorization
+///
+/// See "Authorize with Shared Key" in the Azure Storage REST API reference.
+fn string_to_sign(
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    content_length: usize,
+    account: &str,
+) -> String {
+    let header = |name: &str| header_str(headers, name).unwrap_or_default();
+    let content_length = match content_length {
+        0 => String::new(),
+        length => length.to_string(),
+    };
+
+    let mut canonical_headers: Vec<(&str, &str)> = headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-ms-"))
+        .map(|(name, value)| (name.as_str(), value.to_str().unwrap_or_default().trim()))
+        .collect();
+    canonical_headers.sort();
+
+    let mut parameters: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, value) in url.query_pairs() {
+        parameters
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(value.into_owned());
+    }
+    let mut canonical_resource = format!("/{}{}", account, url.path());
+    for (name, mut values) in parameters {
+        values.sort();
+        canonical_resource.push_str(&format!("\n{}:{}", name, values.join(",")));
+    }
+
+    let mut string_to_sign = [
+        method.as_str(),
+        header("content-encoding"),
+        header("content-language"),
+        &content_length,
+        header("content-md5"),
+        header("content-type"),
+        // Date is sent as x-ms-date
+        "",
+        header("if-modified-since"),
+        header("if-match"),
+        header("if-none-match"),
+        header("if-unmodified-since"),
+        header("range"),
+    ]
+    .join("\n");
+    string_to_sign.push('\n');
+    for (name, value) in canonical_headers {
+        string_to_sign.push_str(&format!("{}:{}\n", name, value));
+    }
+    string_to_sign.push_str(&canonical_resource);
+    string_to_sign
+}
+
+/// Map an unsuccessful response to an S3 error
+fn response_error(response: &Response) -> S3Error {
+    let status = response.status().as_u16();
+    let code = header_str(response.headers(), "x-ms-error-code");
+    match (status, code) {
+        (404, Some("ContainerNotFound")) => S3Error::NoSuchBucket,
+        (404, _) => S3Error::NoSuchKey,
+        (416, _) => S3Error::InvalidRange,
+        (403, _) => S3Error::AccessDenied,
+        (_, code) => S3Error::InternalError(format!(
+            "Azure returned {} ({})",
+            status,
+            code.unwrap_or("no error code")
+        )),
+    }
+}
+
+fn check_response(response: Response) -> Result<Response, S3Error> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(response_error(&response))
+    }
+}
+
+/// Object metadata from the headers of a Get Blob or Get Blob Properties response
+fn metadata_from_headers(key: &str, headers: &HeaderMap) -> ObjectMetadata {
+    // Ranged responses give the size of the whole blob in Content-Range
+    let size = header_str(headers, "content-range")
+        .and_then(|range| range.rsplit_once('/'))
+        .and_then(|(_, size)| size.parse().ok())
+        .or_else(|| header_str(headers, "content-length").and_then(|size| size.parse().ok()))
+        .unwrap_or(0);
+    let user_metadata = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(META_PREFIX)?;
+            Some((decode_metadata_name(name), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+
+    ObjectMetadata {
+        key: key.to_string(),
+        size,
+        etag: header_str(headers, "etag")
+            .map(quote_etag)
+            .unwrap_or_default(),
+        last_modified: header_str(headers, "last-modified")
+            .and_then(parse_http_date)
+            .unwrap_or_else(chrono::Utc::now),
+        content_type: header_str(headers, "content-type")
+            .unwrap_or("binary/octet-stream")
+            .to_string(),
+        version_id: None,
+        user_metadata,
+    }
+}
+
+impl AzureBackend {
+    /// Create a backend for `container` in a storage account
+    ///
+    /// `endpoint` defaults to `https://<account>.blob.core.windows.net`. For the Azurite
+    /// emulator it is `http://127.0.0.1:10000/<account>`.
+    pub fn new(
+        account: String,
+        container: String,
+        endpoint: Option<String>,
+        credentials: AzureCredentials,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint =
+            endpoint.unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account));
+        let mut container_url = Url::parse(&endpoint)?;
+        container_url
+            .path_segments_mut()
+            .map_err(|_| format!("Invalid Azure endpoint: {}", endpoint))?
+            .pop_if_empty()
+            .push(&container);
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            account,
+            container_url,
+            credentials,
+        })
+    }
+
+    fn blob_url(&self, key: &str) -> Url {
+        let mut url = self.container_url.clone();
+        // The container URL was checked to have path segments
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.extend(key.split('/'));
+        }
+        url
+    }
+
+    /// Send an authorized request
+    async fn send(
+        &self,
+        method: Method,
+        mut url: Url,
+        query: &[(&str, &str)],
+        mut headers: HeaderMap,
+        body: Option<Bytes>,
+    ) -> Result<Response, S3Error> {
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let date = chrono::Utc::now()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        headers.insert("x-ms-date", header_value(&date)?);
+        headers.insert("x-ms-version", HeaderValue::from_static(API_VERSION));
+
+        match &self.credentials {
+            AzureCredentials::SharedKey(account_key) => {
+                let content_length = body.as_ref().map_or(0, Bytes::len);
+                let string_to_sign =
+                    string_to_sign(&method, &url, &headers, content_length, &self.account);
+                let mut mac = Hmac::<Sha256>::new_from_slice(account_key)
+                    .map_err(|e| S3Error::InternalError(format!("Invalid account key: {}", e)))?;
+                mac.update(string_to_sign.as_bytes());
+                let signature = BASE64.encode(mac.finalize().into_bytes());
+                headers.insert(
+                    reqwest::header::AUTHORIZATION,
+                    header_value(&format!("SharedKey {}:{}", self.account, signature))?,
+                );
+            }
+            AzureCredentials::Sas(token) => {
+                let query = match url.query() {
+                    Some(query) => format!("{}&{}", query, token),
+                    None => token.clone(),
+                };
+                url.set_query(Some(&query));
+            }
+        }
+
+        let mut request = self.client.request(method, url).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        request.send().await.map_err(request_error)
+    }
+
+    /// Headers storing the properties of a new blob
+    fn blob_headers(options: &PutObjectOptions) -> Result<HeaderMap, S3Error> {
+        let mut headers = HeaderMap::new();
+        if let Some(content_type) = &options.content_type {
+            headers.insert("x-ms-blob-content-type", header_value(content_type)?);
+        }
+        for (name, value) in &options.user_metadata {
+            let name = format!("{}{}", META_PREFIX, encode_metadata_name(name));
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                S3Error::InvalidArgument(format!("Invalid metadata name: {}", name))
+            })?;
+            headers.insert(name, header_value(value)?);
+        }
+        if !options.tags.is_empty() {
+            let tags: Vec<(&str, &str)> = options
+                .tags
+                .iter()
+                .map(|tag| (tag.key.as_str(), tag.value.as_str()))
+                .collect();
+            let tags = serde_urlencoded::to_string(tags)
+                .map_err(|e| S3Error::InternalError(format!("Failed to encode tags: {}", e)))?;
+            headers.insert("x-ms-tags", header_value(&tags)?);
+        }
+        Ok(headers)
+    }
+
+    /// Upload a blob that was staged block by block
+    async fn put_blocks(
+        &self,
+        key: &str,
+        mut buffer: BytesMut,
+        mut body: ObjectStream,
+        headers: HeaderMap,
+    ) -> Result<Response, S3Error> {
+        let url = self.blob_url(key);
+        let mut block_ids = Vec::new();
+        let mut finished = false;
+        while !finished {
+            while buffer.len() < BLOCK_SIZE {
+                match body.next().await {
+                    Some(chunk) => buffer.extend_from_slice(&chunk?),
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+            while buffer.len() >= BLOCK_SIZE || (finished && !buffer.is_empty()) {
+                let block = buffer.split_to(BLOCK_SIZE.min(buffer.len())).freeze();
+                // Block IDs must all have the same length
+                let block_id = BASE64.encode(format!("{:08}", block_ids.len()));
+                let query = [("comp", "block"), ("blockid", block_id.as_str())];
+                let response = self
+                    .send(
+                        Method::PUT,
+                        url.clone(),
+                        &query,
+                        HeaderMap::new(),
+                        Some(block),
+                    )
+                    .await?;
+                check_response(response)?;
+                block_ids.push(block_id);
+            }
+        }
+
+        let block_list: String = block_ids
+            .iter()
+            .map(|block_id| format!("<Latest>{}</Latest>", block_id))
+            .collect();
+        let block_list = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>",
+            block_list
+        );
+        tracing::debug!("Committing {} blocks to {}", block_ids.len(), key);
+        let response = self
+            .send(
+                Method::PUT,
+                url,
+                &[("comp", "blocklist")],
+                headers,
+                Some(Bytes::from(block_list)),
+            )
+            .await?;
+        check_response(response)
+    }
+
+    async fn set_tags(&self, key: &str, tags: Vec<Tag>) -> Result<(), S3Error> {
+        let body = to_xml_string(&BlobTags {
+            tag_set: BlobTagSet { tags },
+        })
+        .map_err(|e| S3Error::InternalError(format!("Failed to encode tags: {}", e)))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        );
+        let response = self
+            .send(
+                Method::PUT,
+                self.blob_url(key),
+                &[("comp", "tags")],
+                headers,
+                Some(Bytes::from(body)),
+            )
+            .await?;
+        check_response(response)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for AzureBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        let response = self
+            .send(
+                Method::HEAD,
+                self.container_url.clone(),
+                &[("restype", "container")],
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        match response.status().as_u16() {
+            200..=299 => Ok(()),
+            404 => Err(S3Error::NoSuchBucket),
+            _ => Err(response_error(&response)),
+        }
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let max_keys = max_keys.max(0) as usize;
+        let mut objects = Vec::new();
+        let mut marker: Option<String> = None;
+
+        while objects.len() < max_keys {
+            let page_size = (max_keys - objects.len()).min(MAX_LIST_RESULTS).to_string();
+            let mut query = vec![
+                ("restype", "container"),
+                ("comp", "list"),
+                ("include", "metadata"),
+                ("maxresults", page_size.as_str()),
+            ];
+            if let Some(prefix) = prefix {
+                query.push(("prefix", prefix));
+            }
+            if let Some(marker) = &marker {
+                query.push(("marker", marker.as_str()));
+            }
+
+            let response = self
+                .send(
+                    Method::GET,
+                    self.container_url.clone(),
+                    &query,
+                    HeaderMap::new(),
+                    None,
+                )
+                .await?;
+            let body = check_response(response)?
+                .text()
+                .await
+                .map_err(request_error)?;
+            let results: EnumerationResults = from_xml_str(&body).map_err(|e| {
+                S3Error::InternalError(format!("Failed to parse blob listing: {}", e))
+            })?;
+
+            objects.extend(results.blobs.blobs.into_iter().map(|blob| {
+                ObjectMetadata {
+                    key: blob.name,
+                    size: blob.properties.content_length,
+                    etag: quote_etag(&blob.properties.etag),
+                    last_modified: parse_http_date(&blob.properties.last_modified)
+                        .unwrap_or_else(chrono::Utc::now),
+                    content_type: blob
+                        .properties
+                        .content_type
+                        .filter(|content_type| !content_type.is_empty())
+                        .unwrap_or_else(|| "binary/octet-stream".to_string()),
+                    version_id: None,
+                    user_metadata: blob
+                        .metadata
+                        .into_iter()
+                        .map(|(name, value)| (decode_metadata_name(&name), value))
+                        .collect(),
+                }
+            }));
+
+            marker = results.next_marker.filter(|marker| !marker.is_empty());
+            if marker.is_none() {
+                break;
+            }
+        }
+
+        objects.truncate(max_keys);
+        Ok(objects)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Blob versioning isn't used, so bucket versioning was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        let response = self
+            .send(
+                Method::HEAD,
+                self.blob_url(key),
+                &[],
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        let response = check_response(response)?;
+        options.check_single_part()?;
+        Ok(metadata_from_headers(key, response.headers()))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(range) = options.range {
+            // Suffix ranges aren't supported by Azure, so they are resolved against the size
+            let range = match range {
+                ByteRange::Suffix(_) => {
+                    let size = self
+                        .head_object(key, &GetObjectOptions::default())
+                        .await?
+                        .size;
+                    let (start, end) = range.resolve(size)?;
+                    ByteRange::FromTo {
+                        start,
+                        end: Some(end),
+                    }
+                }
+                range => range,
+            };
+            headers.insert("x-ms-range", header_value(&range.header_value())?);
+        }
+
+        let response = self
+            .send(Method::GET, self.blob_url(key), &[], headers, None)
+            .await?;
+        let response = check_response(response)?;
+        options.check_single_part()?;
+        let metadata = metadata_from_headers(key, response.headers());
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(request_error));
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        options.reject_sse_customer("Azure")?;
+        let headers = Self::blob_headers(options)?;
+
+        // Objects that fit in a single block are uploaded in one request
+        let mut buffer = BytesMut::new();
+        while buffer.len() < BLOCK_SIZE {
+            let Some(chunk) = body.next().await else {
+                break;
+            };
+            buffer.extend_from_slice(&chunk?);
+        }
+        let response = if buffer.len() < BLOCK_SIZE {
+            let mut headers = headers;
+            headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+            let response = self
+                .send(
+                    Method::PUT,
+                    self.blob_url(key),
+                    &[],
+                    headers,
+                    Some(buffer.freeze()),
+                )
+                .await?;
+            check_response(response)?
+        } else {
+            self.put_blocks(key, buffer, body, headers).await?
+        };
+
+        Ok(PutObjectOutput {
+            etag: header_str(response.headers(), "etag")
+                .map(quote_etag)
+                .unwrap_or_default(),
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+
+        let response = self
+            .send(
+                Method::DELETE,
+                self.blob_url(key),
+                &[],
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        match check_response(response) {
+            Ok(_) | Err(S3Error::NoSuchKey) => Ok(output),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        check_null_version(version_id)?;
+        let response = self
+            .send(
+                Method::GET,
+                self.blob_url(key),
+                &[("comp", "tags")],
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        let body = check_response(response)?
+            .text()
+            .await
+            .map_err(request_error)?;
+        let tags: BlobTags = from_xml_str(&body)
+            .map_err(|e| S3Error::InternalError(format!("Failed to parse blob tags: {}", e)))?;
+        Ok(tags.tag_set.tags)
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        self.set_tags(key, tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        self.set_tags(key, Vec::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_name_round_trip() {
+        for name in [
+            "owner",
+            "replicat4-original-size",
+            "snake_case",
+            "1st",
+            "ünï",
+        ] {
+            let encoded = encode_metadata_name(name);
+            assert!(
+                encoded
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+            );
+            assert!(!encoded.starts_with(|c: char| c.is_ascii_digit()));
+            assert_eq!(decode_metadata_name(&encoded), name);
+        }
+        assert_eq!(
+            encode_metadata_name("replicat4-original-size"),
+            "replicat4_2doriginal_2dsize"
+        );
+        // Names set by other clients are kept
+        assert_eq!(decode_metadata_name("_my_name"), "_my_name");
+    }
+
+    #[test]
+    fn test_string_to_sign() {
+        let mut url = Url::parse("http://127.0.0.1:10000/devstoreaccount1/photos").unwrap();
+        url.query_pairs_mut().extend_pairs([
+            ("restype", "container"),
+            ("comp", "list"),
+            ("prefix", "a b"),
+        ]);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ms-version", HeaderValue::from_static(API_VERSION));
+        headers.insert(
+            "x-ms-date",
+            HeaderValue::from_static("Mon, 27 Jan 2025 12:00:00 GMT"),
+        );
+        headers.insert("x-ms-range", HeaderValue::from_static("bytes=0-9"));
+
+        assert_eq!(
+            string_to_sign(&Method::GET, &url, &headers, 0, "devstoreaccount1"),
+            "GET\n\n\n\n\n\n\n\n\n\n\n\n\
+             x-ms-date:Mon, 27 Jan 2025 12:00:00 GMT\n\
+             x-ms-range:bytes=0-9\n\
+             x-ms-version:2021-12-02\n\
+             /devstoreaccount1/devstoreaccount1/photos\n\
+             comp:list\n\
+             prefix:a b\n\
+             restype:container"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        );
+        let url = Url::parse("https://account.blob.core.windows.net/photos/a%20b.jpg").unwrap();
+        assert_eq!(
+            string_to_sign(&Method::PUT, &url, &headers, 42, "account"),
+            "PUT\n\n\n42\n\napplication/xml\n\n\n\n\n\n\n/account/photos/a%20b.jpg"
+        );
+    }
+
+    #[test]
+    fn test_parse_blob_listing() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ServiceEndpoint="http://127.0.0.1:10000/devstoreaccount1" ContainerName="photos">
+  <Prefix>2024/</Prefix>
+  <MaxResults>2</MaxResults>
+  <Blobs>
+    <Blob>
+      <Name>2024/jan.jpg</Name>
+      <Properties>
+        <Creation-Time>Mon, 27 Jan 2025 12:00:00 GMT</Creation-Time>
+        <Last-Modified>Mon, 27 Jan 2025 12:00:00 GMT</Last-Modified>
+        <Etag>0x8DD3E7C1A2B3C4D</Etag>
+        <Content-Length>1024</Content-Length>
+        <Content-Type>image/jpeg</Content-Type>
+        <BlobType>BlockBlob</BlobType>
+      </Properties>
+      <Metadata>
+        <owner>alice</owner>
+      </Metadata>
+    </Blob>
+    <Blob>
+      <Name>2024/notes</Name>
+      <Properties>
+        <Last-Modified>Tue, 28 Jan 2025 12:00:00 GMT</Last-Modified>
+        <Etag>0x8DD3E7C1A2B3C4E</Etag>
+        <Content-Length>0</Content-Length>
+        <Content-Type />
+      </Properties>
+      <Metadata />
+    </Blob>
+  </Blobs>
+  <NextMarker>2!88!MDAwMDE</NextMarker>
+</EnumerationResults>"#;
+
+        let results: EnumerationResults = from_xml_str(body).unwrap();
+        assert_eq!(results.next_marker.as_deref(), Some("2!88!MDAwMDE"));
+        assert_eq!(results.blobs.blobs.len(), 2);
+        let blob = &results.blobs.blobs[0];
+        assert_eq!(blob.name, "2024/jan.jpg");
+        assert_eq!(blob.properties.content_length, 1024);
+        assert_eq!(quote_etag(&blob.properties.etag), "\"0x8DD3E7C1A2B3C4D\"");
+        assert_eq!(blob.metadata["owner"], "alice");
+        assert!(parse_http_date(&blob.properties.last_modified).is_some());
+
+        let empty: EnumerationResults =
+            from_xml_str(r#"<EnumerationResults><Blobs /><NextMarker /></EnumerationResults>"#)
+                .unwrap();
+        assert!(empty.blobs.blobs.is_empty());
+        assert!(empty.next_marker.filter(|m| !m.is_empty()).is_none());
+    }
+
+    #[test]
+    fn test_blob_tags_xml() {
+        let tags = BlobTags {
+            tag_set: BlobTagSet {
+                tags: vec![Tag {
+                    key: "env".to_string(),
+                    value: "a&b".to_string(),
+                }],
+            },
+        };
+        let xml = to_xml_string(&tags).unwrap();
+        assert_eq!(
+            xml,
+            "<Tags><TagSet><Tag><Key>env</Key><Value>a&amp;b</Value></Tag></TagSet></Tags>"
+        );
+        let parsed: BlobTags = from_xml_str(&xml).unwrap();
+        assert_eq!(parsed.tag_set.tags, tags.tag_set.tags);
+    }
+}
@@ -1,14 +1,224 @@
-use crate::types::{ObjectMetadata, error::S3Error};
+use crate::types::{
+    NULL_VERSION_ID, ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error,
+};
 use bytes::Bytes;
 use futures::stream::Stream;
+use std::collections::HashMap;
 use std::pin::Pin;
 
 /// Type alias for object data stream (used for both input and output)
 pub type ObjectStream = Pin<Box<dyn Stream<Item = Result<Bytes, S3Error>> + Send>>;
 
+/// Options for reading an object (GET and HEAD)
+#[derive(Debug, Clone, Default)]
+pub struct GetObjectOptions {
+    /// Read this version instead of the latest one
+    pub version_id: Option<String>,
+    /// Read a single part of a multipart object (1-based)
+    pub part_number: Option<u32>,
+    /// Read only these bytes (GET only); the returned metadata still describes the whole object
+    pub range: Option<ByteRange>,
+    /// Customer-provided key the object was encrypted with (SSE-C)
+    pub sse_customer: Option<SseCustomerKey>,
+}
+
+impl GetObjectOptions {
+    /// Check the requested part for an object that was stored in a single part
+    ///
+    /// Backends that never store multipart objects accept part 1, which is the whole object.
+    pub fn check_single_part(&self) -> Result<(), S3Error> {
+        match self.part_number {
+            None | Some(1) => Ok(()),
+            Some(_) => Err(S3Error::InvalidPartNumber),
+        }
+    }
+
+    /// Reject a customer-provided key, for backends whose objects are never stored with one
+    pub fn reject_sse_customer(&self) -> Result<(), S3Error> {
+        SseCustomerKey::check_read(None, self.sse_customer.as_ref())
+    }
+}
+
+/// Byte range of a ranged GET, as sent in the `Range` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `bytes=start-end`, or `bytes=start-` up to the end of the object (inclusive)
+    FromTo { start: u64, end: Option<u64> },
+    /// `bytes=-length`: the last `length` bytes of the object
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parse a `Range` header value
+    ///
+    /// Returns None for values S3 ignores, such as multiple ranges or other units, so the
+    /// whole object is returned instead.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+        match (start.trim(), end.trim()) {
+            ("", length) => Some(ByteRange::Suffix(length.parse().ok()?)),
+            (start, "") => Some(ByteRange::FromTo {
+                start: start.parse().ok()?,
+                end: None,
+            }),
+            (start, end) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+                (start <= end).then_some(ByteRange::FromTo {
+                    start,
+                    end: Some(end),
+                })
+            }
+        }
+    }
+
+    /// First and last byte (inclusive) of the range within an object of `size` bytes
+    pub fn resolve(&self, size: u64) -> Result<(u64, u64), S3Error> {
+        match *self {
+            ByteRange::FromTo { start, end } if start < size => {
+                Ok((start, end.map_or(size - 1, |end| end.min(size - 1))))
+            }
+            ByteRange::Suffix(length) if length > 0 && size > 0 => {
+                Ok((size.saturating_sub(length), size - 1))
+            }
+            _ => Err(S3Error::InvalidRange),
+        }
+    }
+
+    /// Value of the `Range` header for this range
+    pub fn header_value(self) -> String {
+        match self {
+            ByteRange::FromTo {
+                start,
+                end: Some(end),
+            } => format!("bytes={}-{}", start, end),
+            ByteRange::FromTo { start, end: None } => format!("bytes={}-", start),
+            ByteRange::Suffix(length) => format!("bytes=-{}", length),
+        }
+    }
+}
+
+/// Options for storing an object
+#[derive(Debug, Clone, Default)]
+pub struct PutObjectOptions {
+    /// Tags to attach to the new object
+    pub tags: Vec<Tag>,
+    /// Content type to store with the object (the backend's default if None)
+    pub content_type: Option<String>,
+    /// User-defined metadata to store with the object, keyed by lowercase name
+    pub user_metadata: HashMap<String, String>,
+    /// Customer-provided key to encrypt the object with (SSE-C)
+    pub sse_customer: Option<SseCustomerKey>,
+}
+
+impl PutObjectOptions {
+    /// Reject a customer-provided key, for backends that can't encrypt objects with one
+    ///
+    /// `backend` names the kind of backend in the error, e.g. "SQLite".
+    pub fn reject_sse_customer(&self, backend: &str) -> Result<(), S3Error> {
+        match self.sse_customer {
+            Some(_) => Err(S3Error::InvalidRequest(format!(
+                "Customer-provided encryption keys are not supported by the {} backend",
+                backend
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Customer-provided encryption key (SSE-C), forwarded to the backends as received
+#[derive(Clone, PartialEq, Eq)]
+pub struct SseCustomerKey {
+    /// Encryption algorithm, always AES256
+    pub algorithm: String,
+    /// Base64-encoded 256-bit key
+    pub key: String,
+    /// Base64-encoded MD5 digest of the key
+    pub key_md5: String,
+}
+
+impl SseCustomerKey {
+    /// Check the key sent with a read against the key an object was stored with
+    ///
+    /// For backends that keep SSE-C objects themselves; `stored_key_md5` is None for objects
+    /// stored without a customer-provided key.
+    pub fn check_read(
+        stored_key_md5: Option<&str>,
+        requested: Option<&SseCustomerKey>,
+    ) -> Result<(), S3Error> {
+        match (stored_key_md5, requested) {
+            (None, None) => Ok(()),
+            (Some(stored), Some(requested)) if stored == requested.key_md5 => Ok(()),
+            (Some(_), Some(_)) => Err(S3Error::AccessDenied),
+            (Some(_), None) => Err(S3Error::InvalidRequest(
+                "The object was stored using a form of Server Side Encryption. The correct parameters must be provided to retrieve the object."
+                    .to_string(),
+            )),
+            (None, Some(_)) => Err(S3Error::InvalidRequest(
+                "The encryption parameters are not applicable to this object.".to_string(),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Debug for SseCustomerKey {
+    // Keep the key itself out of logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseCustomerKey")
+            .field("algorithm", &self.algorithm)
+            .field("key_md5", &self.key_md5)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Result of storing an object
+#[derive(Debug, Clone)]
+pub struct PutObjectOutput {
+    pub etag: String,
+    /// Version created by the write, None if versioning was never enabled
+    pub version_id: Option<String>,
+}
+
+/// Result of deleting an object
+#[derive(Debug, Clone, Default)]
+pub struct DeleteObjectOutput {
+    /// Version that was deleted, or the version of the delete marker that was created
+    pub version_id: Option<String>,
+    /// Whether the deleted version, or the version that was created, is a delete marker
+    pub delete_marker: bool,
+}
+
+/// Check that a request refers to the only version an object has, for backends without versioning
+pub fn check_null_version(version_id: Option<&str>) -> Result<(), S3Error> {
+    match version_id {
+        None | Some(NULL_VERSION_ID) => Ok(()),
+        Some(_) => Err(S3Error::NoSuchVersion),
+    }
+}
+
+/// List objects as their single "null" version, for backends without versioning
+pub fn null_versions(objects: Vec<ObjectMetadata>) -> Vec<ObjectVersion> {
+    objects
+        .into_iter()
+        .map(|mut metadata| {
+            metadata.version_id = Some(NULL_VERSION_ID.to_string());
+            ObjectVersion {
+                metadata,
+                is_latest: true,
+                is_delete_marker: false,
+            }
+        })
+        .collect()
+}
+
 /// Storage backend trait - implement this for different storage backends
 #[async_trait::async_trait]
 pub trait StorageBackend: Send + Sync {
+    /// Whether the backend only serves reads
+    /// Writes are not replicated to read-only backends
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
     // Bucket-level operations
 
     /// Check if bucket exists and user has access to it
@@ -23,21 +233,198 @@ pub trait StorageBackend: Send + Sync {
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error>;
 
+    /// List objects whose keys sort strictly after `start_after`, so that a
+    /// caller can page through a listing by passing the last key it saw
+    /// The default lists everything and skips ahead, which backends with a
+    /// native start-after override
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let mut objects = self.list_objects(prefix, i32::MAX).await?;
+        objects.retain(|object| object.key.as_str() > start_after);
+        objects.truncate(max_keys.max(0) as usize);
+        Ok(objects)
+    }
+
+    /// Get the versioning state of the bucket
+    /// Returns None if versioning has never been configured
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        Err(S3Error::NotImplemented(
+            "Versioning is not supported by this backend".to_string(),
+        ))
+    }
+
+    /// Enable or suspend versioning for the bucket
+    async fn put_bucket_versioning(&self, _status: VersioningStatus) -> Result<(), S3Error> {
+        Err(S3Error::NotImplemented(
+            "Versioning is not supported by this backend".to_string(),
+        ))
+    }
+
+    /// List all versions and delete markers with optional prefix filtering
+    /// Returns versions sorted by key, newest version first, limited by max_keys
+    async fn list_object_versions(
+        &self,
+        _prefix: Option<&str>,
+        _max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        Err(S3Error::NotImplemented(
+            "Versioning is not supported by this backend".to_string(),
+        ))
+    }
+
     // Object-level operations
 
     /// Get object metadata without retrieving the object body
     /// Returns object metadata if found, Err(S3Error::NoSuchKey) otherwise
-    async fn head_object(&self, key: &str) -> Result<ObjectMetadata, S3Error>;
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error>;
 
     /// Get an object as a stream of bytes along with its metadata
     /// Returns a tuple of (stream, metadata) if found, Err(S3Error::NoSuchKey) otherwise
-    async fn get_object(&self, key: &str) -> Result<(ObjectStream, ObjectMetadata), S3Error>;
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error>;
 
     /// Store an object from a streaming body
-    /// Returns the ETag of the stored object
-    async fn put_object(&self, key: &str, body: ObjectStream) -> Result<String, S3Error>;
+    /// Returns the ETag and version of the stored object
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error>;
+
+    /// Delete an object, or a single version of it, from storage
+    /// Returns success regardless of whether the object existed (idempotent)
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error>;
+
+    /// Get the tags of an object, or of a single version of it
+    /// Returns Err(S3Error::NoSuchKey) if the object doesn't exist
+    async fn get_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        Err(S3Error::NotImplemented(
+            "Object tagging is not supported by this backend".to_string(),
+        ))
+    }
+
+    /// Replace the tags of an object, or of a single version of it
+    async fn put_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+        _tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        Err(S3Error::NotImplemented(
+            "Object tagging is not supported by this backend".to_string(),
+        ))
+    }
+
+    /// Remove all tags from an object, or from a single version of it
+    async fn delete_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        Err(S3Error::NotImplemented(
+            "Object tagging is not supported by this backend".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(
+            ByteRange::parse("bytes=0-99"),
+            Some(ByteRange::FromTo {
+                start: 0,
+                end: Some(99)
+            })
+        );
+        assert_eq!(
+            ByteRange::parse("bytes=100-"),
+            Some(ByteRange::FromTo {
+                start: 100,
+                end: None
+            })
+        );
+        assert_eq!(ByteRange::parse("bytes=-10"), Some(ByteRange::Suffix(10)));
+
+        // Ignored, the whole object is returned
+        assert_eq!(ByteRange::parse("bytes=0-1,5-6"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+        assert_eq!(ByteRange::parse("bytes=9-1"), None);
+    }
+
+    #[test]
+    fn test_resolve_byte_range() {
+        let range = |value| ByteRange::parse(value).unwrap();
+        assert_eq!(range("bytes=0-99").resolve(50).unwrap(), (0, 49));
+        assert_eq!(range("bytes=10-").resolve(50).unwrap(), (10, 49));
+        assert_eq!(range("bytes=-10").resolve(50).unwrap(), (40, 49));
+        assert_eq!(range("bytes=-100").resolve(50).unwrap(), (0, 49));
+
+        assert!(matches!(
+            range("bytes=50-").resolve(50),
+            Err(S3Error::InvalidRange)
+        ));
+        assert!(matches!(
+            range("bytes=-10").resolve(0),
+            Err(S3Error::InvalidRange)
+        ));
+    }
+
+    #[test]
+    fn test_reject_sse_customer() {
+        let key = SseCustomerKey {
+            algorithm: "AES256".to_string(),
+            key: "a2V5".to_string(),
+            key_md5: "bWQ1".to_string(),
+        };
+        assert!(GetObjectOptions::default().reject_sse_customer().is_ok());
+        assert!(
+            PutObjectOptions::default()
+                .reject_sse_customer("SQLite")
+                .is_ok()
+        );
 
-    /// Delete an object from storage
-    /// Returns Ok(()) regardless of whether the object existed (idempotent)
-    async fn delete_object(&self, key: &str) -> Result<(), S3Error>;
+        let get = GetObjectOptions {
+            sse_customer: Some(key.clone()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            get.reject_sse_customer(),
+            Err(S3Error::InvalidRequest(_))
+        ));
+        let put = PutObjectOptions {
+            sse_customer: Some(key),
+            ..Default::default()
+        };
+        match put.reject_sse_customer("SQLite") {
+            Err(S3Error::InvalidRequest(message)) => assert_eq!(
+                message,
+                "Customer-provided encryption keys are not supported by the SQLite backend"
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
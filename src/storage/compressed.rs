@@ -0,0 +1,608 @@
+//! Transparent zstd compression of objects before they reach a backend
+//!
+//! Objects whose content type is on the allowlist are compressed on PUT. The size and ETag
+//! of the uncompressed object are stored in the object's user metadata, so GET, HEAD and
+//! listings show the object as the client uploaded it. Objects without that metadata (other
+//! content types, or objects written before compression was enabled) are passed through.
+//!
+//! The metadata is sent before the body, so the compressed body is staged until the whole
+//! upload has been read (see [`Staging`]).
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend,
+};
+use super::staging::Staging;
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use md5::{Digest, Md5};
+use std::io::Write;
+use std::sync::Arc;
+
+/// User metadata marking a compressed object, with the algorithm as value
+const COMPRESSION_KEY: &str = "replicat4-compression";
+const ORIGINAL_SIZE_KEY: &str = "replicat4-original-size";
+const ORIGINAL_ETAG_KEY: &str = "replicat4-original-etag";
+const ALGORITHM: &str = "zstd";
+/// HEAD requests in flight when completing a listing
+const LIST_CONCURRENCY: usize = 16;
+
+/// Storage backend wrapper that zstd-compresses objects of the allowed content types
+pub struct CompressedBackend {
+    inner: Arc<dyn StorageBackend>,
+    level: i32,
+    /// Content types to compress, either exact (`application/json`) or by type (`text/*`)
+    content_types: Vec<String>,
+}
+
+impl CompressedBackend {
+    pub fn new(inner: Arc<dyn StorageBackend>, level: i32, content_types: Vec<String>) -> Self {
+        Self {
+            inner,
+            level,
+            content_types: content_types
+                .into_iter()
+                .map(|content_type| content_type.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Whether objects of this content type are compressed
+    fn should_compress(&self, content_type: Option<&str>) -> bool {
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        // Parameters such as charset don't matter
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        self.content_types.iter().any(|allowed| {
+            if allowed == "*/*" {
+                return true;
+            }
+            match allowed.strip_suffix("/*") {
+                Some(prefix) => mime
+                    .split_once('/')
+                    .is_some_and(|(mime_type, _)| mime_type == prefix),
+                None => *allowed == mime,
+            }
+        })
+    }
+
+    /// Complete listed metadata with the original size and ETag of compressed objects
+    ///
+    /// Listings don't include user metadata, so every object needs a HEAD.
+    async fn restore_listed(&self, metadata: &mut ObjectMetadata) {
+        let options = GetObjectOptions {
+            version_id: metadata.version_id.clone(),
+            ..Default::default()
+        };
+        match self.inner.head_object(&metadata.key, &options).await {
+            Ok(mut head) => {
+                if let Ok(true) = restore_metadata(&mut head) {
+                    metadata.size = head.size;
+                    metadata.etag = head.etag;
+                }
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "Failed to check compression of listed object {}: {}",
+                    metadata.key,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Restore every object of a listing, a few HEADs at a time
+    async fn restore_listing(&self, objects: Vec<ObjectMetadata>) -> Vec<ObjectMetadata> {
+        stream::iter(objects)
+            .map(|mut metadata| async move {
+                self.restore_listed(&mut metadata).await;
+                metadata
+            })
+            .buffered(LIST_CONCURRENCY)
+            .collect()
+            .await
+    }
+}
+
+/// Replace the stored size and ETag of a compressed object with the original ones
+///
+/// Returns whether the object is compressed.
+fn restore_metadata(metadata: &mut ObjectMetadata) -> Result<bool, S3Error> {
+    match metadata
+        .user_metadata
+        .get(COMPRESSION_KEY)
+        .map(String::as_str)
+    {
+        None => return Ok(false),
+        Some(ALGORITHM) => {}
+        Some(algorithm) => {
+            return Err(S3Error::InternalError(format!(
+                "Object is compressed with unsupported algorithm {}",
+                algorithm
+            )));
+        }
+    }
+
+    let size = metadata
+        .user_metadata
+        .remove(ORIGINAL_SIZE_KEY)
+        .and_then(|size| size.parse().ok());
+    let etag = metadata.user_metadata.remove(ORIGINAL_ETAG_KEY);
+    let (Some(size), Some(etag)) = (size, etag) else {
+        return Err(S3Error::InternalError(
+            "Compressed object is missing its original size or ETag".to_string(),
+        ));
+    };
+    metadata.user_metadata.remove(COMPRESSION_KEY);
+    metadata.size = size;
+    metadata.etag = etag;
+    Ok(true)
+}
+
+fn io_error(e: std::io::Error) -> S3Error {
+    S3Error::InternalError(format!("Compression failed: {}", e))
+}
+
+/// Compressed upload with the size and ETag of the original body
+struct CompressedUpload {
+    body: ObjectStream,
+    size: u64,
+    etag: String,
+}
+
+/// Compress an upload, computing its size and MD5 ETag on the way
+async fn compress(mut body: ObjectStream, level: i32) -> Result<CompressedUpload, S3Error> {
+    let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), level).map_err(io_error)?;
+    let mut staging = Staging::new();
+    let mut md5 = Md5::new();
+    let mut size = 0u64;
+
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        md5.update(&chunk);
+        size += chunk.len() as u64;
+        encoder.write_all(&chunk).map_err(io_error)?;
+        staging
+            .write(std::mem::take(encoder.get_mut()).into())
+            .await?;
+    }
+    staging
+        .write(encoder.finish().map_err(io_error)?.into())
+        .await?;
+
+    Ok(CompressedUpload {
+        body: staging.into_stream().await?,
+        size,
+        etag: format!("\"{}\"", hex::encode(md5.finalize())),
+    })
+}
+
+struct DecompressState {
+    input: ObjectStream,
+    decoder: zstd::stream::write::Decoder<'static, Vec<u8>>,
+    /// Decompressed bytes to drop before the requested range
+    skip: u64,
+    /// Decompressed bytes left to return
+    remaining: u64,
+    done: bool,
+}
+
+impl DecompressState {
+    /// Take the output decompressed so far, trimmed to the requested range
+    fn take_output(&mut self) -> Bytes {
+        let output = Bytes::from(std::mem::take(self.decoder.get_mut()));
+        let start = self.skip.min(output.len() as u64);
+        let end = (start + self.remaining).min(output.len() as u64);
+        self.skip -= start;
+        self.remaining -= end - start;
+        output.slice(start as usize..end as usize)
+    }
+}
+
+/// Decompress a stored object, returning `length` bytes starting at `start`
+fn decompress_stream(
+    input: ObjectStream,
+    start: u64,
+    length: u64,
+) -> Result<ObjectStream, S3Error> {
+    let state = DecompressState {
+        input,
+        decoder: zstd::stream::write::Decoder::new(Vec::new()).map_err(io_error)?,
+        skip: start,
+        remaining: length,
+        done: false,
+    };
+    Ok(Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done || state.remaining == 0 {
+                return None;
+            }
+            match state.input.next().await {
+                Some(Ok(chunk)) => {
+                    let written = state
+                        .decoder
+                        .write_all(&chunk)
+                        .and_then(|_| state.decoder.flush());
+                    if let Err(e) = written {
+                        state.done = true;
+                        return Some((Err(io_error(e)), state));
+                    }
+                    let output = state.take_output();
+                    if !output.is_empty() {
+                        return Some((Ok(output), state));
+                    }
+                }
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+                None => {
+                    state.done = true;
+                    let error =
+                        S3Error::InternalError("Compressed object is truncated".to_string());
+                    return Some((Err(error), state));
+                }
+            }
+        }
+    })))
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for CompressedBackend {
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        self.inner.head_bucket().await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let objects = self.inner.list_objects(prefix, max_keys).await?;
+        Ok(self.restore_listing(objects).await)
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let objects = self
+            .inner
+            .list_objects_after(prefix, start_after, max_keys)
+            .await?;
+        Ok(self.restore_listing(objects).await)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        self.inner.get_bucket_versioning().await
+    }
+
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        self.inner.put_bucket_versioning(status).await
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let versions = self.inner.list_object_versions(prefix, max_keys).await?;
+        Ok(stream::iter(versions)
+            .map(|mut version| async move {
+                if !version.is_delete_marker {
+                    self.restore_listed(&mut version.metadata).await;
+                }
+                version
+            })
+            .buffered(LIST_CONCURRENCY)
+            .collect()
+            .await)
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        let mut metadata = self.inner.head_object(key, options).await?;
+        restore_metadata(&mut metadata)?;
+        Ok(metadata)
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        let Some(range) = &options.range else {
+            let (stream, mut metadata) = self.inner.get_object(key, options).await?;
+            if !restore_metadata(&mut metadata)? {
+                return Ok((stream, metadata));
+            }
+            let stream = decompress_stream(stream, 0, metadata.size)?;
+            return Ok((stream, metadata));
+        };
+
+        // Only uncompressed objects can be read by range from the backend
+        let head_options = GetObjectOptions {
+            range: None,
+            ..options.clone()
+        };
+        let mut metadata = self.inner.head_object(key, &head_options).await?;
+        let version_options = GetObjectOptions {
+            version_id: metadata
+                .version_id
+                .clone()
+                .or_else(|| options.version_id.clone()),
+            ..options.clone()
+        };
+        if !restore_metadata(&mut metadata)? {
+            return self.inner.get_object(key, &version_options).await;
+        }
+
+        // A compressed object is decompressed from the start up to the end of the range
+        let (start, end) = range.resolve(metadata.size)?;
+        let whole_options = GetObjectOptions {
+            range: None,
+            ..version_options
+        };
+        let (stream, _) = self.inner.get_object(key, &whole_options).await?;
+        let stream = decompress_stream(stream, start, end + 1 - start)?;
+        Ok((stream, metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        if !self.should_compress(options.content_type.as_deref()) {
+            return self.inner.put_object(key, body, options).await;
+        }
+
+        let upload = compress(body, self.level).await?;
+        let mut options = options.clone();
+        options
+            .user_metadata
+            .insert(COMPRESSION_KEY.to_string(), ALGORITHM.to_string());
+        options
+            .user_metadata
+            .insert(ORIGINAL_SIZE_KEY.to_string(), upload.size.to_string());
+        options
+            .user_metadata
+            .insert(ORIGINAL_ETAG_KEY.to_string(), upload.etag.clone());
+
+        let output = self.inner.put_object(key, upload.body, &options).await?;
+        Ok(PutObjectOutput {
+            etag: upload.etag,
+            version_id: output.version_id,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        self.inner.delete_object(key, version_id).await
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        self.inner.get_object_tagging(key, version_id).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        self.inner.put_object_tagging(key, version_id, tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.inner.delete_object_tagging(key, version_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ByteRange, InMemoryStorage, staging::SPILL_THRESHOLD};
+    use futures::TryStreamExt;
+
+    fn bytes_to_stream(data: Bytes) -> ObjectStream {
+        Box::pin(stream::once(async move { Ok(data) }))
+    }
+
+    async fn collect(stream: ObjectStream) -> Result<Vec<u8>, S3Error> {
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        Ok(chunks.concat())
+    }
+
+    fn log_data(lines: usize) -> Bytes {
+        (0..lines)
+            .map(|i| format!("2026-10-18T12:00:00Z INFO request {} served in 3ms\n", i))
+            .collect::<String>()
+            .into()
+    }
+
+    fn compressed(inner: &Arc<InMemoryStorage>) -> CompressedBackend {
+        CompressedBackend::new(
+            inner.clone(),
+            3,
+            vec!["text/*".to_string(), "application/json".to_string()],
+        )
+    }
+
+    fn put_options(content_type: &str) -> PutObjectOptions {
+        PutObjectOptions {
+            content_type: Some(content_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_should_compress() {
+        let backend = compressed(&Arc::new(InMemoryStorage::new()));
+        assert!(backend.should_compress(Some("text/plain; charset=utf-8")));
+        assert!(backend.should_compress(Some("Application/JSON")));
+        assert!(!backend.should_compress(Some("application/jsonl")));
+        assert!(!backend.should_compress(Some("image/png")));
+        assert!(!backend.should_compress(None));
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = compressed(&inner);
+        let data = log_data(1000);
+
+        let output = backend
+            .put_object(
+                "app.log",
+                bytes_to_stream(data.clone()),
+                &put_options("text/plain"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output.etag,
+            format!("\"{}\"", hex::encode(Md5::digest(&data)))
+        );
+
+        // The backend stores far fewer bytes
+        let stored = inner
+            .head_object("app.log", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert!(stored.size * 10 < data.len() as u64);
+
+        let head = backend
+            .head_object("app.log", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(head.size, data.len() as u64);
+        assert_eq!(head.etag, output.etag);
+        assert!(head.user_metadata.is_empty());
+
+        let (stream, metadata) = backend
+            .get_object("app.log", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, data.len() as u64);
+        assert_eq!(collect(stream).await.unwrap(), data);
+
+        let listed = backend.list_objects(None, 100).await.unwrap();
+        assert_eq!(listed[0].size, data.len() as u64);
+        assert_eq!(listed[0].etag, output.etag);
+    }
+
+    #[tokio::test]
+    async fn test_other_content_types_are_stored_as_is() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = compressed(&inner);
+        let data = log_data(100);
+
+        backend
+            .put_object(
+                "image.png",
+                bytes_to_stream(data.clone()),
+                &put_options("image/png"),
+            )
+            .await
+            .unwrap();
+        let stored = inner
+            .head_object("image.png", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(stored.size, data.len() as u64);
+
+        let (stream, _) = backend
+            .get_object("image.png", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(collect(stream).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_ranged_reads() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = compressed(&inner);
+        let data = log_data(5000);
+        backend
+            .put_object(
+                "app.log",
+                bytes_to_stream(data.clone()),
+                &put_options("text/plain"),
+            )
+            .await
+            .unwrap();
+
+        // Past the end of the compressed object, but within the original one
+        let start = data.len() - 100;
+        let options = GetObjectOptions {
+            range: Some(ByteRange::FromTo {
+                start: start as u64,
+                end: Some(start as u64 + 49),
+            }),
+            ..Default::default()
+        };
+        let (stream, metadata) = backend.get_object("app.log", &options).await.unwrap();
+        assert_eq!(metadata.size, data.len() as u64);
+        assert_eq!(collect(stream).await.unwrap(), &data[start..start + 50]);
+
+        let options = GetObjectOptions {
+            range: Some(ByteRange::Suffix(10)),
+            ..Default::default()
+        };
+        let (stream, _) = backend.get_object("app.log", &options).await.unwrap();
+        assert_eq!(collect(stream).await.unwrap(), &data[data.len() - 10..]);
+    }
+
+    #[tokio::test]
+    async fn test_large_upload_is_staged_in_a_file() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = compressed(&inner);
+        // Random-looking data barely compresses, so it goes past the spill threshold
+        let data: Bytes = (0..SPILL_THRESHOLD as u64 + 1024)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+
+        backend
+            .put_object(
+                "big.txt",
+                bytes_to_stream(data.clone()),
+                &put_options("text/plain"),
+            )
+            .await
+            .unwrap();
+        let (stream, _) = backend
+            .get_object("big.txt", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(collect(stream).await.unwrap(), data);
+    }
+}
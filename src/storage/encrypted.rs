@@ -0,0 +1,991 @@
+//! Envelope encryption of objects before they reach a backend
+//!
+//! Each object is encrypted with its own random data key, stored in a header in front of the
+//! object after being wrapped with the configured master key. The body is split into chunks
+//! sealed with AES-256-GCM under a nonce made of a per-object prefix, the chunk index and a
+//! final-chunk flag, so chunks can't be reordered or dropped and the object can't be
+//! truncated without failing authentication. Ranged reads fetch and decrypt only the chunks
+//! covering the range.
+//!
+//! The object key is authenticated along with the wrapped data key, so a body moved to
+//! another key fails to decrypt. Versions of the same key are not told apart, since the
+//! backend only assigns the version ID once the object is written.
+//!
+//! Every write uses a fresh data key, so the ciphertext of an object differs between
+//! replicas. The MD5 ETag of the plaintext is sealed under the data key at the end of the
+//! header and returned instead of the backend's. It is only known once the whole upload has
+//! been read, so the encrypted body is staged until then (see [`Staging`]), and HEADs and
+//! listings read the header of each object.
+//!
+//! Layout of a stored object:
+//!
+//! ```text
+//! "RT4E" | version (1) | chunk nonce prefix (7) | key nonce (12) | wrapped data key (48)
+//! sealed ETag (16 + 16-byte tag)
+//! chunk 0 (64 KiB + 16-byte tag) | chunk 1 | ... | final chunk (0 to 64 KiB + tag)
+//! ```
+
+use super::backend::{
+    ByteRange, DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions,
+    PutObjectOutput, StorageBackend,
+};
+use super::staging::Staging;
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit, OsRng, Payload, rand_core::RngCore},
+};
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, StreamExt};
+use md5::{Digest, Md5};
+use std::sync::{Arc, Mutex};
+
+/// Plaintext bytes per chunk
+const CHUNK_SIZE: u64 = 64 * 1024;
+/// Authentication tag appended to every chunk and to the wrapped data key
+const TAG_SIZE: u64 = 16;
+/// Size of a full chunk once sealed
+const SEALED_CHUNK_SIZE: u64 = CHUNK_SIZE + TAG_SIZE;
+
+const MAGIC: &[u8; 4] = b"RT4E";
+const FORMAT_VERSION: u8 = 2;
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const NONCE_PREFIX_SIZE: usize = 7;
+/// Size of an MD5 digest
+const ETAG_SIZE: usize = 16;
+/// Header fields authenticated, with the object key, along with the wrapped data key and ETag
+const HEADER_AAD_SIZE: usize = MAGIC.len() + 1 + NONCE_PREFIX_SIZE;
+/// Header up to the sealed ETag
+const KEY_HEADER_SIZE: usize = HEADER_AAD_SIZE + NONCE_SIZE + KEY_SIZE + TAG_SIZE as usize;
+const HEADER_SIZE: u64 = (KEY_HEADER_SIZE + ETAG_SIZE) as u64 + TAG_SIZE;
+/// Final nonce byte of the sealed ETag, chunks use 0 and 1
+const ETAG_NONCE_FLAG: u8 = 2;
+
+/// Header reads in flight when completing a listing
+const LIST_CONCURRENCY: usize = 16;
+
+/// Storage backend wrapper that encrypts objects with a per-object data key
+///
+/// The wrapped backend only ever sees ciphertext. Object sizes and ETags reported by the
+/// wrapper are those of the plaintext.
+pub struct EncryptedBackend {
+    inner: Arc<dyn StorageBackend>,
+    master_key: Aes256Gcm,
+}
+
+impl EncryptedBackend {
+    pub fn new(inner: Arc<dyn StorageBackend>, master_key: &[u8; KEY_SIZE]) -> Self {
+        Self {
+            inner,
+            master_key: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(master_key)),
+        }
+    }
+
+    /// Fetch and unwrap the header of a stored object
+    ///
+    /// Returns the data key, the plaintext ETag and the metadata of the stored object.
+    async fn read_object_header(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(DataKey, String, ObjectMetadata), S3Error> {
+        let header_options = GetObjectOptions {
+            range: Some(ByteRange::FromTo {
+                start: 0,
+                end: Some(HEADER_SIZE - 1),
+            }),
+            ..options.clone()
+        };
+        let (mut stream, metadata) = self.inner.get_object(key, &header_options).await?;
+        let (header, _) = read_header(&mut stream).await?;
+        let (data_key, etag) = DataKey::unwrap(&self.master_key, key, &header)?;
+        Ok((data_key, etag, metadata))
+    }
+
+    /// Complete listed metadata with the plaintext size and ETag
+    ///
+    /// Listings only carry the stored size, the ETag comes from the header of each object.
+    async fn restore_listed(&self, metadata: &mut ObjectMetadata) {
+        metadata.size = plaintext_size(metadata.size);
+        let options = GetObjectOptions {
+            version_id: metadata.version_id.clone(),
+            ..Default::default()
+        };
+        match self.read_object_header(&metadata.key, &options).await {
+            Ok((_, etag, _)) => metadata.etag = etag,
+            Err(e) => {
+                tracing::debug!(
+                    "Failed to get the plaintext ETag of listed object {}: {}",
+                    metadata.key,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Restore every object of a listing, a few HEADs at a time
+    async fn restore_listing(&self, objects: Vec<ObjectMetadata>) -> Vec<ObjectMetadata> {
+        stream::iter(objects)
+            .map(|mut metadata| async move {
+                self.restore_listed(&mut metadata).await;
+                metadata
+            })
+            .buffered(LIST_CONCURRENCY)
+            .collect()
+            .await
+    }
+}
+
+/// Authenticated data of an object header: its leading fields followed by the object key
+fn header_aad(fields: &[u8], object_key: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(fields.len() + object_key.len());
+    aad.extend_from_slice(fields);
+    aad.extend_from_slice(object_key.as_bytes());
+    aad
+}
+
+/// Data key of a single object, with the nonce prefix of its chunks
+#[derive(Clone)]
+struct DataKey {
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+}
+
+impl DataKey {
+    /// Generate the key of a new object, along with the header to store in front of it
+    ///
+    /// The header still lacks the sealed ETag, see [`DataKey::seal_etag`].
+    fn generate(master_key: &Aes256Gcm, object_key: &str) -> Result<(Self, BytesMut), S3Error> {
+        let mut key = [0u8; KEY_SIZE];
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+        let mut key_nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut key);
+        OsRng.fill_bytes(&mut nonce_prefix);
+        OsRng.fill_bytes(&mut key_nonce);
+
+        let mut header = BytesMut::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&[FORMAT_VERSION]);
+        header.extend_from_slice(&nonce_prefix);
+        let wrapped_key = master_key
+            .encrypt(
+                Nonce::from_slice(&key_nonce),
+                Payload {
+                    msg: &key,
+                    aad: &header_aad(&header, object_key),
+                },
+            )
+            .map_err(|_| S3Error::InternalError("Failed to wrap the data key".to_string()))?;
+        header.extend_from_slice(&key_nonce);
+        header.extend_from_slice(&wrapped_key);
+
+        let data_key = Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            nonce_prefix,
+        };
+        Ok((data_key, header))
+    }
+
+    /// Seal the MD5 digest of the plaintext, completing the header of a new object
+    fn seal_etag(
+        &self,
+        object_key: &str,
+        header: &mut BytesMut,
+        digest: &[u8],
+    ) -> Result<(), S3Error> {
+        let nonce = self.nonce(0, ETAG_NONCE_FLAG);
+        let sealed = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: digest,
+                    aad: &header_aad(&header[..HEADER_AAD_SIZE], object_key),
+                },
+            )
+            .map_err(|_| S3Error::InternalError("Failed to seal the ETag".to_string()))?;
+        header.extend_from_slice(&sealed);
+        Ok(())
+    }
+
+    /// Unwrap the data key stored in the header of the object at `object_key`, along with
+    /// the plaintext ETag sealed in it
+    fn unwrap(
+        master_key: &Aes256Gcm,
+        object_key: &str,
+        header: &[u8],
+    ) -> Result<(Self, String), S3Error> {
+        if header.len() as u64 != HEADER_SIZE
+            || !header.starts_with(MAGIC)
+            || header[MAGIC.len()] != FORMAT_VERSION
+        {
+            return Err(S3Error::InternalError(
+                "Object was not stored by the encryption layer".to_string(),
+            ));
+        }
+
+        let (fields, rest) = header.split_at(HEADER_AAD_SIZE);
+        let (key_nonce, rest) = rest.split_at(NONCE_SIZE);
+        let (wrapped_key, sealed_etag) =
+            rest.split_at(KEY_HEADER_SIZE - HEADER_AAD_SIZE - NONCE_SIZE);
+        let aad = header_aad(fields, object_key);
+        let key = master_key
+            .decrypt(
+                Nonce::from_slice(key_nonce),
+                Payload {
+                    msg: wrapped_key,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                tracing::error!(
+                    "Failed to unwrap the data key of encrypted object {}",
+                    object_key
+                );
+                S3Error::InternalError("Failed to unwrap the data key of the object".to_string())
+            })?;
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+        nonce_prefix.copy_from_slice(&fields[MAGIC.len() + 1..]);
+        let data_key = Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            nonce_prefix,
+        };
+
+        let nonce = data_key.nonce(0, ETAG_NONCE_FLAG);
+        let digest = data_key
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: sealed_etag,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                tracing::error!(
+                    "The ETag of encrypted object {} failed authentication",
+                    object_key
+                );
+                S3Error::InternalError("Failed to decrypt the ETag of the object".to_string())
+            })?;
+        Ok((data_key, format!("\"{}\"", hex::encode(digest))))
+    }
+
+    fn nonce(&self, index: u32, flag: u8) -> [u8; NONCE_SIZE] {
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&index.to_be_bytes());
+        nonce[NONCE_SIZE - 1] = flag;
+        nonce
+    }
+
+    fn chunk_nonce(&self, index: u64, last: bool) -> Result<[u8; NONCE_SIZE], S3Error> {
+        let index = u32::try_from(index).map_err(|_| {
+            S3Error::InternalError("Object is too large for the encryption layer".to_string())
+        })?;
+        Ok(self.nonce(index, u8::from(last)))
+    }
+
+    fn seal_chunk(&self, index: u64, last: bool, plaintext: &[u8]) -> Result<Bytes, S3Error> {
+        let nonce = self.chunk_nonce(index, last)?;
+        self.cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map(Bytes::from)
+            .map_err(|_| S3Error::InternalError("Failed to encrypt object".to_string()))
+    }
+
+    fn open_chunk(&self, index: u64, last: bool, ciphertext: &[u8]) -> Result<Bytes, S3Error> {
+        let nonce = self.chunk_nonce(index, last)?;
+        self.cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext)
+            .map(Bytes::from)
+            .map_err(|_| {
+                tracing::error!(
+                    "Chunk {} of an encrypted object failed authentication",
+                    index
+                );
+                S3Error::InternalError("Failed to decrypt object".to_string())
+            })
+    }
+}
+
+/// Plaintext size of an object stored in `stored_size` bytes
+fn plaintext_size(stored_size: u64) -> u64 {
+    let body = stored_size.saturating_sub(HEADER_SIZE);
+    body.saturating_sub(body.div_ceil(SEALED_CHUNK_SIZE) * TAG_SIZE)
+}
+
+/// Number of chunks of an object of `size` plaintext bytes (an empty object has one)
+fn chunk_count(size: u64) -> u64 {
+    size.div_ceil(CHUNK_SIZE).max(1)
+}
+
+/// Read the header from the start of a stored object, returning any bytes read past it
+async fn read_header(stream: &mut ObjectStream) -> Result<(Bytes, BytesMut), S3Error> {
+    let mut buffer = BytesMut::new();
+    while (buffer.len() as u64) < HEADER_SIZE {
+        match stream.next().await {
+            Some(chunk) => buffer.extend_from_slice(&chunk?),
+            None => {
+                return Err(S3Error::InternalError(
+                    "Object was not stored by the encryption layer".to_string(),
+                ));
+            }
+        }
+    }
+    let header = buffer.split_to(HEADER_SIZE as usize).freeze();
+    Ok((header, buffer))
+}
+
+struct EncryptState {
+    key: DataKey,
+    input: ObjectStream,
+    buffer: BytesMut,
+    index: u64,
+    done: bool,
+}
+
+/// Encrypt a plaintext stream into sealed chunks
+fn encrypt_stream(key: DataKey, input: ObjectStream) -> ObjectStream {
+    let state = EncryptState {
+        key,
+        input,
+        buffer: BytesMut::new(),
+        index: 0,
+        done: false,
+    };
+    Box::pin(stream::unfold(state, |mut state| async move {
+        if state.done {
+            return None;
+        }
+        loop {
+            // A full chunk is only known not to be the final one once more data follows it
+            if state.buffer.len() as u64 > CHUNK_SIZE {
+                let chunk = state.buffer.split_to(CHUNK_SIZE as usize);
+                let sealed = state.key.seal_chunk(state.index, false, &chunk);
+                state.index += 1;
+                state.done = sealed.is_err();
+                return Some((sealed, state));
+            }
+            match state.input.next().await {
+                Some(Ok(bytes)) => state.buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+                None => {
+                    state.done = true;
+                    let sealed = state.key.seal_chunk(state.index, true, &state.buffer);
+                    return Some((sealed, state));
+                }
+            }
+        }
+    }))
+}
+
+/// Encrypted upload with the MD5 ETag of the plaintext
+struct EncryptedUpload {
+    body: ObjectStream,
+    etag: String,
+}
+
+/// Encrypt an upload to `object_key` into a staged body, computing the plaintext ETag on the
+/// way and sealing it in the header once the upload has been read
+async fn encrypt_upload(
+    master_key: &Aes256Gcm,
+    object_key: &str,
+    body: ObjectStream,
+) -> Result<EncryptedUpload, S3Error> {
+    let (key, mut header) = DataKey::generate(master_key, object_key)?;
+    let md5 = Arc::new(Mutex::new(Md5::new()));
+    let hashed = {
+        let md5 = md5.clone();
+        body.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                md5.lock().unwrap().update(chunk);
+            }
+        })
+    };
+
+    let mut sealed = encrypt_stream(key.clone(), Box::pin(hashed));
+    let mut staging = Staging::new();
+    while let Some(chunk) = sealed.next().await {
+        staging.write(chunk?).await?;
+    }
+    drop(sealed);
+
+    let digest = std::mem::take(&mut *md5.lock().unwrap()).finalize();
+    key.seal_etag(object_key, &mut header, &digest)?;
+    let header = header.freeze();
+    let body = staging.into_stream().await?;
+    Ok(EncryptedUpload {
+        body: Box::pin(stream::once(async move { Ok(header) }).chain(body)),
+        etag: format!("\"{}\"", hex::encode(digest)),
+    })
+}
+
+/// Chunks to decrypt for a read, and the plaintext bytes to keep from them
+struct ChunkSelection {
+    /// Index of the first chunk in the stream
+    first: u64,
+    /// Index of the last chunk to decrypt
+    last: u64,
+    /// Index of the final chunk of the object
+    final_index: u64,
+    /// Bytes to drop from the start of the first chunk
+    skip: u64,
+    /// Plaintext bytes to return
+    length: u64,
+}
+
+impl ChunkSelection {
+    /// Select the chunks covering plaintext bytes `start..=end` of an object of `size` bytes
+    fn new(size: u64, start: u64, end: u64) -> Self {
+        let first = start / CHUNK_SIZE;
+        Self {
+            first,
+            last: end / CHUNK_SIZE,
+            final_index: chunk_count(size) - 1,
+            skip: start - first * CHUNK_SIZE,
+            length: end + 1 - start,
+        }
+    }
+
+    /// Select every chunk of an object of `size` bytes
+    fn whole(size: u64) -> Self {
+        let final_index = chunk_count(size) - 1;
+        Self {
+            first: 0,
+            last: final_index,
+            final_index,
+            skip: 0,
+            length: size,
+        }
+    }
+
+    /// Range of stored bytes holding the selected chunks
+    fn stored_range(&self, stored_size: u64) -> ByteRange {
+        let end = (HEADER_SIZE + (self.last + 1) * SEALED_CHUNK_SIZE).min(stored_size);
+        ByteRange::FromTo {
+            start: HEADER_SIZE + self.first * SEALED_CHUNK_SIZE,
+            end: Some(end - 1),
+        }
+    }
+}
+
+struct DecryptState {
+    key: DataKey,
+    input: ObjectStream,
+    buffer: BytesMut,
+    index: u64,
+    selection: ChunkSelection,
+    done: bool,
+}
+
+impl DecryptState {
+    /// Decrypt the next chunk and keep the selected part of it
+    fn open(&mut self, chunk: &[u8], last: bool) -> Result<Bytes, S3Error> {
+        let result = self
+            .key
+            .open_chunk(self.index, last, chunk)
+            .map(|plaintext| {
+                let start = (self.selection.skip as usize).min(plaintext.len());
+                let end = (start as u64 + self.selection.length).min(plaintext.len() as u64);
+                self.selection.skip = 0;
+                self.selection.length -= end - start as u64;
+                plaintext.slice(start..end as usize)
+            });
+        self.index += 1;
+        self.done = result.is_err();
+        result
+    }
+}
+
+/// Decrypt the selected chunks of a stored object, `buffer` holding bytes already read
+fn decrypt_stream(
+    key: DataKey,
+    input: ObjectStream,
+    buffer: BytesMut,
+    selection: ChunkSelection,
+) -> ObjectStream {
+    let state = DecryptState {
+        key,
+        input,
+        buffer,
+        index: selection.first,
+        selection,
+        done: false,
+    };
+    Box::pin(stream::unfold(state, |mut state| async move {
+        if state.done || state.index > state.selection.last {
+            return None;
+        }
+        loop {
+            let is_final = state.index == state.selection.final_index;
+            if !is_final && state.buffer.len() as u64 >= SEALED_CHUNK_SIZE {
+                let chunk = state.buffer.split_to(SEALED_CHUNK_SIZE as usize);
+                let plaintext = state.open(&chunk, false);
+                return Some((plaintext, state));
+            }
+            match state.input.next().await {
+                Some(Ok(bytes)) => state.buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+                None if is_final => {
+                    let chunk = state.buffer.split();
+                    let plaintext = state.open(&chunk, true);
+                    return Some((plaintext, state));
+                }
+                None => {
+                    state.done = true;
+                    let error = S3Error::InternalError("Encrypted object is truncated".to_string());
+                    return Some((Err(error), state));
+                }
+            }
+        }
+    }))
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for EncryptedBackend {
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        self.inner.head_bucket().await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let objects = self.inner.list_objects(prefix, max_keys).await?;
+        Ok(self.restore_listing(objects).await)
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let objects = self
+            .inner
+            .list_objects_after(prefix, start_after, max_keys)
+            .await?;
+        Ok(self.restore_listing(objects).await)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        self.inner.get_bucket_versioning().await
+    }
+
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        self.inner.put_bucket_versioning(status).await
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let versions = self.inner.list_object_versions(prefix, max_keys).await?;
+        Ok(stream::iter(versions)
+            .map(|mut version| async move {
+                if !version.is_delete_marker {
+                    self.restore_listed(&mut version.metadata).await;
+                }
+                version
+            })
+            .buffered(LIST_CONCURRENCY)
+            .collect()
+            .await)
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        let (_, etag, mut metadata) = self.read_object_header(key, options).await?;
+        metadata.size = plaintext_size(metadata.size);
+        metadata.etag = etag;
+        Ok(metadata)
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        let Some(range) = &options.range else {
+            let (mut stream, mut metadata) = self.inner.get_object(key, options).await?;
+            let (header, rest) = read_header(&mut stream).await?;
+            let (data_key, etag) = DataKey::unwrap(&self.master_key, key, &header)?;
+            metadata.size = plaintext_size(metadata.size);
+            metadata.etag = etag;
+            let selection = ChunkSelection::whole(metadata.size);
+            return Ok((decrypt_stream(data_key, stream, rest, selection), metadata));
+        };
+
+        // Read the header first, its stored size tells which chunks cover the range
+        let (data_key, etag, mut metadata) = self.read_object_header(key, options).await?;
+        let stored_size = metadata.size;
+        metadata.size = plaintext_size(stored_size);
+        metadata.etag = etag;
+        let (start, end) = range.resolve(metadata.size)?;
+        let selection = ChunkSelection::new(metadata.size, start, end);
+
+        // Pin the version the header came from, in case the object is replaced meanwhile
+        let chunk_options = GetObjectOptions {
+            version_id: metadata
+                .version_id
+                .clone()
+                .or_else(|| options.version_id.clone()),
+            range: Some(selection.stored_range(stored_size)),
+            ..options.clone()
+        };
+        let (stream, _) = self.inner.get_object(key, &chunk_options).await?;
+        Ok((
+            decrypt_stream(data_key, stream, BytesMut::new(), selection),
+            metadata,
+        ))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        let upload = encrypt_upload(&self.master_key, key, body).await?;
+        let output = self.inner.put_object(key, upload.body, options).await?;
+        Ok(PutObjectOutput {
+            etag: upload.etag,
+            version_id: output.version_id,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        self.inner.delete_object(key, version_id).await
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        self.inner.get_object_tagging(key, version_id).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        self.inner.put_object_tagging(key, version_id, tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.inner.delete_object_tagging(key, version_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+
+    fn bytes_to_stream(data: Bytes) -> ObjectStream {
+        Box::pin(stream::once(async move { Ok(data) }))
+    }
+
+    async fn collect(stream: ObjectStream) -> Result<Vec<u8>, S3Error> {
+        let chunks: Vec<Result<Bytes, S3Error>> = stream.collect().await;
+        let mut data = Vec::new();
+        for chunk in chunks {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    fn test_data(size: usize) -> Bytes {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn encrypted(inner: &Arc<InMemoryStorage>, master_key: u8) -> EncryptedBackend {
+        EncryptedBackend::new(inner.clone(), &[master_key; KEY_SIZE])
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = encrypted(&inner, 1);
+        let chunk_size = CHUNK_SIZE as usize;
+
+        for size in [0, 1, chunk_size, chunk_size + 1, 3 * chunk_size + 5] {
+            let data = test_data(size);
+            backend
+                .put_object("key", bytes_to_stream(data.clone()), &Default::default())
+                .await
+                .unwrap();
+
+            let (stream, metadata) = backend
+                .get_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(metadata.size, size as u64);
+            assert_eq!(collect(stream).await.unwrap(), data);
+
+            let head = backend
+                .head_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(head.size, size as u64);
+
+            // The wrapped backend only holds ciphertext
+            let (stream, stored) = inner
+                .get_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            let stored_data = collect(stream).await.unwrap();
+            assert_eq!(plaintext_size(stored.size), size as u64);
+            assert!(size < 16 || !stored_data.windows(16).any(|w| w == &data[..16]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ranged_reads() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = encrypted(&inner, 1);
+        let chunk_size = CHUNK_SIZE as usize;
+        let data = test_data(3 * chunk_size + 100);
+        backend
+            .put_object("key", bytes_to_stream(data.clone()), &Default::default())
+            .await
+            .unwrap();
+
+        let cases = [
+            (0, 9),
+            (chunk_size - 10, chunk_size + 10),
+            (chunk_size, 2 * chunk_size - 1),
+            (10, 3 * chunk_size + 50),
+            (3 * chunk_size + 90, data.len() - 1),
+        ];
+        for (start, end) in cases {
+            let options = GetObjectOptions {
+                range: Some(ByteRange::FromTo {
+                    start: start as u64,
+                    end: Some(end as u64),
+                }),
+                ..Default::default()
+            };
+            let (stream, metadata) = backend.get_object("key", &options).await.unwrap();
+            assert_eq!(metadata.size, data.len() as u64);
+            assert_eq!(collect(stream).await.unwrap(), &data[start..=end]);
+        }
+
+        let options = GetObjectOptions {
+            range: Some(ByteRange::Suffix(5)),
+            ..Default::default()
+        };
+        let (stream, _) = backend.get_object("key", &options).await.unwrap();
+        assert_eq!(collect(stream).await.unwrap(), &data[data.len() - 5..]);
+
+        let options = GetObjectOptions {
+            range: Some(ByteRange::FromTo {
+                start: data.len() as u64,
+                end: None,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            backend.get_object("key", &options).await,
+            Err(S3Error::InvalidRange)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_master_key() {
+        let inner = Arc::new(InMemoryStorage::new());
+        encrypted(&inner, 1)
+            .put_object("key", bytes_to_stream(test_data(100)), &Default::default())
+            .await
+            .unwrap();
+
+        let result = encrypted(&inner, 2)
+            .get_object("key", &GetObjectOptions::default())
+            .await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_and_truncated_objects() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = encrypted(&inner, 1);
+        backend
+            .put_object(
+                "key",
+                bytes_to_stream(test_data(2 * CHUNK_SIZE as usize + 10)),
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        let (stream, _) = inner
+            .get_object("key", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        let stored = collect(stream).await.unwrap();
+
+        // Flip a byte of the second chunk
+        let mut tampered = stored.clone();
+        tampered[(HEADER_SIZE + SEALED_CHUNK_SIZE + 5) as usize] ^= 1;
+        // Drop the final chunk, so the second chunk looks like the last one
+        let truncated = stored[..(HEADER_SIZE + 2 * SEALED_CHUNK_SIZE) as usize].to_vec();
+
+        for stored in [tampered, truncated] {
+            inner
+                .put_object("key", bytes_to_stream(stored.into()), &Default::default())
+                .await
+                .unwrap();
+            let (stream, _) = backend
+                .get_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert!(collect(stream).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replicas_report_the_plaintext_etag() {
+        let data = test_data(CHUNK_SIZE as usize + 100);
+        let expected = format!("\"{}\"", hex::encode(Md5::digest(&data)));
+
+        let inners = [
+            Arc::new(InMemoryStorage::new()),
+            Arc::new(InMemoryStorage::new()),
+        ];
+        let mut stored_etags = Vec::new();
+        for inner in &inners {
+            let backend = encrypted(inner, 1);
+            let output = backend
+                .put_object("key", bytes_to_stream(data.clone()), &Default::default())
+                .await
+                .unwrap();
+            assert_eq!(output.etag, expected);
+
+            let head = backend
+                .head_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(head.etag, expected);
+            assert!(head.user_metadata.is_empty());
+            let (stream, metadata) = backend
+                .get_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(metadata.etag, expected);
+            assert_eq!(collect(stream).await.unwrap(), data);
+            let listed = backend.list_objects(None, 100).await.unwrap();
+            assert_eq!(listed[0].etag, expected);
+            assert_eq!(listed[0].size, data.len() as u64);
+
+            let stored = inner
+                .head_object("key", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            // The plaintext digest never reaches the backend in the clear
+            assert!(stored.user_metadata.is_empty());
+            stored_etags.push(stored.etag);
+        }
+
+        // Each replica stores different ciphertext
+        assert_ne!(stored_etags[0], stored_etags[1]);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_etag() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = encrypted(&inner, 1);
+        backend
+            .put_object("key", bytes_to_stream(test_data(100)), &Default::default())
+            .await
+            .unwrap();
+        let (stream, _) = inner
+            .get_object("key", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        let mut stored = collect(stream).await.unwrap();
+
+        // Flip a byte of the sealed ETag
+        stored[KEY_HEADER_SIZE + 3] ^= 1;
+        inner
+            .put_object("key", bytes_to_stream(stored.into()), &Default::default())
+            .await
+            .unwrap();
+
+        let result = backend
+            .head_object("key", &GetObjectOptions::default())
+            .await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+        let result = backend
+            .get_object("key", &GetObjectOptions::default())
+            .await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_body_moved_to_another_key() {
+        let inner = Arc::new(InMemoryStorage::new());
+        let backend = encrypted(&inner, 1);
+        backend
+            .put_object("a", bytes_to_stream(test_data(100)), &Default::default())
+            .await
+            .unwrap();
+
+        // The backend copies the stored body of "a" to "b"
+        let (stream, _) = inner
+            .get_object("a", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        inner
+            .put_object("b", stream, &Default::default())
+            .await
+            .unwrap();
+
+        let result = backend.get_object("b", &GetObjectOptions::default()).await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+        let result = backend.head_object("b", &GetObjectOptions::default()).await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+    }
+
+    #[test]
+    fn test_plaintext_size() {
+        for size in [
+            0,
+            1,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            CHUNK_SIZE + 1,
+            5 * CHUNK_SIZE,
+        ] {
+            let stored_size = HEADER_SIZE + size + chunk_count(size) * TAG_SIZE;
+            assert_eq!(plaintext_size(stored_size), size);
+        }
+    }
+}
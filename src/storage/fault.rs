@@ -0,0 +1,455 @@
+//! Fault injection into the operations of a backend, for testing
+//!
+//! Each operation of the wrapped backend can be made to fail, to respond late, or to return
+//! made-up ETags, and the bodies of GET requests can be cut short or fail partway through. Every
+//! fault happens with its own probability, drawn from a random number generator that can be
+//! seeded so that test runs are reproducible.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use futures::stream::{self, StreamExt};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Operation of a backend faults are injected into, named after the backend's methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultOperation {
+    HeadBucket,
+    ListObjects,
+    GetBucketVersioning,
+    PutBucketVersioning,
+    ListObjectVersions,
+    HeadObject,
+    GetObject,
+    PutObject,
+    DeleteObject,
+    GetObjectTagging,
+    PutObjectTagging,
+    DeleteObjectTagging,
+}
+
+/// Probabilities of the faults injected into an operation, from 0 (never) to 1 (always)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultRates {
+    /// Fail with an internal error without calling the wrapped backend
+    pub error: f64,
+    /// Wait `latency_ms` before calling the wrapped backend
+    pub latency: f64,
+    pub latency_ms: u64,
+    /// End the body of a GET early, without an error
+    pub truncate: f64,
+    /// Fail the body of a GET partway through
+    pub stream_error: f64,
+    /// Replace the ETags in the response with made-up ones
+    pub wrong_etag: f64,
+}
+
+impl FaultRates {
+    /// Check that every probability is between 0 and 1
+    pub fn validate(&self) -> Result<(), String> {
+        let rates = [
+            ("error", self.error),
+            ("latency", self.latency),
+            ("truncate", self.truncate),
+            ("stream_error", self.stream_error),
+            ("wrong_etag", self.wrong_etag),
+        ];
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!(
+                    "{} probability {} is not between 0 and 1",
+                    name, rate
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Storage backend wrapper that injects faults into the operations of the backend it wraps
+pub struct FaultBackend {
+    inner: Arc<dyn StorageBackend>,
+    /// Faults of the operations missing from `operations`
+    default: FaultRates,
+    operations: HashMap<FaultOperation, FaultRates>,
+    rng: Mutex<StdRng>,
+}
+
+impl FaultBackend {
+    /// Wrap `inner`, drawing faults from a generator seeded with `seed` (a random seed if None)
+    pub fn new(
+        inner: Arc<dyn StorageBackend>,
+        default: FaultRates,
+        operations: HashMap<FaultOperation, FaultRates>,
+        seed: Option<u64>,
+    ) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            inner,
+            default,
+            operations,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    fn rates(&self, operation: FaultOperation) -> &FaultRates {
+        self.operations.get(&operation).unwrap_or(&self.default)
+    }
+
+    /// Whether a fault of the given probability happens this time
+    fn roll(&self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.lock().unwrap().random_bool(probability.min(1.0))
+    }
+
+    /// Inject the faults that happen before the wrapped backend is called
+    async fn before(&self, operation: FaultOperation) -> Result<&FaultRates, S3Error> {
+        let rates = self.rates(operation);
+        if self.roll(rates.latency) {
+            tracing::debug!("Delaying {:?} by {} ms", operation, rates.latency_ms);
+            tokio::time::sleep(Duration::from_millis(rates.latency_ms)).await;
+        }
+        if self.roll(rates.error) {
+            tracing::debug!("Failing {:?}", operation);
+            return Err(S3Error::InternalError(format!(
+                "Injected fault: {:?} failed",
+                operation
+            )));
+        }
+        Ok(rates)
+    }
+
+    /// Replace an ETag with a made-up one, with the given probability
+    fn corrupt_etag(&self, rates: &FaultRates, etag: &mut String) {
+        if self.roll(rates.wrong_etag) {
+            let made_up = format!("\"{:032x}\"", self.rng.lock().unwrap().random::<u128>());
+            tracing::debug!("Replacing ETag {} with {}", etag, made_up);
+            *etag = made_up;
+        }
+    }
+
+    /// Cut a body short after a random number of bytes, ending it or failing it there
+    fn cut_body(&self, body: ObjectStream, length: u64, fail: bool) -> ObjectStream {
+        let cut = match length {
+            0 => 0,
+            length => self.rng.lock().unwrap().random_range(0..length),
+        };
+        tracing::debug!(
+            "Cutting a body of {} bytes after {} bytes ({})",
+            length,
+            cut,
+            if fail { "failing" } else { "truncating" }
+        );
+
+        let stream = stream::unfold(Some((body, cut)), move |state| async move {
+            let (mut body, remaining) = state?;
+            if remaining == 0 {
+                let end = fail.then(|| {
+                    Err(S3Error::InternalError(
+                        "Injected fault: the body failed partway through".to_string(),
+                    ))
+                });
+                return end.map(|error| (error, None));
+            }
+            match body.next().await {
+                Some(Ok(mut chunk)) => {
+                    if chunk.len() as u64 > remaining {
+                        chunk.truncate(remaining as usize);
+                    }
+                    let remaining = remaining - chunk.len() as u64;
+                    Some((Ok(chunk), Some((body, remaining))))
+                }
+                Some(Err(e)) => Some((Err(e), None)),
+                None => None,
+            }
+        });
+        Box::pin(stream)
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for FaultBackend {
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        self.before(FaultOperation::HeadBucket).await?;
+        self.inner.head_bucket().await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let rates = self.before(FaultOperation::ListObjects).await?;
+        let mut objects = self.inner.list_objects(prefix, max_keys).await?;
+        for object in &mut objects {
+            self.corrupt_etag(rates, &mut object.etag);
+        }
+        Ok(objects)
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let rates = self.before(FaultOperation::ListObjects).await?;
+        let mut objects = self
+            .inner
+            .list_objects_after(prefix, start_after, max_keys)
+            .await?;
+        for object in &mut objects {
+            self.corrupt_etag(rates, &mut object.etag);
+        }
+        Ok(objects)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        self.before(FaultOperation::GetBucketVersioning).await?;
+        self.inner.get_bucket_versioning().await
+    }
+
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        self.before(FaultOperation::PutBucketVersioning).await?;
+        self.inner.put_bucket_versioning(status).await
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let rates = self.before(FaultOperation::ListObjectVersions).await?;
+        let mut versions = self.inner.list_object_versions(prefix, max_keys).await?;
+        for version in &mut versions {
+            self.corrupt_etag(rates, &mut version.metadata.etag);
+        }
+        Ok(versions)
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        let rates = self.before(FaultOperation::HeadObject).await?;
+        let mut metadata = self.inner.head_object(key, options).await?;
+        self.corrupt_etag(rates, &mut metadata.etag);
+        Ok(metadata)
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        let rates = self.before(FaultOperation::GetObject).await?;
+        let (mut body, mut metadata) = self.inner.get_object(key, options).await?;
+
+        let fail = self.roll(rates.stream_error);
+        if fail || self.roll(rates.truncate) {
+            let length = match &options.range {
+                Some(range) => range
+                    .resolve(metadata.size)
+                    .map_or(0, |(start, end)| end - start + 1),
+                None => metadata.size,
+            };
+            body = self.cut_body(body, length, fail);
+        }
+        self.corrupt_etag(rates, &mut metadata.etag);
+        Ok((body, metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        let rates = self.before(FaultOperation::PutObject).await?;
+        let mut output = self.inner.put_object(key, body, options).await?;
+        self.corrupt_etag(rates, &mut output.etag);
+        Ok(output)
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        self.before(FaultOperation::DeleteObject).await?;
+        self.inner.delete_object(key, version_id).await
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        self.before(FaultOperation::GetObjectTagging).await?;
+        self.inner.get_object_tagging(key, version_id).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        self.before(FaultOperation::PutObjectTagging).await?;
+        self.inner.put_object_tagging(key, version_id, tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.before(FaultOperation::DeleteObjectTagging).await?;
+        self.inner.delete_object_tagging(key, version_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+    use bytes::Bytes;
+
+    fn bytes_to_stream(data: Bytes) -> ObjectStream {
+        Box::pin(stream::once(async move { Ok(data) }))
+    }
+
+    /// A backend holding `key` with 1000 bytes, wrapped with the given faults on GET
+    async fn faulty_get(rates: FaultRates) -> FaultBackend {
+        let inner = Arc::new(InMemoryStorage::new());
+        inner
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from(vec![7u8; 1000])),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let operations = HashMap::from([(FaultOperation::GetObject, rates)]);
+        FaultBackend::new(inner, FaultRates::default(), operations, Some(7))
+    }
+
+    /// Read a body, returning the bytes read before it ended or failed
+    async fn read_body(mut body: ObjectStream) -> (usize, bool) {
+        let mut read = 0;
+        while let Some(chunk) = body.next().await {
+            match chunk {
+                Ok(chunk) => read += chunk.len(),
+                Err(_) => return (read, true),
+            }
+        }
+        (read, false)
+    }
+
+    #[tokio::test]
+    async fn test_faults_on_get() {
+        let options = GetObjectOptions::default();
+
+        // No faults
+        let backend = faulty_get(FaultRates::default()).await;
+        let (body, metadata) = backend.get_object("key", &options).await.unwrap();
+        assert_eq!(read_body(body).await, (1000, false));
+        let etag = metadata.etag;
+
+        let backend = faulty_get(FaultRates {
+            error: 1.0,
+            ..Default::default()
+        })
+        .await;
+        assert!(matches!(
+            backend.get_object("key", &options).await,
+            Err(S3Error::InternalError(_))
+        ));
+        // Other operations are left alone
+        assert!(backend.head_object("key", &options).await.is_ok());
+
+        let backend = faulty_get(FaultRates {
+            truncate: 1.0,
+            ..Default::default()
+        })
+        .await;
+        let (body, _) = backend.get_object("key", &options).await.unwrap();
+        let (read, failed) = read_body(body).await;
+        assert!(read < 1000 && !failed);
+
+        let backend = faulty_get(FaultRates {
+            stream_error: 1.0,
+            ..Default::default()
+        })
+        .await;
+        let (body, _) = backend.get_object("key", &options).await.unwrap();
+        let (read, failed) = read_body(body).await;
+        assert!(read < 1000 && failed);
+
+        let backend = faulty_get(FaultRates {
+            wrong_etag: 1.0,
+            ..Default::default()
+        })
+        .await;
+        let (_, metadata) = backend.get_object("key", &options).await.unwrap();
+        assert_ne!(metadata.etag, etag);
+    }
+
+    #[tokio::test]
+    async fn test_seeded_faults_are_reproducible() {
+        let rates = FaultRates {
+            error: 0.5,
+            ..Default::default()
+        };
+        let outcomes = |backend: FaultBackend| async move {
+            let mut outcomes = Vec::new();
+            for _ in 0..32 {
+                outcomes.push(backend.head_bucket().await.is_ok());
+            }
+            outcomes
+        };
+
+        let first = outcomes(FaultBackend::new(
+            Arc::new(InMemoryStorage::new()),
+            rates.clone(),
+            HashMap::new(),
+            Some(42),
+        ))
+        .await;
+        let second = outcomes(FaultBackend::new(
+            Arc::new(InMemoryStorage::new()),
+            rates,
+            HashMap::new(),
+            Some(42),
+        ))
+        .await;
+        assert_eq!(first, second);
+        assert!(first.contains(&true) && first.contains(&false));
+    }
+
+    #[test]
+    fn test_validate_rates() {
+        assert!(FaultRates::default().validate().is_ok());
+        let rates = FaultRates {
+            truncate: 1.5,
+            ..Default::default()
+        };
+        assert!(rates.validate().is_err());
+    }
+}
@@ -0,0 +1,955 @@
+//! Storage backend keeping objects as files under a local directory
+//!
+//! Each key maps to the file at the same relative path, so the directory can be browsed and
+//! backed up like any other. The ETag, content type, user metadata and tags of an object are
+//! kept in a JSON sidecar next to it (`<name>.replicat4-meta.json`). Files that were placed in
+//! the directory by other means are served with metadata derived from the file itself.
+//!
+//! Keys ending in `/`, the folder markers created by the S3 console and many tools, are kept as
+//! a `.replicat4-folder` file in the directory they name.
+//!
+//! Writes go to a temporary file under `.replicat4-tmp` in the root, which is then renamed
+//! into place, so readers never see a partially written object.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Suffix of the metadata sidecar stored next to each object
+const SIDECAR_SUFFIX: &str = ".replicat4-meta.json";
+/// Directory in the root holding writes in progress
+const TEMP_DIR: &str = ".replicat4-tmp";
+/// File standing for a key ending in `/`, in the directory the key names
+const FOLDER_MARKER: &str = ".replicat4-folder";
+/// Size of the chunks objects are read in
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Storage backend for a local directory, such as a NAS mount
+#[derive(Clone)]
+pub struct FilesystemBackend {
+    root: PathBuf,
+    /// Held while an object and its sidecar are replaced, so they always come from the same write
+    rename_lock: Arc<Mutex<()>>,
+}
+
+/// Metadata stored next to an object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sidecar {
+    /// Size of the object the sidecar was written for, to detect a stale sidecar
+    size: u64,
+    etag: String,
+    content_type: String,
+    #[serde(default)]
+    user_metadata: HashMap<String, String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+impl Sidecar {
+    /// Metadata for a file without a usable sidecar
+    ///
+    /// The ETag is derived from the modification time and size, so it changes with the file.
+    fn derived(file: &std::fs::Metadata) -> Self {
+        let modified = file
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            size: file.len(),
+            etag: format!("\"{:x}-{:x}\"", modified.as_nanos(), file.len()),
+            content_type: "binary/octet-stream".to_string(),
+            user_metadata: HashMap::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Parse a sidecar, ignoring it if it doesn't describe this file
+    fn parse(data: Option<Vec<u8>>, file: &std::fs::Metadata) -> Self {
+        data.and_then(|data| serde_json::from_slice::<Sidecar>(&data).ok())
+            .filter(|sidecar| sidecar.size == file.len())
+            .unwrap_or_else(|| Self::derived(file))
+    }
+
+    fn metadata(&self, key: &str, file: &std::fs::Metadata) -> ObjectMetadata {
+        ObjectMetadata {
+            key: key.to_string(),
+            size: file.len(),
+            etag: self.etag.clone(),
+            last_modified: file
+                .modified()
+                .map(chrono::DateTime::from)
+                .unwrap_or_else(|_| chrono::Utc::now()),
+            content_type: self.content_type.clone(),
+            version_id: None,
+            user_metadata: self.user_metadata.clone(),
+        }
+    }
+}
+
+/// Paths of an object and its sidecar
+struct ObjectPaths {
+    data: PathBuf,
+    sidecar: PathBuf,
+}
+
+/// File in the temporary directory, removed when dropped unless it was renamed into place
+struct PendingFile {
+    path: PathBuf,
+    committed: bool,
+}
+
+impl PendingFile {
+    async fn create(root: &Path) -> Result<(Self, tokio::fs::File), S3Error> {
+        let dir = root.join(TEMP_DIR);
+        tokio::fs::create_dir_all(&dir).await.map_err(io_error)?;
+        let path = dir.join(uuid::Uuid::new_v4().simple().to_string());
+        let file = tokio::fs::File::create_new(&path).await.map_err(io_error)?;
+        Ok((
+            Self {
+                path,
+                committed: false,
+            },
+            file,
+        ))
+    }
+
+    async fn write_all(root: &Path, data: &[u8]) -> Result<Self, S3Error> {
+        let (pending, mut file) = Self::create(root).await?;
+        file.write_all(data).await.map_err(io_error)?;
+        file.sync_all().await.map_err(io_error)?;
+        Ok(pending)
+    }
+
+    async fn rename_to(&mut self, path: &Path) -> Result<(), S3Error> {
+        tokio::fs::rename(&self.path, path)
+            .await
+            .map_err(conflict_error)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PendingFile {
+    fn drop(&mut self) {
+        if !self.committed
+            && let Err(e) = std::fs::remove_file(&self.path)
+        {
+            tracing::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn io_error(e: std::io::Error) -> S3Error {
+    S3Error::InternalError(format!("Filesystem error: {}", e))
+}
+
+/// Map the errors of a key clashing with a directory, or of a key's parent being a file
+fn conflict_error(e: std::io::Error) -> S3Error {
+    match e.kind() {
+        ErrorKind::IsADirectory | ErrorKind::NotADirectory | ErrorKind::DirectoryNotEmpty => {
+            S3Error::InvalidArgument(
+                "The key conflicts with another key stored on the filesystem backend".to_string(),
+            )
+        }
+        _ => io_error(e),
+    }
+}
+
+/// Whether a key segment can be used as a file name
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && !segment.contains('\\')
+        && !segment.contains('\0')
+        && !segment.ends_with(SIDECAR_SUFFIX)
+        && segment != FOLDER_MARKER
+}
+
+/// Read a file, treating a missing file as empty
+fn read_optional(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether an error means the path doesn't exist as expected
+fn is_missing(e: &std::io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory)
+}
+
+impl FilesystemBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            rename_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Map a key to the paths of its object and sidecar
+    ///
+    /// Returns None for keys that can't be stored safely, such as keys with `..` or empty
+    /// segments, which would otherwise escape the root or clash with directories. A key ending
+    /// in `/` maps to the folder marker in the directory it names.
+    fn paths(&self, key: &str) -> Option<ObjectPaths> {
+        let (name, is_folder) = match key.strip_suffix('/') {
+            Some(folder) => (folder, true),
+            None => (key, false),
+        };
+        let mut segments = name.split('/').peekable();
+        if segments.peek() == Some(&TEMP_DIR) || !name.split('/').all(is_valid_segment) {
+            return None;
+        }
+
+        let mut data = segments.fold(self.root.clone(), |path, segment| path.join(segment));
+        if is_folder {
+            data.push(FOLDER_MARKER);
+        }
+        let mut sidecar = data.clone().into_os_string();
+        sidecar.push(SIDECAR_SUFFIX);
+        Some(ObjectPaths {
+            data,
+            sidecar: sidecar.into(),
+        })
+    }
+
+    /// Paths of a key that must already exist
+    fn existing_paths(&self, key: &str) -> Result<ObjectPaths, S3Error> {
+        self.paths(key).ok_or(S3Error::NoSuchKey)
+    }
+
+    /// Load the stored metadata of an object
+    async fn load(
+        &self,
+        key: &str,
+        paths: &ObjectPaths,
+    ) -> Result<(Sidecar, std::fs::Metadata), S3Error> {
+        let file = match tokio::fs::metadata(&paths.data).await {
+            Ok(file) if file.is_file() => file,
+            Ok(_) => return Err(S3Error::NoSuchKey),
+            Err(e) if is_missing(&e) => return Err(S3Error::NoSuchKey),
+            Err(e) => return Err(io_error(e)),
+        };
+        let sidecar_path = paths.sidecar.clone();
+        let data = tokio::task::spawn_blocking(move || read_optional(&sidecar_path))
+            .await
+            .map_err(|e| {
+                S3Error::InternalError(format!("Failed to read metadata of {}: {}", key, e))
+            })?
+            .map_err(io_error)?;
+        Ok((Sidecar::parse(data, &file), file))
+    }
+
+    /// Write an object's sidecar, replacing the existing one
+    async fn write_sidecar(&self, paths: &ObjectPaths, sidecar: &Sidecar) -> Result<(), S3Error> {
+        let data = serde_json::to_vec(sidecar)
+            .map_err(|e| S3Error::InternalError(format!("Failed to encode metadata: {}", e)))?;
+        let mut pending = PendingFile::write_all(&self.root, &data).await?;
+        pending.rename_to(&paths.sidecar).await
+    }
+
+    /// Collect the keys starting with `prefix`, in key order
+    ///
+    /// Only the directories that can hold such keys are visited.
+    fn list_keys(
+        root: &Path,
+        prefix: &str,
+        max_keys: usize,
+    ) -> std::io::Result<Vec<(String, PathBuf)>> {
+        let mut keys = Vec::new();
+        // Start in the deepest directory the prefix names completely
+        let (dir_key, start) = match prefix.rsplit_once('/') {
+            Some((dir, _)) => {
+                if !dir.split('/').all(is_valid_segment) {
+                    return Ok(keys);
+                }
+                (format!("{}/", dir), root.join(dir))
+            }
+            None => (String::new(), root.to_path_buf()),
+        };
+        Self::walk(&start, &dir_key, prefix, max_keys, &mut keys)?;
+        Ok(keys)
+    }
+
+    fn walk(
+        dir: &Path,
+        dir_key: &str,
+        prefix: &str,
+        max_keys: usize,
+        keys: &mut Vec<(String, PathBuf)>,
+    ) -> std::io::Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if is_missing(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        // The folder marker stands for the directory's own key, which sorts before its contents
+        let marker = dir.join(FOLDER_MARKER);
+        if !dir_key.is_empty()
+            && dir_key.starts_with(prefix)
+            && keys.len() < max_keys
+            && marker.is_file()
+        {
+            keys.push((dir_key.to_string(), marker));
+        }
+
+        // Directories sort as if followed by '/', so that keys come out in S3 order
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !(dir_key.is_empty() && name == TEMP_DIR) {
+                    names.push((format!("{}/", name), entry.path()));
+                }
+            } else if file_type.is_file()
+                && !name.ends_with(SIDECAR_SUFFIX)
+                && name != FOLDER_MARKER
+            {
+                names.push((name, entry.path()));
+            }
+        }
+        names.sort();
+
+        for (name, path) in names {
+            if keys.len() >= max_keys {
+                break;
+            }
+            let key = format!("{}{}", dir_key, name);
+            if key.ends_with('/') {
+                if key.starts_with(prefix) || prefix.starts_with(&key) {
+                    Self::walk(&path, &key, prefix, max_keys, keys)?;
+                }
+            } else if key.starts_with(prefix) {
+                keys.push((key, path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the directories left empty by a delete, up to the root
+    fn remove_empty_parents(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if current == self.root || std::fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for FilesystemBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        match tokio::fs::metadata(&self.root).await {
+            Ok(root) if root.is_dir() => Ok(()),
+            _ => Err(S3Error::NoSuchBucket),
+        }
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let root = self.root.clone();
+        let prefix = prefix.unwrap_or_default().to_string();
+        let max_keys = max_keys.max(0) as usize;
+
+        tokio::task::spawn_blocking(move || {
+            let keys = Self::list_keys(&root, &prefix, max_keys)?;
+            let mut objects = Vec::with_capacity(keys.len());
+            for (key, path) in keys {
+                // Objects deleted since the directory was read are skipped
+                let file = match std::fs::metadata(&path) {
+                    Ok(file) => file,
+                    Err(e) if is_missing(&e) => continue,
+                    Err(e) => return Err(e),
+                };
+                let mut sidecar_path = path.into_os_string();
+                sidecar_path.push(SIDECAR_SUFFIX);
+                let sidecar = Sidecar::parse(read_optional(Path::new(&sidecar_path))?, &file);
+                objects.push(sidecar.metadata(&key, &file));
+            }
+            Ok(objects)
+        })
+        .await
+        .map_err(|e| S3Error::InternalError(format!("Failed to list objects: {}", e)))?
+        .map_err(io_error)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Versioning can't be enabled, so it was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let paths = self.existing_paths(key)?;
+        let (sidecar, file) = self.load(key, &paths).await?;
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        Ok(sidecar.metadata(key, &file))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let paths = self.existing_paths(key)?;
+
+        // Metadata is taken from the open file, so it matches the data even during a PUT
+        let mut file = match tokio::fs::File::open(&paths.data).await {
+            Ok(file) => file,
+            Err(e) if is_missing(&e) => return Err(S3Error::NoSuchKey),
+            Err(e) => return Err(io_error(e)),
+        };
+        let file_metadata = file.metadata().await.map_err(io_error)?;
+        if !file_metadata.is_file() {
+            return Err(S3Error::NoSuchKey);
+        }
+        let sidecar_path = paths.sidecar.clone();
+        let sidecar_data = tokio::task::spawn_blocking(move || read_optional(&sidecar_path))
+            .await
+            .map_err(|e| {
+                S3Error::InternalError(format!("Failed to read metadata of {}: {}", key, e))
+            })?
+            .map_err(io_error)?;
+        let sidecar = Sidecar::parse(sidecar_data, &file_metadata);
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        let metadata = sidecar.metadata(key, &file_metadata);
+
+        let (start, length) = match &options.range {
+            Some(range) => {
+                let (start, end) = range.resolve(metadata.size)?;
+                file.seek(std::io::SeekFrom::Start(start))
+                    .await
+                    .map_err(io_error)?;
+                (start, end + 1 - start)
+            }
+            None => (0, metadata.size),
+        };
+        tracing::trace!("Reading {} bytes of {} from offset {}", length, key, start);
+
+        let stream = stream::unfold((file, length), |(mut file, remaining)| async move {
+            if remaining == 0 {
+                return None;
+            }
+            let mut buffer = vec![0u8; READ_CHUNK_SIZE.min(remaining as usize)];
+            match file.read(&mut buffer).await {
+                Ok(0) => Some((
+                    Err(S3Error::InternalError(
+                        "Object was truncated while reading".to_string(),
+                    )),
+                    (file, 0),
+                )),
+                Ok(n) => {
+                    buffer.truncate(n);
+                    Some((Ok(Bytes::from(buffer)), (file, remaining - n as u64)))
+                }
+                Err(e) => Some((Err(io_error(e)), (file, 0))),
+            }
+        });
+
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        // Objects are stored as plain files, which the customer's key couldn't protect
+        options.reject_sse_customer("filesystem")?;
+        let paths = self.paths(key).ok_or_else(|| {
+            S3Error::InvalidArgument(format!(
+                "Key '{}' can't be stored on the filesystem backend: keys can't have empty, '.' or \
+                 '..' segments, backslashes, or names reserved by the backend",
+                key
+            ))
+        })?;
+
+        let (mut pending, mut file) = PendingFile::create(&self.root).await?;
+        let mut md5 = Md5::new();
+        let mut size = 0u64;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            md5.update(&chunk);
+            size += chunk.len() as u64;
+            file.write_all(&chunk).await.map_err(io_error)?;
+        }
+        file.sync_all().await.map_err(io_error)?;
+        drop(file);
+
+        let etag = format!("\"{}\"", hex::encode(md5.finalize()));
+        let sidecar = Sidecar {
+            size,
+            etag: etag.clone(),
+            content_type: options
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "binary/octet-stream".to_string()),
+            user_metadata: options.user_metadata.clone(),
+            tags: options.tags.clone(),
+        };
+        let sidecar_data = serde_json::to_vec(&sidecar)
+            .map_err(|e| S3Error::InternalError(format!("Failed to encode metadata: {}", e)))?;
+        let mut pending_sidecar = PendingFile::write_all(&self.root, &sidecar_data).await?;
+
+        // Deletes prune empty directories under the same lock, so the parent stays until renamed
+        let _guard = self.rename_lock.lock().await;
+        if let Some(parent) = paths.data.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(conflict_error)?;
+        }
+        pending.rename_to(&paths.data).await?;
+        pending_sidecar.rename_to(&paths.sidecar).await?;
+
+        Ok(PutObjectOutput {
+            etag,
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+        let Some(paths) = self.paths(key) else {
+            return Ok(output);
+        };
+
+        let _guard = self.rename_lock.lock().await;
+        for path in [&paths.data, &paths.sidecar] {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => {}
+                Err(e) if is_missing(&e) => {}
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+        self.remove_empty_parents(&paths.data);
+        Ok(output)
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        check_null_version(version_id)?;
+        let paths = self.existing_paths(key)?;
+        let (sidecar, _) = self.load(key, &paths).await?;
+        Ok(sidecar.tags)
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        let paths = self.existing_paths(key)?;
+
+        let _guard = self.rename_lock.lock().await;
+        let (mut sidecar, _) = self.load(key, &paths).await?;
+        sidecar.tags = tags;
+        self.write_sidecar(&paths, &sidecar).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.put_object_tagging(key, version_id, Vec::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ByteRange, SseCustomerKey};
+    use futures::TryStreamExt;
+
+    fn bytes_to_stream(data: Bytes) -> ObjectStream {
+        Box::pin(stream::once(async move { Ok(data) }))
+    }
+
+    async fn collect(stream: ObjectStream) -> Vec<u8> {
+        let chunks: Vec<Bytes> = stream.try_collect().await.unwrap();
+        chunks.concat()
+    }
+
+    async fn put(storage: &FilesystemBackend, key: &str, data: &'static str) -> PutObjectOutput {
+        storage
+            .put_object(
+                key,
+                bytes_to_stream(Bytes::from(data)),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_put_and_get_object() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+
+        let options = PutObjectOptions {
+            content_type: Some("text/plain".to_string()),
+            user_metadata: HashMap::from([("owner".to_string(), "alice".to_string())]),
+            tags: vec![Tag {
+                key: "env".to_string(),
+                value: "prod".to_string(),
+            }],
+            ..Default::default()
+        };
+        let output = storage
+            .put_object(
+                "docs/readme.txt",
+                bytes_to_stream(Bytes::from("Hello, World!")),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output.etag,
+            format!("\"{}\"", hex::encode(Md5::digest(b"Hello, World!")))
+        );
+
+        // The object is a plain file at the key's path
+        assert_eq!(
+            std::fs::read(dir.path().join("docs/readme.txt")).unwrap(),
+            b"Hello, World!"
+        );
+
+        let (stream, metadata) = storage
+            .get_object("docs/readme.txt", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(collect(stream).await, b"Hello, World!");
+        assert_eq!(metadata.etag, output.etag);
+        assert_eq!(metadata.content_type, "text/plain");
+        assert_eq!(metadata.user_metadata["owner"], "alice");
+        assert_eq!(
+            storage
+                .get_object_tagging("docs/readme.txt", None)
+                .await
+                .unwrap(),
+            options.tags
+        );
+
+        let options = GetObjectOptions {
+            range: Some(ByteRange::FromTo {
+                start: 7,
+                end: Some(11),
+            }),
+            ..Default::default()
+        };
+        let (stream, _) = storage
+            .get_object("docs/readme.txt", &options)
+            .await
+            .unwrap();
+        assert_eq!(collect(stream).await, b"World");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_customer_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+        let sse_customer = Some(SseCustomerKey {
+            algorithm: "AES256".to_string(),
+            key: "a2V5".to_string(),
+            key_md5: "bWQ1".to_string(),
+        });
+
+        let options = PutObjectOptions {
+            sse_customer: sse_customer.clone(),
+            ..Default::default()
+        };
+        let result = storage
+            .put_object("secret.txt", bytes_to_stream(Bytes::from("data")), &options)
+            .await;
+        assert!(matches!(result, Err(S3Error::InvalidRequest(_))));
+        assert!(!dir.path().join("secret.txt").exists());
+
+        put(&storage, "plain.txt", "data").await;
+        let options = GetObjectOptions {
+            sse_customer,
+            ..Default::default()
+        };
+        let result = storage.head_object("plain.txt", &options).await;
+        assert!(matches!(result, Err(S3Error::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsafe_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path().join("root"));
+
+        for key in [
+            "../escape",
+            "a/../../escape",
+            "/absolute",
+            "a//b",
+            "/",
+            "a//",
+            ".replicat4-tmp/x",
+            "a/.replicat4-folder",
+            "x.replicat4-meta.json",
+        ] {
+            let result = storage
+                .put_object(
+                    key,
+                    bytes_to_stream(Bytes::new()),
+                    &PutObjectOptions::default(),
+                )
+                .await;
+            assert!(
+                matches!(result, Err(S3Error::InvalidArgument(_))),
+                "{} was accepted",
+                key
+            );
+        }
+        assert!(!dir.path().join("escape").exists());
+        assert!(matches!(
+            storage
+                .head_object("../escape", &GetObjectOptions::default())
+                .await,
+            Err(S3Error::NoSuchKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_in_key_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+        for key in [
+            "a-c",
+            "a/b",
+            "a/d/e",
+            "b",
+            "photos/2024/jan.jpg",
+            "photos/2024/feb.jpg",
+        ] {
+            put(&storage, key, "data").await;
+        }
+
+        let keys = |objects: Vec<ObjectMetadata>| -> Vec<String> {
+            objects.into_iter().map(|object| object.key).collect()
+        };
+        assert_eq!(
+            keys(storage.list_objects(None, 1000).await.unwrap()),
+            vec![
+                "a-c",
+                "a/b",
+                "a/d/e",
+                "b",
+                "photos/2024/feb.jpg",
+                "photos/2024/jan.jpg"
+            ]
+        );
+        assert_eq!(
+            keys(storage.list_objects(Some("a/"), 1000).await.unwrap()),
+            vec!["a/b", "a/d/e"]
+        );
+        assert_eq!(
+            keys(
+                storage
+                    .list_objects(Some("photos/2024/j"), 1000)
+                    .await
+                    .unwrap()
+            ),
+            vec!["photos/2024/jan.jpg"]
+        );
+        assert_eq!(
+            keys(storage.list_objects(None, 2).await.unwrap()),
+            vec!["a-c", "a/b"]
+        );
+        assert!(
+            storage
+                .list_objects(Some("missing/"), 1000)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_object_removes_empty_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+        put(&storage, "a/b/c.txt", "data").await;
+        put(&storage, "a/d.txt", "data").await;
+
+        storage.delete_object("a/b/c.txt", None).await.unwrap();
+        assert!(!dir.path().join("a/b").exists());
+        assert!(dir.path().join("a/d.txt").exists());
+        assert!(matches!(
+            storage
+                .get_object("a/b/c.txt", &GetObjectOptions::default())
+                .await,
+            Err(S3Error::NoSuchKey)
+        ));
+
+        // Deleting a missing object succeeds
+        storage.delete_object("a/b/c.txt", None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_folder_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+        put(&storage, "photos/", "").await;
+        put(&storage, "photos/2024/jan.jpg", "data").await;
+        assert!(dir.path().join("photos/.replicat4-folder").is_file());
+
+        let head = storage
+            .head_object("photos/", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(head.key, "photos/");
+        assert_eq!(head.size, 0);
+
+        let keys = |objects: Vec<ObjectMetadata>| -> Vec<String> {
+            objects.into_iter().map(|object| object.key).collect()
+        };
+        assert_eq!(
+            keys(storage.list_objects(None, 1000).await.unwrap()),
+            vec!["photos/", "photos/2024/jan.jpg"]
+        );
+        assert_eq!(
+            keys(storage.list_objects(Some("photos/"), 1).await.unwrap()),
+            vec!["photos/"]
+        );
+
+        // The folder outlives its objects until the marker itself is deleted
+        storage
+            .delete_object("photos/2024/jan.jpg", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            keys(storage.list_objects(None, 1000).await.unwrap()),
+            vec!["photos/"]
+        );
+        storage.delete_object("photos/", None).await.unwrap();
+        assert!(!dir.path().join("photos").exists());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_put_and_delete_under_shared_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+
+        for _ in 0..200 {
+            put(&storage, "shared/dir/old.txt", "old").await;
+            // The delete prunes the directory the put is about to rename into
+            let (put_result, delete_result) = tokio::join!(
+                tokio::spawn({
+                    let storage = storage.clone();
+                    async move {
+                        storage
+                            .put_object(
+                                "shared/dir/new.txt",
+                                bytes_to_stream(Bytes::from("new")),
+                                &PutObjectOptions::default(),
+                            )
+                            .await
+                    }
+                }),
+                tokio::spawn({
+                    let storage = storage.clone();
+                    async move { storage.delete_object("shared/dir/old.txt", None).await }
+                }),
+            );
+            put_result.unwrap().unwrap();
+            delete_result.unwrap().unwrap();
+
+            let (stream, _) = storage
+                .get_object("shared/dir/new.txt", &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(collect(stream).await, b"new");
+            storage
+                .delete_object("shared/dir/new.txt", None)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_files_without_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemBackend::new(dir.path());
+        std::fs::write(dir.path().join("copied.bin"), b"copied by hand").unwrap();
+
+        let metadata = storage
+            .head_object("copied.bin", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, 14);
+        assert_eq!(metadata.content_type, "binary/octet-stream");
+
+        // A stale sidecar is ignored too
+        put(&storage, "replaced.txt", "old content").await;
+        std::fs::write(dir.path().join("replaced.txt"), b"new").unwrap();
+        let metadata = storage
+            .head_object("replaced.txt", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, 3);
+        assert_ne!(
+            metadata.etag,
+            format!("\"{}\"", hex::encode(Md5::digest(b"old content")))
+        );
+    }
+}
@@ -0,0 +1,826 @@
+//! Storage backend for a Google Cloud Storage bucket, using the JSON API
+//!
+//! Requests are authorized with OAuth access tokens obtained for a service account key, or
+//! sent without authorization for emulators such as fake-gcs-server. Uploads that fit in one
+//! chunk are sent as a single multipart upload, larger ones through a resumable upload
+//! session, so only one chunk is held in memory.
+//!
+//! GCS has no object tags, so tags are kept in the object's custom metadata.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use bytes::{Bytes, BytesMut};
+use futures::stream::StreamExt;
+use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, LOCATION, RANGE};
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+/// Lifetime requested for access tokens, the longest Google allows
+const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+/// Access tokens are renewed this long before they expire
+const TOKEN_RENEWAL_MARGIN: Duration = Duration::from_secs(60);
+/// Size of the chunks of resumable uploads (must be a multiple of 256 KiB)
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Largest page the objects.list method returns
+const MAX_LIST_RESULTS: usize = 1000;
+/// Custom metadata holding the object's tags, URL-encoded
+const TAGS_METADATA_KEY: &str = "replicat4-tags";
+
+/// Fields of a service account JSON key used to obtain access tokens
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_string()
+}
+
+impl ServiceAccountKey {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Claims of the JWT exchanged for an access token
+#[derive(Serialize)]
+struct TokenClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct AccessToken {
+    token: String,
+    renew_at: Instant,
+}
+
+/// Access tokens for a service account, renewed shortly before they expire
+struct TokenSource {
+    key: ServiceAccountKey,
+    encoding_key: jsonwebtoken::EncodingKey,
+    cached: Mutex<Option<AccessToken>>,
+}
+
+impl TokenSource {
+    fn new(key: ServiceAccountKey) -> Result<Self, Box<dyn std::error::Error>> {
+        let encoding_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())?;
+        Ok(Self {
+            key,
+            encoding_key,
+            cached: Mutex::new(None),
+        })
+    }
+
+    async fn token(&self, client: &reqwest::Client) -> Result<String, S3Error> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached
+            .as_ref()
+            .filter(|token| token.renew_at > Instant::now())
+        {
+            return Ok(token.token.clone());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = TokenClaims {
+            iss: &self.key.client_email,
+            scope: SCOPE,
+            aud: &self.key.token_uri,
+            iat: now,
+            exp: now + TOKEN_LIFETIME.as_secs() as i64,
+        };
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &self.encoding_key,
+        )
+        .map_err(|e| S3Error::InternalError(format!("Failed to sign GCS token request: {}", e)))?;
+
+        let response = client
+            .post(&self.key.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(request_error)?;
+        let response: TokenResponse = check_response(response)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)?;
+
+        let lifetime =
+            Duration::from_secs(response.expires_in).saturating_sub(TOKEN_RENEWAL_MARGIN);
+        tracing::debug!("Obtained GCS access token for {}", self.key.client_email);
+        *cached = Some(AccessToken {
+            token: response.access_token.clone(),
+            renew_at: Instant::now() + lifetime,
+        });
+        Ok(response.access_token)
+    }
+}
+
+pub struct GcsBackend {
+    client: reqwest::Client,
+    bucket: String,
+    endpoint: Url,
+    /// None to send requests without authorization, e.g. to fake-gcs-server
+    tokens: Option<TokenSource>,
+}
+
+/// Object resource of the JSON API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsObject {
+    name: String,
+    /// Decimal size, sent as a string
+    size: String,
+    etag: Option<String>,
+    /// Base64-encoded MD5 of the data, missing for composite objects
+    md5_hash: Option<String>,
+    generation: Option<String>,
+    updated: Option<chrono::DateTime<chrono::Utc>>,
+    content_type: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+impl GcsObject {
+    /// S3-style ETag: the hex MD5 of the data where GCS has it
+    fn s3_etag(&self) -> String {
+        match self
+            .md5_hash
+            .as_ref()
+            .and_then(|md5| BASE64.decode(md5).ok())
+        {
+            Some(md5) => format!("\"{}\"", hex::encode(md5)),
+            None => format!("\"{}\"", self.etag.as_deref().unwrap_or_default()),
+        }
+    }
+
+    fn tags(&self) -> Vec<Tag> {
+        self.metadata
+            .get(TAGS_METADATA_KEY)
+            .and_then(|tags| serde_urlencoded::from_str::<Vec<(String, String)>>(tags).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| Tag { key, value })
+            .collect()
+    }
+
+    fn into_metadata(mut self) -> ObjectMetadata {
+        let etag = self.s3_etag();
+        self.metadata.remove(TAGS_METADATA_KEY);
+        ObjectMetadata {
+            size: self.size.parse().unwrap_or(0),
+            etag,
+            last_modified: self.updated.unwrap_or_else(chrono::Utc::now),
+            content_type: self
+                .content_type
+                .unwrap_or_else(|| "binary/octet-stream".to_string()),
+            version_id: None,
+            user_metadata: self.metadata,
+            key: self.name,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectList {
+    #[serde(default)]
+    items: Vec<GcsObject>,
+    next_page_token: Option<String>,
+}
+
+/// Object resource sent when creating an object
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NewObject<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<&'a str>,
+    metadata: HashMap<String, String>,
+}
+
+impl<'a> NewObject<'a> {
+    fn new(key: &'a str, options: &'a PutObjectOptions) -> Result<Self, S3Error> {
+        let mut metadata = options.user_metadata.clone();
+        if !options.tags.is_empty() {
+            metadata.insert(TAGS_METADATA_KEY.to_string(), encode_tags(&options.tags)?);
+        }
+        Ok(Self {
+            name: key,
+            content_type: options.content_type.as_deref(),
+            metadata,
+        })
+    }
+}
+
+fn encode_tags(tags: &[Tag]) -> Result<String, S3Error> {
+    let tags: Vec<(&str, &str)> = tags
+        .iter()
+        .map(|tag| (tag.key.as_str(), tag.value.as_str()))
+        .collect();
+    serde_urlencoded::to_string(tags)
+        .map_err(|e| S3Error::InternalError(format!("Failed to encode tags: {}", e)))
+}
+
+/// Body of a multipart upload: the object resource followed by the data
+fn multipart_body(resource: &[u8], content_type: &str, data: &[u8], boundary: &str) -> Vec<u8> {
+    let mut body = Vec::with_capacity(resource.len() + data.len() + 256);
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n",
+            boundary
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(resource);
+    body.extend_from_slice(
+        format!(
+            "\r\n--{}\r\nContent-Type: {}\r\n\r\n",
+            boundary, content_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+fn request_error(e: reqwest::Error) -> S3Error {
+    S3Error::InternalError(format!("GCS request failed: {}", e))
+}
+
+fn json_error(e: serde_json::Error) -> S3Error {
+    S3Error::InternalError(format!("Failed to encode GCS request: {}", e))
+}
+
+/// Map an unsuccessful response to an S3 error
+async fn check_response(response: Response) -> Result<Response, S3Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status.as_u16() {
+        404 => S3Error::NoSuchKey,
+        401 | 403 => S3Error::AccessDenied,
+        416 => S3Error::InvalidRange,
+        _ => {
+            let message = response.text().await.unwrap_or_default();
+            S3Error::InternalError(format!("GCS returned {}: {}", status, message))
+        }
+    })
+}
+
+impl GcsBackend {
+    /// Create a backend for a bucket
+    ///
+    /// `endpoint` defaults to `https://storage.googleapis.com`. Without a service account key,
+    /// requests are sent without authorization.
+    pub fn new(
+        bucket: String,
+        endpoint: Option<String>,
+        key: Option<ServiceAccountKey>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+        let endpoint = Url::parse(&endpoint)?;
+        if endpoint.cannot_be_a_base() {
+            return Err(format!("Invalid GCS endpoint: {}", endpoint).into());
+        }
+        // Resumable uploads answer 308 without meaning a redirect
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        Ok(Self {
+            client,
+            bucket,
+            endpoint,
+            tokens: key.map(TokenSource::new).transpose()?,
+        })
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.endpoint.clone();
+        // The endpoint was checked to have path segments
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    fn bucket_url(&self) -> Url {
+        self.url(&["storage", "v1", "b", &self.bucket])
+    }
+
+    fn objects_url(&self) -> Url {
+        self.url(&["storage", "v1", "b", &self.bucket, "o"])
+    }
+
+    /// URL of an object, with the whole key as one segment (`/` is escaped)
+    fn object_url(&self, key: &str) -> Url {
+        self.url(&["storage", "v1", "b", &self.bucket, "o", key])
+    }
+
+    fn upload_url(&self) -> Url {
+        self.url(&["upload", "storage", "v1", "b", &self.bucket, "o"])
+    }
+
+    /// Start an authorized request
+    async fn request(&self, method: Method, url: Url) -> Result<RequestBuilder, S3Error> {
+        let request = self.client.request(method, url);
+        Ok(match &self.tokens {
+            Some(tokens) => request.bearer_auth(tokens.token(&self.client).await?),
+            None => request,
+        })
+    }
+
+    async fn get_resource(&self, key: &str) -> Result<GcsObject, S3Error> {
+        let response = self
+            .request(Method::GET, self.object_url(key))
+            .await?
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_response(response)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)
+    }
+
+    /// Upload an object that fits in a single request
+    async fn upload_multipart(
+        &self,
+        resource: &NewObject<'_>,
+        data: Bytes,
+    ) -> Result<GcsObject, S3Error> {
+        let boundary = format!("replicat4-{}", uuid::Uuid::new_v4().simple());
+        let body = multipart_body(
+            &serde_json::to_vec(resource).map_err(json_error)?,
+            resource.content_type.unwrap_or("application/octet-stream"),
+            &data,
+            &boundary,
+        );
+        let mut url = self.upload_url();
+        url.query_pairs_mut().append_pair("uploadType", "multipart");
+
+        let response = self
+            .request(Method::POST, url)
+            .await?
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/related; boundary={}", boundary),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_response(response)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)
+    }
+
+    /// Upload an object chunk by chunk through a resumable upload session
+    async fn upload_resumable(
+        &self,
+        resource: &NewObject<'_>,
+        mut buffer: BytesMut,
+        mut body: ObjectStream,
+    ) -> Result<GcsObject, S3Error> {
+        let mut url = self.upload_url();
+        url.query_pairs_mut().append_pair("uploadType", "resumable");
+        let response = self
+            .request(Method::POST, url)
+            .await?
+            .header(CONTENT_TYPE, "application/json; charset=UTF-8")
+            .body(serde_json::to_vec(resource).map_err(json_error)?)
+            .send()
+            .await
+            .map_err(request_error)?;
+        let response = check_response(response).await?;
+        let session = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| Url::parse(location).ok())
+            .ok_or_else(|| {
+                S3Error::InternalError("GCS didn't return a resumable upload session".to_string())
+            })?;
+
+        let mut offset = 0u64;
+        let mut finished = false;
+        loop {
+            // A chunk is only known not to be the last one once more data has been read
+            while !finished && buffer.len() <= CHUNK_SIZE {
+                match body.next().await {
+                    Some(chunk) => buffer.extend_from_slice(&chunk?),
+                    None => finished = true,
+                }
+            }
+
+            let is_last = finished && buffer.len() <= CHUNK_SIZE;
+            let chunk = buffer.split_to(buffer.len().min(CHUNK_SIZE)).freeze();
+            let end = offset + chunk.len() as u64;
+            let content_range = match (is_last, chunk.is_empty()) {
+                (true, true) => format!("bytes */{}", end),
+                (true, false) => format!("bytes {}-{}/{}", offset, end - 1, end),
+                (false, _) => format!("bytes {}-{}/*", offset, end - 1),
+            };
+
+            let response = self
+                .request(Method::PUT, session.clone())
+                .await?
+                .header(CONTENT_RANGE, content_range)
+                .body(chunk)
+                .send()
+                .await
+                .map_err(request_error)?;
+            if is_last {
+                return check_response(response)
+                    .await?
+                    .json()
+                    .await
+                    .map_err(request_error);
+            }
+
+            // 308 Resume Incomplete, with the range GCS has persisted
+            if response.status() != StatusCode::PERMANENT_REDIRECT {
+                check_response(response).await?;
+                return Err(S3Error::InternalError(
+                    "GCS completed the upload before the last chunk".to_string(),
+                ));
+            }
+            let persisted = response
+                .headers()
+                .get(RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(|range| range.rsplit_once('-'))
+                .and_then(|(_, last)| last.parse::<u64>().ok());
+            if persisted != Some(end - 1) {
+                return Err(S3Error::InternalError(format!(
+                    "GCS persisted {:?} of the {} bytes sent",
+                    persisted, end
+                )));
+            }
+            offset = end;
+        }
+    }
+
+    async fn set_tags(&self, key: &str, tags: &[Tag]) -> Result<(), S3Error> {
+        // Patching metadata to null removes the key
+        let value = match tags.is_empty() {
+            true => serde_json::Value::Null,
+            false => serde_json::Value::String(encode_tags(tags)?),
+        };
+        let patch = serde_json::json!({ "metadata": { TAGS_METADATA_KEY: value } });
+        let response = self
+            .request(Method::PATCH, self.object_url(key))
+            .await?
+            .json(&patch)
+            .send()
+            .await
+            .map_err(request_error)?;
+        check_response(response).await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for GcsBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        let response = self
+            .request(Method::GET, self.bucket_url())
+            .await?
+            .send()
+            .await
+            .map_err(request_error)?;
+        match check_response(response).await {
+            Ok(_) => Ok(()),
+            Err(S3Error::NoSuchKey) => Err(S3Error::NoSuchBucket),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let max_keys = max_keys.max(0) as usize;
+        let mut objects = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        while objects.len() < max_keys {
+            let mut url = self.objects_url();
+            {
+                let mut query = url.query_pairs_mut();
+                query.append_pair(
+                    "maxResults",
+                    &(max_keys - objects.len()).min(MAX_LIST_RESULTS).to_string(),
+                );
+                if let Some(prefix) = prefix {
+                    query.append_pair("prefix", prefix);
+                }
+                if let Some(page_token) = &page_token {
+                    query.append_pair("pageToken", page_token);
+                }
+            }
+
+            let response = self
+                .request(Method::GET, url)
+                .await?
+                .send()
+                .await
+                .map_err(request_error)?;
+            let list: ObjectList = match check_response(response).await {
+                Ok(response) => response.json().await.map_err(request_error)?,
+                Err(S3Error::NoSuchKey) => return Err(S3Error::NoSuchBucket),
+                Err(e) => return Err(e),
+            };
+            objects.extend(list.items.into_iter().map(GcsObject::into_metadata));
+
+            page_token = list.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        objects.truncate(max_keys);
+        Ok(objects)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Object versioning isn't used, so bucket versioning was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        let object = self.get_resource(key).await?;
+        options.check_single_part()?;
+        Ok(object.into_metadata())
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        let object = self.get_resource(key).await?;
+        options.check_single_part()?;
+
+        // The data is read from the generation the metadata describes
+        let mut url = self.object_url(key);
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("alt", "media");
+            if let Some(generation) = &object.generation {
+                query.append_pair("generation", generation);
+            }
+        }
+        let metadata = object.into_metadata();
+        let mut request = self.request(Method::GET, url).await?;
+        if let Some(range) = &options.range {
+            let (start, end) = range.resolve(metadata.size)?;
+            request = request.header(RANGE, format!("bytes={}-{}", start, end));
+        }
+
+        let response = request.send().await.map_err(request_error)?;
+        let stream = check_response(response)
+            .await?
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(request_error));
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        options.reject_sse_customer("GCS")?;
+        let resource = NewObject::new(key, options)?;
+
+        // Objects that fit in a single chunk are uploaded in one request
+        let mut buffer = BytesMut::new();
+        let mut finished = false;
+        while !finished && buffer.len() <= CHUNK_SIZE {
+            match body.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => finished = true,
+            }
+        }
+        let object = if finished {
+            self.upload_multipart(&resource, buffer.freeze()).await?
+        } else {
+            self.upload_resumable(&resource, buffer, body).await?
+        };
+
+        Ok(PutObjectOutput {
+            etag: object.s3_etag(),
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+
+        let response = self
+            .request(Method::DELETE, self.object_url(key))
+            .await?
+            .send()
+            .await
+            .map_err(request_error)?;
+        match check_response(response).await {
+            Ok(_) | Err(S3Error::NoSuchKey) => Ok(output),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        check_null_version(version_id)?;
+        Ok(self.get_resource(key).await?.tags())
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        self.set_tags(key, &tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        self.set_tags(key, &[]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_resource_to_metadata() {
+        let object: GcsObject = serde_json::from_str(
+            r#"{
+                "kind": "storage#object",
+                "name": "photos/2024/jan.jpg",
+                "bucket": "replicat4",
+                "generation": "1737979200000000",
+                "size": "13",
+                "md5Hash": "ZajifYh5KDgxtmS9i38K1A==",
+                "etag": "CIDAqu/x0IsDEAE=",
+                "updated": "2025-01-27T12:00:00.000Z",
+                "contentType": "image/jpeg",
+                "metadata": {
+                    "owner": "alice",
+                    "replicat4-tags": "env=prod&team=a%26b"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            object.tags(),
+            vec![
+                Tag {
+                    key: "env".to_string(),
+                    value: "prod".to_string(),
+                },
+                Tag {
+                    key: "team".to_string(),
+                    value: "a&b".to_string(),
+                },
+            ]
+        );
+        let metadata = object.into_metadata();
+        assert_eq!(metadata.key, "photos/2024/jan.jpg");
+        assert_eq!(metadata.size, 13);
+        // MD5 of "Hello, World!"
+        assert_eq!(metadata.etag, "\"65a8e27d8879283831b664bd8b7f0ad4\"");
+        assert_eq!(metadata.content_type, "image/jpeg");
+        assert_eq!(
+            metadata.user_metadata,
+            HashMap::from([("owner".to_string(), "alice".to_string())])
+        );
+
+        // Composite objects have no MD5
+        let object: GcsObject =
+            serde_json::from_str(r#"{"name": "composite", "size": "0", "etag": "CAE="}"#).unwrap();
+        assert_eq!(object.s3_etag(), "\"CAE=\"");
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let options = PutObjectOptions {
+            content_type: Some("text/plain".to_string()),
+            tags: vec![Tag {
+                key: "env".to_string(),
+                value: "prod".to_string(),
+            }],
+            ..Default::default()
+        };
+        let resource = NewObject::new("notes.txt", &options).unwrap();
+        let resource = serde_json::to_vec(&resource).unwrap();
+        assert_eq!(
+            String::from_utf8(resource.clone()).unwrap(),
+            r#"{"name":"notes.txt","contentType":"text/plain","metadata":{"replicat4-tags":"env=prod"}}"#
+        );
+
+        let body = multipart_body(&resource, "text/plain", b"data", "boundary");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            format!(
+                "--boundary\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{}\r\n\
+                 --boundary\r\nContent-Type: text/plain\r\n\r\ndata\r\n--boundary--\r\n",
+                String::from_utf8(resource).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_object_urls() {
+        let backend = GcsBackend::new(
+            "replicat4".to_string(),
+            Some("http://localhost:4443/".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            backend.object_url("photos/a b.jpg").as_str(),
+            "http://localhost:4443/storage/v1/b/replicat4/o/photos%2Fa%20b.jpg"
+        );
+        assert_eq!(
+            backend.upload_url().as_str(),
+            "http://localhost:4443/upload/storage/v1/b/replicat4/o"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Read-only storage backend for a plain HTTP(S) file server
+//!
+//! Each key maps to the URL at the same path under a base URL, and objects are read with GET
+//! and HEAD requests. Their metadata comes from the response headers. Static servers can't be
+//! listed or written to, so the backend only serves reads of known keys, for example as a
+//! fallback while data is migrated off a legacy origin.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version,
+};
+use crate::types::{ObjectMetadata, Tag, VersioningStatus, error::S3Error};
+use futures::stream::StreamExt;
+use reqwest::header::{
+    CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, HeaderMap, LAST_MODIFIED, RANGE,
+};
+use reqwest::{Method, Response, StatusCode, Url};
+use std::collections::HashMap;
+
+pub struct HttpBackend {
+    client: reqwest::Client,
+    name: String,
+    /// URL keys are resolved against, ending with '/'
+    base: Url,
+}
+
+fn request_error(e: reqwest::Error) -> S3Error {
+    S3Error::InternalError(format!("HTTP request failed: {}", e))
+}
+
+/// Map an unsuccessful response to an S3 error
+async fn check_response(response: Response) -> Result<Response, S3Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status.as_u16() {
+        404 | 410 => S3Error::NoSuchKey,
+        401 | 403 => S3Error::AccessDenied,
+        416 => S3Error::InvalidRange,
+        _ => {
+            let message = response.text().await.unwrap_or_default();
+            S3Error::InternalError(format!("HTTP server returned {}: {}", status, message))
+        }
+    })
+}
+
+/// Total size of the object from the `Content-Range` header of a 206 response
+fn content_range_size(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// Object metadata from the headers of a response for the whole object, or for part of it
+/// when the size is known from elsewhere
+fn metadata_from_headers(key: &str, headers: &HeaderMap, size: u64) -> ObjectMetadata {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let last_modified = header(LAST_MODIFIED)
+        .and_then(|date| chrono::DateTime::parse_from_rfc2822(date.trim()).ok())
+        .map(|date| date.to_utc());
+    // Servers without strong ETags get one derived from the modification time and size
+    let etag = match header(ETAG).map(str::trim) {
+        Some(etag) if !etag.starts_with("W/") && !etag.trim_matches('"').is_empty() => {
+            format!("\"{}\"", etag.trim_matches('"'))
+        }
+        _ => format!(
+            "\"{:x}-{:x}\"",
+            last_modified.map(|date| date.timestamp()).unwrap_or(0),
+            size
+        ),
+    };
+    ObjectMetadata {
+        key: key.to_string(),
+        size,
+        etag,
+        last_modified: last_modified.unwrap_or_else(chrono::Utc::now),
+        content_type: header(CONTENT_TYPE)
+            .filter(|content_type| !content_type.is_empty())
+            .unwrap_or("binary/octet-stream")
+            .to_string(),
+        version_id: None,
+        user_metadata: HashMap::new(),
+    }
+}
+
+impl HttpBackend {
+    /// Create a backend reading the objects under `url`
+    pub fn new(name: String, url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut base = Url::parse(url)?;
+        if base.cannot_be_a_base() || !matches!(base.scheme(), "http" | "https") {
+            return Err(format!("Invalid HTTP URL: {}", url).into());
+        }
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            name,
+            base,
+        })
+    }
+
+    /// URL of a key, None for keys that can't name a file under the base URL
+    fn url(&self, key: &str) -> Option<Url> {
+        if !key
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+        {
+            return None;
+        }
+        let mut url = self.base.clone();
+        // The base was checked to have path segments
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().extend(key.split('/'));
+        }
+        Some(url)
+    }
+
+    /// Send a request for an object, which must exist
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<Response, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        options.check_single_part()?;
+        let url = self.url(key).ok_or(S3Error::NoSuchKey)?;
+
+        let mut request = self.client.request(method, url);
+        if let Some(range) = options.range {
+            request = request.header(RANGE, range.header_value());
+        }
+        let response = request.send().await.map_err(request_error)?;
+        check_response(response).await
+    }
+
+    fn read_only_error(&self) -> S3Error {
+        S3Error::NotImplemented(format!("The HTTP backend '{}' is read-only", self.name))
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for HttpBackend {
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        // Static servers commonly refuse to show directories, any answer means it is up
+        let response = self
+            .client
+            .head(self.base.clone())
+            .send()
+            .await
+            .map_err(request_error)?;
+        if response.status().is_server_error() {
+            return Err(S3Error::InternalError(format!(
+                "HTTP server returned {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn list_objects(
+        &self,
+        _prefix: Option<&str>,
+        _max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        Err(S3Error::NotImplemented(format!(
+            "Listing is not supported by the HTTP backend '{}'",
+            self.name
+        )))
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Versioning can't be enabled, so it was never configured
+        Ok(None)
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        // The range only matters for the body
+        let options = GetObjectOptions {
+            range: None,
+            ..options.clone()
+        };
+        let response = self.send(Method::HEAD, key, &options).await?;
+        let size = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok())
+            .unwrap_or(0);
+        Ok(metadata_from_headers(key, response.headers(), size))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        let response = self.send(Method::GET, key, options).await?;
+        let size = match (&options.range, response.status()) {
+            (None, _) => response.content_length(),
+            (Some(_), StatusCode::PARTIAL_CONTENT) => content_range_size(response.headers()),
+            (Some(_), _) => {
+                return Err(S3Error::InternalError(format!(
+                    "The HTTP backend '{}' doesn't support ranged reads",
+                    self.name
+                )));
+            }
+        };
+        let size = size.ok_or_else(|| {
+            S3Error::InternalError(format!(
+                "The HTTP backend '{}' didn't return the size of {}",
+                self.name, key
+            ))
+        })?;
+
+        let metadata = metadata_from_headers(key, response.headers(), size);
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(request_error));
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        _key: &str,
+        _body: ObjectStream,
+        _options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        Err(self.read_only_error())
+    }
+
+    async fn delete_object(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        Err(self.read_only_error())
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        let options = GetObjectOptions {
+            version_id: version_id.map(str::to_string),
+            ..Default::default()
+        };
+        self.head_object(key, &options).await?;
+        Ok(Vec::new())
+    }
+
+    async fn put_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+        _tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        Err(self.read_only_error())
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        Err(self.read_only_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_key_urls() {
+        let backend =
+            HttpBackend::new("legacy".to_string(), "https://files.example.com/static").unwrap();
+        assert_eq!(
+            backend.url("images/a b.png").unwrap().as_str(),
+            "https://files.example.com/static/images/a%20b.png"
+        );
+        assert_eq!(
+            backend.url("docs/100%.txt").unwrap().as_str(),
+            "https://files.example.com/static/docs/100%25.txt"
+        );
+        assert!(backend.url("../secret").is_none());
+        assert!(backend.url("images/").is_none());
+        assert!(HttpBackend::new("ftp".to_string(), "ftp://files.example.com/").is_err());
+    }
+
+    #[test]
+    fn test_metadata_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"5e8f-61a\""));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Tue, 03 Jun 2025 10:20:30 GMT"),
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-99/1562"));
+
+        let metadata = metadata_from_headers("logo.png", &headers, 1562);
+        assert_eq!(metadata.etag, "\"5e8f-61a\"");
+        assert_eq!(metadata.content_type, "image/png");
+        assert_eq!(metadata.last_modified.timestamp(), 1748946030);
+        assert_eq!(content_range_size(&headers), Some(1562));
+
+        // Weak ETags don't identify the bytes, so one is derived instead
+        headers.insert(ETAG, HeaderValue::from_static("W/\"5e8f-61a\""));
+        let metadata = metadata_from_headers("logo.png", &headers, 1562);
+        assert_eq!(metadata.etag, "\"683ecc6e-61a\"");
+    }
+}
@@ -1,21 +1,97 @@
-use super::backend::{ObjectStream, StorageBackend};
-use crate::types::{ObjectMetadata, error::S3Error};
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    SseCustomerKey, StorageBackend,
+};
+use crate::types::{
+    NULL_VERSION_ID, ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error,
+};
 use bytes::{Bytes, BytesMut};
 use futures::stream::{self, StreamExt};
-use std::collections::HashMap;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
-/// In-memory storage backend for testing/development
+/// Magic bytes and format version at the start of a snapshot file
+const SNAPSHOT_MAGIC: &[u8; 4] = b"RT4M";
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// In-memory storage backend for testing/development, or as a cache replica
+///
+/// With capacity limits, the least recently used keys are evicted to make room for new
+/// writes. The contents can be saved to a snapshot file and loaded back on startup.
 #[derive(Clone)]
 pub struct InMemoryStorage {
-    objects: Arc<RwLock<HashMap<String, StoredObject>>>,
+    /// Version history per key, oldest first (the last entry is the latest version)
+    objects: Arc<RwLock<HashMap<String, Vec<StoredObject>>>>,
+    versioning: Arc<RwLock<Option<VersioningStatus>>>,
+    /// Memory use and access order of the keys, locked after `objects` when both are needed
+    usage: Arc<Mutex<Usage>>,
+    /// Total size of the stored versions, unlimited if None
+    max_bytes: Option<u64>,
+    /// Number of keys, unlimited if None
+    max_objects: Option<usize>,
+}
+
+/// Memory use and access order of the stored keys
+#[derive(Default)]
+struct Usage {
+    bytes: u64,
+    /// Tick of the last access to each key
+    last_access: HashMap<String, u64>,
+    /// Keys by last access, least recently used first
+    by_access: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl Usage {
+    /// Mark a key as the most recently used one
+    fn touch(&mut self, key: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(previous) = self.last_access.insert(key.to_string(), tick) {
+            self.by_access.remove(&previous);
+        }
+        self.by_access.insert(tick, key.to_string());
+    }
+
+    fn forget(&mut self, key: &str) {
+        if let Some(previous) = self.last_access.remove(key) {
+            self.by_access.remove(&previous);
+        }
+    }
 }
 
 #[derive(Clone)]
 struct StoredObject {
-    data: Bytes,
+    /// Object body, None for delete markers
+    data: Option<Bytes>,
     metadata: ObjectMetadata,
+    tags: Vec<Tag>,
+    /// MD5 of the customer-provided key the object was stored with (SSE-C)
+    sse_customer_key_md5: Option<String>,
+}
+
+impl StoredObject {
+    fn size(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.len() as u64)
+    }
+
+    fn is_delete_marker(&self) -> bool {
+        self.data.is_none()
+    }
+
+    /// Whether this version is addressed by `version_id` (unversioned objects answer to "null")
+    fn has_version(&self, version_id: &str) -> bool {
+        self.metadata
+            .version_id
+            .as_deref()
+            .unwrap_or(NULL_VERSION_ID)
+            == version_id
+    }
 }
 
 impl Default for InMemoryStorage {
@@ -28,14 +104,390 @@ impl InMemoryStorage {
     pub fn new() -> Self {
         Self {
             objects: Arc::new(RwLock::new(HashMap::new())),
+            versioning: Arc::new(RwLock::new(None)),
+            usage: Arc::new(Mutex::new(Usage::default())),
+            max_bytes: None,
+            max_objects: None,
+        }
+    }
+
+    /// Limit the total size of the stored versions and the number of keys
+    ///
+    /// Evicting a key drops all of its versions, so limits don't suit versioned buckets.
+    pub fn with_limits(mut self, max_bytes: Option<u64>, max_objects: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_objects = max_objects;
+        self
+    }
+
+    /// Change the version history of a key, keeping the memory use and access order up to date
+    ///
+    /// Keys left without versions are removed.
+    fn update_history<R>(
+        &self,
+        objects: &mut HashMap<String, Vec<StoredObject>>,
+        key: &str,
+        change: impl FnOnce(&mut Vec<StoredObject>) -> R,
+    ) -> R {
+        let history = objects.entry(key.to_string()).or_default();
+        let before: u64 = history.iter().map(StoredObject::size).sum();
+        let result = change(history);
+        let after: u64 = history.iter().map(StoredObject::size).sum();
+        let is_empty = history.is_empty();
+        if is_empty {
+            objects.remove(key);
+        }
+
+        let mut usage = self.usage.lock().unwrap();
+        usage.bytes = usage.bytes - before + after;
+        if is_empty {
+            usage.forget(key);
+        } else {
+            usage.touch(key);
+        }
+        result
+    }
+
+    /// Evict the least recently used keys until the store is within its limits
+    ///
+    /// `keep` is the key that was just written, which is never evicted.
+    fn evict(&self, objects: &mut HashMap<String, Vec<StoredObject>>, keep: Option<&str>) {
+        let mut usage = self.usage.lock().unwrap();
+        while self.max_bytes.is_some_and(|max| usage.bytes > max)
+            || self.max_objects.is_some_and(|max| objects.len() > max)
+        {
+            let Some(key) = usage
+                .by_access
+                .values()
+                .find(|key| Some(key.as_str()) != keep)
+                .cloned()
+            else {
+                break;
+            };
+            // Every version and delete marker of the key goes with it
+            let versions = match objects.remove(&key) {
+                Some(history) => {
+                    usage.bytes -= history.iter().map(StoredObject::size).sum::<u64>();
+                    history.len()
+                }
+                None => 0,
+            };
+            usage.forget(&key);
+            tracing::warn!(
+                "Evicted {} ({} versions) from in-memory storage",
+                key,
+                versions
+            );
         }
     }
 
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: i32,
+    ) -> Vec<ObjectMetadata> {
+        let objects = self.objects.read().await;
+
+        let mut results: Vec<ObjectMetadata> = objects
+            .iter()
+            .filter_map(|(key, history)| {
+                if let Some(prefix_str) = prefix
+                    && !key.starts_with(prefix_str)
+                {
+                    return None;
+                }
+                if start_after.is_some_and(|after| key.as_str() <= after) {
+                    return None;
+                }
+
+                // Keys whose latest version is a delete marker are hidden
+                history
+                    .last()
+                    .filter(|obj| !obj.is_delete_marker())
+                    .map(|obj| obj.metadata.clone())
+            })
+            .collect();
+
+        results.sort_by(|a, b| a.key.cmp(&b.key));
+        results.truncate(max_keys as usize);
+        results
+    }
+
+    fn touch(&self, key: &str) {
+        self.usage.lock().unwrap().touch(key);
+    }
+
     fn calculate_etag(data: &[u8]) -> String {
         use sha2::{Digest, Sha256};
         let hash = Sha256::digest(data);
         format!("\"{}\"", hex::encode(hash))
     }
+
+    /// Version ID for a new write, based on the bucket's versioning state
+    fn new_version_id(versioning: Option<VersioningStatus>) -> Option<String> {
+        match versioning {
+            None => None,
+            Some(VersioningStatus::Suspended) => Some(NULL_VERSION_ID.to_string()),
+            Some(VersioningStatus::Enabled) => Some(uuid::Uuid::new_v4().simple().to_string()),
+        }
+    }
+
+    /// Add a new latest version to a key's history
+    ///
+    /// Writes that don't create a distinct version replace the key's "null" version, like S3.
+    fn push_version(history: &mut Vec<StoredObject>, object: StoredObject) {
+        if object.has_version(NULL_VERSION_ID) {
+            history.retain(|existing| !existing.has_version(NULL_VERSION_ID));
+        }
+        history.push(object);
+    }
+
+    /// Find the mutable version a tagging request refers to
+    fn find_version_mut<'a>(
+        history: Option<&'a mut Vec<StoredObject>>,
+        version_id: Option<&str>,
+    ) -> Result<&'a mut StoredObject, S3Error> {
+        let history = history.ok_or(S3Error::NoSuchKey)?;
+        let obj = match version_id {
+            Some(version_id) => history
+                .iter_mut()
+                .find(|obj| obj.has_version(version_id))
+                .ok_or(S3Error::NoSuchVersion)?,
+            None => history.last_mut().ok_or(S3Error::NoSuchKey)?,
+        };
+
+        if obj.is_delete_marker() {
+            return Err(match version_id {
+                Some(_) => S3Error::MethodNotAllowed,
+                None => S3Error::NoSuchKey,
+            });
+        }
+        Ok(obj)
+    }
+
+    /// Find the version a read refers to
+    fn find_version<'a>(
+        history: Option<&'a Vec<StoredObject>>,
+        options: &GetObjectOptions,
+    ) -> Result<&'a StoredObject, S3Error> {
+        match &options.version_id {
+            Some(version_id) => {
+                let obj = history
+                    .and_then(|history| history.iter().find(|obj| obj.has_version(version_id)))
+                    .ok_or(S3Error::NoSuchVersion)?;
+                if obj.is_delete_marker() {
+                    return Err(S3Error::MethodNotAllowed);
+                }
+                options.check_single_part()?;
+                Ok(obj)
+            }
+            None => {
+                let obj = history
+                    .and_then(|history| history.last())
+                    .filter(|obj| !obj.is_delete_marker())
+                    .ok_or(S3Error::NoSuchKey)?;
+                options.check_single_part()?;
+                Ok(obj)
+            }
+        }
+    }
+}
+
+/// First record of a snapshot
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader {
+    versioning: Option<String>,
+}
+
+/// Record of a single version in a snapshot, followed by the version's body
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    key: String,
+    etag: String,
+    last_modified: chrono::DateTime<chrono::Utc>,
+    content_type: String,
+    version_id: Option<String>,
+    #[serde(default)]
+    user_metadata: HashMap<String, String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    sse_customer_key_md5: Option<String>,
+    /// Size of the body, None for delete markers
+    size: Option<u64>,
+}
+
+fn invalid_snapshot(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+/// Write a length-prefixed JSON record
+fn write_record<T: Serialize>(writer: &mut impl Write, record: &T) -> std::io::Result<()> {
+    let json = serde_json::to_vec(record)?;
+    let length = u32::try_from(json.len()).map_err(|_| invalid_snapshot("Record is too large"))?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&json)
+}
+
+/// Read a length-prefixed JSON record, None at the end of the file
+fn read_record<T: for<'de> Deserialize<'de>>(reader: &mut impl Read) -> std::io::Result<Option<T>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut json = vec![0u8; u32::from_be_bytes(length) as usize];
+    reader.read_exact(&mut json)?;
+    Ok(Some(serde_json::from_slice(&json)?))
+}
+
+impl InMemoryStorage {
+    /// Save the contents to a snapshot file, replacing it atomically
+    ///
+    /// Keys are written least recently used first, so loading the snapshot restores the
+    /// eviction order.
+    pub async fn save_snapshot(&self, path: &Path) -> std::io::Result<()> {
+        let header = SnapshotHeader {
+            versioning: self
+                .versioning
+                .read()
+                .await
+                .map(|status| status.as_str().to_string()),
+        };
+        // Bodies are reference-counted, so the copy is cheap and the lock is released quickly
+        let histories: Vec<(String, Vec<StoredObject>)> = {
+            let objects = self.objects.read().await;
+            let usage = self.usage.lock().unwrap();
+            usage
+                .by_access
+                .values()
+                .filter_map(|key| Some((key.clone(), objects.get(key)?.clone())))
+                .collect()
+        };
+
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+            let mut writer = BufWriter::new(std::fs::File::create(&tmp_path)?);
+            writer.write_all(SNAPSHOT_MAGIC)?;
+            writer.write_all(&[SNAPSHOT_VERSION])?;
+            write_record(&mut writer, &header)?;
+            for (key, history) in histories {
+                for obj in history {
+                    let entry = SnapshotEntry {
+                        key: key.clone(),
+                        etag: obj.metadata.etag,
+                        last_modified: obj.metadata.last_modified,
+                        content_type: obj.metadata.content_type,
+                        version_id: obj.metadata.version_id,
+                        user_metadata: obj.metadata.user_metadata,
+                        tags: obj.tags,
+                        sse_customer_key_md5: obj.sse_customer_key_md5,
+                        size: obj.data.as_ref().map(|data| data.len() as u64),
+                    };
+                    write_record(&mut writer, &entry)?;
+                    if let Some(data) = &obj.data {
+                        writer.write_all(data)?;
+                    }
+                }
+            }
+            writer.into_inner()?.sync_all()?;
+            std::fs::rename(&tmp_path, &path)
+        })
+        .await?
+    }
+
+    /// Replace the contents with those of a snapshot file
+    ///
+    /// A missing file leaves the store empty. Keys beyond the capacity limits are evicted,
+    /// least recently used first.
+    pub async fn load_snapshot(&self, path: &Path) -> std::io::Result<()> {
+        let path = path.to_path_buf();
+        let (header, entries) = tokio::task::spawn_blocking(move || {
+            let file = match std::fs::File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok((None, Vec::new()));
+                }
+                Err(e) => return Err(e),
+            };
+            let mut reader = BufReader::new(file);
+            let mut magic = [0u8; 5];
+            reader.read_exact(&mut magic)?;
+            if &magic[..4] != SNAPSHOT_MAGIC || magic[4] != SNAPSHOT_VERSION {
+                return Err(invalid_snapshot("Not an in-memory storage snapshot"));
+            }
+            let header: SnapshotHeader = read_record(&mut reader)?
+                .ok_or_else(|| invalid_snapshot("Snapshot is missing its header"))?;
+
+            let mut entries = Vec::new();
+            while let Some(entry) = read_record::<SnapshotEntry>(&mut reader)? {
+                let data = match entry.size {
+                    Some(size) => {
+                        let mut data = vec![0u8; size as usize];
+                        reader.read_exact(&mut data)?;
+                        Some(Bytes::from(data))
+                    }
+                    None => None,
+                };
+                entries.push((entry, data));
+            }
+            Ok((Some(header), entries))
+        })
+        .await??;
+
+        let versioning = match header.and_then(|header| header.versioning) {
+            Some(status) => Some(VersioningStatus::parse(&status).ok_or_else(|| {
+                invalid_snapshot(format!("Unknown versioning status {}", status))
+            })?),
+            None => None,
+        };
+
+        let mut objects = self.objects.write().await;
+        objects.clear();
+        *self.usage.lock().unwrap() = Usage::default();
+        for (entry, data) in entries {
+            let object = StoredObject {
+                metadata: ObjectMetadata {
+                    key: entry.key.clone(),
+                    size: data.as_ref().map_or(0, |data| data.len() as u64),
+                    etag: entry.etag,
+                    last_modified: entry.last_modified,
+                    content_type: entry.content_type,
+                    version_id: entry.version_id,
+                    user_metadata: entry.user_metadata,
+                },
+                data,
+                tags: entry.tags,
+                sse_customer_key_md5: entry.sse_customer_key_md5,
+            };
+            self.update_history(&mut objects, &entry.key, |history| history.push(object));
+        }
+        self.evict(&mut objects, None);
+        *self.versioning.write().await = versioning;
+        Ok(())
+    }
+
+    /// Save a snapshot every `interval`
+    pub fn spawn_snapshot_worker(
+        &self,
+        path: PathBuf,
+        interval: std::time::Duration,
+    ) -> JoinHandle<()> {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately, right after the snapshot was loaded
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = storage.save_snapshot(&path).await {
+                    tracing::error!("Failed to save snapshot to {}: {}", path.display(), e);
+                }
+            }
+        })
+    }
 }
 
 #[async_trait::async_trait]
@@ -51,44 +503,110 @@ impl StorageBackend for InMemoryStorage {
         prefix: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        Ok(self.list(prefix, None, max_keys).await)
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        Ok(self.list(prefix, Some(start_after), max_keys).await)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        Ok(*self.versioning.read().await)
+    }
+
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        if status == VersioningStatus::Enabled
+            && (self.max_bytes.is_some() || self.max_objects.is_some())
+        {
+            tracing::warn!(
+                "Versioning enabled on in-memory storage with a capacity limit, \
+                 evicting a key will drop all of its versions"
+            );
+        }
+        *self.versioning.write().await = Some(status);
+        Ok(())
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
         let objects = self.objects.read().await;
 
-        let mut results: Vec<ObjectMetadata> = objects
-            .iter()
-            .filter_map(|(key, obj)| {
-                if let Some(prefix_str) = prefix
-                    && !key.starts_with(prefix_str)
-                {
-                    return None;
-                }
+        let mut keys: Vec<&String> = objects
+            .keys()
+            .filter(|key| prefix.is_none_or(|prefix| key.starts_with(prefix)))
+            .collect();
+        keys.sort();
 
-                Some(obj.metadata.clone())
+        let results = keys
+            .into_iter()
+            .flat_map(|key| {
+                let history = &objects[key];
+                history.iter().rev().enumerate().map(|(idx, obj)| {
+                    let mut metadata = obj.metadata.clone();
+                    metadata.version_id = Some(
+                        metadata
+                            .version_id
+                            .unwrap_or_else(|| NULL_VERSION_ID.to_string()),
+                    );
+                    ObjectVersion {
+                        metadata,
+                        is_latest: idx == 0,
+                        is_delete_marker: obj.is_delete_marker(),
+                    }
+                })
             })
+            .take(max_keys as usize)
             .collect();
 
-        results.sort_by(|a, b| a.key.cmp(&b.key));
-        results.truncate(max_keys as usize);
-
         Ok(results)
     }
 
     // Object-level operations
-    async fn head_object(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         let objects = self.objects.read().await;
 
-        objects
-            .get(key)
-            .map(|obj| obj.metadata.clone())
-            .ok_or(S3Error::NoSuchKey)
+        let obj = Self::find_version(objects.get(key), options)?;
+        SseCustomerKey::check_read(
+            obj.sse_customer_key_md5.as_deref(),
+            options.sse_customer.as_ref(),
+        )?;
+        self.touch(key);
+        Ok(obj.metadata.clone())
     }
 
-    async fn get_object(&self, key: &str) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         let objects = self.objects.read().await;
 
-        let obj = objects.get(key).ok_or(S3Error::NoSuchKey)?;
+        let obj = Self::find_version(objects.get(key), options)?;
+        SseCustomerKey::check_read(
+            obj.sse_customer_key_md5.as_deref(),
+            options.sse_customer.as_ref(),
+        )?;
+
+        self.touch(key);
 
-        let data = obj.data.clone();
+        let mut data = obj.data.clone().unwrap_or_default();
         let metadata = obj.metadata.clone();
+        if let Some(range) = &options.range {
+            let (start, end) = range.resolve(data.len() as u64)?;
+            data = data.slice(start as usize..=end as usize);
+        }
 
         // Convert Bytes to a stream with a single item
         let stream = Box::pin(stream::once(async { Ok(data) }));
@@ -96,7 +614,12 @@ impl StorageBackend for InMemoryStorage {
         Ok((stream, metadata))
     }
 
-    async fn put_object(&self, key: &str, mut body: ObjectStream) -> Result<String, S3Error> {
+    async fn put_object(
+        &self,
+        key: &str,
+        mut body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
         // Collect the streaming body into Bytes
         let mut data = BytesMut::new();
         while let Some(chunk) = body.next().await {
@@ -104,32 +627,138 @@ impl StorageBackend for InMemoryStorage {
             data.extend_from_slice(&chunk);
         }
         let data = data.freeze();
+        if self
+            .max_bytes
+            .is_some_and(|max_bytes| data.len() as u64 > max_bytes)
+        {
+            return Err(S3Error::EntityTooLarge);
+        }
 
         let etag = Self::calculate_etag(&data);
+        let version_id = Self::new_version_id(*self.versioning.read().await);
 
         let metadata = ObjectMetadata {
             key: key.to_string(),
             size: data.len() as u64,
             etag: etag.clone(),
             last_modified: chrono::Utc::now(),
-            content_type: "binary/octet-stream".to_string(),
+            content_type: options
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "binary/octet-stream".to_string()),
+            version_id: version_id.clone(),
+            user_metadata: options.user_metadata.clone(),
         };
 
-        let stored_object = StoredObject { data, metadata };
+        let stored_object = StoredObject {
+            data: Some(data),
+            metadata,
+            tags: options.tags.clone(),
+            sse_customer_key_md5: options
+                .sse_customer
+                .as_ref()
+                .map(|sse_customer| sse_customer.key_md5.clone()),
+        };
 
         let mut objects = self.objects.write().await;
-        objects.insert(key.to_string(), stored_object);
+        self.update_history(&mut objects, key, |history| {
+            Self::push_version(history, stored_object)
+        });
+        self.evict(&mut objects, Some(key));
 
-        Ok(etag)
+        Ok(PutObjectOutput { etag, version_id })
     }
 
-    async fn delete_object(&self, key: &str) -> Result<(), S3Error> {
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        let versioning = *self.versioning.read().await;
         let mut objects = self.objects.write().await;
-        objects.remove(key);
 
-        // S3 returns success even if object doesn't exist
+        // Deleting a specific version removes it permanently
+        if let Some(version_id) = version_id {
+            let mut output = DeleteObjectOutput {
+                version_id: Some(version_id.to_string()),
+                delete_marker: false,
+            };
+            if objects.contains_key(key) {
+                self.update_history(&mut objects, key, |history| {
+                    if let Some(idx) = history.iter().position(|obj| obj.has_version(version_id)) {
+                        output.delete_marker = history.remove(idx).is_delete_marker();
+                    }
+                });
+            }
+            return Ok(output);
+        }
+
+        // Without versioning the object is simply removed
+        let Some(marker_version_id) = Self::new_version_id(versioning) else {
+            self.update_history(&mut objects, key, Vec::clear);
+            // S3 returns success even if object doesn't exist
+            return Ok(DeleteObjectOutput::default());
+        };
+
+        // With versioning a delete marker becomes the latest version
+        let marker = StoredObject {
+            data: None,
+            metadata: ObjectMetadata {
+                key: key.to_string(),
+                size: 0,
+                etag: String::new(),
+                last_modified: chrono::Utc::now(),
+                content_type: String::new(),
+                version_id: Some(marker_version_id.clone()),
+                user_metadata: HashMap::new(),
+            },
+            tags: Vec::new(),
+            sse_customer_key_md5: None,
+        };
+        self.update_history(&mut objects, key, |history| {
+            Self::push_version(history, marker)
+        });
+        self.evict(&mut objects, Some(key));
+
+        Ok(DeleteObjectOutput {
+            version_id: Some(marker_version_id),
+            delete_marker: true,
+        })
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        let options = GetObjectOptions {
+            version_id: version_id.map(|v| v.to_string()),
+            ..Default::default()
+        };
+        let objects = self.objects.read().await;
+
+        Self::find_version(objects.get(key), &options).map(|obj| obj.tags.clone())
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        let mut objects = self.objects.write().await;
+
+        Self::find_version_mut(objects.get_mut(key), version_id)?.tags = tags;
         Ok(())
     }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.put_object_tagging(key, version_id, Vec::new()).await
+    }
 }
 
 #[cfg(test)]
@@ -148,13 +777,21 @@ mod tests {
         let key = "test-key";
         let data = Bytes::from("Hello, World!");
 
-        let etag = storage
-            .put_object(key, bytes_to_stream(data.clone()))
+        let output = storage
+            .put_object(
+                key,
+                bytes_to_stream(data.clone()),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
-        assert!(!etag.is_empty());
+        assert!(!output.etag.is_empty());
+        assert!(output.version_id.is_none());
 
-        let (mut stream, metadata) = storage.get_object(key).await.unwrap();
+        let (mut stream, metadata) = storage
+            .get_object(key, &GetObjectOptions::default())
+            .await
+            .unwrap();
         assert_eq!(metadata.key, key);
         assert_eq!(metadata.size, data.len() as u64);
 
@@ -169,7 +806,9 @@ mod tests {
     async fn test_get_nonexistent() {
         let storage = InMemoryStorage::new();
         assert!(matches!(
-            storage.get_object("nonexistent").await,
+            storage
+                .get_object("nonexistent", &GetObjectOptions::default())
+                .await,
             Err(S3Error::NoSuchKey)
         ));
     }
@@ -180,13 +819,17 @@ mod tests {
         let key = "test-key";
 
         storage
-            .put_object(key, bytes_to_stream(Bytes::from("data")))
+            .put_object(
+                key,
+                bytes_to_stream(Bytes::from("data")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
-        storage.delete_object(key).await.unwrap();
+        storage.delete_object(key, None).await.unwrap();
 
         assert!(matches!(
-            storage.head_object(key).await,
+            storage.head_object(key, &GetObjectOptions::default()).await,
             Err(S3Error::NoSuchKey)
         ));
     }
@@ -196,15 +839,27 @@ mod tests {
         let storage = InMemoryStorage::new();
 
         storage
-            .put_object("photos/a.jpg", bytes_to_stream(Bytes::from("1")))
+            .put_object(
+                "photos/a.jpg",
+                bytes_to_stream(Bytes::from("1")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
         storage
-            .put_object("photos/b.jpg", bytes_to_stream(Bytes::from("2")))
+            .put_object(
+                "photos/b.jpg",
+                bytes_to_stream(Bytes::from("2")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
         storage
-            .put_object("docs/c.pdf", bytes_to_stream(Bytes::from("3")))
+            .put_object(
+                "docs/c.pdf",
+                bytes_to_stream(Bytes::from("3")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -221,4 +876,403 @@ mod tests {
         let result = storage.head_bucket().await;
         assert!(result.is_ok());
     }
+
+    fn version(version_id: &str) -> GetObjectOptions {
+        GetObjectOptions {
+            version_id: Some(version_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versioning_keeps_overwritten_versions() {
+        let storage = InMemoryStorage::new();
+        storage
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+
+        let first = storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("one")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let second = storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("two")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let first_version = first.version_id.unwrap();
+        let second_version = second.version_id.unwrap();
+        assert_ne!(first_version, second_version);
+
+        let latest = storage
+            .head_object("key", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(latest.version_id.as_deref(), Some(second_version.as_str()));
+
+        let (mut stream, metadata) = storage
+            .get_object("key", &version(&first_version))
+            .await
+            .unwrap();
+        assert_eq!(metadata.etag, first.etag);
+        assert_eq!(stream.next().await.unwrap().unwrap(), Bytes::from("one"));
+
+        assert!(matches!(
+            storage.head_object("key", &version("missing")).await,
+            Err(S3Error::NoSuchVersion)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_delete_creates_delete_marker() {
+        let storage = InMemoryStorage::new();
+        storage
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+
+        let put = storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("data")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let delete = storage.delete_object("key", None).await.unwrap();
+        assert!(delete.delete_marker);
+        let marker_version = delete.version_id.unwrap();
+
+        // The key is hidden, but the old version is still readable
+        assert!(matches!(
+            storage
+                .head_object("key", &GetObjectOptions::default())
+                .await,
+            Err(S3Error::NoSuchKey)
+        ));
+        assert!(storage.list_objects(None, 100).await.unwrap().is_empty());
+        let version_id = put.version_id.unwrap();
+        assert!(
+            storage
+                .head_object("key", &version(&version_id))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            storage.head_object("key", &version(&marker_version)).await,
+            Err(S3Error::MethodNotAllowed)
+        ));
+
+        let versions = storage.list_object_versions(None, 100).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].is_delete_marker && versions[0].is_latest);
+        assert!(!versions[1].is_delete_marker && !versions[1].is_latest);
+
+        // Removing the delete marker restores the object
+        let removed = storage
+            .delete_object("key", Some(&marker_version))
+            .await
+            .unwrap();
+        assert!(removed.delete_marker);
+        assert!(
+            storage
+                .head_object("key", &GetObjectOptions::default())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_suspended_versioning_replaces_null_version() {
+        let storage = InMemoryStorage::new();
+
+        storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("unversioned")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        storage
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+        storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("versioned")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        storage
+            .put_bucket_versioning(VersioningStatus::Suspended)
+            .await
+            .unwrap();
+        let suspended = storage
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("suspended")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(suspended.version_id.as_deref(), Some(NULL_VERSION_ID));
+
+        // The pre-versioning object was the "null" version and got replaced
+        let versions = storage.list_object_versions(None, 100).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            versions[0].metadata.version_id.as_deref(),
+            Some(NULL_VERSION_ID)
+        );
+        assert_eq!(versions[0].metadata.etag, suspended.etag);
+    }
+
+    #[tokio::test]
+    async fn test_object_tagging() {
+        let storage = InMemoryStorage::new();
+        let tag = |key: &str, value: &str| Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+
+        let options = PutObjectOptions {
+            tags: vec![tag("team", "data")],
+            ..Default::default()
+        };
+        storage
+            .put_object("key", bytes_to_stream(Bytes::from("data")), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_object_tagging("key", None).await.unwrap(),
+            vec![tag("team", "data")]
+        );
+
+        storage
+            .put_object_tagging("key", None, vec![tag("env", "prod")])
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_object_tagging("key", None).await.unwrap(),
+            vec![tag("env", "prod")]
+        );
+
+        storage.delete_object_tagging("key", None).await.unwrap();
+        assert!(
+            storage
+                .get_object_tagging("key", None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(matches!(
+            storage.put_object_tagging("missing", None, vec![]).await,
+            Err(S3Error::NoSuchKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sse_customer_key_required_on_read() {
+        let storage = InMemoryStorage::new();
+        let sse_key = |key_md5: &str| SseCustomerKey {
+            algorithm: "AES256".to_string(),
+            key: "key".to_string(),
+            key_md5: key_md5.to_string(),
+        };
+
+        let options = PutObjectOptions {
+            sse_customer: Some(sse_key("md5-a")),
+            ..Default::default()
+        };
+        storage
+            .put_object("key", bytes_to_stream(Bytes::from("secret")), &options)
+            .await
+            .unwrap();
+
+        let read = |sse_customer| GetObjectOptions {
+            sse_customer,
+            ..Default::default()
+        };
+        assert!(
+            storage
+                .get_object("key", &read(Some(sse_key("md5-a"))))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            storage.head_object("key", &read(None)).await,
+            Err(S3Error::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            storage
+                .head_object("key", &read(Some(sse_key("md5-b"))))
+                .await,
+            Err(S3Error::AccessDenied)
+        ));
+    }
+
+    async fn put(storage: &InMemoryStorage, key: &str, data: &'static str) {
+        storage
+            .put_object(
+                key,
+                bytes_to_stream(Bytes::from(data)),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn keys(storage: &InMemoryStorage) -> Vec<String> {
+        let objects = storage.list_objects(None, 1000).await.unwrap();
+        objects.into_iter().map(|obj| obj.key).collect()
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let storage = InMemoryStorage::new().with_limits(Some(10), Some(3));
+        put(&storage, "a", "1234").await;
+        put(&storage, "b", "1234").await;
+
+        // Reading "a" makes "b" the least recently used key
+        storage
+            .head_object("a", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        put(&storage, "c", "1234").await;
+        assert_eq!(keys(&storage).await, vec!["a", "c"]);
+
+        // The key count is limited too
+        put(&storage, "d", "1").await;
+        put(&storage, "e", "1").await;
+        assert_eq!(keys(&storage).await, vec!["c", "d", "e"]);
+
+        // An object larger than the whole store is rejected
+        let result = storage
+            .put_object(
+                "big",
+                bytes_to_stream(Bytes::from("12345678901")),
+                &PutObjectOptions::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(S3Error::EntityTooLarge)));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.snapshot");
+
+        let storage = InMemoryStorage::new();
+        storage
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+        let options = PutObjectOptions {
+            content_type: Some("text/plain".to_string()),
+            tags: vec![Tag {
+                key: "env".to_string(),
+                value: "prod".to_string(),
+            }],
+            ..Default::default()
+        };
+        let first = storage
+            .put_object("a", bytes_to_stream(Bytes::from("v1")), &options)
+            .await
+            .unwrap();
+        put(&storage, "a", "v2").await;
+        put(&storage, "b", "data").await;
+        storage.delete_object("b", None).await.unwrap();
+        storage.save_snapshot(&path).await.unwrap();
+
+        let restored = InMemoryStorage::new();
+        restored.load_snapshot(&path).await.unwrap();
+        assert_eq!(
+            restored.get_bucket_versioning().await.unwrap(),
+            Some(VersioningStatus::Enabled)
+        );
+        assert_eq!(keys(&restored).await, vec!["a"]);
+        assert_eq!(
+            restored
+                .list_object_versions(None, 1000)
+                .await
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let options = GetObjectOptions {
+            version_id: first.version_id,
+            ..Default::default()
+        };
+        let (stream, metadata) = restored.get_object("a", &options).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks[0].as_ref().unwrap(), &Bytes::from("v1"));
+        assert_eq!(metadata.content_type, "text/plain");
+        assert_eq!(
+            restored
+                .get_object_tagging("a", options.version_id.as_deref())
+                .await
+                .unwrap(),
+            vec![Tag {
+                key: "env".to_string(),
+                value: "prod".to_string(),
+            }]
+        );
+
+        // A missing snapshot leaves the store empty, and limits apply to loaded snapshots
+        let missing = InMemoryStorage::new();
+        missing
+            .load_snapshot(&dir.path().join("missing"))
+            .await
+            .unwrap();
+        assert!(keys(&missing).await.is_empty());
+
+        let limited = InMemoryStorage::new().with_limits(None, Some(1));
+        limited.load_snapshot(&path).await.unwrap();
+        assert_eq!(
+            limited
+                .list_object_versions(None, 1000)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_paths_differing_in_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let first_path = dir.path().join("cache.a");
+        let second_path = dir.path().join("cache.tmp");
+
+        let first = InMemoryStorage::new();
+        put(&first, "first", "data").await;
+        let second = InMemoryStorage::new();
+        put(&second, "second", "data").await;
+        // Neither snapshot may be written through the other's file
+        let (first_result, second_result) = tokio::join!(
+            first.save_snapshot(&first_path),
+            second.save_snapshot(&second_path)
+        );
+        first_result.unwrap();
+        second_result.unwrap();
+
+        for (path, key) in [(&first_path, "first"), (&second_path, "second")] {
+            let restored = InMemoryStorage::new();
+            restored.load_snapshot(path).await.unwrap();
+            assert_eq!(keys(&restored).await, vec![key]);
+        }
+    }
 }
@@ -1,9 +1,32 @@
+mod azure;
 mod backend;
+mod compressed;
+mod encrypted;
+mod fault;
+mod filesystem;
+mod gcs;
+mod http;
 mod in_memory;
 mod multi_backend;
 mod s3;
+mod sftp;
+mod sqlite;
+mod staging;
+mod webdav;
 
-pub use backend::StorageBackend;
+pub use azure::{AzureBackend, AzureCredentials};
+pub use backend::{
+    ByteRange, GetObjectOptions, ObjectStream, PutObjectOptions, SseCustomerKey, StorageBackend,
+};
+pub use compressed::CompressedBackend;
+pub use encrypted::EncryptedBackend;
+pub use fault::{FaultBackend, FaultOperation, FaultRates};
+pub use filesystem::FilesystemBackend;
+pub use gcs::{GcsBackend, ServiceAccountKey};
+pub use http::HttpBackend;
 pub use in_memory::InMemoryStorage;
 pub use multi_backend::{MultiBackend, determine_primary_by_latency};
 pub use s3::S3Backend;
+pub use sftp::{SftpAuth, SftpBackend};
+pub use sqlite::SqliteBackend;
+pub use webdav::WebdavBackend;
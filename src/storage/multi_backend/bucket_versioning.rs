@@ -0,0 +1,81 @@
+use super::MultiBackend;
+use crate::config::ReadMode;
+use crate::types::{VersioningStatus, error::S3Error};
+
+impl MultiBackend {
+    pub(super) async fn get_bucket_versioning_impl(
+        &self,
+    ) -> Result<Option<VersioningStatus>, S3Error> {
+        match self.read_mode {
+            ReadMode::PrimaryOnly => {
+                tracing::debug!("GET bucket versioning (primary only mode)");
+                self.primary().get_bucket_versioning().await
+            }
+            _ => {
+                self.try_primary_fallback(
+                    "GET bucket versioning",
+                    |_| false,
+                    S3Error::InternalError("All backends failed".to_string()),
+                    |_, backend| async move { backend.get_bucket_versioning().await },
+                )
+                .await
+            }
+        }
+    }
+
+    /// Versioning is configured on every writable backend regardless of the write mode, otherwise
+    /// versions written to one backend could not be replicated as versions to the others
+    pub(super) async fn put_bucket_versioning_impl(
+        &self,
+        status: VersioningStatus,
+    ) -> Result<(), S3Error> {
+        self.check_writable()?;
+        let tasks = self.writable_backends().map(|(idx, backend)| async move {
+            (idx, backend.put_bucket_versioning(status).await)
+        });
+        for (idx, result) in futures::future::join_all(tasks).await {
+            result.map_err(|e| {
+                tracing::error!("Backend {} failed for PUT bucket versioning: {}", idx, e);
+                S3Error::InternalError(format!("Consistency check failed: backend {} failed", idx))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WriteMode;
+    use crate::storage::{InMemoryStorage, backend::StorageBackend};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_put_bucket_versioning_applies_to_all_backends() {
+        let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let backend2 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+
+        let multi = MultiBackend::new(
+            vec![backend1.clone(), backend2.clone()],
+            0,
+            ReadMode::PrimaryFallback,
+            WriteMode::AsyncReplication,
+        );
+
+        assert_eq!(multi.get_bucket_versioning().await.unwrap(), None);
+
+        multi
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backend1.get_bucket_versioning().await.unwrap(),
+            Some(VersioningStatus::Enabled)
+        );
+        assert_eq!(
+            backend2.get_bucket_versioning().await.unwrap(),
+            Some(VersioningStatus::Enabled)
+        );
+    }
+}
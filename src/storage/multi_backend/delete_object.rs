@@ -1,58 +1,82 @@
 use super::MultiBackend;
+use super::version_map::VersionMap;
 use crate::config::WriteMode;
+use crate::storage::backend::{DeleteObjectOutput, StorageBackend};
 use crate::types::error::S3Error;
 use std::sync::Arc;
 
 impl MultiBackend {
-    pub(super) async fn delete_object_impl(&self, key: &str) -> Result<(), S3Error> {
-        match self.write_mode {
-            WriteMode::AsyncReplication => self.delete_object_async_replication(key).await,
-            WriteMode::MultiSync => self.delete_object_multi_sync(key).await,
+    pub(super) async fn delete_object_impl(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        self.check_writable()?;
+        let output = match self.write_mode {
+            WriteMode::AsyncReplication => {
+                self.delete_object_async_replication(key, version_id).await
+            }
+            WriteMode::MultiSync => self.delete_object_multi_sync(key, version_id).await,
+        }?;
+
+        // A deleted version can't be addressed anymore, on any backend
+        if let Some(version_id) = version_id {
+            self.versions.remove(key, version_id);
         }
+
+        Ok(output)
     }
 
-    async fn delete_object_async_replication(&self, key: &str) -> Result<(), S3Error> {
+    async fn delete_object_async_replication(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
         // Delete from primary backend immediately
         let primary = self.primary();
         tracing::info!(
             "DELETE object (async replication): deleting from primary backend immediately"
         );
 
-        primary.delete_object(key).await?;
+        let output = primary.delete_object(key, version_id).await?;
         tracing::info!("Primary backend successfully deleted object {}", key);
 
         // Spawn background tasks to delete from other backends
-        self.spawn_background_delete_tasks(key);
+        self.spawn_background_delete_tasks(key, version_id, &output);
 
-        Ok(())
+        Ok(output)
     }
 
-    async fn delete_object_multi_sync(&self, key: &str) -> Result<(), S3Error> {
+    async fn delete_object_multi_sync(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
         // Delete from all backends concurrently, all must succeed
-        tracing::info!(
-            "DELETE object (multi sync): deleting from {} backends (all must succeed)",
-            self.backends.len()
-        );
-
         let tasks: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .writable_backends()
             .map(|(idx, backend)| {
                 let backend = Arc::clone(backend);
                 let key = key.to_string();
+                let backend_version_id =
+                    version_id.map(|v| self.versions.backend_version_id(&key, v, idx));
                 async move {
-                    let result = backend.delete_object(&key).await;
+                    let result = delete_from_backend(idx, backend, &key, backend_version_id).await;
                     (idx, result)
                 }
             })
             .collect();
+        tracing::info!(
+            "DELETE object (multi sync): deleting from {} backends (all must succeed)",
+            tasks.len()
+        );
 
         let results = futures::future::join_all(tasks).await;
 
         // All must succeed
+        let mut outputs = Vec::with_capacity(results.len());
         for (idx, result) in results {
-            result.map_err(|e| {
+            let output = result.map_err(|e| {
                 tracing::error!("Backend {} failed to delete object {}: {}", idx, key, e);
                 S3Error::InternalError(format!(
                     "Backend {} failed to delete in multi sync mode: {}",
@@ -60,24 +84,46 @@ impl MultiBackend {
                 ))
             })?;
             tracing::info!("Backend {} successfully deleted object {}", idx, key);
+            outputs.push((idx, output));
         }
 
         tracing::info!("DELETE object (multi sync): all backends succeeded");
-        Ok(())
+
+        // Return primary output, remembering the delete markers created on each backend
+        let primary_output = outputs
+            .iter()
+            .find(|(idx, _)| *idx == self.primary_index)
+            .map(|(_, output)| output.clone())
+            .unwrap_or_default();
+        if version_id.is_none() {
+            for (idx, output) in outputs {
+                record_delete_marker(&self.versions, key, &primary_output, idx, output);
+            }
+        }
+
+        Ok(primary_output)
     }
 
-    fn spawn_background_delete_tasks(&self, key: &str) {
+    fn spawn_background_delete_tasks(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        primary_output: &DeleteObjectOutput,
+    ) {
         if self.backends.len() <= 1 {
             return;
         }
 
         let primary_idx = self.primary_index;
         let other_backends: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .writable_backends()
             .filter(move |(idx, _)| *idx != primary_idx)
-            .map(|(idx, backend)| (idx, Arc::clone(backend)))
+            .map(|(idx, backend)| {
+                // Translate now, the mapping is dropped once the primary delete returns
+                let backend_version_id =
+                    version_id.map(|v| self.versions.backend_version_id(key, v, idx));
+                (idx, Arc::clone(backend), backend_version_id)
+            })
             .collect();
 
         if other_backends.is_empty() {
@@ -90,19 +136,24 @@ impl MultiBackend {
         );
 
         let key_clone = key.to_string();
+        let versions = self.versions.clone();
+        let primary_output = primary_output.clone();
         tokio::spawn(async move {
-            for (idx, backend) in other_backends {
+            for (idx, backend, backend_version_id) in other_backends {
                 let backend_clone = Arc::clone(&backend);
                 let key = key_clone.clone();
+                let versions = versions.clone();
+                let primary_output = primary_output.clone();
 
                 tokio::spawn(async move {
-                    match backend_clone.delete_object(&key).await {
-                        Ok(_) => {
+                    match delete_from_backend(idx, backend_clone, &key, backend_version_id).await {
+                        Ok(output) => {
                             tracing::info!(
                                 "Background deletion: backend {} successfully deleted object {}",
                                 idx,
                                 key
                             );
+                            record_delete_marker(&versions, &key, &primary_output, idx, output);
                         }
                         Err(e) => {
                             tracing::error!(
@@ -119,11 +170,53 @@ impl MultiBackend {
     }
 }
 
+/// Delete from a single backend
+///
+/// `backend_version_id` is None for a plain delete, and Some(None) when the version being
+/// deleted was never written to this backend through the proxy, so there is nothing to delete.
+async fn delete_from_backend(
+    idx: usize,
+    backend: Arc<dyn StorageBackend>,
+    key: &str,
+    backend_version_id: Option<Option<String>>,
+) -> Result<DeleteObjectOutput, S3Error> {
+    match backend_version_id {
+        None => backend.delete_object(key, None).await,
+        Some(Some(version_id)) => backend.delete_object(key, Some(&version_id)).await,
+        Some(None) => {
+            tracing::warn!(
+                "The deleted version of {} is not known on backend {}, it is left there",
+                key,
+                idx
+            );
+            Ok(DeleteObjectOutput::default())
+        }
+    }
+}
+
+/// Remember the delete marker a backend created for the primary's delete marker
+fn record_delete_marker(
+    versions: &VersionMap,
+    key: &str,
+    primary_output: &DeleteObjectOutput,
+    idx: usize,
+    output: DeleteObjectOutput,
+) {
+    if let (Some(proxy_version_id), Some(backend_version_id)) =
+        (&primary_output.version_id, output.version_id)
+    {
+        versions.record(key, proxy_version_id, idx, &backend_version_id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::ReadMode;
-    use crate::storage::{InMemoryStorage, backend::StorageBackend};
+    use crate::storage::{
+        InMemoryStorage,
+        backend::{GetObjectOptions, PutObjectOptions},
+    };
     use bytes::Bytes;
     use futures::stream;
 
@@ -148,11 +241,15 @@ mod tests {
         let data = Bytes::from("data");
 
         // Put and then delete
-        multi.put_object(key, bytes_to_stream(data)).await.unwrap();
-        multi.delete_object(key).await.unwrap();
+        multi
+            .put_object(key, bytes_to_stream(data), &PutObjectOptions::default())
+            .await
+            .unwrap();
+        multi.delete_object(key, None).await.unwrap();
 
         // Verify both backends deleted the object
-        assert!(backend1.head_object(key).await.is_err());
-        assert!(backend2.head_object(key).await.is_err());
+        let options = GetObjectOptions::default();
+        assert!(backend1.head_object(key, &options).await.is_err());
+        assert!(backend2.head_object(key, &options).await.is_err());
     }
 }
@@ -1,42 +1,53 @@
 use super::MultiBackend;
+use super::version_map::VersionMap;
 use crate::config::ReadMode;
-use crate::storage::backend::ObjectStream;
+use crate::storage::backend::{GetObjectOptions, ObjectStream, StorageBackend};
 use crate::types::{ObjectMetadata, error::S3Error};
+use std::sync::Arc;
 
 impl MultiBackend {
     pub(super) async fn get_object_impl(
         &self,
         key: &str,
+        options: &GetObjectOptions,
     ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         match self.read_mode {
-            ReadMode::PrimaryOnly => self.get_object_primary_only(key).await,
-            ReadMode::PrimaryFallback => self.get_object_primary_fallback(key).await,
-            ReadMode::BestEffort => self.get_object_best_effort(key).await,
-            ReadMode::AllConsistent => self.get_object_all_consistent(key).await,
+            ReadMode::PrimaryOnly => self.get_object_primary_only(key, options).await,
+            ReadMode::PrimaryFallback => self.get_object_primary_fallback(key, options).await,
+            ReadMode::BestEffort => self.get_object_best_effort(key, options).await,
+            ReadMode::AllConsistent => self.get_object_all_consistent(key, options).await,
         }
     }
 
     async fn get_object_primary_only(
         &self,
         key: &str,
+        options: &GetObjectOptions,
     ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         // Only read from primary backend
         tracing::debug!("GET object (primary only mode)");
-        self.primary().get_object(key).await
+        self.primary().get_object(key, options).await
     }
 
     async fn get_object_primary_fallback(
         &self,
         key: &str,
+        options: &GetObjectOptions,
     ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         let key = key.to_string();
+        let options = options.clone();
         self.try_primary_fallback(
             "GET object",
-            |e| matches!(e, S3Error::NoSuchKey),
-            S3Error::NoSuchKey,
-            |backend| {
-                let key = key.clone();
-                async move { backend.get_object(&key).await }
+            is_not_found,
+            not_found_error(&options),
+            |idx, backend| {
+                get_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    key.clone(),
+                    options.clone(),
+                )
             },
         )
         .await
@@ -45,15 +56,22 @@ impl MultiBackend {
     async fn get_object_best_effort(
         &self,
         key: &str,
+        options: &GetObjectOptions,
     ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         let key = key.to_string();
+        let options = options.clone();
         self.race_all_backends(
             "GET object",
-            |e| matches!(e, S3Error::NoSuchKey),
-            S3Error::NoSuchKey,
-            |backend| {
-                let key = key.clone();
-                async move { backend.get_object(&key).await }
+            is_not_found,
+            not_found_error(&options),
+            |idx, backend| {
+                get_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    key.clone(),
+                    options.clone(),
+                )
             },
         )
         .await
@@ -62,27 +80,158 @@ impl MultiBackend {
     async fn get_object_all_consistent(
         &self,
         key: &str,
+        options: &GetObjectOptions,
     ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         let key = key.to_string();
+        let options = options.clone();
         self.verify_all_consistent_etag(
             "GET object",
             |result: &(ObjectStream, ObjectMetadata)| &result.1.etag,
-            |backend| {
-                let key = key.clone();
-                async move { backend.get_object(&key).await }
+            |idx, backend| {
+                get_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    key.clone(),
+                    options.clone(),
+                )
             },
         )
         .await
     }
 }
 
+/// Errors that are a valid answer from a backend, not a reason to try another one
+pub(super) fn is_not_found(e: &S3Error) -> bool {
+    matches!(
+        e,
+        S3Error::NoSuchKey
+            | S3Error::NoSuchVersion
+            | S3Error::MethodNotAllowed
+            | S3Error::InvalidPartNumber
+            | S3Error::InvalidRange
+            // Wrong or missing SSE-C key, every backend stores the object with the same key
+            | S3Error::AccessDenied
+            | S3Error::InvalidRequest(_)
+    )
+}
+
+/// Error returned when no backend could answer a read
+pub(super) fn not_found_error(options: &GetObjectOptions) -> S3Error {
+    if options.version_id.is_some() {
+        S3Error::NoSuchVersion
+    } else {
+        S3Error::NoSuchKey
+    }
+}
+
+/// GET from a single backend, translating version IDs between the proxy and the backend
+async fn get_from_backend(
+    versions: VersionMap,
+    idx: usize,
+    backend: Arc<dyn StorageBackend>,
+    key: String,
+    options: GetObjectOptions,
+) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+    let backend_options = versions.backend_options(&key, &options, idx)?;
+    let (stream, mut metadata) = backend.get_object(&key, &backend_options).await?;
+    metadata.version_id = metadata
+        .version_id
+        .and_then(|version_id| versions.proxy_version_id(&key, idx, &version_id));
+    Ok((stream, metadata))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::WriteMode;
-    use crate::storage::{InMemoryStorage, backend::StorageBackend};
+    use crate::storage::{
+        FaultBackend, FaultOperation, FaultRates, InMemoryStorage,
+        backend::{PutObjectOptions, StorageBackend},
+    };
+    use bytes::Bytes;
+    use futures::{StreamExt, stream};
+    use std::collections::HashMap;
     use std::sync::Arc;
 
+    /// Two in-memory backends holding `key`, the first wrapped with the given faults on GET
+    async fn faulty_primary(
+        key: &str,
+        rates: FaultRates,
+    ) -> (Arc<dyn StorageBackend>, Arc<dyn StorageBackend>) {
+        let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let backend2 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        for backend in [&backend1, &backend2] {
+            let body = Box::pin(stream::once(async { Ok(Bytes::from("data")) }));
+            backend
+                .put_object(key, body, &PutObjectOptions::default())
+                .await
+                .unwrap();
+        }
+        let operations = HashMap::from([(FaultOperation::GetObject, rates)]);
+        let faulty = FaultBackend::new(backend1, FaultRates::default(), operations, Some(1));
+        (Arc::new(faulty), backend2)
+    }
+
+    #[tokio::test]
+    async fn test_multibackend_get_survives_failing_backend() {
+        let key = "test-key";
+        let failing = FaultRates {
+            error: 1.0,
+            ..Default::default()
+        };
+
+        for read_mode in [ReadMode::PrimaryFallback, ReadMode::BestEffort] {
+            let (backend1, backend2) = faulty_primary(key, failing.clone()).await;
+            let multi = MultiBackend::new(
+                vec![backend1, backend2],
+                0, // the failing backend is primary
+                read_mode,
+                WriteMode::MultiSync,
+            );
+
+            let (mut body, metadata) = multi
+                .get_object(key, &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(metadata.size, 4);
+            assert_eq!(body.next().await.unwrap().unwrap(), Bytes::from("data"));
+        }
+
+        // Only reading from the primary surfaces its failure
+        let (backend1, backend2) = faulty_primary(key, failing).await;
+        let multi = MultiBackend::new(
+            vec![backend1, backend2],
+            0,
+            ReadMode::PrimaryOnly,
+            WriteMode::MultiSync,
+        );
+        let result = multi.get_object(key, &GetObjectOptions::default()).await;
+        assert!(matches!(result, Err(S3Error::InternalError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_multibackend_get_all_consistent_detects_wrong_etag() {
+        let key = "test-key";
+        let (backend1, backend2) = faulty_primary(
+            key,
+            FaultRates {
+                wrong_etag: 1.0,
+                ..Default::default()
+            },
+        )
+        .await;
+        let multi = MultiBackend::new(
+            vec![backend1, backend2],
+            0,
+            ReadMode::AllConsistent,
+            WriteMode::MultiSync,
+        );
+
+        let result = multi.get_object(key, &GetObjectOptions::default()).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_multibackend_get_no_such_key() {
         let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
@@ -98,7 +247,7 @@ mod tests {
         let key = "nonexistent-key";
 
         // Get should return NoSuchKey without checking other backends
-        let result = multi.get_object(key).await;
+        let result = multi.get_object(key, &GetObjectOptions::default()).await;
         assert!(matches!(result, Err(S3Error::NoSuchKey)));
     }
 }
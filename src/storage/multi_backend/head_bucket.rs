@@ -23,7 +23,7 @@ impl MultiBackend {
             "HEAD bucket",
             |e| matches!(e, S3Error::NoSuchBucket),
             S3Error::NoSuchBucket,
-            |backend| async move { backend.head_bucket().await },
+            |_, backend| async move { backend.head_bucket().await },
         )
         .await
     }
@@ -33,13 +33,13 @@ impl MultiBackend {
             "HEAD bucket",
             |e| matches!(e, S3Error::NoSuchBucket),
             S3Error::NoSuchBucket,
-            |backend| async move { backend.head_bucket().await },
+            |_, backend| async move { backend.head_bucket().await },
         )
         .await
     }
 
     async fn head_bucket_all_consistent(&self) -> Result<(), S3Error> {
-        self.verify_all_succeed("HEAD bucket", |backend| async move {
+        self.verify_all_succeed("HEAD bucket", |_, backend| async move {
             backend.head_bucket().await
         })
         .await
@@ -1,33 +1,49 @@
 use super::MultiBackend;
+use super::get_object::{is_not_found, not_found_error};
+use super::version_map::VersionMap;
 use crate::config::ReadMode;
+use crate::storage::backend::{GetObjectOptions, StorageBackend};
 use crate::types::{ObjectMetadata, error::S3Error};
+use std::sync::Arc;
 
 impl MultiBackend {
-    pub(super) async fn head_object_impl(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    pub(super) async fn head_object_impl(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         match self.read_mode {
-            ReadMode::PrimaryOnly => self.head_object_primary_only(key).await,
-            ReadMode::PrimaryFallback => self.head_object_primary_fallback(key).await,
-            ReadMode::BestEffort => self.head_object_best_effort(key).await,
-            ReadMode::AllConsistent => self.head_object_all_consistent(key).await,
+            ReadMode::PrimaryOnly => self.head_object_primary_only(key, options).await,
+            ReadMode::PrimaryFallback => self.head_object_primary_fallback(key, options).await,
+            ReadMode::BestEffort => self.head_object_best_effort(key, options).await,
+            ReadMode::AllConsistent => self.head_object_all_consistent(key, options).await,
         }
     }
 
-    async fn head_object_primary_only(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object_primary_only(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         // Only read from primary backend
         tracing::debug!("HEAD object (primary only mode)");
-        self.primary().head_object(key).await
+        self.primary().head_object(key, options).await
     }
 
-    async fn head_object_primary_fallback(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object_primary_fallback(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         // Try primary first, then fallback to others
         let primary = self.primary();
         tracing::debug!("HEAD object (trying primary backend first)");
-        match primary.head_object(key).await {
+        match primary.head_object(key, options).await {
             Ok(metadata) => return Ok(metadata),
-            Err(S3Error::NoSuchKey) => {
+            Err(e) if is_not_found(&e) => {
                 // Don't fallback if key doesn't exist in primary
                 tracing::debug!("Key {} not found in primary backend, not falling back", key);
-                return Err(S3Error::NoSuchKey);
+                return Err(e);
             }
             Err(e) => {
                 tracing::warn!("Primary backend failed for HEAD {}: {}", key, e);
@@ -35,9 +51,17 @@ impl MultiBackend {
         }
 
         // Try other backends (only if there was a real error, not NoSuchKey)
-        for (idx, backend) in self.other_backends().enumerate() {
+        for (idx, backend) in self.other_backends() {
             tracing::debug!("HEAD object (trying fallback backend {})", idx);
-            match backend.head_object(key).await {
+            let result = head_from_backend(
+                self.versions.clone(),
+                idx,
+                Arc::clone(backend),
+                key.to_string(),
+                options.clone(),
+            )
+            .await;
+            match result {
                 Ok(metadata) => return Ok(metadata),
                 Err(e) => {
                     tracing::warn!("Fallback backend {} failed for HEAD {}: {}", idx, key, e);
@@ -45,33 +69,69 @@ impl MultiBackend {
             }
         }
 
-        Err(S3Error::NoSuchKey)
+        Err(not_found_error(options))
     }
 
-    async fn head_object_best_effort(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object_best_effort(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         let key = key.to_string();
+        let options = options.clone();
         self.race_all_backends(
             "HEAD object",
-            |e| matches!(e, S3Error::NoSuchKey),
-            S3Error::NoSuchKey,
-            |backend| {
-                let key = key.clone();
-                async move { backend.head_object(&key).await }
+            is_not_found,
+            not_found_error(&options),
+            |idx, backend| {
+                head_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    key.clone(),
+                    options.clone(),
+                )
             },
         )
         .await
     }
 
-    async fn head_object_all_consistent(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object_all_consistent(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         let key = key.to_string();
+        let options = options.clone();
         self.verify_all_consistent_etag(
             "HEAD object",
             |metadata: &ObjectMetadata| &metadata.etag,
-            |backend| {
-                let key = key.clone();
-                async move { backend.head_object(&key).await }
+            |idx, backend| {
+                head_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    key.clone(),
+                    options.clone(),
+                )
             },
         )
         .await
     }
 }
+
+/// HEAD a single backend, translating version IDs between the proxy and the backend
+async fn head_from_backend(
+    versions: VersionMap,
+    idx: usize,
+    backend: Arc<dyn StorageBackend>,
+    key: String,
+    options: GetObjectOptions,
+) -> Result<ObjectMetadata, S3Error> {
+    let backend_options = versions.backend_options(&key, &options, idx)?;
+    let mut metadata = backend.head_object(&key, &backend_options).await?;
+    metadata.version_id = metadata
+        .version_id
+        .and_then(|version_id| versions.proxy_version_id(&key, idx, &version_id));
+    Ok(metadata)
+}
@@ -0,0 +1,67 @@
+use super::MultiBackend;
+use super::version_map::VersionMap;
+use crate::config::ReadMode;
+use crate::storage::backend::StorageBackend;
+use crate::types::{ObjectVersion, error::S3Error};
+use std::sync::Arc;
+
+impl MultiBackend {
+    /// List versions from the primary backend, falling back to the others unless the read
+    /// mode is PRIMARY_ONLY
+    ///
+    /// Version histories of different backends can't be compared entry by entry (replicated
+    /// versions get newer timestamps and their own IDs), so racing and consistency checks
+    /// are not applied here.
+    pub(super) async fn list_object_versions_impl(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        if self.read_mode == ReadMode::PrimaryOnly {
+            tracing::debug!("LIST object versions (primary only mode)");
+            return self.primary().list_object_versions(prefix, max_keys).await;
+        }
+
+        let prefix = prefix.map(|s| s.to_string());
+        self.try_primary_fallback(
+            "LIST object versions",
+            |_| false,
+            S3Error::InternalError("All backends failed".to_string()),
+            |idx, backend| {
+                list_versions_from_backend(
+                    self.versions.clone(),
+                    idx,
+                    backend,
+                    prefix.clone(),
+                    max_keys,
+                )
+            },
+        )
+        .await
+    }
+}
+
+/// List versions of a single backend, translating its version IDs to proxy version IDs
+///
+/// Versions the proxy doesn't know about can't be addressed through it and are left out.
+async fn list_versions_from_backend(
+    versions: VersionMap,
+    idx: usize,
+    backend: Arc<dyn StorageBackend>,
+    prefix: Option<String>,
+    max_keys: i32,
+) -> Result<Vec<ObjectVersion>, S3Error> {
+    let backend_versions = backend
+        .list_object_versions(prefix.as_deref(), max_keys)
+        .await?;
+
+    Ok(backend_versions
+        .into_iter()
+        .filter_map(|mut version| {
+            let backend_version_id = version.metadata.version_id.as_deref()?;
+            version.metadata.version_id =
+                versions.proxy_version_id(&version.metadata.key, idx, backend_version_id);
+            version.metadata.version_id.is_some().then_some(version)
+        })
+        .collect())
+}
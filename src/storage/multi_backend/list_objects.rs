@@ -1,42 +1,71 @@
 use super::MultiBackend;
 use crate::config::ReadMode;
+use crate::storage::StorageBackend;
 use crate::types::{ObjectMetadata, error::S3Error};
 use futures::FutureExt;
 use std::sync::Arc;
 
+/// List one page of a backend, from the start or after a key
+async fn list_page(
+    backend: &dyn StorageBackend,
+    prefix: Option<&str>,
+    start_after: Option<&str>,
+    max_keys: i32,
+) -> Result<Vec<ObjectMetadata>, S3Error> {
+    match start_after {
+        Some(key) => backend.list_objects_after(prefix, key, max_keys).await,
+        None => backend.list_objects(prefix, max_keys).await,
+    }
+}
+
 impl MultiBackend {
     pub(super) async fn list_objects_impl(
         &self,
         prefix: Option<&str>,
+        start_after: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
         match self.read_mode {
-            ReadMode::PrimaryOnly => self.list_objects_primary_only(prefix, max_keys).await,
-            ReadMode::PrimaryFallback => self.list_objects_primary_fallback(prefix, max_keys).await,
-            ReadMode::BestEffort => self.list_objects_best_effort(prefix, max_keys).await,
-            ReadMode::AllConsistent => self.list_objects_all_consistent(prefix, max_keys).await,
+            ReadMode::PrimaryOnly => {
+                self.list_objects_primary_only(prefix, start_after, max_keys)
+                    .await
+            }
+            ReadMode::PrimaryFallback => {
+                self.list_objects_primary_fallback(prefix, start_after, max_keys)
+                    .await
+            }
+            ReadMode::BestEffort => {
+                self.list_objects_best_effort(prefix, start_after, max_keys)
+                    .await
+            }
+            ReadMode::AllConsistent => {
+                self.list_objects_all_consistent(prefix, start_after, max_keys)
+                    .await
+            }
         }
     }
 
     async fn list_objects_primary_only(
         &self,
         prefix: Option<&str>,
+        start_after: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
         // Only list from primary backend
         tracing::debug!("LIST objects (primary only mode)");
-        self.primary().list_objects(prefix, max_keys).await
+        list_page(self.primary().as_ref(), prefix, start_after, max_keys).await
     }
 
     async fn list_objects_primary_fallback(
         &self,
         prefix: Option<&str>,
+        start_after: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
         // Try primary first, then fallback to others
         let primary = self.primary();
         tracing::debug!("LIST objects (trying primary backend first)");
-        match primary.list_objects(prefix, max_keys).await {
+        match list_page(primary.as_ref(), prefix, start_after, max_keys).await {
             Ok(objects) => return Ok(objects),
             Err(e) => {
                 tracing::warn!("Primary backend failed for LIST objects: {}", e);
@@ -44,9 +73,9 @@ impl MultiBackend {
         }
 
         // Try other backends (on any error - even an empty list is valid, not an error)
-        for (idx, backend) in self.other_backends().enumerate() {
+        for (idx, backend) in self.other_backends() {
             tracing::debug!("LIST objects (trying fallback backend {})", idx);
-            match backend.list_objects(prefix, max_keys).await {
+            match list_page(backend.as_ref(), prefix, start_after, max_keys).await {
                 Ok(objects) => return Ok(objects),
                 Err(e) => {
                     tracing::warn!("Fallback backend {} failed for LIST objects: {}", idx, e);
@@ -60,16 +89,27 @@ impl MultiBackend {
     async fn list_objects_best_effort(
         &self,
         prefix: Option<&str>,
+        start_after: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
         let prefix = prefix.map(|s| s.to_string());
+        let start_after = start_after.map(|s| s.to_string());
         self.race_all_backends(
             "LIST objects",
             |_| false, // No "not found" error for list - empty list is success
             S3Error::InternalError("All backends failed".to_string()),
-            |backend| {
+            |_, backend| {
                 let prefix = prefix.clone();
-                async move { backend.list_objects(prefix.as_deref(), max_keys).await }
+                let start_after = start_after.clone();
+                async move {
+                    list_page(
+                        backend.as_ref(),
+                        prefix.as_deref(),
+                        start_after.as_deref(),
+                        max_keys,
+                    )
+                    .await
+                }
             },
         )
         .await
@@ -78,28 +118,34 @@ impl MultiBackend {
     async fn list_objects_all_consistent(
         &self,
         prefix: Option<&str>,
+        start_after: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
         // Fetch from all backends and verify lists match
-        tracing::debug!(
-            "LIST objects (all consistent mode - verifying {} backends)",
-            self.backends.len()
-        );
-
         let tasks: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .readable_backends()
             .map(|(idx, backend)| {
                 let backend = Arc::clone(backend);
                 let prefix = prefix.map(|s| s.to_string());
+                let start_after = start_after.map(|s| s.to_string());
                 async move {
-                    let result = backend.list_objects(prefix.as_deref(), max_keys).await;
+                    let result = list_page(
+                        backend.as_ref(),
+                        prefix.as_deref(),
+                        start_after.as_deref(),
+                        max_keys,
+                    )
+                    .await;
                     (idx, result)
                 }
                 .boxed()
             })
             .collect();
+        let num_backends = tasks.len();
+        tracing::debug!(
+            "LIST objects (all consistent mode - verifying {} backends)",
+            num_backends
+        );
 
         let results = futures::future::join_all(tasks).await;
 
@@ -122,18 +168,22 @@ impl MultiBackend {
         }
 
         // All backends must succeed
-        if all_lists.len() != self.backends.len() {
+        if all_lists.len() != num_backends {
             return Err(S3Error::InternalError(format!(
                 "Consistency check failed: only {}/{} backends succeeded",
                 all_lists.len(),
-                self.backends.len()
+                num_backends
             )));
         }
 
         // Verify all lists have the same objects (same keys and ETags)
         use std::collections::HashMap;
 
-        let primary_list = &all_lists[self.primary_index].1;
+        let primary_position = all_lists
+            .iter()
+            .position(|(idx, _)| *idx == self.primary_index)
+            .expect("the primary can be read from");
+        let primary_list = &all_lists[primary_position].1;
         let primary_map: HashMap<_, _> = primary_list
             .iter()
             .map(|obj| (&obj.key, &obj.etag))
@@ -194,7 +244,7 @@ impl MultiBackend {
         );
 
         // Return primary result
-        Ok(all_lists.remove(self.primary_index).1)
+        Ok(all_lists.remove(primary_position).1)
     }
 }
 
@@ -202,7 +252,10 @@ impl MultiBackend {
 mod tests {
     use super::*;
     use crate::config::{ReadMode, WriteMode};
-    use crate::storage::{InMemoryStorage, backend::StorageBackend};
+    use crate::storage::{
+        InMemoryStorage,
+        backend::{PutObjectOptions, StorageBackend},
+    };
     use bytes::Bytes;
     use futures::stream;
     use std::sync::Arc;
@@ -226,11 +279,19 @@ mod tests {
 
         // Put objects through multi-backend
         multi
-            .put_object("test1", bytes_to_stream(Bytes::from("data1")))
+            .put_object(
+                "test1",
+                bytes_to_stream(Bytes::from("data1")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
         multi
-            .put_object("test2", bytes_to_stream(Bytes::from("data2")))
+            .put_object(
+                "test2",
+                bytes_to_stream(Bytes::from("data2")),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
 
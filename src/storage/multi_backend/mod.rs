@@ -1,16 +1,26 @@
-use super::backend::{ObjectStream, StorageBackend};
-use crate::config::{ReadMode, WriteMode};
-use crate::types::{ObjectMetadata, error::S3Error};
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend,
+};
+use crate::config::{BackendRole, ReadMode, WriteMode};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+mod bucket_versioning;
 mod delete_object;
 mod get_object;
 mod head_bucket;
 mod head_object;
+mod list_object_versions;
 mod list_objects;
+mod object_tagging;
 mod put_object;
 mod utils;
+mod version_map;
+
+use version_map::VersionMap;
 
 /// Multi-backend storage that replicates operations across multiple backends
 pub struct MultiBackend {
@@ -18,6 +28,10 @@ pub struct MultiBackend {
     pub(super) primary_index: usize,
     pub(super) read_mode: ReadMode,
     pub(super) write_mode: WriteMode,
+    /// Role of each backend, in the order of `backends`
+    pub(super) roles: Vec<BackendRole>,
+    /// Translation between proxy version IDs and each backend's version IDs
+    versions: VersionMap,
 }
 
 impl MultiBackend {
@@ -25,7 +39,7 @@ impl MultiBackend {
     ///
     /// # Arguments
     /// * `backends` - List of storage backends to use (must be non-empty)
-    /// * `primary_index` - Index of the primary backend (0-based)
+    /// * `primary_index` - Index of the primary backend (0-based), which must be readable and writable
     /// * `read_mode` - Read consistency mode
     /// * `write_mode` - Write consistency mode
     pub fn new(
@@ -42,44 +56,113 @@ impl MultiBackend {
             write_mode
         );
 
+        // Backends that can't be written to are read-only, the others read-write until told otherwise
+        let roles = backends
+            .iter()
+            .map(|backend| {
+                if backend.is_read_only() {
+                    BackendRole::ReadOnly
+                } else {
+                    BackendRole::ReadWrite
+                }
+            })
+            .collect();
+
         Self {
             backends,
             primary_index,
             read_mode,
             write_mode,
+            roles,
+            versions: VersionMap::new(primary_index),
         }
     }
 
+    /// Set the role of each backend, in the order of the backends
+    pub fn with_roles(mut self, roles: Vec<BackendRole>) -> Self {
+        assert_eq!(roles.len(), self.backends.len(), "one role per backend");
+        self.roles = roles;
+        self
+    }
+
+    /// Persist the mapping of version IDs between backends to `path`, loading it if it exists
+    ///
+    /// `backend_names` identify the backends in the file, in the order of the backends.
+    pub fn with_version_map_file(
+        mut self,
+        path: &Path,
+        backend_names: Vec<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        assert_eq!(
+            backend_names.len(),
+            self.backends.len(),
+            "one name per backend"
+        );
+        self.versions = VersionMap::open(self.primary_index, path, backend_names)?;
+        Ok(self)
+    }
+
     /// Get the primary backend
     pub(super) fn primary(&self) -> &Arc<dyn StorageBackend> {
         &self.backends[self.primary_index]
     }
 
-    /// Get all backends except the primary (for fallback reads)
-    pub(super) fn other_backends(&self) -> impl Iterator<Item = &Arc<dyn StorageBackend>> {
+    /// Get all backends except the primary that can be read from, with their indices (for fallback reads)
+    pub(super) fn other_backends(&self) -> impl Iterator<Item = (usize, &Arc<dyn StorageBackend>)> {
         let primary_idx = self.primary_index;
+        self.readable_backends()
+            .filter(move |(idx, _)| *idx != primary_idx)
+    }
+
+    /// Get the backends reads are served from, with their indices (write-only ones are skipped)
+    pub(super) fn readable_backends(
+        &self,
+    ) -> impl Iterator<Item = (usize, &Arc<dyn StorageBackend>)> {
         self.backends
             .iter()
             .enumerate()
-            .filter(move |(idx, _)| *idx != primary_idx)
-            .map(|(_, backend)| backend)
+            .filter(|(idx, _)| self.roles[*idx].can_read())
+    }
+
+    /// Get the backends writes are replicated to, with their indices (read-only ones are skipped)
+    pub(super) fn writable_backends(
+        &self,
+    ) -> impl Iterator<Item = (usize, &Arc<dyn StorageBackend>)> {
+        self.backends
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.roles[*idx].can_write())
+    }
+
+    /// Reject writes to a bucket whose primary can't be written to
+    ///
+    /// Only a bucket with a single read-only backend has such a primary.
+    pub(super) fn check_writable(&self) -> Result<(), S3Error> {
+        if !self.roles[self.primary_index].can_write() {
+            tracing::debug!("Rejecting a write to a read-only bucket");
+            return Err(S3Error::AccessDenied);
+        }
+        Ok(())
     }
 }
 
 /// Determine the best backend based on latency by benchmarking HEAD bucket requests
 ///
 /// Issues 10 HEAD bucket requests to each backend and calculates the median (P50) latency.
-/// Returns the index of the backend with the lowest P50 latency.
+/// Returns the index of the backend with the lowest P50 latency. Backends without the read-write
+/// role are skipped, they can't be the primary.
 ///
 /// # Arguments
 /// * `backends` - List of backends to benchmark
 /// * `backend_names` - Names of the backends (for logging)
+/// * `roles` - Roles of the backends
 ///
 /// # Returns
 /// The index of the backend with the best (lowest) P50 latency, or 0 if all backends fail
 pub async fn determine_primary_by_latency(
     backends: &[Arc<dyn StorageBackend>],
     backend_names: &[String],
+    roles: &[BackendRole],
 ) -> usize {
     const BENCHMARK_REQUESTS: usize = 10;
 
@@ -93,6 +176,14 @@ pub async fn determine_primary_by_latency(
 
     // Benchmark each backend
     for (idx, backend) in backends.iter().enumerate() {
+        if roles[idx] != BackendRole::ReadWrite {
+            tracing::debug!(
+                "Skipping backend '{}' with the {:?} role",
+                backend_names[idx],
+                roles[idx]
+            );
+            continue;
+        }
         let mut latencies = Vec::new();
         let backend_name = &backend_names[idx];
 
@@ -175,19 +266,22 @@ pub async fn determine_primary_by_latency(
         (None, None) => std::cmp::Ordering::Equal,
     });
 
-    let best_index = backend_p50s[0].0;
-    if let Some(best_p50) = backend_p50s[0].1 {
+    let Some(&(best_index, best_p50)) = backend_p50s.first() else {
+        tracing::warn!("No read-write backend to benchmark, defaulting to first backend");
+        return 0;
+    };
+    if let Some(best_p50) = best_p50 {
         tracing::info!(
             "Selected backend '{}' as primary based on best P50 latency: {:?}",
             backend_names[best_index],
             best_p50
         );
     } else {
+        // The sort is stable, so this is the first backend that was benchmarked
         tracing::warn!(
             "All backends failed benchmarking, defaulting to first backend '{}'",
-            backend_names[0]
+            backend_names[best_index]
         );
-        return 0;
     }
 
     best_index
@@ -204,23 +298,91 @@ impl StorageBackend for MultiBackend {
         prefix: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
-        self.list_objects_impl(prefix, max_keys).await
+        self.list_objects_impl(prefix, None, max_keys).await
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        self.list_objects_impl(prefix, Some(start_after), max_keys)
+            .await
     }
 
-    async fn head_object(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
-        self.head_object_impl(key).await
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        self.get_bucket_versioning_impl().await
     }
 
-    async fn get_object(&self, key: &str) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
-        self.get_object_impl(key).await
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        self.put_bucket_versioning_impl(status).await
     }
 
-    async fn put_object(&self, key: &str, body: ObjectStream) -> Result<String, S3Error> {
-        self.put_object_impl(key, body).await
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        self.list_object_versions_impl(prefix, max_keys).await
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        self.head_object_impl(key, options).await
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        self.get_object_impl(key, options).await
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        self.put_object_impl(key, body, options).await
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        self.delete_object_impl(key, version_id).await
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        self.get_object_tagging_impl(key, version_id).await
     }
 
-    async fn delete_object(&self, key: &str) -> Result<(), S3Error> {
-        self.delete_object_impl(key).await
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        self.put_object_tagging_impl(key, version_id, tags).await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.delete_object_tagging_impl(key, version_id).await
     }
 }
 
@@ -258,9 +420,10 @@ mod tests {
             "backend2".to_string(),
             "backend3".to_string(),
         ];
+        let roles = vec![BackendRole::ReadWrite; 3];
 
         // All backends should succeed, so one of them will be selected
-        let primary_index = determine_primary_by_latency(&backends, &backend_names).await;
+        let primary_index = determine_primary_by_latency(&backends, &backend_names, &roles).await;
 
         // The result should be a valid index (0, 1, or 2)
         assert!(primary_index < 3);
@@ -274,11 +437,98 @@ mod tests {
 
         let backends = vec![backend1, backend2];
         let backend_names = vec!["fast".to_string(), "slow".to_string()];
+        let roles = vec![BackendRole::ReadWrite; 2];
 
         // Both backends should succeed, and one will be chosen based on latency
-        let primary_index = determine_primary_by_latency(&backends, &backend_names).await;
+        let primary_index = determine_primary_by_latency(&backends, &backend_names, &roles).await;
 
         // Should return a valid index
         assert!(primary_index < 2);
     }
+
+    #[tokio::test]
+    async fn test_determine_primary_by_latency_skips_other_roles() {
+        let backends: Vec<Arc<dyn StorageBackend>> = (0..3)
+            .map(|_| Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>)
+            .collect();
+        let backend_names = vec![
+            "legacy".to_string(),
+            "archive".to_string(),
+            "main".to_string(),
+        ];
+        let roles = vec![
+            BackendRole::ReadOnly,
+            BackendRole::WriteOnly,
+            BackendRole::ReadWrite,
+        ];
+
+        // Only the read-write backend can be the primary
+        let primary_index = determine_primary_by_latency(&backends, &backend_names, &roles).await;
+        assert_eq!(primary_index, 2);
+    }
+
+    #[tokio::test]
+    async fn test_backend_roles() {
+        use bytes::Bytes;
+        use futures::stream;
+
+        let put = |data: &'static str| {
+            let body: ObjectStream = Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
+            body
+        };
+        let main = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let legacy = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let archive = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+
+        let multi = MultiBackend::new(
+            vec![legacy.clone(), archive.clone(), main.clone()],
+            2,
+            ReadMode::AllConsistent,
+            WriteMode::MultiSync,
+        )
+        .with_roles(vec![
+            BackendRole::ReadOnly,
+            BackendRole::WriteOnly,
+            BackendRole::ReadWrite,
+        ]);
+
+        // Writes skip the read-only backend
+        let options = PutObjectOptions::default();
+        multi.put_object("key", put("new"), &options).await.unwrap();
+        let read = GetObjectOptions::default();
+        assert!(main.head_object("key", &read).await.is_ok());
+        assert!(archive.head_object("key", &read).await.is_ok());
+        assert!(legacy.head_object("key", &read).await.is_err());
+
+        // Reads skip the write-only backend, so its diverging copy doesn't fail the check
+        legacy
+            .put_object("key", put("new"), &options)
+            .await
+            .unwrap();
+        archive
+            .put_object("key", put("old"), &options)
+            .await
+            .unwrap();
+        let metadata = multi.head_object("key", &read).await.unwrap();
+        assert_eq!(metadata.size, 3);
+        assert_eq!(multi.list_objects(None, 1000).await.unwrap().len(), 1);
+
+        // A bucket whose only backend is read-only rejects writes
+        let read_only = MultiBackend::new(
+            vec![legacy.clone()],
+            0,
+            ReadMode::PrimaryOnly,
+            WriteMode::AsyncReplication,
+        )
+        .with_roles(vec![BackendRole::ReadOnly]);
+        assert!(read_only.head_object("key", &read).await.is_ok());
+        assert!(matches!(
+            read_only.put_object("key", put("x"), &options).await,
+            Err(S3Error::AccessDenied)
+        ));
+        assert!(matches!(
+            read_only.delete_object("key", None).await,
+            Err(S3Error::AccessDenied)
+        ));
+    }
 }
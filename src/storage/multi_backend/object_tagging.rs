@@ -0,0 +1,283 @@
+use super::MultiBackend;
+use super::get_object::{is_not_found, not_found_error};
+use crate::config::{ReadMode, WriteMode};
+use crate::storage::backend::{GetObjectOptions, StorageBackend};
+use crate::types::{Tag, error::S3Error};
+use std::future::Future;
+use std::sync::Arc;
+
+impl MultiBackend {
+    pub(super) async fn get_object_tagging_impl(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        let key = key.to_string();
+        let options = GetObjectOptions {
+            version_id: version_id.map(str::to_string),
+            ..Default::default()
+        };
+        let get_tags = |idx: usize, backend: Arc<dyn StorageBackend>| {
+            let versions = self.versions.clone();
+            let key = key.clone();
+            let options = options.clone();
+            async move {
+                let backend_options = versions.backend_options(&key, &options, idx)?;
+                backend
+                    .get_object_tagging(&key, backend_options.version_id.as_deref())
+                    .await
+            }
+        };
+
+        match self.read_mode {
+            ReadMode::PrimaryOnly => {
+                tracing::debug!("GET object tagging (primary only mode)");
+                get_tags(self.primary_index, Arc::clone(self.primary())).await
+            }
+            ReadMode::PrimaryFallback => {
+                self.try_primary_fallback(
+                    "GET object tagging",
+                    is_not_found,
+                    not_found_error(&options),
+                    get_tags,
+                )
+                .await
+            }
+            ReadMode::BestEffort => {
+                self.race_all_backends(
+                    "GET object tagging",
+                    is_not_found,
+                    not_found_error(&options),
+                    get_tags,
+                )
+                .await
+            }
+            ReadMode::AllConsistent => {
+                self.verify_all_succeed("GET object tagging", get_tags)
+                    .await
+            }
+        }
+    }
+
+    pub(super) async fn put_object_tagging_impl(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        self.write_tagging(
+            "PUT object tagging",
+            key,
+            version_id,
+            move |backend, key, version_id| {
+                let tags = tags.clone();
+                async move {
+                    backend
+                        .put_object_tagging(&key, version_id.as_deref(), tags)
+                        .await
+                }
+            },
+        )
+        .await
+    }
+
+    pub(super) async fn delete_object_tagging_impl(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.write_tagging(
+            "DELETE object tagging",
+            key,
+            version_id,
+            |backend, key, version_id| async move {
+                backend
+                    .delete_object_tagging(&key, version_id.as_deref())
+                    .await
+            },
+        )
+        .await
+    }
+
+    /// Apply a tagging change to every backend according to the write mode
+    ///
+    /// Backends that never received the addressed version through the proxy are skipped.
+    async fn write_tagging<F, Fut>(
+        &self,
+        operation_name: &str,
+        key: &str,
+        version_id: Option<&str>,
+        operation: F,
+    ) -> Result<(), S3Error>
+    where
+        F: Fn(Arc<dyn StorageBackend>, String, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), S3Error>> + Send + 'static,
+    {
+        self.check_writable()?;
+        // Translate now, a concurrent version delete may drop the mapping
+        let targets: Vec<_> = self
+            .writable_backends()
+            .filter_map(|(idx, backend)| match version_id {
+                None => Some((idx, Arc::clone(backend), None)),
+                Some(v) => match self.versions.backend_version_id(key, v, idx) {
+                    Some(backend_version_id) => {
+                        Some((idx, Arc::clone(backend), Some(backend_version_id)))
+                    }
+                    None => {
+                        tracing::warn!(
+                            "{}: version {} of {} is not known on backend {}, skipping it",
+                            operation_name,
+                            v,
+                            key,
+                            idx
+                        );
+                        None
+                    }
+                },
+            })
+            .collect();
+
+        match self.write_mode {
+            WriteMode::AsyncReplication => {
+                tracing::info!("{} (async replication): primary first", operation_name);
+                operation(
+                    Arc::clone(self.primary()),
+                    key.to_string(),
+                    version_id.map(str::to_string),
+                )
+                .await?;
+
+                let primary_idx = self.primary_index;
+                let operation = Arc::new(operation);
+                for (idx, backend, backend_version_id) in targets
+                    .into_iter()
+                    .filter(|(idx, _, _)| *idx != primary_idx)
+                {
+                    let operation = Arc::clone(&operation);
+                    let operation_name = operation_name.to_string();
+                    let key = key.to_string();
+                    tokio::spawn(async move {
+                        if let Err(e) = operation(backend, key.clone(), backend_version_id).await {
+                            tracing::error!(
+                                "Background {}: backend {} failed for object {}: {}",
+                                operation_name,
+                                idx,
+                                key,
+                                e
+                            );
+                        }
+                    });
+                }
+
+                Ok(())
+            }
+            WriteMode::MultiSync => {
+                tracing::info!(
+                    "{} (multi sync): {} backends (all must succeed)",
+                    operation_name,
+                    targets.len()
+                );
+
+                let tasks = targets
+                    .into_iter()
+                    .map(|(idx, backend, backend_version_id)| {
+                        let result = operation(backend, key.to_string(), backend_version_id);
+                        async move { (idx, result.await) }
+                    });
+
+                for (idx, result) in futures::future::join_all(tasks).await {
+                    result.map_err(|e| {
+                        tracing::error!(
+                            "Backend {} failed {} for object {}: {}",
+                            idx,
+                            operation_name,
+                            key,
+                            e
+                        );
+                        S3Error::InternalError(format!(
+                            "Backend {} failed {} in multi sync mode: {}",
+                            idx, operation_name, e
+                        ))
+                    })?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{InMemoryStorage, backend::PutObjectOptions};
+    use bytes::Bytes;
+    use futures::stream;
+
+    fn tag(key: &str, value: &str) -> Tag {
+        Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tagging_multi_sync_applies_to_all_backends() {
+        let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let backend2 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+
+        let multi = MultiBackend::new(
+            vec![backend1.clone(), backend2.clone()],
+            0,
+            ReadMode::PrimaryFallback,
+            WriteMode::MultiSync,
+        );
+
+        let options = PutObjectOptions {
+            tags: vec![tag("team", "storage")],
+            ..Default::default()
+        };
+        multi
+            .put_object(
+                "key",
+                Box::pin(stream::once(async { Ok(Bytes::from("data")) })),
+                &options,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backend2.get_object_tagging("key", None).await.unwrap(),
+            vec![tag("team", "storage")]
+        );
+
+        multi
+            .put_object_tagging("key", None, vec![tag("env", "prod")])
+            .await
+            .unwrap();
+        assert_eq!(
+            backend1.get_object_tagging("key", None).await.unwrap(),
+            vec![tag("env", "prod")]
+        );
+        assert_eq!(
+            backend2.get_object_tagging("key", None).await.unwrap(),
+            vec![tag("env", "prod")]
+        );
+
+        multi.delete_object_tagging("key", None).await.unwrap();
+        assert!(
+            multi
+                .get_object_tagging("key", None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            backend2
+                .get_object_tagging("key", None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use super::MultiBackend;
+use super::version_map::VersionMap;
 use crate::config::WriteMode;
-use crate::storage::backend::{ObjectStream, StorageBackend};
+use crate::storage::backend::{
+    GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput, StorageBackend,
+};
 use crate::types::error::S3Error;
 use bytes::Bytes;
 use futures::stream::StreamExt;
@@ -13,19 +16,23 @@ impl MultiBackend {
         &self,
         key: &str,
         body: ObjectStream,
-    ) -> Result<String, S3Error> {
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        self.check_writable()?;
         match self.write_mode {
             WriteMode::AsyncReplication => {
                 // Stream to primary while buffering chunks for background replication
                 tracing::debug!(
                     "PUT object (async): streaming to primary with background replication"
                 );
-                self.put_object_async_replication_streaming(key, body).await
+                self.put_object_async_replication_streaming(key, body, options)
+                    .await
             }
             WriteMode::MultiSync => {
                 // Stream chunks to all backends without full buffering
                 tracing::debug!("PUT object (multi-sync): streaming to all backends");
-                self.put_object_multi_sync_streaming(key, body).await
+                self.put_object_multi_sync_streaming(key, body, options)
+                    .await
             }
         }
     }
@@ -35,20 +42,25 @@ impl MultiBackend {
         &self,
         key: &str,
         body: ObjectStream,
-    ) -> Result<String, S3Error> {
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
         let primary = self.primary();
         tracing::info!("PUT object (async replication streaming): streaming to primary");
 
         // Upload to primary backend (true streaming, no buffering)
-        let etag = primary.put_object(key, body).await?;
+        let output = primary.put_object(key, body, options).await?;
 
         tracing::info!("Primary backend successfully wrote object {}", key);
 
         // Spawn background tasks to replicate to other backends
         // These will GET from primary and stream to other backends
-        self.spawn_background_replication_tasks_streaming(key);
+        self.spawn_background_replication_tasks_streaming(
+            key,
+            output.version_id.clone(),
+            options.clone(),
+        );
 
-        Ok(etag)
+        Ok(output)
     }
 
     /// Stream chunks to all backends simultaneously without buffering the entire object
@@ -56,29 +68,27 @@ impl MultiBackend {
         &self,
         key: &str,
         body: ObjectStream,
-    ) -> Result<String, S3Error> {
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
         let num_backends = self.backends.len();
-        tracing::info!(
-            "PUT object (multi sync streaming): streaming to {} backends",
-            num_backends
-        );
-
         let backends: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .writable_backends()
             .map(|(idx, backend)| (idx, Arc::clone(backend)))
             .collect();
+        tracing::info!(
+            "PUT object (multi sync streaming): streaming to {} backends",
+            backends.len()
+        );
 
-        let results = Self::broadcast_stream_to_backends(backends, key, body).await?;
+        let results = Self::broadcast_stream_to_backends(backends, key, body, options).await?;
 
         // Check that all backends succeeded
-        let mut etags = vec![String::new(); num_backends];
+        let mut outputs = vec![None; num_backends];
         for (idx, result) in results {
             match result {
-                Ok(etag) => {
+                Ok(output) => {
                     tracing::info!("Backend {} successfully wrote object", idx);
-                    etags[idx] = etag;
+                    outputs[idx] = Some(output);
                 }
                 Err(e) => {
                     tracing::error!("Backend {} failed to write object: {}", idx, e);
@@ -93,12 +103,30 @@ impl MultiBackend {
 
         tracing::info!("PUT object (multi sync streaming): all backends succeeded");
 
-        // Return primary etag
-        Ok(etags[self.primary_index].clone())
+        // Return primary output, remembering which version each backend created for it
+        let primary_output = outputs[self.primary_index].take().unwrap();
+        if let Some(proxy_version_id) = &primary_output.version_id {
+            for (idx, output) in outputs.into_iter().enumerate() {
+                if let Some(backend_version_id) = output.and_then(|output| output.version_id) {
+                    self.versions
+                        .record(key, proxy_version_id, idx, &backend_version_id);
+                }
+            }
+        }
+
+        Ok(primary_output)
     }
 
     /// Spawn background task that GETs from primary once and broadcasts to other backends
-    fn spawn_background_replication_tasks_streaming(&self, key: &str) {
+    ///
+    /// `version_id` is the version the primary created, so a newer write to the same key
+    /// can't be replicated in its place.
+    fn spawn_background_replication_tasks_streaming(
+        &self,
+        key: &str,
+        version_id: Option<String>,
+        put_options: PutObjectOptions,
+    ) {
         if self.backends.len() <= 1 {
             return;
         }
@@ -106,22 +134,30 @@ impl MultiBackend {
         let primary_idx = self.primary_index;
         let primary_backend = Arc::clone(&self.backends[primary_idx]);
         let other_backends: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .writable_backends()
             .filter(move |(idx, _)| *idx != primary_idx)
             .map(|(idx, backend)| (idx, Arc::clone(backend)))
             .collect();
 
+        if other_backends.is_empty() {
+            return;
+        }
+
         tracing::info!(
             "Spawning background task to replicate to {} other backends (streaming from primary)",
             other_backends.len()
         );
 
         let key_clone = key.to_string();
+        let versions = self.versions.clone();
         tokio::spawn(async move {
-            // GET from primary once
-            let (stream, _metadata) = match primary_backend.get_object(&key_clone).await {
+            // GET from primary once, with the customer key it was just encrypted with
+            let options = GetObjectOptions {
+                version_id: version_id.clone(),
+                sse_customer: put_options.sse_customer.clone(),
+                ..Default::default()
+            };
+            let (stream, _metadata) = match primary_backend.get_object(&key_clone, &options).await {
                 Ok(result) => result,
                 Err(e) => {
                     tracing::error!(
@@ -134,18 +170,24 @@ impl MultiBackend {
             };
 
             // Use shared broadcast function to stream to all other backends
-            match MultiBackend::broadcast_stream_to_backends(other_backends, &key_clone, stream)
-                .await
+            match MultiBackend::broadcast_stream_to_backends(
+                other_backends,
+                &key_clone,
+                stream,
+                &put_options,
+            )
+            .await
             {
                 Ok(results) => {
                     for (idx, result) in results {
                         match result {
-                            Ok(_) => {
+                            Ok(output) => {
                                 tracing::info!(
                                     "Background replication: backend {} successfully wrote object {}",
                                     idx,
                                     key_clone
                                 );
+                                record_version(&versions, &key_clone, &version_id, idx, output);
                             }
                             Err(e) => {
                                 tracing::error!(
@@ -175,7 +217,8 @@ impl MultiBackend {
         backends: Vec<(usize, Arc<dyn StorageBackend>)>,
         key: &str,
         mut stream: ObjectStream,
-    ) -> Result<Vec<(usize, Result<String, S3Error>)>, S3Error> {
+        options: &PutObjectOptions,
+    ) -> Result<Vec<(usize, Result<PutObjectOutput, S3Error>)>, S3Error> {
         let num_backends = backends.len();
 
         // Create a channel for each backend
@@ -190,9 +233,10 @@ impl MultiBackend {
 
             // Spawn a task for each backend to consume from its channel
             let key = key.to_string();
+            let options = options.clone();
             let task = tokio::spawn(async move {
                 let stream: ObjectStream = Box::pin(ReceiverStream::new(rx));
-                let result = backend.put_object(&key, stream).await;
+                let result = backend.put_object(&key, stream, &options).await;
                 (idx, result)
             });
             backend_tasks.push(task);
@@ -236,11 +280,26 @@ impl MultiBackend {
     }
 }
 
+/// Remember the version a backend created when a proxy version was replicated to it
+fn record_version(
+    versions: &VersionMap,
+    key: &str,
+    proxy_version_id: &Option<String>,
+    idx: usize,
+    output: PutObjectOutput,
+) {
+    if let (Some(proxy_version_id), Some(backend_version_id)) =
+        (proxy_version_id, output.version_id)
+    {
+        versions.record(key, proxy_version_id, idx, &backend_version_id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::ReadMode;
-    use crate::storage::{InMemoryStorage, backend::StorageBackend};
+    use crate::storage::InMemoryStorage;
     use bytes::Bytes;
     use futures::stream::{self, StreamExt};
 
@@ -265,14 +324,21 @@ mod tests {
         let data = Bytes::from("Hello, World!");
 
         // Put object (synchronous to all backends)
-        let etag = multi
-            .put_object(key, bytes_to_stream(data.clone()))
+        let output = multi
+            .put_object(
+                key,
+                bytes_to_stream(data.clone()),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
-        assert!(!etag.is_empty());
+        assert!(!output.etag.is_empty());
 
         // Get object
-        let (mut stream, metadata) = multi.get_object(key).await.unwrap();
+        let (mut stream, metadata) = multi
+            .get_object(key, &GetObjectOptions::default())
+            .await
+            .unwrap();
         assert_eq!(metadata.key, key);
         assert_eq!(metadata.size, data.len() as u64);
 
@@ -283,8 +349,47 @@ mod tests {
         assert_eq!(collected, data);
 
         // Verify both backends have the object (guaranteed with MultiSync)
-        assert!(backend1.head_object(key).await.is_ok());
-        assert!(backend2.head_object(key).await.is_ok());
+        let options = GetObjectOptions::default();
+        assert!(backend1.head_object(key, &options).await.is_ok());
+        assert!(backend2.head_object(key, &options).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_multibackend_writes_skip_read_only_backends() {
+        // Nothing listens there, any request to it would fail
+        let origin = Arc::new(
+            crate::storage::HttpBackend::new("legacy".to_string(), "http://127.0.0.1:9/").unwrap(),
+        ) as Arc<dyn StorageBackend>;
+        let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let backend2 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+
+        let multi = MultiBackend::new(
+            vec![origin, backend1.clone(), backend2.clone()],
+            1,
+            ReadMode::PrimaryFallback,
+            WriteMode::MultiSync,
+        );
+
+        let key = "test-key";
+        multi
+            .put_object(
+                key,
+                bytes_to_stream(Bytes::from("data")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let options = GetObjectOptions::default();
+        assert!(backend1.head_object(key, &options).await.is_ok());
+        assert!(backend2.head_object(key, &options).await.is_ok());
+
+        multi
+            .put_object_tagging(key, None, Vec::new())
+            .await
+            .unwrap();
+        multi.delete_object(key, None).await.unwrap();
+        assert!(backend1.head_object(key, &options).await.is_err());
+        assert!(backend2.head_object(key, &options).await.is_err());
     }
 
     #[tokio::test]
@@ -303,14 +408,80 @@ mod tests {
         let data = Bytes::from("Consistent data");
 
         // Put object in consistent mode
-        let etag = multi
-            .put_object(key, bytes_to_stream(data.clone()))
+        let output = multi
+            .put_object(
+                key,
+                bytes_to_stream(data.clone()),
+                &PutObjectOptions::default(),
+            )
             .await
             .unwrap();
-        assert!(!etag.is_empty());
+        assert!(!output.etag.is_empty());
 
         // Verify both backends have the object
-        assert!(backend1.head_object(key).await.is_ok());
-        assert!(backend2.head_object(key).await.is_ok());
+        let options = GetObjectOptions::default();
+        assert!(backend1.head_object(key, &options).await.is_ok());
+        assert!(backend2.head_object(key, &options).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_multibackend_versions_are_mapped_per_backend() {
+        use crate::types::VersioningStatus;
+
+        let backend1 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+        let backend2 = Arc::new(InMemoryStorage::new()) as Arc<dyn StorageBackend>;
+
+        let multi = MultiBackend::new(
+            vec![backend1.clone(), backend2.clone()],
+            0,
+            ReadMode::PrimaryFallback,
+            WriteMode::MultiSync,
+        );
+        multi
+            .put_bucket_versioning(VersioningStatus::Enabled)
+            .await
+            .unwrap();
+
+        let first = multi
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("one")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        multi
+            .put_object(
+                "key",
+                bytes_to_stream(Bytes::from("two")),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap();
+        let proxy_version_id = first.version_id.unwrap();
+
+        // The secondary assigned its own ID, which the proxy translates to
+        let backend_version_id = multi
+            .versions
+            .backend_version_id("key", &proxy_version_id, 1)
+            .unwrap();
+        assert_ne!(backend_version_id, proxy_version_id);
+        let options = GetObjectOptions {
+            version_id: Some(backend_version_id),
+            ..Default::default()
+        };
+        let (mut stream, _) = backend2.get_object("key", &options).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), Bytes::from("one"));
+
+        // Deleting the version through the proxy removes it everywhere
+        multi
+            .delete_object("key", Some(&proxy_version_id))
+            .await
+            .unwrap();
+        assert!(backend2.head_object("key", &options).await.is_err());
+        assert_eq!(
+            multi.list_object_versions(None, 100).await.unwrap().len(),
+            1
+        );
     }
 }
@@ -13,13 +13,13 @@ impl MultiBackend {
         operation: F,
     ) -> Result<T, S3Error>
     where
-        F: Fn(Arc<dyn StorageBackend>) -> Fut,
+        F: Fn(usize, Arc<dyn StorageBackend>) -> Fut,
         Fut: std::future::Future<Output = Result<T, S3Error>>,
     {
         let primary = self.primary();
         tracing::debug!("{} (trying primary backend first)", operation_name);
 
-        match operation(Arc::clone(primary)).await {
+        match operation(self.primary_index, Arc::clone(primary)).await {
             Ok(result) => return Ok(result),
             Err(e) if is_not_found_error(&e) => {
                 tracing::debug!("Primary backend returned not found, not falling back");
@@ -31,9 +31,9 @@ impl MultiBackend {
         }
 
         // Try other backends
-        for (idx, backend) in self.other_backends().enumerate() {
+        for (idx, backend) in self.other_backends() {
             tracing::debug!("{} (trying fallback backend {})", operation_name, idx);
-            match operation(Arc::clone(backend)).await {
+            match operation(idx, Arc::clone(backend)).await {
                 Ok(result) => return Ok(result),
                 Err(e) => {
                     tracing::warn!(
@@ -58,7 +58,7 @@ impl MultiBackend {
         operation: F,
     ) -> Result<T, S3Error>
     where
-        F: Fn(Arc<dyn StorageBackend>) -> Fut,
+        F: Fn(usize, Arc<dyn StorageBackend>) -> Fut,
         Fut: std::future::Future<Output = Result<T, S3Error>> + Send + 'static,
         T: Send + 'static,
     {
@@ -68,16 +68,14 @@ impl MultiBackend {
         tracing::debug!(
             "{} (best effort mode - racing {} backends)",
             operation_name,
-            self.backends.len()
+            self.readable_backends().count()
         );
 
         let tasks: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .readable_backends()
             .map(|(idx, backend)| {
                 let backend = Arc::clone(backend);
-                let fut = operation(backend);
+                let fut = operation(idx, backend);
                 async move {
                     let result = fut.await;
                     (idx, result)
@@ -119,7 +117,7 @@ impl MultiBackend {
         operation: F,
     ) -> Result<T, S3Error>
     where
-        F: Fn(Arc<dyn StorageBackend>) -> Fut,
+        F: Fn(usize, Arc<dyn StorageBackend>) -> Fut,
         Fut: Future<Output = Result<T, S3Error>> + Send + 'static,
         T: Send + 'static,
     {
@@ -128,16 +126,14 @@ impl MultiBackend {
         tracing::debug!(
             "{} (all consistent mode - verifying {} backends)",
             operation_name,
-            self.backends.len()
+            self.readable_backends().count()
         );
 
         let tasks: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .readable_backends()
             .map(|(idx, backend)| {
                 let backend = Arc::clone(backend);
-                let fut = operation(backend);
+                let fut = operation(idx, backend);
                 async move {
                     let result = fut.await;
                     (idx, result)
@@ -146,6 +142,7 @@ impl MultiBackend {
             })
             .collect();
 
+        let num_backends = tasks.len();
         let results = futures::future::join_all(tasks).await;
 
         // Collect all successful results
@@ -163,16 +160,20 @@ impl MultiBackend {
         }
 
         // All backends must succeed
-        if successful_results.len() != self.backends.len() {
+        if successful_results.len() != num_backends {
             return Err(S3Error::InternalError(format!(
                 "Consistency check failed: only {}/{} backends succeeded",
                 successful_results.len(),
-                self.backends.len()
+                num_backends
             )));
         }
 
         // Verify all ETags match
-        let primary_etag = extract_etag(&successful_results[self.primary_index].1);
+        let primary_position = successful_results
+            .iter()
+            .position(|(idx, _)| *idx == self.primary_index)
+            .expect("the primary can be read from");
+        let primary_etag = extract_etag(&successful_results[primary_position].1);
         for (idx, data) in &successful_results {
             let etag = extract_etag(data);
             if etag != primary_etag {
@@ -191,7 +192,7 @@ impl MultiBackend {
         tracing::debug!("All backends returned consistent ETags: {}", primary_etag);
 
         // Return primary result
-        Ok(successful_results.remove(self.primary_index).1)
+        Ok(successful_results.remove(primary_position).1)
     }
 
     /// Helper: Verify all backends succeed (no ETag checking)
@@ -201,7 +202,7 @@ impl MultiBackend {
         operation: F,
     ) -> Result<T, S3Error>
     where
-        F: Fn(Arc<dyn StorageBackend>) -> Fut,
+        F: Fn(usize, Arc<dyn StorageBackend>) -> Fut,
         Fut: std::future::Future<Output = Result<T, S3Error>> + Send + 'static,
         T: Send + 'static,
     {
@@ -210,16 +211,14 @@ impl MultiBackend {
         tracing::debug!(
             "{} (all consistent mode - verifying {} backends)",
             operation_name,
-            self.backends.len()
+            self.readable_backends().count()
         );
 
         let tasks: Vec<_> = self
-            .backends
-            .iter()
-            .enumerate()
+            .readable_backends()
             .map(|(idx, backend)| {
                 let backend = Arc::clone(backend);
-                let fut = operation(backend);
+                let fut = operation(idx, backend);
                 async move {
                     let result = fut.await;
                     (idx, result)
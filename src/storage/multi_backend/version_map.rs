@@ -0,0 +1,332 @@
+use crate::storage::backend::GetObjectOptions;
+use crate::types::{NULL_VERSION_ID, error::S3Error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Maps the version IDs the proxy hands out to the version IDs of each backend
+///
+/// Clients see the version ID assigned by the primary backend. Every other backend assigns
+/// its own ID when the version is written to it, so reads and deletes of a specific version
+/// have to be translated per backend, and IDs coming back from a backend translated back.
+///
+/// The map is kept in memory unless it is opened from a file, in which case the whole map is
+/// rewritten to the file after every change. Without a file, versions created before a restart
+/// can only be addressed on the primary backend.
+#[derive(Clone)]
+pub(super) struct VersionMap {
+    primary_index: usize,
+    inner: Arc<RwLock<VersionMapInner>>,
+    store: Option<Arc<VersionMapStore>>,
+}
+
+/// File a version map is persisted to
+///
+/// Backends are recorded by name rather than by index, so the file stays valid when backends
+/// are reordered or the primary changes.
+struct VersionMapStore {
+    path: PathBuf,
+    backend_names: Vec<String>,
+}
+
+/// The backend version IDs of one proxy version, as written to the store file
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionMapEntry {
+    key: String,
+    version_id: String,
+    /// Backend name -> backend version ID
+    backends: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct VersionMapInner {
+    /// (key, proxy version ID) -> (backend index -> backend version ID)
+    to_backend: HashMap<(String, String), HashMap<usize, String>>,
+    /// (key, backend index, backend version ID) -> proxy version ID
+    to_proxy: HashMap<(String, usize, String), String>,
+}
+
+impl VersionMap {
+    pub(super) fn new(primary_index: usize) -> Self {
+        Self {
+            primary_index,
+            inner: Arc::new(RwLock::new(VersionMapInner::default())),
+            store: None,
+        }
+    }
+
+    /// Open a map persisted to `path`, loading its content if the file exists
+    ///
+    /// `backend_names` are the names of the backends, in the order of their indices.
+    pub(super) fn open(
+        primary_index: usize,
+        path: &Path,
+        backend_names: Vec<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut inner = VersionMapInner::default();
+        if path.exists() {
+            let entries: Vec<VersionMapEntry> =
+                serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            for entry in entries {
+                for (name, backend_version_id) in entry.backends {
+                    let Some(backend_idx) = backend_names.iter().position(|n| *n == name) else {
+                        tracing::warn!(
+                            "Version map {:?} refers to unknown backend '{}', ignoring it",
+                            path,
+                            name
+                        );
+                        continue;
+                    };
+                    inner.insert(
+                        &entry.key,
+                        &entry.version_id,
+                        backend_idx,
+                        &backend_version_id,
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            primary_index,
+            inner: Arc::new(RwLock::new(inner)),
+            store: Some(Arc::new(VersionMapStore {
+                path: path.to_path_buf(),
+                backend_names,
+            })),
+        })
+    }
+
+    /// Remember the version ID a backend assigned to a proxy version
+    pub(super) fn record(
+        &self,
+        key: &str,
+        proxy_version_id: &str,
+        backend_idx: usize,
+        backend_version_id: &str,
+    ) {
+        if backend_idx == self.primary_index || proxy_version_id == NULL_VERSION_ID {
+            return;
+        }
+
+        let mut inner = self.inner.write().unwrap();
+        inner.insert(key, proxy_version_id, backend_idx, backend_version_id);
+        // The primary's own ID is kept too, so the version stays addressable on it if another
+        // backend becomes the primary after a restart
+        inner.insert(key, proxy_version_id, self.primary_index, proxy_version_id);
+        self.persist(&inner);
+    }
+
+    /// Forget a proxy version once it has been deleted
+    pub(super) fn remove(&self, key: &str, proxy_version_id: &str) {
+        let mut inner = self.inner.write().unwrap();
+        if let Some(backend_versions) = inner
+            .to_backend
+            .remove(&(key.to_string(), proxy_version_id.to_string()))
+        {
+            for (backend_idx, backend_version_id) in backend_versions {
+                inner
+                    .to_proxy
+                    .remove(&(key.to_string(), backend_idx, backend_version_id));
+            }
+            self.persist(&inner);
+        }
+    }
+
+    /// Translate a proxy version ID to the version ID on a backend
+    /// Returns None if the version was never written to that backend through the proxy
+    pub(super) fn backend_version_id(
+        &self,
+        key: &str,
+        proxy_version_id: &str,
+        backend_idx: usize,
+    ) -> Option<String> {
+        if proxy_version_id == NULL_VERSION_ID {
+            return Some(proxy_version_id.to_string());
+        }
+
+        let inner = self.inner.read().unwrap();
+        inner
+            .to_backend
+            .get(&(key.to_string(), proxy_version_id.to_string()))
+            .and_then(|backend_versions| backend_versions.get(&backend_idx))
+            .cloned()
+            // Versions created by the current primary are its own IDs
+            .or_else(|| (backend_idx == self.primary_index).then(|| proxy_version_id.to_string()))
+    }
+
+    /// Translate a version ID reported by a backend to the proxy version ID
+    /// Returns None if the version is unknown to the proxy
+    pub(super) fn proxy_version_id(
+        &self,
+        key: &str,
+        backend_idx: usize,
+        backend_version_id: &str,
+    ) -> Option<String> {
+        if backend_version_id == NULL_VERSION_ID {
+            return Some(backend_version_id.to_string());
+        }
+
+        let inner = self.inner.read().unwrap();
+        inner
+            .to_proxy
+            .get(&(key.to_string(), backend_idx, backend_version_id.to_string()))
+            .cloned()
+            .or_else(|| (backend_idx == self.primary_index).then(|| backend_version_id.to_string()))
+    }
+
+    /// Translate read options for a backend
+    ///
+    /// Fails with an InternalError (not NoSuchVersion) when the version is unknown to the
+    /// backend, so reads fall back to backends that do have it.
+    pub(super) fn backend_options(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+        backend_idx: usize,
+    ) -> Result<GetObjectOptions, S3Error> {
+        let Some(proxy_version_id) = &options.version_id else {
+            return Ok(options.clone());
+        };
+
+        let version_id = self
+            .backend_version_id(key, proxy_version_id, backend_idx)
+            .ok_or_else(|| {
+                S3Error::InternalError(format!(
+                    "Version {} of {} is not known on backend {}",
+                    proxy_version_id, key, backend_idx
+                ))
+            })?;
+
+        let mut backend_options = options.clone();
+        backend_options.version_id = Some(version_id);
+        Ok(backend_options)
+    }
+
+    /// Write the map to its file, through a temporary file so a crash can't truncate it
+    ///
+    /// Failures are logged: the write the mapping belongs to has already succeeded.
+    fn persist(&self, inner: &VersionMapInner) {
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let entries: Vec<VersionMapEntry> = inner
+            .to_backend
+            .iter()
+            .map(|((key, version_id), backend_versions)| VersionMapEntry {
+                key: key.clone(),
+                version_id: version_id.clone(),
+                backends: backend_versions
+                    .iter()
+                    .map(|(idx, backend_version_id)| {
+                        (
+                            store.backend_names[*idx].clone(),
+                            backend_version_id.clone(),
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let tmp_path = PathBuf::from(format!("{}.tmp", store.path.display()));
+        let result = serde_json::to_string(&entries)
+            .map_err(std::io::Error::other)
+            .and_then(|content| std::fs::write(&tmp_path, content))
+            .and_then(|()| std::fs::rename(&tmp_path, &store.path));
+        if let Err(e) = result {
+            tracing::error!("Failed to persist version map to {:?}: {}", store.path, e);
+        }
+    }
+}
+
+impl VersionMapInner {
+    fn insert(
+        &mut self,
+        key: &str,
+        proxy_version_id: &str,
+        backend_idx: usize,
+        backend_version_id: &str,
+    ) {
+        self.to_backend
+            .entry((key.to_string(), proxy_version_id.to_string()))
+            .or_default()
+            .insert(backend_idx, backend_version_id.to_string());
+        self.to_proxy.insert(
+            (key.to_string(), backend_idx, backend_version_id.to_string()),
+            proxy_version_id.to_string(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primary_and_null_versions_are_not_translated() {
+        let versions = VersionMap::new(0);
+
+        assert_eq!(
+            versions.backend_version_id("key", "v1", 0).as_deref(),
+            Some("v1")
+        );
+        assert_eq!(
+            versions
+                .backend_version_id("key", NULL_VERSION_ID, 1)
+                .as_deref(),
+            Some(NULL_VERSION_ID)
+        );
+        assert_eq!(versions.backend_version_id("key", "v1", 1), None);
+    }
+
+    #[test]
+    fn test_record_and_remove() {
+        let versions = VersionMap::new(0);
+        versions.record("key", "v1", 1, "b1");
+
+        assert_eq!(
+            versions.backend_version_id("key", "v1", 1).as_deref(),
+            Some("b1")
+        );
+        assert_eq!(
+            versions.proxy_version_id("key", 1, "b1").as_deref(),
+            Some("v1")
+        );
+        assert_eq!(versions.proxy_version_id("other", 1, "b1"), None);
+
+        versions.remove("key", "v1");
+        assert_eq!(versions.backend_version_id("key", "v1", 1), None);
+        assert_eq!(versions.proxy_version_id("key", 1, "b1"), None);
+    }
+
+    #[test]
+    fn test_persisted_map_survives_reordered_backends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bucket.versions.json");
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        let versions = VersionMap::open(0, &path, names(&["aws", "minio"])).unwrap();
+        versions.record("key", "v1", 1, "m1");
+        versions.record("key", "v2", 1, "m2");
+        versions.remove("key", "v2");
+
+        // After a restart minio comes first and is the primary
+        let versions = VersionMap::open(0, &path, names(&["minio", "aws"])).unwrap();
+        assert_eq!(
+            versions.backend_version_id("key", "v1", 0).as_deref(),
+            Some("m1")
+        );
+        assert_eq!(
+            versions.backend_version_id("key", "v1", 1).as_deref(),
+            Some("v1")
+        );
+        assert_eq!(
+            versions.proxy_version_id("key", 0, "m1").as_deref(),
+            Some("v1")
+        );
+        assert_eq!(versions.backend_version_id("key", "v2", 1), None);
+    }
+}
@@ -1,12 +1,22 @@
-use crate::storage::backend::{ObjectStream, StorageBackend};
-use crate::types::{ObjectMetadata, error::S3Error};
+use crate::config::ServerSideEncryptionConfig;
+use crate::storage::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    SseCustomerKey, StorageBackend,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
 use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{
+    BucketVersioningStatus, ServerSideEncryption, Tagging, VersioningConfiguration,
+};
 use bytes::Bytes;
 use futures::stream::{Stream, StreamExt};
 use http_body::{Body, Frame};
 use http_body_util::BodyExt;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
@@ -17,6 +27,8 @@ pub struct S3Backend {
     client: S3Client,
     bucket: String,
     name: String,
+    /// Encryption requested on every write, unless the client sends its own key
+    server_side_encryption: Option<ServerSideEncryptionConfig>,
 }
 
 /// Type alias for the receiver stream used in StreamBody
@@ -84,6 +96,8 @@ impl S3Backend {
         etag: Option<&str>,
         last_modified: Option<&aws_sdk_s3::primitives::DateTime>,
         content_type: Option<&str>,
+        version_id: Option<&str>,
+        user_metadata: Option<&HashMap<String, String>>,
     ) -> ObjectMetadata {
         let size = content_length.unwrap_or(0) as u64;
         let etag = etag.map(|s| s.to_string()).unwrap_or_default();
@@ -103,6 +117,73 @@ impl S3Backend {
             etag,
             last_modified,
             content_type,
+            version_id: version_id.map(|s| s.to_string()),
+            user_metadata: user_metadata.cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Helper function to convert an AWS SDK timestamp
+    fn convert_timestamp(
+        dt: Option<&aws_sdk_s3::primitives::DateTime>,
+    ) -> chrono::DateTime<chrono::Utc> {
+        dt.and_then(|dt| chrono::DateTime::from_timestamp(dt.secs(), 0))
+            .unwrap_or_else(chrono::Utc::now)
+    }
+
+    /// Encode tags the way the x-amz-tagging header expects them (URL query string)
+    fn encode_tagging(tags: &[Tag]) -> Option<String> {
+        if tags.is_empty() {
+            return None;
+        }
+        let pairs: Vec<(&str, &str)> = tags
+            .iter()
+            .map(|tag| (tag.key.as_str(), tag.value.as_str()))
+            .collect();
+        serde_urlencoded::to_string(pairs).ok()
+    }
+
+    /// Map a failed tagging request to the error returned to the client
+    fn tagging_error<E: ProvideErrorMetadata>(
+        &self,
+        operation: &str,
+        err: &SdkError<E, HttpResponse>,
+    ) -> S3Error {
+        match err.code() {
+            Some("NoSuchKey") => S3Error::NoSuchKey,
+            Some("NoSuchVersion") => S3Error::NoSuchVersion,
+            Some("MethodNotAllowed") => S3Error::MethodNotAllowed,
+            _ => {
+                tracing::error!("[{}] Failed to {}: {}", self.name, operation, err);
+                S3Error::InternalError(format!("Failed to {} in {}: {}", operation, self.name, err))
+            }
+        }
+    }
+
+    /// Map a failed GET/HEAD to the error returned to the client
+    ///
+    /// HEAD responses have no body, so the status code is used when there is no error code.
+    fn read_error<E: ProvideErrorMetadata>(
+        err: &SdkError<E, HttpResponse>,
+        options: &GetObjectOptions,
+    ) -> S3Error {
+        let status = err
+            .raw_response()
+            .map(|response| response.status().as_u16());
+        match (err.code(), status) {
+            (Some("NoSuchVersion"), _) => S3Error::NoSuchVersion,
+            (Some("InvalidRange"), _) => S3Error::InvalidRange,
+            (_, Some(416)) if options.range.is_some() => S3Error::InvalidRange,
+            (Some("InvalidPartNumber"), _) | (_, Some(416)) => S3Error::InvalidPartNumber,
+            (Some("MethodNotAllowed"), _) | (_, Some(405)) => S3Error::MethodNotAllowed,
+            // SSE-C objects read with a wrong key, or without the key they were stored with
+            (Some("AccessDenied"), _) | (None, Some(403)) => S3Error::AccessDenied,
+            (Some("InvalidRequest"), _) | (None, Some(400)) => S3Error::InvalidRequest(
+                err.message()
+                    .unwrap_or("The encryption parameters do not match the object")
+                    .to_string(),
+            ),
+            _ if options.version_id.is_some() => S3Error::NoSuchVersion,
+            _ => S3Error::NoSuchKey,
         }
     }
 
@@ -142,9 +223,80 @@ impl S3Backend {
             client,
             bucket,
             name,
+            server_side_encryption: None,
         })
     }
 
+    /// Ask the backend to encrypt the objects the proxy writes to it
+    pub fn with_server_side_encryption(
+        mut self,
+        server_side_encryption: Option<ServerSideEncryptionConfig>,
+    ) -> Self {
+        self.server_side_encryption = server_side_encryption;
+        self
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        tracing::debug!("[{}] Listing objects with prefix: {:?}", self.name, prefix);
+
+        let mut request = self.client.list_objects_v2().bucket(&self.bucket);
+
+        if let Some(p) = prefix {
+            request = request.prefix(p);
+        }
+        if let Some(key) = start_after {
+            request = request.start_after(key);
+        }
+
+        let result = request.max_keys(max_keys).send().await;
+
+        match result {
+            Ok(output) => {
+                let objects: Vec<ObjectMetadata> = output
+                    .contents()
+                    .iter()
+                    .filter_map(|obj| {
+                        let key = obj.key()?.to_string();
+                        let size = obj.size().unwrap_or(0) as u64;
+                        let etag = obj.e_tag().map(|s| s.to_string()).unwrap_or_default();
+                        let last_modified = obj
+                            .last_modified()
+                            .and_then(|dt| {
+                                let secs = dt.secs();
+                                chrono::DateTime::from_timestamp(secs, 0)
+                            })
+                            .unwrap_or_else(chrono::Utc::now);
+
+                        Some(ObjectMetadata {
+                            key,
+                            size,
+                            etag,
+                            last_modified,
+                            content_type: "binary/octet-stream".to_string(),
+                            version_id: None,
+                            user_metadata: HashMap::new(),
+                        })
+                    })
+                    .collect();
+
+                tracing::debug!("[{}] Found {} objects", self.name, objects.len());
+                Ok(objects)
+            }
+            Err(err) => {
+                tracing::error!("[{}] Failed to list objects: {}", self.name, err);
+                Err(S3Error::InternalError(format!(
+                    "Failed to list objects in {}: {}",
+                    self.name, err
+                )))
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub fn name(&self) -> &str {
         &self.name
@@ -158,6 +310,14 @@ impl S3Backend {
     }
 }
 
+/// One field of an optional SSE-C key, in the form the SDK setters take
+fn sse_customer_field(
+    sse_customer: &Option<SseCustomerKey>,
+    field: fn(&SseCustomerKey) -> &String,
+) -> Option<String> {
+    sse_customer.as_ref().map(|key| field(key).clone())
+}
+
 #[async_trait::async_trait]
 impl StorageBackend for S3Backend {
     // Bucket-level operations
@@ -187,50 +347,144 @@ impl StorageBackend for S3Backend {
         prefix: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<ObjectMetadata>, S3Error> {
-        tracing::debug!("[{}] Listing objects with prefix: {:?}", self.name, prefix);
+        self.list(prefix, None, max_keys).await
+    }
 
-        let mut request = self.client.list_objects_v2().bucket(&self.bucket);
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        self.list(prefix, Some(start_after), max_keys).await
+    }
 
-        if let Some(p) = prefix {
-            request = request.prefix(p);
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        tracing::debug!("[{}] Getting bucket versioning", self.name);
+
+        let result = self
+            .client
+            .get_bucket_versioning()
+            .bucket(&self.bucket)
+            .send()
+            .await;
+
+        match result {
+            Ok(output) => Ok(output
+                .status()
+                .and_then(|status| VersioningStatus::parse(status.as_str()))),
+            Err(err) => {
+                tracing::error!("[{}] Failed to get bucket versioning: {}", self.name, err);
+                Err(S3Error::InternalError(format!(
+                    "Failed to get bucket versioning in {}: {}",
+                    self.name, err
+                )))
+            }
         }
+    }
 
-        let result = request.max_keys(max_keys).send().await;
+    async fn put_bucket_versioning(&self, status: VersioningStatus) -> Result<(), S3Error> {
+        tracing::debug!("[{}] Setting bucket versioning: {:?}", self.name, status);
+
+        let configuration = VersioningConfiguration::builder()
+            .status(BucketVersioningStatus::from(status.as_str()))
+            .build();
+
+        let result = self
+            .client
+            .put_bucket_versioning()
+            .bucket(&self.bucket)
+            .versioning_configuration(configuration)
+            .send()
+            .await;
 
         match result {
-            Ok(output) => {
-                let objects: Vec<ObjectMetadata> = output
-                    .contents()
-                    .iter()
-                    .filter_map(|obj| {
-                        let key = obj.key()?.to_string();
-                        let size = obj.size().unwrap_or(0) as u64;
-                        let etag = obj.e_tag().map(|s| s.to_string()).unwrap_or_default();
-                        let last_modified = obj
-                            .last_modified()
-                            .and_then(|dt| {
-                                let secs = dt.secs();
-                                chrono::DateTime::from_timestamp(secs, 0)
-                            })
-                            .unwrap_or_else(chrono::Utc::now);
+            Ok(_) => {
+                tracing::info!("[{}] Bucket versioning set to {:?}", self.name, status);
+                Ok(())
+            }
+            Err(err) => {
+                tracing::error!("[{}] Failed to set bucket versioning: {}", self.name, err);
+                Err(S3Error::InternalError(format!(
+                    "Failed to set bucket versioning in {}: {}",
+                    self.name, err
+                )))
+            }
+        }
+    }
 
-                        Some(ObjectMetadata {
-                            key,
-                            size,
-                            etag,
-                            last_modified,
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        tracing::debug!(
+            "[{}] Listing object versions with prefix: {:?}",
+            self.name,
+            prefix
+        );
+
+        let result = self
+            .client
+            .list_object_versions()
+            .bucket(&self.bucket)
+            .set_prefix(prefix.map(|s| s.to_string()))
+            .max_keys(max_keys)
+            .send()
+            .await;
+
+        match result {
+            Ok(output) => {
+                let versions = output.versions().iter().filter_map(|version| {
+                    Some(ObjectVersion {
+                        metadata: ObjectMetadata {
+                            key: version.key()?.to_string(),
+                            size: version.size().unwrap_or(0) as u64,
+                            etag: version.e_tag().map(|s| s.to_string()).unwrap_or_default(),
+                            last_modified: Self::convert_timestamp(version.last_modified()),
                             content_type: "binary/octet-stream".to_string(),
-                        })
+                            version_id: version.version_id().map(|s| s.to_string()),
+                            user_metadata: HashMap::new(),
+                        },
+                        is_latest: version.is_latest().unwrap_or(false),
+                        is_delete_marker: false,
                     })
-                    .collect();
+                });
+                let delete_markers = output.delete_markers().iter().filter_map(|marker| {
+                    Some(ObjectVersion {
+                        metadata: ObjectMetadata {
+                            key: marker.key()?.to_string(),
+                            size: 0,
+                            etag: String::new(),
+                            last_modified: Self::convert_timestamp(marker.last_modified()),
+                            content_type: String::new(),
+                            version_id: marker.version_id().map(|s| s.to_string()),
+                            user_metadata: HashMap::new(),
+                        },
+                        is_latest: marker.is_latest().unwrap_or(false),
+                        is_delete_marker: true,
+                    })
+                });
 
-                tracing::debug!("[{}] Found {} objects", self.name, objects.len());
-                Ok(objects)
+                // S3 returns versions and delete markers separately, merge them back into
+                // key order with the newest version first
+                let mut results: Vec<ObjectVersion> = versions.chain(delete_markers).collect();
+                results.sort_by(|a, b| {
+                    a.metadata.key.cmp(&b.metadata.key).then(
+                        b.metadata
+                            .last_modified
+                            .cmp(&a.metadata.last_modified)
+                            .then(b.is_latest.cmp(&a.is_latest)),
+                    )
+                });
+
+                tracing::debug!("[{}] Found {} versions", self.name, results.len());
+                Ok(results)
             }
             Err(err) => {
-                tracing::error!("[{}] Failed to list objects: {}", self.name, err);
+                tracing::error!("[{}] Failed to list object versions: {}", self.name, err);
                 Err(S3Error::InternalError(format!(
-                    "Failed to list objects in {}: {}",
+                    "Failed to list object versions in {}: {}",
                     self.name, err
                 )))
             }
@@ -238,7 +492,11 @@ impl StorageBackend for S3Backend {
     }
 
     // Object-level operations
-    async fn head_object(&self, key: &str) -> Result<ObjectMetadata, S3Error> {
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
         tracing::debug!("[{}] Getting metadata for object: {}", self.name, key);
 
         let result = self
@@ -246,6 +504,11 @@ impl StorageBackend for S3Backend {
             .head_object()
             .bucket(&self.bucket)
             .key(key)
+            .set_version_id(options.version_id.clone())
+            .set_part_number(options.part_number.map(|n| n as i32))
+            .set_sse_customer_algorithm(sse_customer_field(&options.sse_customer, |k| &k.algorithm))
+            .set_sse_customer_key(sse_customer_field(&options.sse_customer, |k| &k.key))
+            .set_sse_customer_key_md5(sse_customer_field(&options.sse_customer, |k| &k.key_md5))
             .send()
             .await;
 
@@ -257,17 +520,23 @@ impl StorageBackend for S3Backend {
                     output.e_tag(),
                     output.last_modified(),
                     output.content_type(),
+                    output.version_id(),
+                    output.metadata(),
                 );
                 Ok(metadata)
             }
-            Err(_) => {
+            Err(err) => {
                 tracing::warn!("[{}] Object not found: {}", self.name, key);
-                Err(S3Error::NoSuchKey)
+                Err(Self::read_error(&err, options))
             }
         }
     }
 
-    async fn get_object(&self, key: &str) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
         tracing::debug!("[{}] Getting object: {}", self.name, key);
 
         let result = self
@@ -275,18 +544,33 @@ impl StorageBackend for S3Backend {
             .get_object()
             .bucket(&self.bucket)
             .key(key)
+            .set_version_id(options.version_id.clone())
+            .set_part_number(options.part_number.map(|n| n as i32))
+            .set_range(options.range.map(|range| range.header_value()))
+            .set_sse_customer_algorithm(sse_customer_field(&options.sse_customer, |k| &k.algorithm))
+            .set_sse_customer_key(sse_customer_field(&options.sse_customer, |k| &k.key))
+            .set_sse_customer_key_md5(sse_customer_field(&options.sse_customer, |k| &k.key_md5))
             .send()
             .await;
 
         match result {
             Ok(output) => {
-                // Extract metadata from the response
+                // Extract metadata from the response; a ranged response only carries the
+                // size of the whole object in its Content-Range
+                let content_length = match output.content_range() {
+                    Some(content_range) if options.range.is_some() => content_range
+                        .rsplit_once('/')
+                        .and_then(|(_, size)| size.parse().ok()),
+                    _ => output.content_length(),
+                };
                 let metadata = Self::extract_metadata(
                     key,
-                    output.content_length(),
+                    content_length,
                     output.e_tag(),
                     output.last_modified(),
                     output.content_type(),
+                    output.version_id(),
+                    output.metadata(),
                 );
 
                 let name = self.name.clone();
@@ -302,14 +586,19 @@ impl StorageBackend for S3Backend {
 
                 Ok((Box::pin(stream), metadata))
             }
-            Err(_err) => {
+            Err(err) => {
                 tracing::warn!("[{}] Object not found: {}", self.name, key);
-                Err(S3Error::NoSuchKey)
+                Err(Self::read_error(&err, options))
             }
         }
     }
 
-    async fn put_object(&self, key: &str, body: ObjectStream) -> Result<String, S3Error> {
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
         tracing::debug!("[{}] Putting object (streaming): {}", self.name, key);
 
         // Wrap the stream in our Body adapter for true streaming
@@ -319,14 +608,33 @@ impl StorageBackend for S3Backend {
         // Convert to ByteStream using the Body adapter
         let body_stream = ByteStream::from_body_1_x(stream_body);
 
-        let result = self
+        let mut request = self
             .client
             .put_object()
             .bucket(&self.bucket)
             .key(key)
             .body(body_stream)
-            .send()
-            .await;
+            .set_tagging(Self::encode_tagging(&options.tags))
+            .set_content_type(options.content_type.clone())
+            .set_metadata(
+                (!options.user_metadata.is_empty()).then(|| options.user_metadata.clone()),
+            );
+        // S3 refuses a customer-provided key combined with another form of encryption
+        match (&options.sse_customer, &self.server_side_encryption) {
+            (Some(sse_customer), _) => {
+                request = request
+                    .sse_customer_algorithm(&sse_customer.algorithm)
+                    .sse_customer_key(&sse_customer.key)
+                    .sse_customer_key_md5(&sse_customer.key_md5);
+            }
+            (None, Some(sse)) => {
+                request = request
+                    .server_side_encryption(ServerSideEncryption::from(sse.mode.as_str()))
+                    .set_ssekms_key_id(sse.kms_key_id.clone());
+            }
+            (None, None) => {}
+        }
+        let result = request.send().await;
 
         match result {
             Ok(output) => {
@@ -336,7 +644,10 @@ impl StorageBackend for S3Backend {
                     .e_tag()
                     .map(|s| s.to_string())
                     .unwrap_or_else(|| Self::calculate_etag(&[]));
-                Ok(etag)
+                Ok(PutObjectOutput {
+                    etag,
+                    version_id: output.version_id().map(|s| s.to_string()),
+                })
             }
             Err(err) => {
                 tracing::error!("[{}] Failed to put object: {}", self.name, err);
@@ -348,7 +659,11 @@ impl StorageBackend for S3Backend {
         }
     }
 
-    async fn delete_object(&self, key: &str) -> Result<(), S3Error> {
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
         tracing::debug!("[{}] Deleting object: {}", self.name, key);
 
         let result = self
@@ -356,19 +671,123 @@ impl StorageBackend for S3Backend {
             .delete_object()
             .bucket(&self.bucket)
             .key(key)
+            .set_version_id(version_id.map(|s| s.to_string()))
             .send()
             .await;
 
         match result {
-            Ok(_) => {
+            Ok(output) => {
                 tracing::info!("[{}] Successfully deleted object: {}", self.name, key);
-                Ok(())
+                Ok(DeleteObjectOutput {
+                    version_id: output.version_id().map(|s| s.to_string()),
+                    delete_marker: output.delete_marker().unwrap_or(false),
+                })
             }
             Err(err) => {
                 tracing::error!("[{}] Failed to delete object: {}", self.name, err);
                 // S3 delete is idempotent - returns success even if object doesn't exist
+                Ok(DeleteObjectOutput::default())
+            }
+        }
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        tracing::debug!("[{}] Getting tags of object: {}", self.name, key);
+
+        let result = self
+            .client
+            .get_object_tagging()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_version_id(version_id.map(|s| s.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(output) => Ok(output
+                .tag_set()
+                .iter()
+                .map(|tag| Tag {
+                    key: tag.key().to_string(),
+                    value: tag.value().to_string(),
+                })
+                .collect()),
+            Err(err) => Err(self.tagging_error("get object tagging", &err)),
+        }
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        tracing::debug!("[{}] Setting tags of object: {}", self.name, key);
+
+        let tag_set = tags
+            .into_iter()
+            .map(|tag| {
+                aws_sdk_s3::types::Tag::builder()
+                    .key(tag.key)
+                    .value(tag.value)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| S3Error::InternalError(format!("Failed to build tag set: {}", e)))?;
+        let tagging = Tagging::builder()
+            .set_tag_set(Some(tag_set))
+            .build()
+            .map_err(|e| S3Error::InternalError(format!("Failed to build tagging: {}", e)))?;
+
+        let result = self
+            .client
+            .put_object_tagging()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_version_id(version_id.map(|s| s.to_string()))
+            .tagging(tagging)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => {
+                tracing::info!("[{}] Successfully set tags of object: {}", self.name, key);
+                Ok(())
+            }
+            Err(err) => Err(self.tagging_error("put object tagging", &err)),
+        }
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        tracing::debug!("[{}] Deleting tags of object: {}", self.name, key);
+
+        let result = self
+            .client
+            .delete_object_tagging()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_version_id(version_id.map(|s| s.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => {
+                tracing::info!(
+                    "[{}] Successfully deleted tags of object: {}",
+                    self.name,
+                    key
+                );
                 Ok(())
             }
+            Err(err) => Err(self.tagging_error("delete object tagging", &err)),
         }
     }
 }
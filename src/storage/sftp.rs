@@ -0,0 +1,712 @@
+//! Storage backend for a directory on an SFTP server
+//!
+//! Each key maps to the file at the same path under the remote root directory, so the objects
+//! can be picked up like any other delivery. Only the data is stored: content types, user
+//! metadata and tags are not kept, and the ETag is derived from the file's modification time
+//! and size.
+//!
+//! Uploads are streamed to a hidden temporary file next to the object, which is then renamed
+//! into place, so readers never see a partially written object. One SSH connection is shared
+//! by all requests and reopened when it drops.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use russh::client;
+use russh::keys::{PrivateKey, PrivateKeyWithHashAlg, PublicKey};
+use russh_sftp::client::SftpSession;
+use russh_sftp::client::error::Error as SftpError;
+use russh_sftp::protocol::{FileAttributes, StatusCode};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Suffix of the temporary files uploads are written to
+const TEMP_SUFFIX: &str = ".replicat4-tmp";
+/// Size of the chunks objects are read in
+const READ_CHUNK_SIZE: usize = 255 * 1024;
+/// Interval of the keepalive messages sent on idle connections
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How the backend authenticates to the SFTP server
+pub enum SftpAuth {
+    Password(String),
+    PrivateKey(Arc<PrivateKey>),
+}
+
+impl SftpAuth {
+    /// Load an OpenSSH or PKCS#8 private key, decrypting it with the passphrase if needed
+    pub fn private_key_file<P: AsRef<Path>>(
+        path: P,
+        passphrase: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let key = russh::keys::load_secret_key(path, passphrase)?;
+        Ok(Self::PrivateKey(Arc::new(key)))
+    }
+}
+
+/// Accepts the server only if its host key is in the known hosts file
+struct KnownHosts {
+    host: String,
+    port: u16,
+    /// `~/.ssh/known_hosts` if unset
+    path: Option<PathBuf>,
+}
+
+impl client::Handler for KnownHosts {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &PublicKey) -> Result<bool, Self::Error> {
+        let known = match &self.path {
+            Some(path) => russh::keys::check_known_hosts_path(&self.host, self.port, key, path),
+            None => russh::keys::check_known_hosts(&self.host, self.port, key),
+        };
+        match known {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                tracing::error!(
+                    "Host key of {}:{} is not in the known hosts file",
+                    self.host,
+                    self.port
+                );
+                Ok(false)
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to verify the host key of {}:{}: {}",
+                    self.host,
+                    self.port,
+                    e
+                );
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Open SSH connection with its SFTP session
+struct Connection {
+    handle: client::Handle<KnownHosts>,
+    sftp: SftpSession,
+}
+
+pub struct SftpBackend {
+    name: String,
+    host: String,
+    port: u16,
+    username: String,
+    auth: SftpAuth,
+    known_hosts_file: Option<PathBuf>,
+    /// Remote directory holding the objects, without a trailing '/'
+    root: String,
+    connection: Mutex<Option<Arc<Connection>>>,
+}
+
+fn ssh_error(e: russh::Error) -> S3Error {
+    S3Error::InternalError(format!("SSH error: {}", e))
+}
+
+fn sftp_error(e: SftpError) -> S3Error {
+    match &e {
+        SftpError::Status(status) if status.status_code == StatusCode::PermissionDenied => {
+            S3Error::AccessDenied
+        }
+        _ => S3Error::InternalError(format!("SFTP error: {}", e)),
+    }
+}
+
+fn io_error(e: std::io::Error) -> S3Error {
+    S3Error::InternalError(format!("SFTP error: {}", e))
+}
+
+fn conflict_error() -> S3Error {
+    S3Error::InvalidArgument(
+        "The key conflicts with another key stored on the SFTP backend".to_string(),
+    )
+}
+
+/// Whether an error means the path doesn't exist
+fn is_missing(e: &SftpError) -> bool {
+    matches!(e, SftpError::Status(status) if status.status_code == StatusCode::NoSuchFile)
+}
+
+/// Whether a key segment can be used as a file name
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && !segment.contains('\\')
+        && !segment.contains('\0')
+        && !segment.ends_with(TEMP_SUFFIX)
+}
+
+/// Metadata of an object, with an ETag that changes with the file
+fn object_metadata(key: &str, attrs: &FileAttributes) -> ObjectMetadata {
+    let size = attrs.size.unwrap_or_default();
+    let mtime = attrs.mtime.unwrap_or_default();
+    ObjectMetadata {
+        key: key.to_string(),
+        size,
+        etag: format!("\"{:x}-{:x}\"", mtime, size),
+        last_modified: chrono::DateTime::from_timestamp(mtime.into(), 0)
+            .unwrap_or_else(chrono::Utc::now),
+        content_type: "binary/octet-stream".to_string(),
+        version_id: None,
+        user_metadata: HashMap::new(),
+    }
+}
+
+impl SftpBackend {
+    pub fn new(
+        name: String,
+        host: String,
+        port: u16,
+        username: String,
+        auth: SftpAuth,
+        known_hosts_file: Option<PathBuf>,
+        root: String,
+    ) -> Self {
+        let root = match root.trim_end_matches('/') {
+            "" if root.starts_with('/') => String::new(),
+            "" => ".".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+        Self {
+            name,
+            host,
+            port,
+            username,
+            auth,
+            known_hosts_file,
+            root,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Path of the root directory itself
+    fn root_dir(&self) -> &str {
+        if self.root.is_empty() {
+            "/"
+        } else {
+            &self.root
+        }
+    }
+
+    /// Map a key to its remote path
+    ///
+    /// Returns None for keys that can't be stored safely, such as keys with `..` or empty
+    /// segments, which would otherwise escape the root or clash with directories.
+    fn remote_path(&self, key: &str) -> Option<String> {
+        key.split('/')
+            .all(is_valid_segment)
+            .then(|| format!("{}/{}", self.root, key))
+    }
+
+    /// Remote path of a key that must already exist
+    fn existing_path(&self, key: &str) -> Result<String, S3Error> {
+        self.remote_path(key).ok_or(S3Error::NoSuchKey)
+    }
+
+    /// The shared SFTP session, connecting first if there is none or it was closed
+    async fn session(&self) -> Result<Arc<Connection>, S3Error> {
+        let mut connection = self.connection.lock().await;
+        if let Some(existing) = connection.as_ref()
+            && !existing.handle.is_closed()
+        {
+            return Ok(existing.clone());
+        }
+
+        let connected = Arc::new(self.connect().await?);
+        *connection = Some(connected.clone());
+        Ok(connected)
+    }
+
+    async fn connect(&self) -> Result<Connection, S3Error> {
+        tracing::debug!(
+            "Connecting SFTP backend '{}' to {}:{}",
+            self.name,
+            self.host,
+            self.port
+        );
+        let config = Arc::new(client::Config {
+            keepalive_interval: Some(KEEPALIVE_INTERVAL),
+            ..Default::default()
+        });
+        let known_hosts = KnownHosts {
+            host: self.host.clone(),
+            port: self.port,
+            path: self.known_hosts_file.clone(),
+        };
+        let mut handle = client::connect(config, (self.host.as_str(), self.port), known_hosts)
+            .await
+            .map_err(ssh_error)?;
+
+        let result = match &self.auth {
+            SftpAuth::Password(password) => {
+                handle.authenticate_password(&self.username, password).await
+            }
+            SftpAuth::PrivateKey(key) => {
+                let hash_alg = handle
+                    .best_supported_rsa_hash()
+                    .await
+                    .map_err(ssh_error)?
+                    .flatten();
+                handle
+                    .authenticate_publickey(
+                        &self.username,
+                        PrivateKeyWithHashAlg::new(key.clone(), hash_alg),
+                    )
+                    .await
+            }
+        }
+        .map_err(ssh_error)?;
+        if !result.success() {
+            tracing::error!(
+                "SFTP backend '{}' failed to authenticate as {}",
+                self.name,
+                self.username
+            );
+            return Err(S3Error::AccessDenied);
+        }
+
+        let channel = handle.channel_open_session().await.map_err(ssh_error)?;
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .map_err(ssh_error)?;
+        let sftp = SftpSession::new(channel.into_stream())
+            .await
+            .map_err(sftp_error)?;
+        Ok(Connection { handle, sftp })
+    }
+
+    /// Create the directories holding a key that don't exist yet
+    async fn create_parents(&self, sftp: &SftpSession, key: &str) -> Result<(), S3Error> {
+        let Some((parents, _)) = key.rsplit_once('/') else {
+            return Ok(());
+        };
+        let mut dir = self.root.clone();
+        for segment in parents.split('/') {
+            dir = format!("{}/{}", dir, segment);
+            match sftp.metadata(dir.as_str()).await {
+                Ok(attrs) if attrs.file_type().is_dir() => continue,
+                Ok(_) => return Err(conflict_error()),
+                Err(e) if is_missing(&e) => {}
+                Err(e) => return Err(sftp_error(e)),
+            }
+            if let Err(e) = sftp.create_dir(dir.as_str()).await {
+                // Another upload may have created it in the meantime
+                match sftp.metadata(dir.as_str()).await {
+                    Ok(attrs) if attrs.file_type().is_dir() => {}
+                    _ => return Err(sftp_error(e)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move an uploaded file into place, replacing the existing object
+    ///
+    /// SFTP servers refuse to rename over an existing file, so the object is removed first when
+    /// that happens.
+    async fn replace(&self, sftp: &SftpSession, temp: &str, path: &str) -> Result<(), S3Error> {
+        if sftp.rename(temp, path).await.is_ok() {
+            return Ok(());
+        }
+        match sftp.metadata(path).await {
+            Ok(attrs) if attrs.file_type().is_dir() => return Err(conflict_error()),
+            Ok(_) => match sftp.remove_file(path).await {
+                Ok(()) => {}
+                Err(e) if is_missing(&e) => {}
+                Err(e) => return Err(sftp_error(e)),
+            },
+            Err(e) if is_missing(&e) => {}
+            Err(e) => return Err(sftp_error(e)),
+        }
+        sftp.rename(temp, path).await.map_err(sftp_error)
+    }
+
+    /// Collect the objects whose keys start with `prefix` under a directory, in key order
+    ///
+    /// Only the directories that can hold such keys are visited.
+    fn walk<'a>(
+        sftp: &'a SftpSession,
+        dir: String,
+        dir_key: String,
+        prefix: &'a str,
+        max_keys: usize,
+        objects: &'a mut Vec<ObjectMetadata>,
+    ) -> BoxFuture<'a, Result<(), S3Error>> {
+        Box::pin(async move {
+            let entries = match sftp.read_dir(dir.as_str()).await {
+                Ok(entries) => entries,
+                Err(e) if is_missing(&e) => return Ok(()),
+                Err(e) => return Err(sftp_error(e)),
+            };
+
+            // Directories sort as if followed by '/', so that keys come out in S3 order
+            let mut names = Vec::new();
+            for entry in entries {
+                let name = entry.file_name();
+                let attrs = entry.metadata();
+                let file_type = attrs.file_type();
+                if file_type.is_dir() {
+                    names.push((format!("{}/", name), attrs));
+                } else if file_type.is_file() && !name.ends_with(TEMP_SUFFIX) {
+                    names.push((name, attrs));
+                }
+            }
+            names.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (name, attrs) in names {
+                if objects.len() >= max_keys {
+                    break;
+                }
+                let key = format!("{}{}", dir_key, name);
+                if let Some(dir_name) = name.strip_suffix('/') {
+                    if key.starts_with(prefix) || prefix.starts_with(&key) {
+                        let path = format!("{}/{}", dir, dir_name);
+                        Self::walk(sftp, path, key, prefix, max_keys, objects).await?;
+                    }
+                } else if key.starts_with(prefix) {
+                    objects.push(object_metadata(&key, &attrs));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Remove the directories left empty by a delete, up to the root
+    async fn remove_empty_parents(&self, sftp: &SftpSession, key: &str) {
+        let mut parents = key;
+        while let Some((parent, _)) = parents.rsplit_once('/') {
+            if sftp
+                .remove_dir(format!("{}/{}", self.root, parent))
+                .await
+                .is_err()
+            {
+                break;
+            }
+            parents = parent;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for SftpBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        let connection = self.session().await?;
+        match connection.sftp.metadata(self.root_dir()).await {
+            Ok(attrs) if attrs.file_type().is_dir() => Ok(()),
+            Ok(_) => Err(S3Error::NoSuchBucket),
+            Err(e) if is_missing(&e) => Err(S3Error::NoSuchBucket),
+            Err(e) => Err(sftp_error(e)),
+        }
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let prefix = prefix.unwrap_or_default();
+        let max_keys = max_keys.max(0) as usize;
+
+        // Start in the deepest directory the prefix names completely
+        let (dir_key, start) = match prefix.rsplit_once('/') {
+            Some((dir, _)) => {
+                if !dir.split('/').all(is_valid_segment) {
+                    return Ok(Vec::new());
+                }
+                (format!("{}/", dir), format!("{}/{}", self.root, dir))
+            }
+            None => (String::new(), self.root_dir().to_string()),
+        };
+
+        let connection = self.session().await?;
+        let mut objects = Vec::new();
+        Self::walk(
+            &connection.sftp,
+            start,
+            dir_key,
+            prefix,
+            max_keys,
+            &mut objects,
+        )
+        .await?;
+        Ok(objects)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Versioning can't be enabled, so it was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let path = self.existing_path(key)?;
+        let connection = self.session().await?;
+        let attrs = match connection.sftp.metadata(path).await {
+            Ok(attrs) if attrs.file_type().is_file() => attrs,
+            Ok(_) => return Err(S3Error::NoSuchKey),
+            Err(e) if is_missing(&e) => return Err(S3Error::NoSuchKey),
+            Err(e) => return Err(sftp_error(e)),
+        };
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        Ok(object_metadata(key, &attrs))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let path = self.existing_path(key)?;
+        let connection = self.session().await?;
+
+        // Metadata is taken from the open file, so it matches the data even during a PUT
+        let mut file = match connection.sftp.open(path).await {
+            Ok(file) => file,
+            Err(e) if is_missing(&e) => return Err(S3Error::NoSuchKey),
+            Err(e) => return Err(sftp_error(e)),
+        };
+        let attrs = file.metadata().await.map_err(sftp_error)?;
+        if !attrs.file_type().is_file() {
+            return Err(S3Error::NoSuchKey);
+        }
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        let metadata = object_metadata(key, &attrs);
+
+        let (start, length) = match &options.range {
+            Some(range) => {
+                let (start, end) = range.resolve(metadata.size)?;
+                file.seek(std::io::SeekFrom::Start(start))
+                    .await
+                    .map_err(io_error)?;
+                (start, end + 1 - start)
+            }
+            None => (0, metadata.size),
+        };
+        tracing::trace!("Reading {} bytes of {} from offset {}", length, key, start);
+
+        let stream = stream::unfold((file, length), |(mut file, remaining)| async move {
+            if remaining == 0 {
+                return None;
+            }
+            let mut buffer = vec![0u8; READ_CHUNK_SIZE.min(remaining as usize)];
+            match file.read(&mut buffer).await {
+                Ok(0) => Some((
+                    Err(S3Error::InternalError(
+                        "Object was truncated while reading".to_string(),
+                    )),
+                    (file, 0),
+                )),
+                Ok(n) => {
+                    buffer.truncate(n);
+                    Some((Ok(Bytes::from(buffer)), (file, remaining - n as u64)))
+                }
+                Err(e) => Some((Err(io_error(e)), (file, 0))),
+            }
+        });
+
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        options.reject_sse_customer("SFTP")?;
+        let path = self.remote_path(key).ok_or_else(|| {
+            S3Error::InvalidArgument(format!("Key '{}' can't be stored on the SFTP backend", key))
+        })?;
+        let connection = self.session().await?;
+        let sftp = &connection.sftp;
+        self.create_parents(sftp, key).await?;
+
+        // The temporary file is hidden, so that pickups skip it
+        let (dir, _) = path.rsplit_once('/').unwrap_or_default();
+        let temp = format!("{}/.{}{}", dir, uuid::Uuid::new_v4().simple(), TEMP_SUFFIX);
+        let mut file = sftp.create(temp.as_str()).await.map_err(sftp_error)?;
+        let written = async {
+            while let Some(chunk) = body.next().await {
+                file.write_all(&chunk?).await.map_err(io_error)?;
+            }
+            file.shutdown().await.map_err(io_error)?;
+            self.replace(sftp, &temp, &path).await
+        }
+        .await;
+        if let Err(e) = written {
+            if let Err(remove_error) = sftp.remove_file(temp.as_str()).await
+                && !is_missing(&remove_error)
+            {
+                tracing::warn!("Failed to remove {}: {}", temp, remove_error);
+            }
+            return Err(e);
+        }
+
+        let attrs = sftp.metadata(path).await.map_err(sftp_error)?;
+        Ok(PutObjectOutput {
+            etag: object_metadata(key, &attrs).etag,
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+        let Some(path) = self.remote_path(key) else {
+            return Ok(output);
+        };
+
+        let connection = self.session().await?;
+        match connection.sftp.remove_file(path).await {
+            Ok(()) => {}
+            Err(e) if is_missing(&e) => {}
+            Err(e) => return Err(sftp_error(e)),
+        }
+        self.remove_empty_parents(&connection.sftp, key).await;
+        Ok(output)
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        self.head_object(
+            key,
+            &GetObjectOptions {
+                version_id: version_id.map(|v| v.to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(Vec::new())
+    }
+
+    async fn put_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+        _tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        Err(S3Error::NotImplemented(format!(
+            "Object tags are not supported by the SFTP backend '{}'",
+            self.name
+        )))
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        // Objects never have tags, so there is nothing to remove
+        self.get_object_tagging(key, version_id).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(root: &str) -> SftpBackend {
+        SftpBackend::new(
+            "partner".to_string(),
+            "sftp.example.com".to_string(),
+            22,
+            "replicat4".to_string(),
+            SftpAuth::Password("secret".to_string()),
+            None,
+            root.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_remote_paths() {
+        let storage = backend("/upload/replicat4/");
+        assert_eq!(
+            storage.remote_path("docs/readme.txt").as_deref(),
+            Some("/upload/replicat4/docs/readme.txt")
+        );
+        for key in [
+            "../escape",
+            "a/../../escape",
+            "/absolute",
+            "a//b",
+            "folder/",
+            "a\\b",
+            "x.replicat4-tmp",
+        ] {
+            assert_eq!(storage.remote_path(key), None, "{} was accepted", key);
+        }
+
+        // The root can be the server's root directory or the login directory
+        assert_eq!(backend("/").remote_path("a").as_deref(), Some("/a"));
+        assert_eq!(backend("/").root_dir(), "/");
+        assert_eq!(backend("").remote_path("a").as_deref(), Some("./a"));
+        assert_eq!(backend("incoming").root_dir(), "incoming");
+    }
+
+    #[test]
+    fn test_object_metadata_from_attributes() {
+        let attrs = FileAttributes {
+            size: Some(1024),
+            mtime: Some(1_700_000_000),
+            ..Default::default()
+        };
+        let metadata = object_metadata("docs/readme.txt", &attrs);
+        assert_eq!(metadata.key, "docs/readme.txt");
+        assert_eq!(metadata.size, 1024);
+        assert_eq!(metadata.etag, "\"6553f100-400\"");
+        assert_eq!(metadata.last_modified.timestamp(), 1_700_000_000);
+        assert_eq!(metadata.content_type, "binary/octet-stream");
+
+        // The ETag changes when the file is rewritten
+        let rewritten = FileAttributes {
+            mtime: Some(1_700_000_001),
+            ..attrs
+        };
+        assert_ne!(
+            object_metadata("docs/readme.txt", &rewritten).etag,
+            metadata.etag
+        );
+    }
+}
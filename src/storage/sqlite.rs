@@ -0,0 +1,847 @@
+//! Storage backend keeping objects in a single SQLite database file
+//!
+//! Suited to large numbers of small objects. An object's metadata is a row of the `objects`
+//! table, keyed by the object key so that prefix listings are range scans of its primary key,
+//! and its data is split into rows of the `chunks` table.
+//!
+//! The chunks of an upload are written under a new object id and only become visible when the
+//! object's row is replaced, in the same transaction that removes the previous chunks. The
+//! database runs in WAL mode, so reads proceed while an object is being written.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, StreamExt};
+use md5::{Digest, Md5};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+/// Size of the chunks object data is stored in
+const CHUNK_SIZE: usize = 256 * 1024;
+/// Idle read connections kept open for later reads
+const MAX_IDLE_READERS: usize = 8;
+/// How long a connection waits for a lock held by another one
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS objects (
+    key TEXT PRIMARY KEY NOT NULL,
+    object_id INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    chunk_size INTEGER NOT NULL,
+    etag TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    last_modified INTEGER NOT NULL,
+    user_metadata TEXT NOT NULL,
+    tags TEXT NOT NULL
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS chunks (
+    object_id INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (object_id, seq)
+) WITHOUT ROWID;
+";
+
+const OBJECT_COLUMNS: &str = "key, object_id, size, chunk_size, etag, content_type, \
+    last_modified, user_metadata, tags";
+
+/// Storage backend for a SQLite database file
+#[derive(Clone)]
+pub struct SqliteBackend {
+    db: Arc<Database>,
+}
+
+struct Database {
+    path: PathBuf,
+    /// SQLite allows one writer at a time, so writes share a connection
+    writer: std::sync::Mutex<Connection>,
+    readers: std::sync::Mutex<Vec<Connection>>,
+    /// Id for the chunks of the next upload
+    next_object_id: AtomicI64,
+}
+
+/// Row of the `objects` table
+struct ObjectRow {
+    key: String,
+    object_id: i64,
+    size: i64,
+    chunk_size: i64,
+    etag: String,
+    content_type: String,
+    /// Milliseconds since the Unix epoch
+    last_modified: i64,
+    user_metadata: HashMap<String, String>,
+    tags: Vec<Tag>,
+}
+
+impl ObjectRow {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            key: row.get(0)?,
+            object_id: row.get(1)?,
+            size: row.get(2)?,
+            chunk_size: row.get(3)?,
+            etag: row.get(4)?,
+            content_type: row.get(5)?,
+            last_modified: row.get(6)?,
+            user_metadata: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
+            tags: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+        })
+    }
+
+    fn metadata(&self) -> ObjectMetadata {
+        ObjectMetadata {
+            key: self.key.clone(),
+            size: self.size as u64,
+            etag: self.etag.clone(),
+            last_modified: chrono::DateTime::from_timestamp_millis(self.last_modified)
+                .unwrap_or_else(chrono::Utc::now),
+            content_type: self.content_type.clone(),
+            version_id: None,
+            user_metadata: self.user_metadata.clone(),
+        }
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> S3Error {
+    S3Error::InternalError(format!("SQLite error: {}", e))
+}
+
+fn json_error(e: serde_json::Error) -> S3Error {
+    S3Error::InternalError(format!("Failed to encode metadata: {}", e))
+}
+
+/// Smallest string greater than every string starting with `prefix`
+///
+/// UTF-8 orders strings by code point, so the bound is the prefix with its last character
+/// incremented. Returns None if no string is greater, i.e. the prefix is all `char::MAX`.
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        let next = match last {
+            // Skip the surrogate range, which has no chars
+            '\u{d7ff}' => Some('\u{e000}'),
+            _ => char::from_u32(last as u32 + 1),
+        };
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
+impl Database {
+    fn connect(path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Ok(conn)
+    }
+
+    /// Run a query on an idle read connection, opening one if there is none
+    fn read<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, S3Error> {
+        let idle = self.readers.lock().unwrap().pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => Self::connect(&self.path).map_err(db_error)?,
+        };
+        let result = f(&conn).map_err(db_error);
+        let mut readers = self.readers.lock().unwrap();
+        if readers.len() < MAX_IDLE_READERS {
+            readers.push(conn);
+        }
+        result
+    }
+
+    /// Run statements in a write transaction
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&rusqlite::Transaction<'_>) -> Result<T, S3Error>,
+    ) -> Result<T, S3Error> {
+        let mut conn = self.writer.lock().unwrap();
+        let tx = conn.transaction().map_err(db_error)?;
+        let result = f(&tx)?;
+        tx.commit().map_err(db_error)?;
+        Ok(result)
+    }
+
+    fn get_object(&self, key: &str) -> Result<ObjectRow, S3Error> {
+        self.read(|conn| {
+            conn.prepare_cached(&format!(
+                "SELECT {} FROM objects WHERE key = ?1",
+                OBJECT_COLUMNS
+            ))?
+            .query_row([key], ObjectRow::from_row)
+            .optional()
+        })?
+        .ok_or(S3Error::NoSuchKey)
+    }
+
+    fn read_chunk(&self, object_id: i64, seq: i64) -> Result<Option<Vec<u8>>, S3Error> {
+        self.read(|conn| {
+            conn.prepare_cached("SELECT data FROM chunks WHERE object_id = ?1 AND seq = ?2")?
+                .query_row(params![object_id, seq], |row| row.get(0))
+                .optional()
+        })
+    }
+
+    fn insert_chunk(
+        tx: &rusqlite::Transaction<'_>,
+        object_id: i64,
+        seq: i64,
+        data: &[u8],
+    ) -> Result<(), S3Error> {
+        tx.prepare_cached("INSERT INTO chunks (object_id, seq, data) VALUES (?1, ?2, ?3)")
+            .and_then(|mut statement| statement.execute(params![object_id, seq, data]))
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn delete_chunks(tx: &rusqlite::Transaction<'_>, object_id: i64) -> Result<(), S3Error> {
+        tx.prepare_cached("DELETE FROM chunks WHERE object_id = ?1")
+            .and_then(|mut statement| statement.execute([object_id]))
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// The object id of a key, if it exists
+    fn object_id(tx: &rusqlite::Transaction<'_>, key: &str) -> Result<Option<i64>, S3Error> {
+        tx.prepare_cached("SELECT object_id FROM objects WHERE key = ?1")
+            .and_then(|mut statement| statement.query_row([key], |row| row.get(0)).optional())
+            .map_err(db_error)
+    }
+
+    /// Make an upload visible: store its last chunk and row, and drop the chunks it replaces
+    fn commit_object(
+        &self,
+        row: &ObjectRow,
+        last_chunk: Option<(i64, Bytes)>,
+    ) -> Result<(), S3Error> {
+        let user_metadata = serde_json::to_string(&row.user_metadata).map_err(json_error)?;
+        let tags = serde_json::to_string(&row.tags).map_err(json_error)?;
+        self.write(|tx| {
+            if let Some((seq, data)) = &last_chunk {
+                Self::insert_chunk(tx, row.object_id, *seq, data)?;
+            }
+            let previous = Self::object_id(tx, &row.key)?;
+            tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO objects ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                OBJECT_COLUMNS
+            ))
+            .and_then(|mut statement| {
+                statement.execute(params![
+                    row.key,
+                    row.object_id,
+                    row.size,
+                    row.chunk_size,
+                    row.etag,
+                    row.content_type,
+                    row.last_modified,
+                    user_metadata,
+                    tags,
+                ])
+            })
+            .map_err(db_error)?;
+            if let Some(previous) = previous {
+                Self::delete_chunks(tx, previous)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl SqliteBackend {
+    /// Open the database at `path`, creating it if it doesn't exist
+    ///
+    /// Chunks left behind by uploads that were interrupted are removed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, S3Error> {
+        let path = path.into();
+        let writer = Database::connect(&path).map_err(db_error)?;
+        writer
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        writer.execute_batch(SCHEMA).map_err(db_error)?;
+
+        let orphans = writer
+            .execute(
+                "DELETE FROM chunks WHERE object_id NOT IN (SELECT object_id FROM objects)",
+                [],
+            )
+            .map_err(db_error)?;
+        if orphans > 0 {
+            tracing::info!(
+                "Removed {} chunks of interrupted uploads from {}",
+                orphans,
+                path.display()
+            );
+        }
+        let max_object_id: Option<i64> = writer
+            .query_row("SELECT MAX(object_id) FROM objects", [], |row| row.get(0))
+            .map_err(db_error)?;
+
+        Ok(Self {
+            db: Arc::new(Database {
+                path,
+                writer: std::sync::Mutex::new(writer),
+                readers: std::sync::Mutex::new(Vec::new()),
+                next_object_id: AtomicI64::new(max_object_id.unwrap_or(0) + 1),
+            }),
+        })
+    }
+
+    /// Run database work on the blocking thread pool
+    async fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Database) -> Result<T, S3Error> + Send + 'static,
+    ) -> Result<T, S3Error> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(|e| S3Error::InternalError(format!("SQLite task failed: {}", e)))?
+    }
+
+    /// Write the chunks of an upload, returning the row for it and its last chunk
+    ///
+    /// The last chunk is kept back to be written with the row, so that objects of a single
+    /// chunk take one transaction.
+    async fn write_chunks(
+        &self,
+        object_id: i64,
+        mut body: ObjectStream,
+    ) -> Result<(i64, String, Option<(i64, Bytes)>), S3Error> {
+        let mut md5 = Md5::new();
+        let mut size = 0i64;
+        let mut seq = 0i64;
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            md5.update(&chunk);
+            size += chunk.len() as i64;
+            buffer.extend_from_slice(&chunk);
+            // A full chunk is only written once more data follows it
+            while buffer.len() > CHUNK_SIZE {
+                let data = buffer.split_to(CHUNK_SIZE).freeze();
+                self.blocking(move |db| {
+                    db.write(|tx| Database::insert_chunk(tx, object_id, seq, &data))
+                })
+                .await?;
+                seq += 1;
+            }
+        }
+
+        let etag = format!("\"{}\"", hex::encode(md5.finalize()));
+        let last_chunk = (!buffer.is_empty()).then(|| (seq, buffer.freeze()));
+        Ok((size, etag, last_chunk))
+    }
+
+    /// List objects in key order, only those after `start_after` if given
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let prefix = prefix.unwrap_or_default().to_string();
+        let start_after = start_after.map(str::to_string);
+        let max_keys = max_keys.max(0) as i64;
+        self.blocking(move |db| {
+            db.read(|conn| {
+                // Both bounds make this a range scan of the primary key
+                let upper = prefix_upper_bound(&prefix);
+                let sql = format!(
+                    "SELECT {} FROM objects WHERE key >= ?1 AND (?2 IS NULL OR key < ?2) \
+                     AND (?3 IS NULL OR key > ?3) ORDER BY key LIMIT ?4",
+                    OBJECT_COLUMNS
+                );
+                let mut statement = conn.prepare_cached(&sql)?;
+                let rows = statement
+                    .query_map(params![prefix, upper, start_after, max_keys], |row| {
+                        ObjectRow::from_row(row).map(|row| row.metadata())
+                    })?;
+                rows.collect()
+            })
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for SqliteBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        self.blocking(|db| db.read(|conn| conn.query_row("SELECT 1", [], |_| Ok(()))))
+            .await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        self.list(prefix, None, max_keys).await
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: &str,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        self.list(prefix, Some(start_after), max_keys).await
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Versioning can't be enabled, so it was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let key = key.to_string();
+        let row = self.blocking(move |db| db.get_object(&key)).await?;
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        Ok(row.metadata())
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        let owned_key = key.to_string();
+        let row = self.blocking(move |db| db.get_object(&owned_key)).await?;
+        options.check_single_part()?;
+        options.reject_sse_customer()?;
+        let metadata = row.metadata();
+
+        let (start, end) = match &options.range {
+            Some(range) => {
+                let (start, end) = range.resolve(metadata.size)?;
+                (start, end + 1)
+            }
+            None => (0, metadata.size),
+        };
+        tracing::trace!("Reading bytes {}..{} of {}", start, end, key);
+
+        // Chunks are read one at a time; they only go away if the object is replaced meanwhile
+        let backend = self.clone();
+        let object_id = row.object_id;
+        let chunk_size = row.chunk_size.max(1) as u64;
+        let stream = stream::unfold(start, move |position| {
+            let backend = backend.clone();
+            async move {
+                if position >= end {
+                    return None;
+                }
+                let seq = position / chunk_size;
+                let chunk = backend
+                    .blocking(move |db| db.read_chunk(object_id, seq as i64))
+                    .await;
+                let data = match chunk {
+                    Ok(Some(data)) => data,
+                    Ok(None) => {
+                        return Some((
+                            Err(S3Error::InternalError(
+                                "Object was replaced while reading".to_string(),
+                            )),
+                            end,
+                        ));
+                    }
+                    Err(e) => return Some((Err(e), end)),
+                };
+
+                let chunk_start = seq * chunk_size;
+                let from = (position - chunk_start) as usize;
+                let to = ((end - chunk_start) as usize).min(data.len());
+                if from >= to {
+                    return Some((
+                        Err(S3Error::InternalError(
+                            "Object was truncated while reading".to_string(),
+                        )),
+                        end,
+                    ));
+                }
+                let data = Bytes::from(data).slice(from..to);
+                Some((Ok(data), chunk_start + to as u64))
+            }
+        });
+
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        // Chunks are stored as plain BLOBs, which the customer's key couldn't protect
+        options.reject_sse_customer("SQLite")?;
+        let object_id = self.db.next_object_id.fetch_add(1, Ordering::Relaxed);
+        let (size, etag, last_chunk) = match self.write_chunks(object_id, body).await {
+            Ok(written) => written,
+            Err(e) => {
+                // Remove the chunks written so far, they are unreachable
+                let cleanup = self
+                    .blocking(move |db| db.write(|tx| Database::delete_chunks(tx, object_id)))
+                    .await;
+                if let Err(cleanup_error) = cleanup {
+                    tracing::warn!(
+                        "Failed to remove the chunks of an interrupted upload: {}",
+                        cleanup_error
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        let row = ObjectRow {
+            key: key.to_string(),
+            object_id,
+            size,
+            chunk_size: CHUNK_SIZE as i64,
+            etag: etag.clone(),
+            content_type: options
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "binary/octet-stream".to_string()),
+            last_modified: chrono::Utc::now().timestamp_millis(),
+            user_metadata: options.user_metadata.clone(),
+            tags: options.tags.clone(),
+        };
+        let result = self
+            .blocking(move |db| {
+                let result = db.commit_object(&row, last_chunk);
+                if result.is_err() {
+                    let _ = db.write(|tx| Database::delete_chunks(tx, object_id));
+                }
+                result
+            })
+            .await;
+        result?;
+
+        Ok(PutObjectOutput {
+            etag,
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+
+        let key = key.to_string();
+        self.blocking(move |db| {
+            db.write(|tx| {
+                if let Some(object_id) = Database::object_id(tx, &key)? {
+                    tx.execute("DELETE FROM objects WHERE key = ?1", [&key])
+                        .map_err(db_error)?;
+                    Database::delete_chunks(tx, object_id)?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+        Ok(output)
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        check_null_version(version_id)?;
+        let key = key.to_string();
+        let row = self.blocking(move |db| db.get_object(&key)).await?;
+        Ok(row.tags)
+    }
+
+    async fn put_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+        tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        check_null_version(version_id)?;
+        let key = key.to_string();
+        let tags = serde_json::to_string(&tags).map_err(json_error)?;
+        let updated = self
+            .blocking(move |db| {
+                db.write(|tx| {
+                    tx.execute("UPDATE objects SET tags = ?1 WHERE key = ?2", [&tags, &key])
+                        .map_err(db_error)
+                })
+            })
+            .await?;
+        if updated == 0 {
+            return Err(S3Error::NoSuchKey);
+        }
+        Ok(())
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        self.put_object_tagging(key, version_id, Vec::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ByteRange, SseCustomerKey};
+    use futures::TryStreamExt;
+
+    fn bytes_to_stream(data: Bytes) -> ObjectStream {
+        Box::pin(stream::once(async move { Ok(data) }))
+    }
+
+    async fn collect(stream: ObjectStream) -> Vec<u8> {
+        let chunks: Vec<Bytes> = stream.try_collect().await.unwrap();
+        chunks.concat()
+    }
+
+    async fn put(storage: &SqliteBackend, key: &str, data: Vec<u8>) -> PutObjectOutput {
+        storage
+            .put_object(
+                key,
+                bytes_to_stream(Bytes::from(data)),
+                &PutObjectOptions::default(),
+            )
+            .await
+            .unwrap()
+    }
+
+    fn chunk_count(storage: &SqliteBackend) -> i64 {
+        storage
+            .db
+            .read(|conn| conn.query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0)))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_put_and_get_object_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteBackend::open(dir.path().join("objects.db")).unwrap();
+
+        // Two full chunks and a partial one
+        let data: Vec<u8> = (0..2 * CHUNK_SIZE + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let output = put(&storage, "blobs/large", data.clone()).await;
+        assert_eq!(
+            output.etag,
+            format!("\"{}\"", hex::encode(Md5::digest(&data)))
+        );
+        assert_eq!(chunk_count(&storage), 3);
+
+        let (stream, metadata) = storage
+            .get_object("blobs/large", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, data.len() as u64);
+        assert_eq!(collect(stream).await, data);
+
+        // A range spanning a chunk boundary
+        let start = CHUNK_SIZE as u64 - 10;
+        let options = GetObjectOptions {
+            range: Some(ByteRange::FromTo {
+                start,
+                end: Some(start + 19),
+            }),
+            ..Default::default()
+        };
+        let (stream, _) = storage.get_object("blobs/large", &options).await.unwrap();
+        assert_eq!(
+            collect(stream).await,
+            &data[start as usize..start as usize + 20]
+        );
+
+        // Replacing the object drops the previous chunks
+        put(&storage, "blobs/large", b"small".to_vec()).await;
+        assert_eq!(chunk_count(&storage), 1);
+        let (stream, _) = storage
+            .get_object("blobs/large", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(collect(stream).await, b"small");
+
+        // Empty objects have no chunks
+        put(&storage, "blobs/empty", Vec::new()).await;
+        let (stream, metadata) = storage
+            .get_object("blobs/empty", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, 0);
+        assert!(collect(stream).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_customer_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteBackend::open(dir.path().join("objects.db")).unwrap();
+        let sse_customer = Some(SseCustomerKey {
+            algorithm: "AES256".to_string(),
+            key: "a2V5".to_string(),
+            key_md5: "bWQ1".to_string(),
+        });
+
+        let options = PutObjectOptions {
+            sse_customer: sse_customer.clone(),
+            ..Default::default()
+        };
+        let result = storage
+            .put_object("secret.txt", bytes_to_stream(Bytes::from("data")), &options)
+            .await;
+        assert!(matches!(result, Err(S3Error::InvalidRequest(_))));
+        assert_eq!(chunk_count(&storage), 0);
+
+        put(&storage, "plain.txt", b"data".to_vec()).await;
+        let options = GetObjectOptions {
+            sse_customer,
+            ..Default::default()
+        };
+        let result = storage.get_object("plain.txt", &options).await;
+        assert!(matches!(result, Err(S3Error::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_by_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteBackend::open(dir.path().join("objects.db")).unwrap();
+        for key in [
+            "a",
+            "config/app.json",
+            "config/db.json",
+            "config0",
+            "configs/x",
+        ] {
+            put(&storage, key, b"{}".to_vec()).await;
+        }
+
+        let keys = |objects: Vec<ObjectMetadata>| -> Vec<String> {
+            objects.into_iter().map(|object| object.key).collect()
+        };
+        assert_eq!(
+            keys(storage.list_objects(Some("config/"), 1000).await.unwrap()),
+            vec!["config/app.json", "config/db.json"]
+        );
+        assert_eq!(
+            keys(storage.list_objects(Some("config"), 2).await.unwrap()),
+            vec!["config/app.json", "config/db.json"]
+        );
+        assert_eq!(storage.list_objects(None, 1000).await.unwrap().len(), 5);
+        assert_eq!(
+            keys(
+                storage
+                    .list_objects_after(Some("config/"), "config/app.json", 1000)
+                    .await
+                    .unwrap()
+            ),
+            vec!["config/db.json"]
+        );
+
+        assert_eq!(prefix_upper_bound("config/").as_deref(), Some("config0"));
+        assert_eq!(
+            prefix_upper_bound("a\u{d7ff}").as_deref(),
+            Some("a\u{e000}")
+        );
+        assert_eq!(prefix_upper_bound("a\u{10ffff}").as_deref(), Some("b"));
+        assert_eq!(prefix_upper_bound(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_objects_persist_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("objects.db");
+        {
+            let storage = SqliteBackend::open(&path).unwrap();
+            let options = PutObjectOptions {
+                content_type: Some("application/json".to_string()),
+                user_metadata: HashMap::from([("owner".to_string(), "alice".to_string())]),
+                tags: vec![Tag {
+                    key: "env".to_string(),
+                    value: "prod".to_string(),
+                }],
+                ..Default::default()
+            };
+            storage
+                .put_object("config.json", bytes_to_stream(Bytes::from("{}")), &options)
+                .await
+                .unwrap();
+
+            // Chunks of an upload that never completed
+            storage
+                .db
+                .write(|tx| Database::insert_chunk(tx, 1000, 0, b"orphan"))
+                .unwrap();
+        }
+
+        let storage = SqliteBackend::open(&path).unwrap();
+        assert_eq!(chunk_count(&storage), 1);
+        let metadata = storage
+            .head_object("config.json", &GetObjectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(metadata.content_type, "application/json");
+        assert_eq!(metadata.user_metadata["owner"], "alice");
+        assert_eq!(
+            storage
+                .get_object_tagging("config.json", None)
+                .await
+                .unwrap()[0]
+                .value,
+            "prod"
+        );
+
+        storage.delete_object("config.json", None).await.unwrap();
+        assert_eq!(chunk_count(&storage), 0);
+        assert!(matches!(
+            storage
+                .head_object("config.json", &GetObjectOptions::default())
+                .await,
+            Err(S3Error::NoSuchKey)
+        ));
+        assert!(matches!(
+            storage
+                .put_object_tagging("config.json", None, Vec::new())
+                .await,
+            Err(S3Error::NoSuchKey)
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+//! Staging of upload bodies that can only be sent once they have been fully read
+//!
+//! Wrappers that store something computed from the whole body (such as its ETag) in the
+//! object's user metadata have to send that metadata before the body, so they stage the
+//! transformed body first: in memory for small objects, in a temporary file past
+//! `SPILL_THRESHOLD`.
+
+use super::backend::ObjectStream;
+use crate::types::error::S3Error;
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Staged bytes kept in memory before the upload is moved to a temporary file
+pub(super) const SPILL_THRESHOLD: usize = 8 * 1024 * 1024;
+/// Size of the chunks read back from a temporary file
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+fn io_error(e: std::io::Error) -> S3Error {
+    S3Error::InternalError(format!("Failed to stage upload: {}", e))
+}
+
+/// Temporary file deleted when dropped
+pub(super) struct TempFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+}
+
+impl TempFile {
+    async fn create() -> Result<Self, S3Error> {
+        let path = std::env::temp_dir().join(format!("replicat4-{}.upload", uuid::Uuid::new_v4()));
+        let file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+            .map_err(io_error)?;
+        Ok(Self { path, file })
+    }
+
+    /// Stream the file from the start, deleting it once the stream is dropped
+    async fn into_stream(mut self) -> Result<ObjectStream, S3Error> {
+        self.file.flush().await.map_err(io_error)?;
+        self.file
+            .seek(std::io::SeekFrom::Start(0))
+            .await
+            .map_err(io_error)?;
+        Ok(Box::pin(stream::unfold(Some(self), |temp| async move {
+            let mut temp = temp?;
+            let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+            match temp.file.read(&mut buffer).await {
+                Ok(0) => None,
+                Ok(n) => {
+                    buffer.truncate(n);
+                    Some((Ok(Bytes::from(buffer)), Some(temp)))
+                }
+                Err(e) => Some((Err(io_error(e)), None)),
+            }
+        })))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Body of an upload, waiting for the upload to be fully read
+pub(super) enum Staging {
+    Memory { chunks: Vec<Bytes>, size: usize },
+    File(TempFile),
+}
+
+impl Staging {
+    pub(super) fn new() -> Self {
+        Staging::Memory {
+            chunks: Vec::new(),
+            size: 0,
+        }
+    }
+
+    pub(super) async fn write(&mut self, data: Bytes) -> Result<(), S3Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        match self {
+            Staging::Memory { chunks, size } => {
+                *size += data.len();
+                chunks.push(data);
+                if *size > SPILL_THRESHOLD {
+                    let mut temp = TempFile::create().await?;
+                    for chunk in chunks.iter() {
+                        temp.file.write_all(chunk).await.map_err(io_error)?;
+                    }
+                    *self = Staging::File(temp);
+                }
+            }
+            Staging::File(temp) => temp.file.write_all(&data).await.map_err(io_error)?,
+        }
+        Ok(())
+    }
+
+    pub(super) async fn into_stream(self) -> Result<ObjectStream, S3Error> {
+        match self {
+            Staging::Memory { chunks, .. } => Ok(Box::pin(stream::iter(chunks).map(Ok))),
+            Staging::File(temp) => temp.into_stream().await,
+        }
+    }
+}
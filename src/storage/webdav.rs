@@ -0,0 +1,688 @@
+//! Storage backend for a WebDAV collection, such as a Nextcloud folder
+//!
+//! Each key maps to the resource at the same path under the root collection, and the
+//! collections a key needs are created with MKCOL before it is uploaded. Objects are listed by
+//! walking the collections with PROPFIND, and their metadata comes from the server's ETags and
+//! `getlastmodified`. User metadata and tags are not stored.
+
+use super::backend::{
+    DeleteObjectOutput, GetObjectOptions, ObjectStream, PutObjectOptions, PutObjectOutput,
+    StorageBackend, check_null_version, null_versions,
+};
+use crate::types::{ObjectMetadata, ObjectVersion, Tag, VersioningStatus, error::S3Error};
+use futures::future::BoxFuture;
+use futures::stream::StreamExt;
+use percent_encoding::percent_decode_str;
+use quick_xml::de::from_str as from_xml_str;
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MATCH, RANGE};
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Properties requested for every resource
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:resourcetype/>
+    <d:getetag/>
+    <d:getlastmodified/>
+    <d:getcontentlength/>
+    <d:getcontenttype/>
+  </d:prop>
+</d:propfind>"#;
+
+pub struct WebdavBackend {
+    client: reqwest::Client,
+    name: String,
+    /// URL of the root collection, ending with '/'
+    root: Url,
+    /// Username and password for basic authentication
+    credentials: Option<(String, String)>,
+}
+
+/// Body of a 207 Multi-Status response
+#[derive(Debug, Deserialize)]
+struct Multistatus {
+    #[serde(rename = "response", default)]
+    responses: Vec<DavResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DavResponse {
+    href: String,
+    #[serde(rename = "propstat", default)]
+    propstats: Vec<Propstat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Propstat {
+    status: String,
+    prop: Prop,
+}
+
+/// Properties of a resource, as sent by the server
+#[derive(Debug, Deserialize)]
+struct Prop {
+    getetag: Option<String>,
+    getlastmodified: Option<String>,
+    getcontentlength: Option<String>,
+    getcontenttype: Option<String>,
+    resourcetype: Option<ResourceType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceType {
+    collection: Option<()>,
+}
+
+impl DavResponse {
+    /// Properties the server returned with a 200 status
+    fn found(self) -> Option<Prop> {
+        self.propstats
+            .into_iter()
+            .find(|propstat| propstat.status.split_whitespace().nth(1) == Some("200"))
+            .map(|propstat| propstat.prop)
+    }
+}
+
+impl Prop {
+    fn is_collection(&self) -> bool {
+        self.resourcetype
+            .as_ref()
+            .is_some_and(|resourcetype| resourcetype.collection.is_some())
+    }
+
+    fn into_metadata(self, key: &str) -> ObjectMetadata {
+        let size = self
+            .getcontentlength
+            .and_then(|length| length.trim().parse().ok())
+            .unwrap_or(0);
+        let last_modified = self
+            .getlastmodified
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date.trim()).ok())
+            .map(|date| date.to_utc());
+        // Servers without ETags get one derived from the modification time and size
+        let etag = match self.getetag.as_deref().map(s3_etag) {
+            Some(etag) if etag != "\"\"" => etag,
+            _ => format!(
+                "\"{:x}-{:x}\"",
+                last_modified.map(|date| date.timestamp()).unwrap_or(0),
+                size
+            ),
+        };
+        ObjectMetadata {
+            key: key.to_string(),
+            size,
+            etag,
+            last_modified: last_modified.unwrap_or_else(chrono::Utc::now),
+            content_type: self
+                .getcontenttype
+                .filter(|content_type| !content_type.is_empty())
+                .unwrap_or_else(|| "binary/octet-stream".to_string()),
+            version_id: None,
+            user_metadata: HashMap::new(),
+        }
+    }
+}
+
+/// S3-style ETag of a WebDAV ETag: strong and quoted
+fn s3_etag(etag: &str) -> String {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    format!("\"{}\"", etag.trim_matches('"'))
+}
+
+/// Whether a key segment can be used as a resource name
+fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && segment != "." && segment != ".."
+}
+
+fn method(name: &str) -> Method {
+    Method::from_bytes(name.as_bytes()).expect("valid HTTP method")
+}
+
+fn request_error(e: reqwest::Error) -> S3Error {
+    S3Error::InternalError(format!("WebDAV request failed: {}", e))
+}
+
+fn conflict_error() -> S3Error {
+    S3Error::InvalidArgument(
+        "The key conflicts with another key stored on the WebDAV backend".to_string(),
+    )
+}
+
+/// Map an unsuccessful response to an S3 error
+async fn check_response(response: Response) -> Result<Response, S3Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status.as_u16() {
+        404 => S3Error::NoSuchKey,
+        401 | 403 => S3Error::AccessDenied,
+        416 => S3Error::InvalidRange,
+        _ => {
+            let message = response.text().await.unwrap_or_default();
+            S3Error::InternalError(format!("WebDAV server returned {}: {}", status, message))
+        }
+    })
+}
+
+impl WebdavBackend {
+    /// Create a backend for the collection at `url`
+    ///
+    /// Without credentials, requests are sent without authorization.
+    pub fn new(
+        name: String,
+        url: &str,
+        credentials: Option<(String, String)>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut root = Url::parse(url)?;
+        if root.cannot_be_a_base() {
+            return Err(format!("Invalid WebDAV URL: {}", url).into());
+        }
+        if !root.path().ends_with('/') {
+            root.set_path(&format!("{}/", root.path()));
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            name,
+            root,
+            credentials,
+        })
+    }
+
+    /// URL of a key, or of a collection when `key` ends with '/'
+    fn url(&self, key: &str) -> Url {
+        let mut url = self.root.clone();
+        // The root was checked to have path segments
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().extend(key.split('/'));
+        }
+        url
+    }
+
+    /// URL of a key, if it can be stored safely
+    fn object_url(&self, key: &str) -> Option<Url> {
+        key.split('/').all(is_valid_segment).then(|| self.url(key))
+    }
+
+    /// Key of a resource from its `href`, None if it isn't under the root collection
+    fn relative_key(&self, href: &str) -> Option<String> {
+        let url = self.root.join(href).ok()?;
+        let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+        let root = percent_decode_str(self.root.path()).decode_utf8().ok()?;
+        path.strip_prefix(root.as_ref()).map(|key| key.to_string())
+    }
+
+    /// Start a request with the configured credentials
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    /// Properties of a resource (depth 0) or of a collection and its members (depth 1)
+    ///
+    /// Returns None if the resource doesn't exist.
+    async fn propfind(&self, url: Url, depth: &str) -> Result<Option<Multistatus>, S3Error> {
+        let response = self
+            .request(method("PROPFIND"), url)
+            .header("Depth", depth)
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await
+            .map_err(request_error)?;
+        let body = match check_response(response).await {
+            Ok(response) => response.text().await.map_err(request_error)?,
+            Err(S3Error::NoSuchKey) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        from_xml_str(&body).map(Some).map_err(|e| {
+            S3Error::InternalError(format!("Failed to parse PROPFIND response: {}", e))
+        })
+    }
+
+    /// Properties of a resource, if it exists
+    async fn stat(&self, url: Url) -> Result<Option<Prop>, S3Error> {
+        Ok(self
+            .propfind(url, "0")
+            .await?
+            .and_then(|multistatus| multistatus.responses.into_iter().next())
+            .and_then(DavResponse::found))
+    }
+
+    /// Properties of an object, which must exist and not be a collection
+    async fn stat_object(&self, key: &str) -> Result<Prop, S3Error> {
+        let url = self.object_url(key).ok_or(S3Error::NoSuchKey)?;
+        match self.stat(url).await? {
+            Some(prop) if !prop.is_collection() => Ok(prop),
+            _ => Err(S3Error::NoSuchKey),
+        }
+    }
+
+    /// Create the collections holding a key that don't exist yet
+    async fn create_parents(&self, key: &str) -> Result<(), S3Error> {
+        let Some((parents, _)) = key.rsplit_once('/') else {
+            return Ok(());
+        };
+        // Most uploads go to collections that already exist
+        match self.stat(self.url(&format!("{}/", parents))).await? {
+            Some(prop) if prop.is_collection() => return Ok(()),
+            Some(_) => return Err(conflict_error()),
+            None => {}
+        }
+
+        let mut dir = String::new();
+        for segment in parents.split('/') {
+            dir.push_str(segment);
+            dir.push('/');
+            let response = self
+                .request(method("MKCOL"), self.url(&dir))
+                .send()
+                .await
+                .map_err(request_error)?;
+            // 405 Method Not Allowed means the collection exists already
+            if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                check_response(response).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the objects whose keys start with `prefix` under a collection, in key order
+    ///
+    /// Only the collections that can hold such keys are visited.
+    fn walk<'a>(
+        &'a self,
+        dir_key: String,
+        prefix: &'a str,
+        max_keys: usize,
+        objects: &'a mut Vec<ObjectMetadata>,
+    ) -> BoxFuture<'a, Result<(), S3Error>> {
+        Box::pin(async move {
+            let Some(multistatus) = self.propfind(self.url(&dir_key), "1").await? else {
+                return Ok(());
+            };
+
+            // Collections sort as if followed by '/', so that keys come out in S3 order
+            let mut entries = Vec::new();
+            for response in multistatus.responses {
+                let Some(key) = self.relative_key(&response.href) else {
+                    continue;
+                };
+                let Some(name) = key.strip_prefix(&dir_key) else {
+                    continue;
+                };
+                // Skip the collection itself
+                let name = name.trim_end_matches('/').to_string();
+                if name.is_empty() || name.contains('/') {
+                    continue;
+                }
+                let Some(prop) = response.found() else {
+                    continue;
+                };
+                if prop.is_collection() {
+                    entries.push((format!("{}/", name), None));
+                } else {
+                    entries.push((name, Some(prop)));
+                }
+            }
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (name, prop) in entries {
+                if objects.len() >= max_keys {
+                    break;
+                }
+                let key = format!("{}{}", dir_key, name);
+                match prop {
+                    None => {
+                        if key.starts_with(prefix) || prefix.starts_with(&key) {
+                            self.walk(key, prefix, max_keys, objects).await?;
+                        }
+                    }
+                    Some(prop) => {
+                        if key.starts_with(prefix) {
+                            objects.push(prop.into_metadata(&key));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for WebdavBackend {
+    async fn head_bucket(&self) -> Result<(), S3Error> {
+        match self.stat(self.root.clone()).await? {
+            Some(prop) if prop.is_collection() => Ok(()),
+            _ => Err(S3Error::NoSuchBucket),
+        }
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectMetadata>, S3Error> {
+        let prefix = prefix.unwrap_or_default();
+        let max_keys = max_keys.max(0) as usize;
+
+        // Start in the deepest collection the prefix names completely
+        let dir_key = match prefix.rsplit_once('/') {
+            Some((dir, _)) => {
+                if !dir.split('/').all(is_valid_segment) {
+                    return Ok(Vec::new());
+                }
+                format!("{}/", dir)
+            }
+            None => String::new(),
+        };
+
+        let mut objects = Vec::new();
+        self.walk(dir_key, prefix, max_keys, &mut objects).await?;
+        Ok(objects)
+    }
+
+    async fn get_bucket_versioning(&self) -> Result<Option<VersioningStatus>, S3Error> {
+        // Versioning can't be enabled, so it was never configured
+        Ok(None)
+    }
+
+    async fn list_object_versions(
+        &self,
+        prefix: Option<&str>,
+        max_keys: i32,
+    ) -> Result<Vec<ObjectVersion>, S3Error> {
+        let objects = self.list_objects(prefix, max_keys).await?;
+        Ok(null_versions(objects))
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<ObjectMetadata, S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        let prop = self.stat_object(key).await?;
+        options.check_single_part()?;
+        Ok(prop.into_metadata(key))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<(ObjectStream, ObjectMetadata), S3Error> {
+        check_null_version(options.version_id.as_deref())?;
+        options.reject_sse_customer()?;
+        let prop = self.stat_object(key).await?;
+        options.check_single_part()?;
+
+        // The data is read from the version the metadata describes
+        let mut request = self.request(Method::GET, self.url(key));
+        if let Some(etag) = &prop.getetag {
+            request = request.header(IF_MATCH, etag.trim());
+        }
+        let metadata = prop.into_metadata(key);
+        if let Some(range) = &options.range {
+            let (start, end) = range.resolve(metadata.size)?;
+            request = request.header(RANGE, format!("bytes={}-{}", start, end));
+        }
+
+        let response = request.send().await.map_err(request_error)?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Err(S3Error::InternalError(format!(
+                "{} changed while it was being read",
+                key
+            )));
+        }
+        let response = check_response(response).await?;
+        if options.range.is_some() && response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(S3Error::InternalError(format!(
+                "The WebDAV backend '{}' doesn't support ranged reads",
+                self.name
+            )));
+        }
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(request_error));
+        Ok((Box::pin(stream), metadata))
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        body: ObjectStream,
+        options: &PutObjectOptions,
+    ) -> Result<PutObjectOutput, S3Error> {
+        options.reject_sse_customer("WebDAV")?;
+        let url = self.object_url(key).ok_or_else(|| {
+            S3Error::InvalidArgument(format!(
+                "Key '{}' can't be stored on the WebDAV backend",
+                key
+            ))
+        })?;
+        self.create_parents(key).await?;
+
+        let mut request = self
+            .request(Method::PUT, url)
+            .body(reqwest::Body::wrap_stream(body));
+        if let Some(content_type) = &options.content_type {
+            request = request.header(CONTENT_TYPE, content_type);
+        }
+        let response = request.send().await.map_err(request_error)?;
+        // A collection at the key, or a resource in place of one of its parents
+        if matches!(
+            response.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::CONFLICT
+        ) {
+            return Err(conflict_error());
+        }
+        let response = check_response(response).await?;
+
+        let etag = match response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+        {
+            Some(etag) if !etag.starts_with("W/") => s3_etag(etag),
+            _ => self.stat_object(key).await?.into_metadata(key).etag,
+        };
+        Ok(PutObjectOutput {
+            etag,
+            version_id: None,
+        })
+    }
+
+    async fn delete_object(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<DeleteObjectOutput, S3Error> {
+        let output = DeleteObjectOutput {
+            version_id: version_id.map(|v| v.to_string()),
+            delete_marker: false,
+        };
+        // Only the "null" version exists, deleting any other one is a no-op like on S3
+        if check_null_version(version_id).is_err() {
+            return Ok(output);
+        }
+
+        // DELETE removes collections with all their members, so only objects are deleted
+        let prop = match self.stat_object(key).await {
+            Ok(prop) => prop,
+            Err(S3Error::NoSuchKey) => return Ok(output),
+            Err(e) => return Err(e),
+        };
+        let mut request = self.request(Method::DELETE, self.url(key));
+        if let Some(etag) = &prop.getetag {
+            request = request.header(IF_MATCH, etag.trim());
+        }
+        let response = request.send().await.map_err(request_error)?;
+        // The object was replaced since it was checked, by a write that wins over the delete
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(output);
+        }
+        match check_response(response).await {
+            Ok(_) | Err(S3Error::NoSuchKey) => Ok(output),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Vec<Tag>, S3Error> {
+        check_null_version(version_id)?;
+        self.stat_object(key).await?;
+        Ok(Vec::new())
+    }
+
+    async fn put_object_tagging(
+        &self,
+        _key: &str,
+        _version_id: Option<&str>,
+        _tags: Vec<Tag>,
+    ) -> Result<(), S3Error> {
+        Err(S3Error::NotImplemented(format!(
+            "Object tags are not supported by the WebDAV backend '{}'",
+            self.name
+        )))
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<(), S3Error> {
+        // Objects never have tags, so there is nothing to remove
+        self.get_object_tagging(key, version_id).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend() -> WebdavBackend {
+        WebdavBackend::new(
+            "nextcloud".to_string(),
+            "https://cloud.example.com/remote.php/dav/files/alice/Replicat4 Backup",
+            Some(("alice".to_string(), "app-password".to_string())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resource_urls() {
+        let storage = backend();
+        assert_eq!(
+            storage.object_url("photos/jan 1.jpg").unwrap().as_str(),
+            "https://cloud.example.com/remote.php/dav/files/alice/Replicat4%20Backup/photos/jan%201.jpg"
+        );
+        assert_eq!(
+            storage.url("photos/").as_str(),
+            "https://cloud.example.com/remote.php/dav/files/alice/Replicat4%20Backup/photos/"
+        );
+        assert_eq!(storage.url("").as_str(), storage.root.as_str());
+        for key in ["../escape", "a//b", "folder/", "./a"] {
+            assert!(storage.object_url(key).is_none(), "{} was accepted", key);
+        }
+    }
+
+    #[test]
+    fn test_relative_keys() {
+        let storage = backend();
+        assert_eq!(
+            storage
+                .relative_key("/remote.php/dav/files/alice/Replicat4%20Backup/photos/jan%201.jpg")
+                .as_deref(),
+            Some("photos/jan 1.jpg")
+        );
+        assert_eq!(
+            storage
+                .relative_key(
+                    "https://cloud.example.com/remote.php/dav/files/alice/Replicat4%20Backup/a/"
+                )
+                .as_deref(),
+            Some("a/")
+        );
+        assert_eq!(
+            storage
+                .relative_key("/remote.php/dav/files/alice/Replicat4%20Backup/")
+                .as_deref(),
+            Some("")
+        );
+        assert_eq!(
+            storage.relative_key("/remote.php/dav/files/alice/Other/a"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/Replicat4%20Backup/photos/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype><d:collection/></d:resourcetype>
+        <d:getetag>&quot;66fbc1a2b3c4d&quot;</d:getetag>
+        <d:getlastmodified>Tue, 01 Oct 2024 10:00:00 GMT</d:getlastmodified>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop>
+        <d:getcontentlength/>
+        <d:getcontenttype/>
+      </d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/Replicat4%20Backup/photos/jan.jpg</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype/>
+        <d:getetag>"8f14e45fceea167a"</d:getetag>
+        <d:getlastmodified>Wed, 02 Oct 2024 08:30:00 GMT</d:getlastmodified>
+        <d:getcontentlength>2048</d:getcontentlength>
+        <d:getcontenttype>image/jpeg</d:getcontenttype>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+        let multistatus: Multistatus = from_xml_str(xml).unwrap();
+        let mut responses = multistatus.responses.into_iter();
+
+        let collection = responses.next().unwrap().found().unwrap();
+        assert!(collection.is_collection());
+
+        let object = responses.next().unwrap().found().unwrap();
+        assert!(!object.is_collection());
+        let metadata = object.into_metadata("photos/jan.jpg");
+        assert_eq!(metadata.size, 2048);
+        assert_eq!(metadata.etag, "\"8f14e45fceea167a\"");
+        assert_eq!(metadata.content_type, "image/jpeg");
+        assert_eq!(
+            metadata.last_modified.to_rfc3339(),
+            "2024-10-02T08:30:00+00:00"
+        );
+
+        assert_eq!(s3_etag("W/\"abc\""), "\"abc\"");
+        assert_eq!(s3_etag("abc"), "\"abc\"");
+    }
+}
@@ -10,9 +10,42 @@ use serde::Serialize;
 pub enum S3Error {
     NoSuchKey,
     NoSuchBucket,
+    NoSuchVersion,
     InvalidRequest(String),
+    /// A request argument is invalid, e.g. a retention date in the past
+    InvalidArgument(String),
+    /// The object has no retention or legal hold configured
+    NoSuchObjectLockConfiguration,
+    /// The bucket has no lifecycle configuration
+    NoSuchLifecycleConfiguration,
+    /// The bucket has no CORS configuration
+    NoSuchCORSConfiguration,
+    /// A CORS preflight request is not allowed by the bucket's CORS configuration
+    AccessForbidden(String),
+    /// The XML in the request body is not well-formed or doesn't match the expected schema
+    MalformedXML,
+    /// The tag set of a request is invalid (too many tags, duplicate keys, ...)
+    InvalidTag(String),
+    /// The requested part number does not exist in the object
+    InvalidPartNumber,
+    /// The requested byte range lies outside the object
+    InvalidRange,
+    /// A POST upload is larger than the policy's content-length-range allows
+    EntityTooLarge,
+    /// A POST upload is smaller than the policy's content-length-range allows
+    EntityTooSmall,
     AccessDenied,
     SignatureDoesNotMatch,
+    /// The credential scope is malformed or does not match this endpoint;
+    /// `region` carries the expected region when the region was wrong
+    AuthorizationHeaderMalformed {
+        message: String,
+        region: Option<String>,
+    },
+    /// The operation is not allowed on this resource, e.g. GET on a delete marker
+    MethodNotAllowed,
+    /// The operation is not supported by the proxy or the backend
+    NotImplemented(String),
     InternalError(String),
 }
 
@@ -24,6 +57,8 @@ struct S3ErrorResponse {
     code: String,
     #[serde(rename = "Message")]
     message: String,
+    #[serde(rename = "Region", skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
     #[serde(rename = "RequestId")]
     request_id: String,
 }
@@ -33,9 +68,24 @@ impl S3Error {
         match self {
             S3Error::NoSuchKey => StatusCode::NOT_FOUND,
             S3Error::NoSuchBucket => StatusCode::NOT_FOUND,
+            S3Error::NoSuchVersion => StatusCode::NOT_FOUND,
             S3Error::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            S3Error::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            S3Error::NoSuchObjectLockConfiguration => StatusCode::NOT_FOUND,
+            S3Error::NoSuchLifecycleConfiguration => StatusCode::NOT_FOUND,
+            S3Error::NoSuchCORSConfiguration => StatusCode::NOT_FOUND,
+            S3Error::AccessForbidden(_) => StatusCode::FORBIDDEN,
+            S3Error::MalformedXML => StatusCode::BAD_REQUEST,
+            S3Error::InvalidTag(_) => StatusCode::BAD_REQUEST,
+            S3Error::InvalidPartNumber => StatusCode::RANGE_NOT_SATISFIABLE,
+            S3Error::InvalidRange => StatusCode::RANGE_NOT_SATISFIABLE,
+            S3Error::EntityTooLarge => StatusCode::BAD_REQUEST,
+            S3Error::EntityTooSmall => StatusCode::BAD_REQUEST,
             S3Error::AccessDenied => StatusCode::FORBIDDEN,
             S3Error::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
+            S3Error::AuthorizationHeaderMalformed { .. } => StatusCode::BAD_REQUEST,
+            S3Error::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            S3Error::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
             S3Error::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -44,9 +94,24 @@ impl S3Error {
         match self {
             S3Error::NoSuchKey => "NoSuchKey",
             S3Error::NoSuchBucket => "NoSuchBucket",
+            S3Error::NoSuchVersion => "NoSuchVersion",
             S3Error::InvalidRequest(_) => "InvalidRequest",
+            S3Error::InvalidArgument(_) => "InvalidArgument",
+            S3Error::NoSuchObjectLockConfiguration => "NoSuchObjectLockConfiguration",
+            S3Error::NoSuchLifecycleConfiguration => "NoSuchLifecycleConfiguration",
+            S3Error::NoSuchCORSConfiguration => "NoSuchCORSConfiguration",
+            S3Error::AccessForbidden(_) => "AccessForbidden",
+            S3Error::MalformedXML => "MalformedXML",
+            S3Error::InvalidTag(_) => "InvalidTag",
+            S3Error::InvalidPartNumber => "InvalidPartNumber",
+            S3Error::InvalidRange => "InvalidRange",
+            S3Error::EntityTooLarge => "EntityTooLarge",
+            S3Error::EntityTooSmall => "EntityTooSmall",
             S3Error::AccessDenied => "AccessDenied",
             S3Error::SignatureDoesNotMatch => "SignatureDoesNotMatch",
+            S3Error::AuthorizationHeaderMalformed { .. } => "AuthorizationHeaderMalformed",
+            S3Error::MethodNotAllowed => "MethodNotAllowed",
+            S3Error::NotImplemented(_) => "NotImplemented",
             S3Error::InternalError(_) => "InternalError",
         }
     }
@@ -55,15 +120,51 @@ impl S3Error {
         match self {
             S3Error::NoSuchKey => "The specified key does not exist.".to_string(),
             S3Error::NoSuchBucket => "The specified bucket does not exist.".to_string(),
+            S3Error::NoSuchVersion => {
+                "The specified version does not exist.".to_string()
+            }
             S3Error::InvalidRequest(msg) => msg.clone(),
+            S3Error::InvalidArgument(msg) => msg.clone(),
+            S3Error::NoSuchObjectLockConfiguration => {
+                "The specified object does not have a ObjectLock configuration".to_string()
+            }
+            S3Error::NoSuchLifecycleConfiguration => {
+                "The lifecycle configuration does not exist".to_string()
+            }
+            S3Error::NoSuchCORSConfiguration => {
+                "The CORS configuration does not exist".to_string()
+            }
+            S3Error::AccessForbidden(msg) => msg.clone(),
+            S3Error::InvalidTag(msg) => msg.clone(),
+            S3Error::InvalidPartNumber => "The requested partnumber is not satisfiable".to_string(),
+            S3Error::InvalidRange => "The requested range is not satisfiable".to_string(),
+            S3Error::EntityTooLarge => {
+                "Your proposed upload exceeds the maximum allowed size".to_string()
+            }
+            S3Error::EntityTooSmall => {
+                "Your proposed upload is smaller than the minimum allowed size".to_string()
+            }
+            S3Error::MalformedXML => "The XML you provided was not well-formed or did not validate against our published schema.".to_string(),
             S3Error::AccessDenied => "Access Denied".to_string(),
             S3Error::SignatureDoesNotMatch => {
                 "The request signature we calculated does not match the signature you provided."
                     .to_string()
             }
+            S3Error::AuthorizationHeaderMalformed { message, .. } => message.clone(),
+            S3Error::MethodNotAllowed => {
+                "The specified method is not allowed against this resource.".to_string()
+            }
+            S3Error::NotImplemented(msg) => msg.clone(),
             S3Error::InternalError(msg) => format!("Internal Error: {}", msg),
         }
     }
+
+    fn region(&self) -> Option<String> {
+        match self {
+            S3Error::AuthorizationHeaderMalformed { region, .. } => region.clone(),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for S3Error {
@@ -79,6 +180,7 @@ impl IntoResponse for S3Error {
         let error_response = S3ErrorResponse {
             code: self.error_code().to_string(),
             message: self.message(),
+            region: self.region(),
             request_id: uuid::Uuid::new_v4().to_string(),
         };
 
@@ -1,4 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Represents an S3 object metadata
 #[derive(Debug, Clone)]
@@ -8,6 +10,69 @@ pub struct ObjectMetadata {
     pub etag: String,
     pub last_modified: chrono::DateTime<chrono::Utc>,
     pub content_type: String,
+    /// Version of the object, None if it was written while versioning was never enabled
+    pub version_id: Option<String>,
+    /// User-defined metadata (`x-amz-meta-*`), keyed by lowercase name without the prefix
+    pub user_metadata: HashMap<String, String>,
+}
+
+impl ObjectMetadata {
+    /// Number of parts of a multipart object, None for objects stored in a single part
+    ///
+    /// Multipart ETags are the MD5 of the part MD5s followed by `-<part count>`. Other ETags
+    /// with a dash, such as the `inode-size` ETags of WebDAV servers, are not multipart.
+    pub fn parts_count(&self) -> Option<u32> {
+        let (digest, count) = self.etag.trim_matches('"').rsplit_once('-')?;
+        if digest.len() != 32 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        count.parse().ok()
+    }
+}
+
+/// Version ID S3 uses for objects written while versioning is not enabled
+pub const NULL_VERSION_ID: &str = "null";
+
+/// Versioning state of a bucket once versioning has been configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersioningStatus {
+    Enabled,
+    Suspended,
+}
+
+impl VersioningStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersioningStatus::Enabled => "Enabled",
+            VersioningStatus::Suspended => "Suspended",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "Enabled" => Some(VersioningStatus::Enabled),
+            "Suspended" => Some(VersioningStatus::Suspended),
+            _ => None,
+        }
+    }
+}
+
+/// A key/value tag attached to an object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+/// A single entry of a key's version history
+#[derive(Debug, Clone)]
+pub struct ObjectVersion {
+    /// Metadata of the version; size and etag are meaningless for delete markers
+    pub metadata: ObjectMetadata,
+    pub is_latest: bool,
+    pub is_delete_marker: bool,
 }
 
 /// S3 XML response for ListObjectsV2
@@ -53,5 +118,312 @@ pub struct Credentials {
 /// Authentication context passed through request extensions
 #[derive(Debug, Clone)]
 pub struct AuthContext {
-    pub _access_key_id: String,
+    pub access_key_id: String,
+    /// Buckets the credential is limited to, None if it can access every bucket
+    pub allowed_buckets: Option<Arc<HashSet<String>>>,
+}
+
+impl AuthContext {
+    /// Whether the credential can access a bucket
+    pub fn can_access(&self, bucket: &str) -> bool {
+        self.allowed_buckets
+            .as_ref()
+            .is_none_or(|buckets| buckets.contains(bucket))
+    }
+}
+
+/// S3 XML response for ListBuckets
+#[derive(Serialize)]
+#[serde(rename = "ListAllMyBucketsResult")]
+pub struct ListAllMyBucketsResult {
+    #[serde(rename = "Owner")]
+    pub owner: Owner,
+    #[serde(rename = "Buckets")]
+    pub buckets: Buckets,
+}
+
+#[derive(Serialize)]
+pub struct Owner {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "DisplayName")]
+    pub display_name: String,
+}
+
+#[derive(Serialize)]
+pub struct Buckets {
+    #[serde(rename = "Bucket")]
+    pub bucket: Vec<BucketEntry>,
+}
+
+#[derive(Serialize)]
+pub struct BucketEntry {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "CreationDate")]
+    pub creation_date: String,
+}
+
+/// S3 XML response for GetBucketLocation
+#[derive(Serialize)]
+#[serde(rename = "LocationConstraint")]
+pub struct LocationConstraint {
+    #[serde(rename = "$text")]
+    pub region: String,
+}
+
+/// S3 XML response for GetObjectAttributes
+///
+/// Only the attributes the client asked for are set.
+#[derive(Serialize, Default)]
+#[serde(rename = "GetObjectAttributesResponse")]
+pub struct GetObjectAttributesOutput {
+    #[serde(rename = "ETag", skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(rename = "ObjectParts", skip_serializing_if = "Option::is_none")]
+    pub object_parts: Option<ObjectParts>,
+    #[serde(rename = "StorageClass", skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
+    #[serde(rename = "ObjectSize", skip_serializing_if = "Option::is_none")]
+    pub object_size: Option<u64>,
+}
+
+/// Part layout of a multipart object in a GetObjectAttributes response
+#[derive(Serialize)]
+pub struct ObjectParts {
+    #[serde(rename = "TotalPartsCount")]
+    pub total_parts_count: u32,
+}
+
+/// S3 XML response for a browser POST upload with `success_action_status` 201
+#[derive(Serialize)]
+#[serde(rename = "PostResponse")]
+pub struct PostResponse {
+    #[serde(rename = "Location")]
+    pub location: String,
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+/// S3 XML body for PutBucketVersioning and GetBucketVersioning
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "VersioningConfiguration")]
+pub struct VersioningConfiguration {
+    #[serde(rename = "Status", skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// S3 XML response for ListObjectVersions
+#[derive(Serialize)]
+#[serde(rename = "ListVersionsResult")]
+pub struct ListVersionsResult {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Prefix")]
+    pub prefix: Option<String>,
+    #[serde(rename = "MaxKeys")]
+    pub max_keys: i32,
+    #[serde(rename = "IsTruncated")]
+    pub is_truncated: bool,
+    #[serde(rename = "Version")]
+    pub versions: Vec<S3ObjectVersion>,
+    #[serde(rename = "DeleteMarker")]
+    pub delete_markers: Vec<S3DeleteMarker>,
+}
+
+#[derive(Serialize)]
+pub struct S3ObjectVersion {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId")]
+    pub version_id: String,
+    #[serde(rename = "IsLatest")]
+    pub is_latest: bool,
+    #[serde(rename = "LastModified")]
+    pub last_modified: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+    #[serde(rename = "Size")]
+    pub size: u64,
+    #[serde(rename = "StorageClass")]
+    pub storage_class: String,
+}
+
+#[derive(Serialize)]
+pub struct S3DeleteMarker {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "VersionId")]
+    pub version_id: String,
+    #[serde(rename = "IsLatest")]
+    pub is_latest: bool,
+    #[serde(rename = "LastModified")]
+    pub last_modified: String,
+}
+
+/// S3 XML body for PutObjectTagging and GetObjectTagging
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Tagging")]
+pub struct Tagging {
+    #[serde(rename = "TagSet")]
+    pub tag_set: TagSet,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagSet {
+    #[serde(rename = "Tag", default)]
+    pub tags: Vec<Tag>,
+}
+
+/// S3 XML body for PutObjectRetention and GetObjectRetention
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Retention")]
+pub struct ObjectLockRetention {
+    #[serde(rename = "Mode", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(rename = "RetainUntilDate", skip_serializing_if = "Option::is_none")]
+    pub retain_until_date: Option<String>,
+}
+
+/// S3 XML body for PutObjectLegalHold and GetObjectLegalHold
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "LegalHold")]
+pub struct ObjectLockLegalHold {
+    #[serde(rename = "Status")]
+    pub status: String,
+}
+
+/// S3 XML body for PutBucketLifecycleConfiguration and GetBucketLifecycleConfiguration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "LifecycleConfiguration")]
+pub struct LifecycleConfiguration {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRule {
+    #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Filter", skip_serializing_if = "Option::is_none")]
+    pub filter: Option<LifecycleRuleFilter>,
+    /// Deprecated rule-level prefix, superseded by Filter
+    #[serde(rename = "Prefix", skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Expiration", skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<LifecycleExpiration>,
+    /// Parsed so it can be rejected, multipart uploads aren't served
+    #[serde(
+        rename = "AbortIncompleteMultipartUpload",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRuleFilter {
+    #[serde(rename = "Prefix", skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Tag>,
+    #[serde(rename = "And", skip_serializing_if = "Option::is_none")]
+    pub and: Option<LifecycleRuleAndOperator>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRuleAndOperator {
+    #[serde(rename = "Prefix", skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(rename = "Tag", default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleExpiration {
+    #[serde(rename = "Days", skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    #[serde(rename = "Date", skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbortIncompleteMultipartUpload {
+    #[serde(rename = "DaysAfterInitiation")]
+    pub days_after_initiation: u32,
+}
+
+/// S3 XML body for PutBucketCors and GetBucketCors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "CORSConfiguration")]
+pub struct CorsConfiguration {
+    #[serde(rename = "CORSRule", default)]
+    pub rules: Vec<CorsRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsRule {
+    #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "AllowedOrigin", default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(rename = "AllowedMethod", default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(
+        rename = "AllowedHeader",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub allowed_headers: Vec<String>,
+    #[serde(
+        rename = "ExposeHeader",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub expose_headers: Vec<String>,
+    #[serde(rename = "MaxAgeSeconds", skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_with_etag(etag: &str) -> ObjectMetadata {
+        ObjectMetadata {
+            key: "key".to_string(),
+            size: 0,
+            etag: etag.to_string(),
+            last_modified: chrono::Utc::now(),
+            content_type: "application/octet-stream".to_string(),
+            version_id: None,
+            user_metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parts_count_of_multipart_etag() {
+        let metadata = metadata_with_etag("\"d41d8cd98f00b204e9800998ecf8427e-3\"");
+        assert_eq!(metadata.parts_count(), Some(3));
+    }
+
+    #[test]
+    fn test_parts_count_of_single_part_etag() {
+        let metadata = metadata_with_etag("\"d41d8cd98f00b204e9800998ecf8427e\"");
+        assert_eq!(metadata.parts_count(), None);
+    }
+
+    #[test]
+    fn test_parts_count_ignores_non_md5_etag_with_dash() {
+        // WebDAV servers such as Apache use `inode-size` ETags
+        let metadata = metadata_with_etag("\"2c4f-5e1d\"");
+        assert_eq!(metadata.parts_count(), None);
+        let metadata = metadata_with_etag("\"1a2b3c-42\"");
+        assert_eq!(metadata.parts_count(), None);
+    }
 }
@@ -0,0 +1,280 @@
+//! Requests run against real backends by the emulator tests
+//!
+//! The emulator tests are `#[ignore]`d, since they need the emulator running. Each test file
+//! says how to start its emulator; run them with `cargo test --test <file> -- --ignored`.
+
+use super::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use replicat4::storage::PutObjectOptions;
+use replicat4::{StorageBackend, VirtualBucket};
+use std::sync::Arc;
+
+/// Size of the large object, past the 8 MiB blocks and chunks of chunked uploads
+const LARGE_OBJECT_SIZE: usize = 8 * 1024 * 1024 + 1000;
+/// Requests in flight while writing or deleting many objects
+const LOAD_CONCURRENCY: usize = 32;
+
+/// Read an emulator setting from the environment, or use the emulator's usual local value
+pub fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+/// A prefix no other run uses, so runs don't see each other's objects
+pub fn unique_prefix() -> String {
+    format!("replicat4-test-{}/", uuid::Uuid::new_v4().simple())
+}
+
+/// Serve a backend through the proxy
+pub async fn serve(storage: Arc<dyn StorageBackend>) -> TestServer {
+    TestServer::start_with_buckets(
+        vec![VirtualBucket::new(TEST_BUCKET.to_string(), storage)],
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await
+}
+
+async fn get_range(server: &TestServer, key: &str, range: &str) -> (Vec<u8>, Option<String>) {
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .range(range)
+        .send()
+        .await
+        .unwrap();
+    let content_range = result.content_range().map(str::to_string);
+    let body = result.body.collect().await.unwrap().to_vec();
+    (body, content_range)
+}
+
+async fn list_keys(server: &TestServer, prefix: &str) -> Vec<String> {
+    let result = server
+        .client
+        .list_objects_v2()
+        .bucket(&server.bucket_name)
+        .prefix(prefix)
+        .send()
+        .await
+        .unwrap();
+    result
+        .contents()
+        .iter()
+        .filter_map(|object| object.key().map(str::to_string))
+        .collect()
+}
+
+/// Run put, get, ranged get, list, delete and versioning requests against a backend
+///
+/// Backends that only keep the data, such as SFTP, pass `stores_content_type: false` so the
+/// content type isn't checked.
+pub async fn run_backend_suite(storage: Arc<dyn StorageBackend>, stores_content_type: bool) {
+    let server = serve(storage).await;
+    let prefix = unique_prefix();
+    let small_key = format!("{}small.txt", prefix);
+    let large_key = format!("{}nested/large.bin", prefix);
+    let last_key = format!("{}z.txt", prefix);
+    let small = b"Hello from ReplicaT4".to_vec();
+    let large: Vec<u8> = (0..LARGE_OBJECT_SIZE).map(|i| (i % 251) as u8).collect();
+
+    // Put
+    let put = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(&small_key)
+        .content_type("text/plain")
+        .body(ByteStream::from(small.clone()))
+        .send()
+        .await
+        .unwrap();
+    for (key, data) in [(&large_key, &large), (&last_key, &small)] {
+        server
+            .client
+            .put_object()
+            .bucket(&server.bucket_name)
+            .key(key)
+            .body(ByteStream::from(data.clone()))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    // Head and get
+    let head = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key(&small_key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(head.content_length(), Some(small.len() as i64));
+    assert_eq!(head.e_tag(), put.e_tag());
+    if stores_content_type {
+        assert_eq!(head.content_type(), Some("text/plain"));
+    }
+
+    for (key, data) in [(&small_key, &small), (&large_key, &large)] {
+        let result = server
+            .client
+            .get_object()
+            .bucket(&server.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(result.content_length(), Some(data.len() as i64));
+        assert_eq!(&result.body.collect().await.unwrap().to_vec(), data);
+    }
+
+    // Ranges, including one across the first 8 MiB block of the large object
+    let (body, content_range) = get_range(&server, &small_key, "bytes=6-9").await;
+    assert_eq!(body, &small[6..=9]);
+    assert_eq!(
+        content_range.as_deref(),
+        Some(format!("bytes 6-9/{}", small.len()).as_str())
+    );
+    let (body, _) = get_range(&server, &small_key, "bytes=-4").await;
+    assert_eq!(body, &small[small.len() - 4..]);
+    let start = 8 * 1024 * 1024 - 10;
+    let (body, _) = get_range(&server, &large_key, &format!("bytes={}-", start)).await;
+    assert_eq!(body, &large[start..]);
+
+    // List, in key order
+    let expected = vec![large_key.clone(), small_key.clone(), last_key.clone()];
+    assert_eq!(list_keys(&server, &prefix).await, expected);
+    assert_eq!(
+        list_keys(&server, &format!("{}nested/", prefix)).await,
+        vec![large_key.clone()]
+    );
+
+    // Versioning can't be enabled, objects have the "null" version
+    let versioning = server
+        .client
+        .get_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    assert!(versioning.status().is_none());
+    let result = server
+        .client
+        .put_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await;
+    assert!(result.is_err());
+    let versions = server
+        .client
+        .list_object_versions()
+        .bucket(&server.bucket_name)
+        .prefix(&prefix)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(versions.versions().len(), 3);
+    for version in versions.versions() {
+        assert_eq!(version.version_id(), Some("null"));
+        assert_eq!(version.is_latest(), Some(true));
+    }
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key(&small_key)
+        .version_id("null")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(result.body.collect().await.unwrap().to_vec(), small);
+
+    // Delete
+    for key in &expected {
+        server
+            .client
+            .delete_object()
+            .bucket(&server.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .unwrap();
+    }
+    let error = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key(&small_key)
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert!(error.is_not_found());
+    assert!(list_keys(&server, &prefix).await.is_empty());
+}
+
+/// Check that listings span several pages of the backend's own List API
+///
+/// Writes `count` empty objects, which should be more than the backend's List API returns per
+/// page, then lists and deletes them.
+pub async fn run_listing_pages_suite(storage: Arc<dyn StorageBackend>, count: usize) {
+    let prefix = unique_prefix();
+    let mut expected: Vec<String> = (0..count)
+        .map(|i| format!("{}object-{:05}", prefix, i))
+        .collect();
+    stream::iter(expected.clone())
+        .map(|key| {
+            let storage = storage.clone();
+            async move {
+                let body = Box::pin(stream::once(async { Ok(Bytes::new()) }));
+                storage
+                    .put_object(&key, body, &PutObjectOptions::default())
+                    .await
+            }
+        })
+        .buffer_unordered(LOAD_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    expected.sort();
+
+    let listed: Vec<String> = storage
+        .list_objects(Some(&prefix), i32::MAX)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|object| object.key)
+        .collect();
+    assert_eq!(listed, expected);
+    let listed = storage
+        .list_objects(Some(&prefix), count as i32 - 1)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), count - 1);
+
+    stream::iter(expected)
+        .map(|key| {
+            let storage = storage.clone();
+            async move { storage.delete_object(&key, None).await }
+        })
+        .buffer_unordered(LOAD_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert!(
+        storage
+            .list_objects(Some(&prefix), i32::MAX)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
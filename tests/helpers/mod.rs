@@ -1,3 +1,8 @@
+// Each integration test binary only uses a subset of these helpers
+#![allow(dead_code, unused_imports)]
+
+pub mod backend_suite;
+pub mod signing;
 pub mod test_server;
 
 pub use test_server::TestServer;
@@ -1,7 +1,8 @@
 use aws_sdk_s3::Client as S3Client;
 use aws_sdk_s3::config::Credentials as AwsCredentials;
 use replicat4::{
-    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, create_app,
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+    create_app,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -30,6 +31,24 @@ impl TestServer {
         // Create in-memory storage backend
         let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
 
+        Self::start_with_buckets(
+            vec![VirtualBucket::new(bucket_name, storage)],
+            access_key_id,
+            secret_access_key,
+        )
+        .await
+    }
+
+    /// Start a test server serving the given virtual buckets
+    ///
+    /// `bucket_name` on the returned server is the name of the first bucket.
+    pub async fn start_with_buckets(
+        buckets: Vec<VirtualBucket>,
+        access_key_id: String,
+        secret_access_key: String,
+    ) -> Self {
+        let bucket_name = buckets[0].name.clone();
+
         // Create credentials store with test credentials
         let mut credentials_map = HashMap::new();
         credentials_map.insert(
@@ -42,10 +61,20 @@ impl TestServer {
         let credentials_store = CredentialsStore::new(credentials_map);
 
         // Create app state
-        let app_state = AppState::new(storage, credentials_store, bucket_name.clone());
+        let app_state = AppState::new(buckets, credentials_store, "us-east-1".to_string(), None);
 
+        Self::start_with_app_state(app_state, bucket_name, access_key_id, secret_access_key).await
+    }
+
+    /// Start a test server for the given app state, with a client signing as `access_key_id`
+    pub async fn start_with_app_state(
+        app_state: AppState,
+        bucket_name: String,
+        access_key_id: String,
+        secret_access_key: String,
+    ) -> Self {
         // Use the ACTUAL production create_app function
-        let app = create_app(app_state, bucket_name.clone());
+        let app = create_app(app_state);
 
         // Bind to a random available port
         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -0,0 +1,50 @@
+//! Tests of the Azure backend against the Azurite emulator
+//!
+//! Start Azurite and create the container, then run the ignored tests:
+//!
+//! ```text
+//! azurite-blob --blobHost 127.0.0.1 --blobPort 10000
+//! az storage container create --name replicat4 --connection-string "UseDevelopmentStorage=true"
+//! cargo test --test test_azure_backend -- --ignored
+//! ```
+//!
+//! `AZURITE_ENDPOINT` and `AZURITE_CONTAINER` point the tests at another endpoint or container.
+
+mod helpers;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use helpers::backend_suite::{env_or, run_backend_suite, run_listing_pages_suite};
+use replicat4::StorageBackend;
+use replicat4::storage::{AzureBackend, AzureCredentials};
+use std::sync::Arc;
+
+/// Well-known account key of Azurite's development account
+const AZURITE_ACCOUNT_KEY: &str =
+    "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+
+fn azurite() -> Arc<dyn StorageBackend> {
+    let backend = AzureBackend::new(
+        "devstoreaccount1".to_string(),
+        env_or("AZURITE_CONTAINER", "replicat4"),
+        Some(env_or(
+            "AZURITE_ENDPOINT",
+            "http://127.0.0.1:10000/devstoreaccount1",
+        )),
+        AzureCredentials::SharedKey(BASE64.decode(AZURITE_ACCOUNT_KEY).unwrap()),
+    )
+    .unwrap();
+    Arc::new(backend)
+}
+
+#[tokio::test]
+#[ignore = "needs Azurite"]
+async fn test_azure_backend_against_azurite() {
+    run_backend_suite(azurite(), true).await;
+}
+
+#[tokio::test]
+#[ignore = "needs Azurite"]
+async fn test_azure_backend_lists_across_pages() {
+    // List Blobs returns up to 5000 blobs per page
+    run_listing_pages_suite(azurite(), 5001).await;
+}
@@ -0,0 +1,142 @@
+mod helpers;
+
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, ExpirationStatus,
+    LifecycleExpiration, LifecycleRule, LifecycleRuleFilter,
+};
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+
+async fn start_server() -> TestServer {
+    TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await
+}
+
+fn expire_logs_rule(days: i32) -> LifecycleRule {
+    LifecycleRule::builder()
+        .id("expire-logs")
+        .status(ExpirationStatus::Enabled)
+        .filter(LifecycleRuleFilter::builder().prefix("logs/").build())
+        .expiration(LifecycleExpiration::builder().days(days).build())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_bucket_lifecycle_not_configured() {
+    let server = start_server().await;
+
+    let error = server
+        .client
+        .get_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert_eq!(error.meta().code(), Some("NoSuchLifecycleConfiguration"));
+}
+
+#[tokio::test]
+async fn test_put_get_delete_bucket_lifecycle() {
+    let server = start_server().await;
+
+    server
+        .client
+        .put_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .lifecycle_configuration(
+            BucketLifecycleConfiguration::builder()
+                .rules(expire_logs_rule(30))
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let configuration = server
+        .client
+        .get_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    let rule = &configuration.rules()[0];
+    assert_eq!(rule.id(), Some("expire-logs"));
+    assert_eq!(rule.status(), &ExpirationStatus::Enabled);
+    assert_eq!(rule.expiration().unwrap().days(), Some(30));
+    assert_eq!(rule.filter().unwrap().prefix(), Some("logs/"));
+
+    server
+        .client
+        .delete_bucket_lifecycle()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+
+    let result = server
+        .client
+        .get_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .send()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_put_bucket_lifecycle_rejects_invalid_days() {
+    let server = start_server().await;
+
+    let error = server
+        .client
+        .put_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .lifecycle_configuration(
+            BucketLifecycleConfiguration::builder()
+                .rules(expire_logs_rule(0))
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert_eq!(error.meta().code(), Some("InvalidArgument"));
+}
+
+#[tokio::test]
+async fn test_put_bucket_lifecycle_rejects_abort_incomplete_multipart_upload() {
+    let server = start_server().await;
+
+    let rule = LifecycleRule::builder()
+        .id("abort-uploads")
+        .status(ExpirationStatus::Enabled)
+        .filter(LifecycleRuleFilter::builder().prefix("").build())
+        .abort_incomplete_multipart_upload(
+            AbortIncompleteMultipartUpload::builder()
+                .days_after_initiation(7)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let error = server
+        .client
+        .put_bucket_lifecycle_configuration()
+        .bucket(&server.bucket_name)
+        .lifecycle_configuration(
+            BucketLifecycleConfiguration::builder()
+                .rules(rule)
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert_eq!(error.meta().code(), Some("NotImplemented"));
+}
@@ -0,0 +1,213 @@
+mod helpers;
+
+use aws_sdk_s3::types::{CorsConfiguration, CorsRule};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use helpers::signing::signed_request;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+use replicat4::types::CorsConfiguration as CorsConfigurationXml;
+use replicat4::types::CorsRule as CorsRuleXml;
+use replicat4::{
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+    create_app,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const ORIGIN: &str = "https://app.example.com";
+
+fn create_cors_app(cors: Option<CorsConfigurationXml>) -> axum::Router {
+    let mut credentials_map = HashMap::new();
+    credentials_map.insert(
+        TEST_ACCESS_KEY_ID.to_string(),
+        Credentials {
+            _access_key_id: TEST_ACCESS_KEY_ID.to_string(),
+            secret_access_key: TEST_SECRET_ACCESS_KEY.to_string(),
+        },
+    );
+
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let bucket = VirtualBucket::new(TEST_BUCKET.to_string(), storage);
+    *bucket.cors.write().unwrap() = cors;
+
+    create_app(AppState::new(
+        vec![bucket],
+        CredentialsStore::new(credentials_map),
+        "us-east-1".to_string(),
+        None,
+    ))
+}
+
+fn upload_rule() -> CorsConfigurationXml {
+    CorsConfigurationXml {
+        rules: vec![CorsRuleXml {
+            id: None,
+            allowed_origins: vec![ORIGIN.to_string()],
+            allowed_methods: vec!["GET".to_string(), "PUT".to_string()],
+            allowed_headers: vec!["*".to_string()],
+            expose_headers: vec!["ETag".to_string()],
+            max_age_seconds: Some(600),
+        }],
+    }
+}
+
+fn preflight_request(method: &str, origin: &str) -> Request<Body> {
+    Request::builder()
+        .method("OPTIONS")
+        .uri(format!("/{}/upload.txt", TEST_BUCKET))
+        .header("origin", origin)
+        .header("access-control-request-method", method)
+        .header("access-control-request-headers", "content-type, x-amz-date")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_preflight_allowed_without_credentials() {
+    let app = create_cors_app(Some(upload_rule()));
+
+    let response = app.oneshot(preflight_request("PUT", ORIGIN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], ORIGIN);
+    assert_eq!(headers["access-control-allow-methods"], "GET, PUT");
+    assert_eq!(
+        headers["access-control-allow-headers"],
+        "content-type, x-amz-date"
+    );
+    assert_eq!(headers["access-control-max-age"], "600");
+    assert_eq!(headers["access-control-expose-headers"], "ETag");
+}
+
+#[tokio::test]
+async fn test_preflight_rejected() {
+    // Origin not allowed
+    let app = create_cors_app(Some(upload_rule()));
+    let response = app
+        .oneshot(preflight_request("PUT", "https://evil.example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Method not allowed
+    let app = create_cors_app(Some(upload_rule()));
+    let response = app
+        .oneshot(preflight_request("DELETE", ORIGIN))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // No CORS configuration
+    let app = create_cors_app(None);
+    let response = app.oneshot(preflight_request("PUT", ORIGIN)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_cors_headers_on_matching_request() {
+    let app = create_cors_app(Some(upload_rule()));
+
+    let mut request = signed_request(
+        "GET",
+        "localhost",
+        &format!("/{}", TEST_BUCKET),
+        "",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+    request
+        .headers_mut()
+        .insert("origin", ORIGIN.parse().unwrap());
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], ORIGIN);
+
+    // Requests from other origins get no CORS headers
+    let mut request = signed_request(
+        "GET",
+        "localhost",
+        &format!("/{}", TEST_BUCKET),
+        "",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+    request
+        .headers_mut()
+        .insert("origin", "https://evil.example.com".parse().unwrap());
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_put_get_delete_bucket_cors() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    server
+        .client
+        .put_bucket_cors()
+        .bucket(&server.bucket_name)
+        .cors_configuration(
+            CorsConfiguration::builder()
+                .cors_rules(
+                    CorsRule::builder()
+                        .allowed_origins(ORIGIN)
+                        .allowed_methods("PUT")
+                        .allowed_headers("*")
+                        .max_age_seconds(3000)
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let cors = server
+        .client
+        .get_bucket_cors()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    let rule = &cors.cors_rules()[0];
+    assert_eq!(rule.allowed_origins(), [ORIGIN]);
+    assert_eq!(rule.allowed_methods(), ["PUT"]);
+    assert_eq!(rule.max_age_seconds(), Some(3000));
+
+    server
+        .client
+        .delete_bucket_cors()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+
+    let error = server
+        .client
+        .get_bucket_cors()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert_eq!(error.meta().code(), Some("NoSuchCORSConfiguration"));
+}
@@ -0,0 +1,41 @@
+//! Tests of the GCS backend against fake-gcs-server
+//!
+//! Start fake-gcs-server with the bucket, then run the ignored tests:
+//!
+//! ```text
+//! mkdir -p data/replicat4
+//! fake-gcs-server -scheme http -port 4443 -data data
+//! cargo test --test test_gcs_backend -- --ignored
+//! ```
+//!
+//! `FAKE_GCS_ENDPOINT` and `FAKE_GCS_BUCKET` point the tests at another endpoint or bucket.
+
+mod helpers;
+
+use helpers::backend_suite::{env_or, run_backend_suite, run_listing_pages_suite};
+use replicat4::StorageBackend;
+use replicat4::storage::GcsBackend;
+use std::sync::Arc;
+
+fn fake_gcs() -> Arc<dyn StorageBackend> {
+    let backend = GcsBackend::new(
+        env_or("FAKE_GCS_BUCKET", "replicat4"),
+        Some(env_or("FAKE_GCS_ENDPOINT", "http://localhost:4443")),
+        None,
+    )
+    .unwrap();
+    Arc::new(backend)
+}
+
+#[tokio::test]
+#[ignore = "needs fake-gcs-server"]
+async fn test_gcs_backend_against_fake_gcs_server() {
+    run_backend_suite(fake_gcs(), true).await;
+}
+
+#[tokio::test]
+#[ignore = "needs fake-gcs-server"]
+async fn test_gcs_backend_lists_across_pages() {
+    // Objects are listed 1000 at a time
+    run_listing_pages_suite(fake_gcs(), 1001).await;
+}
@@ -165,3 +165,61 @@ async fn test_get_object_with_special_characters_in_key() {
     let body = get_result.body.collect().await.unwrap().to_vec();
     assert_eq!(body.as_slice(), test_content);
 }
+
+#[tokio::test]
+async fn test_get_object_range() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let test_key = "test-file-range.txt";
+    let test_content = b"0123456789abcdefghij";
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(test_key)
+        .body(ByteStream::from_static(test_content))
+        .send()
+        .await
+        .unwrap();
+
+    for (range, expected, content_range) in [
+        ("bytes=5-9", &test_content[5..10], "bytes 5-9/20"),
+        ("bytes=15-", &test_content[15..], "bytes 15-19/20"),
+        ("bytes=-3", &test_content[17..], "bytes 17-19/20"),
+    ] {
+        let get_result = server
+            .client
+            .get_object()
+            .bucket(&server.bucket_name)
+            .key(test_key)
+            .range(range)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(get_result.content_range(), Some(content_range));
+        assert_eq!(get_result.content_length(), Some(expected.len() as i64));
+        let body = get_result.body.collect().await.unwrap().to_vec();
+        assert_eq!(body.as_slice(), expected);
+    }
+
+    // A range starting past the end of the object can't be satisfied
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key(test_key)
+        .range("bytes=20-")
+        .send()
+        .await;
+    let err = result.unwrap_err().into_service_error();
+    assert_eq!(
+        aws_sdk_s3::error::ProvideErrorMetadata::code(&err),
+        Some("InvalidRange")
+    );
+}
@@ -0,0 +1,146 @@
+mod helpers;
+
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+use replicat4::{
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_list_buckets_single_bucket() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server.client.list_buckets().send().await.unwrap();
+
+    let names: Vec<&str> = result.buckets().iter().filter_map(|b| b.name()).collect();
+    assert_eq!(names, vec![TEST_BUCKET]);
+    assert!(result.buckets()[0].creation_date().is_some());
+    assert_eq!(
+        result.owner().and_then(|owner| owner.id()),
+        Some(TEST_ACCESS_KEY_ID)
+    );
+}
+
+#[tokio::test]
+async fn test_list_buckets_sorted_by_name() {
+    let photos: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let logs: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let server = TestServer::start_with_buckets(
+        vec![
+            VirtualBucket::new("photos".to_string(), photos),
+            VirtualBucket::new("logs".to_string(), logs),
+        ],
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server.client.list_buckets().send().await.unwrap();
+
+    let names: Vec<&str> = result.buckets().iter().filter_map(|b| b.name()).collect();
+    assert_eq!(names, vec!["logs", "photos"]);
+}
+
+#[tokio::test]
+async fn test_list_buckets_limited_credential() {
+    let photos: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let logs: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let credentials = HashMap::from([(
+        TEST_ACCESS_KEY_ID.to_string(),
+        Credentials {
+            _access_key_id: TEST_ACCESS_KEY_ID.to_string(),
+            secret_access_key: TEST_SECRET_ACCESS_KEY.to_string(),
+        },
+    )]);
+    let scopes = HashMap::from([(
+        TEST_ACCESS_KEY_ID.to_string(),
+        HashSet::from(["photos".to_string()]),
+    )]);
+    let app_state = AppState::new(
+        vec![
+            VirtualBucket::new("photos".to_string(), photos),
+            VirtualBucket::new("logs".to_string(), logs),
+        ],
+        CredentialsStore::new(credentials).with_bucket_scopes(scopes),
+        "us-east-1".to_string(),
+        None,
+    );
+    let server = TestServer::start_with_app_state(
+        app_state,
+        "photos".to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server.client.list_buckets().send().await.unwrap();
+    let names: Vec<&str> = result.buckets().iter().filter_map(|b| b.name()).collect();
+    assert_eq!(names, vec!["photos"]);
+
+    // Buckets the credential can't see can't be used either
+    let result = server.client.list_objects_v2().bucket("logs").send().await;
+    let err = result.expect_err("listing a bucket outside the credential's scope should fail");
+    assert_eq!(err.into_service_error().meta().code(), Some("AccessDenied"));
+    assert!(
+        server
+            .client
+            .list_objects_v2()
+            .bucket("photos")
+            .send()
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_get_bucket_location() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server
+        .client
+        .get_bucket_location()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+
+    // S3 reports us-east-1 as an empty location constraint
+    assert_eq!(
+        result
+            .location_constraint()
+            .map(|c| c.as_str())
+            .unwrap_or_default(),
+        ""
+    );
+}
+
+#[tokio::test]
+async fn test_get_bucket_location_unknown_bucket() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server
+        .client
+        .get_bucket_location()
+        .bucket("no-such-bucket")
+        .send()
+        .await;
+
+    let err = result.expect_err("location of unknown bucket should fail");
+    assert_eq!(err.into_service_error().meta().code(), Some("NoSuchBucket"));
+}
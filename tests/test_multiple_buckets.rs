@@ -0,0 +1,96 @@
+mod helpers;
+
+use aws_sdk_s3::primitives::ByteStream;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_SECRET_ACCESS_KEY, TestServer};
+use replicat4::{InMemoryStorage, StorageBackend, VirtualBucket};
+use std::sync::Arc;
+
+async fn start_two_bucket_server() -> TestServer {
+    let photos: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let logs: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+
+    TestServer::start_with_buckets(
+        vec![
+            VirtualBucket::new("photos".to_string(), photos),
+            VirtualBucket::new("logs".to_string(), logs),
+        ],
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_objects_are_isolated_between_buckets() {
+    let server = start_two_bucket_server().await;
+
+    server
+        .client
+        .put_object()
+        .bucket("photos")
+        .key("cat.jpg")
+        .body(ByteStream::from_static(b"meow"))
+        .send()
+        .await
+        .unwrap();
+
+    let get_result = server
+        .client
+        .get_object()
+        .bucket("photos")
+        .key("cat.jpg")
+        .send()
+        .await
+        .unwrap();
+    let body = get_result.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), b"meow");
+
+    let other_bucket = server
+        .client
+        .get_object()
+        .bucket("logs")
+        .key("cat.jpg")
+        .send()
+        .await;
+    assert!(
+        other_bucket.is_err(),
+        "Object should not be visible in another bucket"
+    );
+
+    let list_result = server
+        .client
+        .list_objects_v2()
+        .bucket("logs")
+        .send()
+        .await
+        .unwrap();
+    assert!(list_result.contents().is_empty());
+}
+
+#[tokio::test]
+async fn test_each_bucket_is_reachable() {
+    let server = start_two_bucket_server().await;
+
+    for bucket in ["photos", "logs"] {
+        let head_result = server.client.head_bucket().bucket(bucket).send().await;
+        assert!(head_result.is_ok(), "HEAD {} should succeed", bucket);
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_bucket_returns_no_such_bucket() {
+    let server = start_two_bucket_server().await;
+
+    let result = server
+        .client
+        .put_object()
+        .bucket("videos")
+        .key("dog.mp4")
+        .body(ByteStream::from_static(b"woof"))
+        .send()
+        .await;
+
+    let err = result.expect_err("PUT to unknown bucket should fail");
+    let service_err = err.into_service_error();
+    assert_eq!(service_err.meta().code(), Some("NoSuchBucket"));
+}
@@ -0,0 +1,122 @@
+mod helpers;
+
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::ObjectAttributes;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+
+async fn start_with_object(key: &str, content: &'static [u8]) -> (TestServer, String) {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let put_result = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .body(ByteStream::from_static(content))
+        .send()
+        .await
+        .unwrap();
+    let etag = put_result.e_tag.unwrap();
+
+    (server, etag)
+}
+
+#[tokio::test]
+async fn test_get_object_attributes() {
+    let content = b"Content for GetObjectAttributes";
+    let (server, etag) = start_with_object("attributes.txt", content).await;
+
+    let attributes = server
+        .client
+        .get_object_attributes()
+        .bucket(&server.bucket_name)
+        .key("attributes.txt")
+        .object_attributes(ObjectAttributes::Etag)
+        .object_attributes(ObjectAttributes::ObjectSize)
+        .object_attributes(ObjectAttributes::StorageClass)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(attributes.e_tag(), Some(etag.trim_matches('"')));
+    assert_eq!(attributes.object_size(), Some(content.len() as i64));
+    assert_eq!(
+        attributes.storage_class().map(|class| class.as_str()),
+        Some("STANDARD")
+    );
+    assert!(attributes.last_modified().is_some());
+    // Objects uploaded in a single part have no part layout
+    assert!(attributes.object_parts().is_none());
+}
+
+#[tokio::test]
+async fn test_get_object_attributes_no_such_key() {
+    let (server, _) = start_with_object("attributes.txt", b"data").await;
+
+    let result = server
+        .client
+        .get_object_attributes()
+        .bucket(&server.bucket_name)
+        .key("missing.txt")
+        .object_attributes(ObjectAttributes::Etag)
+        .send()
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_get_and_head_part_number() {
+    let content = b"Single part object";
+    let (server, etag) = start_with_object("parts.txt", content).await;
+
+    // Part 1 of a single-part object is the whole object
+    let head_result = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key("parts.txt")
+        .part_number(1)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(head_result.e_tag(), Some(etag.as_str()));
+    assert_eq!(head_result.content_length(), Some(content.len() as i64));
+    assert_eq!(head_result.parts_count(), None);
+
+    let get_result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("parts.txt")
+        .part_number(1)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        get_result.content_range(),
+        Some(format!("bytes 0-{}/{}", content.len() - 1, content.len()).as_str())
+    );
+    let body = get_result.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), content);
+
+    // There is no second part
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("parts.txt")
+        .part_number(2)
+        .send()
+        .await;
+    let err = result.unwrap_err().into_service_error();
+    assert_eq!(
+        aws_sdk_s3::error::ProvideErrorMetadata::code(&err),
+        Some("InvalidPartNumber")
+    );
+}
@@ -0,0 +1,281 @@
+mod helpers;
+
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::types::{
+    ObjectLockLegalHold, ObjectLockLegalHoldStatus, ObjectLockRetention, ObjectLockRetentionMode,
+};
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+use std::time::{Duration, SystemTime};
+
+async fn start_server_with_object(key: &str) -> TestServer {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    server
+}
+
+fn in_one_day() -> DateTime {
+    DateTime::from(SystemTime::now() + Duration::from_secs(24 * 60 * 60))
+}
+
+async fn put_retention(server: &TestServer, key: &str, mode: ObjectLockRetentionMode) {
+    server
+        .client
+        .put_object_retention()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .retention(
+            ObjectLockRetention::builder()
+                .mode(mode)
+                .retain_until_date(in_one_day())
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+}
+
+async fn delete(server: &TestServer, key: &str, bypass_governance: bool) -> Option<String> {
+    server
+        .client
+        .delete_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .bypass_governance_retention(bypass_governance)
+        .send()
+        .await
+        .err()
+        .and_then(|e| e.into_service_error().meta().code().map(str::to_string))
+}
+
+#[tokio::test]
+async fn test_get_object_retention_not_configured() {
+    let server = start_server_with_object("file.txt").await;
+
+    let error = server
+        .client
+        .get_object_retention()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await
+        .unwrap_err()
+        .into_service_error();
+    assert_eq!(error.meta().code(), Some("NoSuchObjectLockConfiguration"));
+}
+
+#[tokio::test]
+async fn test_compliance_retention_blocks_delete_and_overwrite() {
+    let server = start_server_with_object("file.txt").await;
+    put_retention(&server, "file.txt", ObjectLockRetentionMode::Compliance).await;
+
+    let retention = server
+        .client
+        .get_object_retention()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        retention.retention().unwrap().mode(),
+        Some(&ObjectLockRetentionMode::Compliance)
+    );
+
+    assert_eq!(
+        delete(&server, "file.txt", true).await.as_deref(),
+        Some("AccessDenied")
+    );
+
+    let overwrite = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .body(ByteStream::from_static(b"new data"))
+        .send()
+        .await;
+    assert_eq!(
+        overwrite.unwrap_err().into_service_error().meta().code(),
+        Some("AccessDenied")
+    );
+
+    // Compliance retention can't be downgraded
+    let downgrade = server
+        .client
+        .put_object_retention()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .bypass_governance_retention(true)
+        .retention(
+            ObjectLockRetention::builder()
+                .mode(ObjectLockRetentionMode::Governance)
+                .retain_until_date(in_one_day())
+                .build(),
+        )
+        .send()
+        .await;
+    assert_eq!(
+        downgrade.unwrap_err().into_service_error().meta().code(),
+        Some("AccessDenied")
+    );
+}
+
+#[tokio::test]
+async fn test_governance_retention_can_be_bypassed() {
+    let server = start_server_with_object("file.txt").await;
+    put_retention(&server, "file.txt", ObjectLockRetentionMode::Governance).await;
+
+    assert_eq!(
+        delete(&server, "file.txt", false).await.as_deref(),
+        Some("AccessDenied")
+    );
+    assert_eq!(delete(&server, "file.txt", true).await, None);
+
+    let result = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_legal_hold_blocks_delete_until_released() {
+    let server = start_server_with_object("file.txt").await;
+
+    let set_legal_hold = |status: ObjectLockLegalHoldStatus| {
+        server
+            .client
+            .put_object_legal_hold()
+            .bucket(&server.bucket_name)
+            .key("file.txt")
+            .legal_hold(ObjectLockLegalHold::builder().status(status).build())
+            .send()
+    };
+
+    set_legal_hold(ObjectLockLegalHoldStatus::On).await.unwrap();
+
+    let legal_hold = server
+        .client
+        .get_object_legal_hold()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        legal_hold.legal_hold().unwrap().status(),
+        Some(&ObjectLockLegalHoldStatus::On)
+    );
+
+    assert_eq!(
+        delete(&server, "file.txt", true).await.as_deref(),
+        Some("AccessDenied")
+    );
+
+    set_legal_hold(ObjectLockLegalHoldStatus::Off)
+        .await
+        .unwrap();
+    assert_eq!(delete(&server, "file.txt", false).await, None);
+}
+
+#[tokio::test]
+async fn test_put_object_with_lock_headers() {
+    let server = start_server_with_object("other.txt").await;
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("locked.txt")
+        .object_lock_mode(aws_sdk_s3::types::ObjectLockMode::Governance)
+        .object_lock_retain_until_date(in_one_day())
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        delete(&server, "locked.txt", false).await.as_deref(),
+        Some("AccessDenied")
+    );
+    assert_eq!(delete(&server, "other.txt", false).await, None);
+}
+
+#[tokio::test]
+async fn test_versioned_bucket_keeps_locked_version() {
+    use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+    server
+        .client
+        .put_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+    let locked = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+    let locked_version_id = locked.version_id().unwrap();
+    put_retention(&server, "file.txt", ObjectLockRetentionMode::Compliance).await;
+
+    // A new version and a delete marker stack on top of the locked version
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .body(ByteStream::from_static(b"new data"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete(&server, "file.txt", false).await, None);
+
+    // Deleting the locked version itself is still refused
+    let result = server
+        .client
+        .delete_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .version_id(locked_version_id)
+        .send()
+        .await;
+    assert_eq!(
+        result.unwrap_err().into_service_error().meta().code(),
+        Some("AccessDenied")
+    );
+}
@@ -0,0 +1,166 @@
+mod helpers;
+
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Tag, Tagging};
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+
+async fn start_server() -> TestServer {
+    TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await
+}
+
+fn tag(key: &str, value: &str) -> Tag {
+    Tag::builder().key(key).value(value).build().unwrap()
+}
+
+async fn get_tags(server: &TestServer, key: &str) -> Vec<(String, String)> {
+    server
+        .client
+        .get_object_tagging()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .send()
+        .await
+        .unwrap()
+        .tag_set()
+        .iter()
+        .map(|tag| (tag.key().to_string(), tag.value().to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_put_object_with_tagging_header() {
+    let server = start_server().await;
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("tagged.txt")
+        .tagging("team=storage&env=prod%20eu")
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_tags(&server, "tagged.txt").await,
+        vec![
+            ("team".to_string(), "storage".to_string()),
+            ("env".to_string(), "prod eu".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_put_get_delete_object_tagging() {
+    let server = start_server().await;
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(get_tags(&server, "file.txt").await.is_empty());
+
+    server
+        .client
+        .put_object_tagging()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .tagging(
+            Tagging::builder()
+                .tag_set(tag("owner", "alice"))
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_tags(&server, "file.txt").await,
+        vec![("owner".to_string(), "alice".to_string())]
+    );
+
+    server
+        .client
+        .delete_object_tagging()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await
+        .unwrap();
+
+    assert!(get_tags(&server, "file.txt").await.is_empty());
+
+    // The object itself is untouched by tagging operations
+    let object = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .send()
+        .await
+        .unwrap();
+    let body = object.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), b"data");
+}
+
+#[tokio::test]
+async fn test_object_tagging_no_such_key() {
+    let server = start_server().await;
+
+    let result = server
+        .client
+        .get_object_tagging()
+        .bucket(&server.bucket_name)
+        .key("missing.txt")
+        .send()
+        .await;
+
+    let error = result.unwrap_err().into_service_error();
+    assert_eq!(error.meta().code(), Some("NoSuchKey"));
+}
+
+#[tokio::test]
+async fn test_put_object_tagging_rejects_duplicate_keys() {
+    let server = start_server().await;
+
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    let result = server
+        .client
+        .put_object_tagging()
+        .bucket(&server.bucket_name)
+        .key("file.txt")
+        .tagging(
+            Tagging::builder()
+                .tag_set(tag("owner", "alice"))
+                .tag_set(tag("owner", "bob"))
+                .build()
+                .unwrap(),
+        )
+        .send()
+        .await;
+
+    let error = result.unwrap_err().into_service_error();
+    assert_eq!(error.meta().code(), Some("InvalidTag"));
+}
@@ -0,0 +1,256 @@
+mod helpers;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use futures::StreamExt;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY};
+use hmac::{Hmac, Mac};
+use replicat4::storage::GetObjectOptions;
+use replicat4::{
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+    create_app,
+};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const BOUNDARY: &str = "----replicat4-form-boundary";
+
+fn create_post_app() -> (axum::Router, Arc<dyn StorageBackend>) {
+    let mut credentials_map = HashMap::new();
+    credentials_map.insert(
+        TEST_ACCESS_KEY_ID.to_string(),
+        Credentials {
+            _access_key_id: TEST_ACCESS_KEY_ID.to_string(),
+            secret_access_key: TEST_SECRET_ACCESS_KEY.to_string(),
+        },
+    );
+
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let app = create_app(AppState::new(
+        vec![VirtualBucket::new(TEST_BUCKET.to_string(), storage.clone())],
+        CredentialsStore::new(credentials_map),
+        "us-east-1".to_string(),
+        None,
+    ));
+    (app, storage)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Build the signed form fields for a policy with the given extra conditions
+fn signed_fields(conditions: &str, secret_access_key: &str) -> Vec<(String, String)> {
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let date = &timestamp[..8];
+    let credential = format!("{}/{}/us-east-1/s3/aws4_request", TEST_ACCESS_KEY_ID, date);
+    let expiration = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+
+    let policy = BASE64.encode(format!(
+        r#"{{"expiration": "{}", "conditions": [
+            {{"bucket": "{}"}},
+            {{"x-amz-algorithm": "AWS4-HMAC-SHA256"}},
+            {{"x-amz-credential": "{}"}},
+            {{"x-amz-date": "{}"}},
+            {}
+        ]}}"#,
+        expiration, TEST_BUCKET, credential, timestamp, conditions
+    ));
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, b"us-east-1");
+    let k_service = hmac_sha256(&k_region, b"s3");
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, policy.as_bytes()));
+
+    vec![
+        (
+            "x-amz-algorithm".to_string(),
+            "AWS4-HMAC-SHA256".to_string(),
+        ),
+        ("x-amz-credential".to_string(), credential),
+        ("x-amz-date".to_string(), timestamp),
+        ("policy".to_string(), policy),
+        ("x-amz-signature".to_string(), signature),
+    ]
+}
+
+fn form_request(fields: &[(String, String)], file_name: &str, file: &[u8]) -> Request<Body> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                BOUNDARY, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            BOUNDARY, file_name
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    Request::builder()
+        .method("POST")
+        .uri(format!("/{}", TEST_BUCKET))
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn field(name: &str, value: &str) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
+
+async fn read_object(storage: &Arc<dyn StorageBackend>, key: &str) -> Vec<u8> {
+    let (mut stream, _) = storage
+        .get_object(key, &GetObjectOptions::default())
+        .await
+        .unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    data
+}
+
+#[tokio::test]
+async fn test_post_object_upload() {
+    let (app, storage) = create_post_app();
+
+    let mut fields = signed_fields(
+        r#"["starts-with", "$key", "uploads/"],
+           {"success_action_status": "201"},
+           ["content-length-range", 1, 1024]"#,
+        TEST_SECRET_ACCESS_KEY,
+    );
+    fields.push(field("key", "uploads/${filename}"));
+    fields.push(field("success_action_status", "201"));
+
+    let response = app
+        .oneshot(form_request(&fields, "cat.txt", b"meow"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(response.headers().contains_key("etag"));
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("<Key>uploads/cat.txt</Key>"));
+
+    assert_eq!(read_object(&storage, "uploads/cat.txt").await, b"meow");
+}
+
+#[tokio::test]
+async fn test_post_object_redirect() {
+    let (app, storage) = create_post_app();
+
+    let mut fields = signed_fields(
+        r#"{"key": "photo.jpg"},
+           ["starts-with", "$success_action_redirect", "https://app.example.com/"]"#,
+        TEST_SECRET_ACCESS_KEY,
+    );
+    fields.push(field("key", "photo.jpg"));
+    fields.push(field(
+        "success_action_redirect",
+        "https://app.example.com/done",
+    ));
+
+    let response = app
+        .oneshot(form_request(&fields, "photo.jpg", b"jpeg"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("https://app.example.com/done?bucket=test-bucket&key=photo.jpg"));
+
+    assert_eq!(read_object(&storage, "photo.jpg").await, b"jpeg");
+}
+
+#[tokio::test]
+async fn test_post_object_policy_violations() {
+    // Key outside the allowed prefix
+    let (app, _) = create_post_app();
+    let mut fields = signed_fields(
+        r#"["starts-with", "$key", "uploads/"]"#,
+        TEST_SECRET_ACCESS_KEY,
+    );
+    fields.push(field("key", "admin/cat.txt"));
+    let response = app
+        .oneshot(form_request(&fields, "cat.txt", b"meow"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // File larger than the content-length-range
+    let (app, storage) = create_post_app();
+    let mut fields = signed_fields(
+        r#"{"key": "big.bin"}, ["content-length-range", 0, 3]"#,
+        TEST_SECRET_ACCESS_KEY,
+    );
+    fields.push(field("key", "big.bin"));
+    let response = app
+        .oneshot(form_request(&fields, "big.bin", b"too large"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("<Code>EntityTooLarge</Code>"));
+    assert!(
+        storage
+            .head_object("big.bin", &GetObjectOptions::default())
+            .await
+            .is_err()
+    );
+
+    // Field not covered by the policy
+    let (app, _) = create_post_app();
+    let mut fields = signed_fields(r#"{"key": "cat.txt"}"#, TEST_SECRET_ACCESS_KEY);
+    fields.push(field("key", "cat.txt"));
+    fields.push(field("content-type", "text/html"));
+    let response = app
+        .oneshot(form_request(&fields, "cat.txt", b"meow"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_post_object_bad_signature() {
+    let (app, _) = create_post_app();
+
+    let mut fields = signed_fields(r#"{"key": "cat.txt"}"#, "not-the-secret-key");
+    fields.push(field("key", "cat.txt"));
+
+    let response = app
+        .oneshot(form_request(&fields, "cat.txt", b"meow"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("<Code>SignatureDoesNotMatch</Code>"));
+}
@@ -0,0 +1,146 @@
+mod helpers;
+
+use aws_sdk_s3::config::Credentials as AwsCredentials;
+use aws_sdk_s3::presigning::{PresignedRequest, PresigningConfig};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use bytes::Bytes;
+use futures::stream;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY};
+use replicat4::storage::PutObjectOptions;
+use replicat4::{
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+    create_app,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+const HOST: &str = "localhost:3000";
+
+async fn create_app_with_object(key: &str) -> axum::Router {
+    let mut credentials_map = HashMap::new();
+    credentials_map.insert(
+        TEST_ACCESS_KEY_ID.to_string(),
+        Credentials {
+            _access_key_id: TEST_ACCESS_KEY_ID.to_string(),
+            secret_access_key: TEST_SECRET_ACCESS_KEY.to_string(),
+        },
+    );
+
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    storage
+        .put_object(
+            key,
+            Box::pin(stream::once(async { Ok(Bytes::from("report data")) })),
+            &PutObjectOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    create_app(AppState::new(
+        vec![VirtualBucket::new(TEST_BUCKET.to_string(), storage)],
+        CredentialsStore::new(credentials_map),
+        "us-east-1".to_string(),
+        None,
+    ))
+}
+
+/// S3 client used only to presign URLs for the proxy at HOST
+fn presigning_client(secret_access_key: &str) -> aws_sdk_s3::Client {
+    let creds = AwsCredentials::new(TEST_ACCESS_KEY_ID, secret_access_key, None, None, "test");
+    let config = aws_sdk_s3::config::Builder::new()
+        .behavior_version_latest()
+        .credentials_provider(creds)
+        .region(aws_sdk_s3::config::Region::new("us-east-1"))
+        .endpoint_url(format!("http://{}", HOST))
+        .force_path_style(true)
+        .build();
+    aws_sdk_s3::Client::from_conf(config)
+}
+
+fn to_request(presigned: &PresignedRequest) -> Request<Body> {
+    let uri: axum::http::Uri = presigned.uri().parse().unwrap();
+    let mut builder = Request::builder()
+        .method(presigned.method())
+        .uri(uri.path_and_query().unwrap().as_str())
+        .header("host", HOST);
+    for (name, value) in presigned.headers() {
+        builder = builder.header(name, value);
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn test_presigned_get_with_response_overrides() {
+    let app = create_app_with_object("report.csv").await;
+
+    let presigned = presigning_client(TEST_SECRET_ACCESS_KEY)
+        .get_object()
+        .bucket(TEST_BUCKET)
+        .key("report.csv")
+        .response_content_disposition("attachment; filename=\"report.csv\"")
+        .response_content_type("text/csv")
+        .response_cache_control("no-cache")
+        .presigned(PresigningConfig::expires_in(Duration::from_secs(300)).unwrap())
+        .await
+        .unwrap();
+
+    let response = app.oneshot(to_request(&presigned)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let headers = response.headers();
+    assert_eq!(
+        headers["content-disposition"],
+        "attachment; filename=\"report.csv\""
+    );
+    assert_eq!(headers["content-type"], "text/csv");
+    assert_eq!(headers["cache-control"], "no-cache");
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body, "report data");
+}
+
+#[tokio::test]
+async fn test_presigned_get_rejects_bad_signature() {
+    let app = create_app_with_object("report.csv").await;
+
+    let presigned = presigning_client("not-the-secret-key")
+        .get_object()
+        .bucket(TEST_BUCKET)
+        .key("report.csv")
+        .presigned(PresigningConfig::expires_in(Duration::from_secs(300)).unwrap())
+        .await
+        .unwrap();
+
+    let response = app.oneshot(to_request(&presigned)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_response_overrides_are_signed() {
+    let app = create_app_with_object("report.csv").await;
+
+    let presigned = presigning_client(TEST_SECRET_ACCESS_KEY)
+        .get_object()
+        .bucket(TEST_BUCKET)
+        .key("report.csv")
+        .response_content_disposition("inline")
+        .presigned(PresigningConfig::expires_in(Duration::from_secs(300)).unwrap())
+        .await
+        .unwrap();
+
+    // Tampering with an override invalidates the signature
+    let mut request = to_request(&presigned);
+    let tampered = request.uri().to_string().replace(
+        "response-content-disposition=inline",
+        "response-content-disposition=attachment",
+    );
+    *request.uri_mut() = tampered.parse().unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
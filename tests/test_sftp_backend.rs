@@ -0,0 +1,38 @@
+//! Tests of the SFTP backend against an OpenSSH server
+//!
+//! Start a server with a writable directory, record its host key, then run the ignored tests:
+//!
+//! ```text
+//! docker run -d -p 2222:22 atmoz/sftp replicat4:secret:::upload
+//! ssh-keyscan -p 2222 localhost > /tmp/replicat4-known-hosts
+//! cargo test --test test_sftp_backend -- --ignored
+//! ```
+//!
+//! `SFTP_HOST`, `SFTP_PORT`, `SFTP_USERNAME`, `SFTP_PASSWORD`, `SFTP_ROOT` and
+//! `SFTP_KNOWN_HOSTS` point the tests at another server.
+
+mod helpers;
+
+use helpers::backend_suite::{env_or, run_backend_suite};
+use replicat4::StorageBackend;
+use replicat4::storage::{SftpAuth, SftpBackend};
+use std::sync::Arc;
+
+fn openssh() -> Arc<dyn StorageBackend> {
+    Arc::new(SftpBackend::new(
+        "local-sftp".to_string(),
+        env_or("SFTP_HOST", "localhost"),
+        env_or("SFTP_PORT", "2222").parse().unwrap(),
+        env_or("SFTP_USERNAME", "replicat4"),
+        SftpAuth::Password(env_or("SFTP_PASSWORD", "secret")),
+        Some(env_or("SFTP_KNOWN_HOSTS", "/tmp/replicat4-known-hosts").into()),
+        env_or("SFTP_ROOT", "upload"),
+    ))
+}
+
+#[tokio::test]
+#[ignore = "needs an SFTP server"]
+async fn test_sftp_backend_against_openssh() {
+    // Only the data is stored, objects are served as binary/octet-stream
+    run_backend_suite(openssh(), false).await;
+}
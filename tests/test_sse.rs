@@ -0,0 +1,139 @@
+mod helpers;
+
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+use md5::{Digest, Md5};
+
+/// Base64 key and key MD5, as sent in the SSE-C headers
+fn sse_customer_key(byte: u8) -> (String, String) {
+    let key = [byte; 32];
+    (BASE64.encode(key), BASE64.encode(Md5::digest(key)))
+}
+
+async fn start_with_encrypted_object(key: &str, content: &'static [u8]) -> TestServer {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let (customer_key, customer_key_md5) = sse_customer_key(1);
+    let put_result = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .body(ByteStream::from_static(content))
+        .sse_customer_algorithm("AES256")
+        .sse_customer_key(customer_key)
+        .sse_customer_key_md5(&customer_key_md5)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(put_result.sse_customer_algorithm(), Some("AES256"));
+    assert_eq!(
+        put_result.sse_customer_key_md5(),
+        Some(customer_key_md5.as_str())
+    );
+
+    server
+}
+
+#[tokio::test]
+async fn test_sse_customer_key_round_trip() {
+    let content = b"Encrypted with a customer key";
+    let server = start_with_encrypted_object("sse-c.txt", content).await;
+    let (customer_key, customer_key_md5) = sse_customer_key(1);
+
+    let head_result = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key("sse-c.txt")
+        .sse_customer_algorithm("AES256")
+        .sse_customer_key(&customer_key)
+        .sse_customer_key_md5(&customer_key_md5)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(head_result.content_length(), Some(content.len() as i64));
+    assert_eq!(
+        head_result.sse_customer_key_md5(),
+        Some(customer_key_md5.as_str())
+    );
+
+    let get_result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("sse-c.txt")
+        .sse_customer_algorithm("AES256")
+        .sse_customer_key(&customer_key)
+        .sse_customer_key_md5(&customer_key_md5)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(get_result.sse_customer_algorithm(), Some("AES256"));
+    let body = get_result.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), content);
+}
+
+#[tokio::test]
+async fn test_sse_customer_key_required() {
+    let server = start_with_encrypted_object("sse-c.txt", b"secret").await;
+
+    // Without the key
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("sse-c.txt")
+        .send()
+        .await;
+    let err = result.unwrap_err().into_service_error();
+    assert_eq!(err.code(), Some("InvalidRequest"));
+
+    // With another key
+    let (customer_key, customer_key_md5) = sse_customer_key(2);
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("sse-c.txt")
+        .sse_customer_algorithm("AES256")
+        .sse_customer_key(customer_key)
+        .sse_customer_key_md5(customer_key_md5)
+        .send()
+        .await;
+    let err = result.unwrap_err().into_service_error();
+    assert_eq!(err.code(), Some("AccessDenied"));
+}
+
+#[tokio::test]
+async fn test_sse_customer_key_md5_mismatch() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let (customer_key, _) = sse_customer_key(1);
+    let (_, other_key_md5) = sse_customer_key(2);
+    let result = server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key("sse-c.txt")
+        .body(ByteStream::from_static(b"data"))
+        .sse_customer_algorithm("AES256")
+        .sse_customer_key(customer_key)
+        .sse_customer_key_md5(other_key_md5)
+        .send()
+        .await;
+    let err = result.unwrap_err().into_service_error();
+    assert_eq!(err.code(), Some("InvalidArgument"));
+}
@@ -0,0 +1,205 @@
+mod helpers;
+
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY, TestServer};
+
+async fn start_versioned_server() -> TestServer {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    server
+        .client
+        .put_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    server
+}
+
+async fn put(server: &TestServer, key: &str, content: &'static [u8]) -> String {
+    server
+        .client
+        .put_object()
+        .bucket(&server.bucket_name)
+        .key(key)
+        .body(ByteStream::from_static(content))
+        .send()
+        .await
+        .unwrap()
+        .version_id()
+        .expect("versioned PUT should return a version ID")
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_get_bucket_versioning() {
+    let server = TestServer::start(
+        TEST_BUCKET.to_string(),
+        TEST_ACCESS_KEY_ID.to_string(),
+        TEST_SECRET_ACCESS_KEY.to_string(),
+    )
+    .await;
+
+    let result = server
+        .client
+        .get_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    assert!(result.status().is_none());
+
+    let server = start_versioned_server().await;
+    let result = server
+        .client
+        .get_bucket_versioning()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(result.status(), Some(&BucketVersioningStatus::Enabled));
+}
+
+#[tokio::test]
+async fn test_get_previous_version_after_overwrite() {
+    let server = start_versioned_server().await;
+
+    let first_version = put(&server, "doc.txt", b"first").await;
+    let second_version = put(&server, "doc.txt", b"second").await;
+    assert_ne!(first_version, second_version);
+
+    let latest = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(latest.version_id(), Some(second_version.as_str()));
+    let body = latest.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), b"second");
+
+    let previous = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .version_id(&first_version)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(previous.version_id(), Some(first_version.as_str()));
+    let body = previous.body.collect().await.unwrap().into_bytes();
+    assert_eq!(body.as_ref(), b"first");
+
+    let head = server
+        .client
+        .head_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .version_id(&first_version)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(head.content_length(), Some(5));
+}
+
+#[tokio::test]
+async fn test_delete_marker_and_restore() {
+    let server = start_versioned_server().await;
+
+    let version = put(&server, "doc.txt", b"content").await;
+
+    let delete = server
+        .client
+        .delete_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.delete_marker(), Some(true));
+    let marker_version = delete.version_id().unwrap().to_string();
+
+    // The latest version is now a delete marker
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .send()
+        .await;
+    assert!(result.is_err());
+
+    let versions = server
+        .client
+        .list_object_versions()
+        .bucket(&server.bucket_name)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(versions.versions().len(), 1);
+    assert_eq!(versions.versions()[0].version_id(), Some(version.as_str()));
+    assert_eq!(versions.versions()[0].is_latest(), Some(false));
+    assert_eq!(versions.delete_markers().len(), 1);
+    assert_eq!(
+        versions.delete_markers()[0].version_id(),
+        Some(marker_version.as_str())
+    );
+
+    // Deleting the delete marker restores the object
+    server
+        .client
+        .delete_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .version_id(&marker_version)
+        .send()
+        .await
+        .unwrap();
+
+    let restored = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(restored.version_id(), Some(version.as_str()));
+}
+
+#[tokio::test]
+async fn test_get_unknown_version() {
+    let server = start_versioned_server().await;
+
+    put(&server, "doc.txt", b"content").await;
+
+    let result = server
+        .client
+        .get_object()
+        .bucket(&server.bucket_name)
+        .key("doc.txt")
+        .version_id("does-not-exist")
+        .send()
+        .await;
+
+    let err = result.expect_err("unknown version should fail");
+    assert_eq!(
+        err.into_service_error().meta().code(),
+        Some("NoSuchVersion")
+    );
+}
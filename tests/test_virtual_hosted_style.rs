@@ -0,0 +1,148 @@
+mod helpers;
+
+use axum::http::StatusCode;
+use bytes::Bytes;
+use helpers::signing::signed_request;
+use helpers::{TEST_ACCESS_KEY_ID, TEST_BUCKET, TEST_SECRET_ACCESS_KEY};
+use replicat4::storage::PutObjectOptions;
+use replicat4::types::error::S3Error;
+use replicat4::{
+    AppState, Credentials, CredentialsStore, InMemoryStorage, StorageBackend, VirtualBucket,
+    create_app,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const BASE_DOMAIN: &str = "s3.example.com";
+
+fn create_virtual_hosted_app(storage: Arc<dyn StorageBackend>) -> axum::Router {
+    let mut credentials_map = HashMap::new();
+    credentials_map.insert(
+        TEST_ACCESS_KEY_ID.to_string(),
+        Credentials {
+            _access_key_id: TEST_ACCESS_KEY_ID.to_string(),
+            secret_access_key: TEST_SECRET_ACCESS_KEY.to_string(),
+        },
+    );
+
+    let app_state = AppState::new(
+        vec![VirtualBucket::new(TEST_BUCKET.to_string(), storage)],
+        CredentialsStore::new(credentials_map),
+        "us-east-1".to_string(),
+        Some(BASE_DOMAIN.to_string()),
+    );
+
+    create_app(app_state)
+}
+
+#[tokio::test]
+async fn test_virtual_hosted_get_object() {
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    storage
+        .put_object(
+            "photos/cat.jpg",
+            Box::pin(futures::stream::once(async {
+                Ok::<_, S3Error>(Bytes::from_static(b"meow"))
+            })),
+            &PutObjectOptions::default(),
+        )
+        .await
+        .unwrap();
+    let app = create_virtual_hosted_app(storage);
+
+    let host = format!("{}.{}", TEST_BUCKET, BASE_DOMAIN);
+    let request = signed_request(
+        "GET",
+        &host,
+        "/photos/cat.jpg",
+        "",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), b"meow");
+}
+
+#[tokio::test]
+async fn test_virtual_hosted_list_objects() {
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    storage
+        .put_object(
+            "file.txt",
+            Box::pin(futures::stream::once(async {
+                Ok::<_, S3Error>(Bytes::from_static(b"data"))
+            })),
+            &PutObjectOptions::default(),
+        )
+        .await
+        .unwrap();
+    let app = create_virtual_hosted_app(storage);
+
+    // Port in the host header must be ignored when matching the base domain
+    let host = format!("{}.{}:3000", TEST_BUCKET, BASE_DOMAIN);
+    let request = signed_request(
+        "GET",
+        &host,
+        "/",
+        "list-type=2",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("<Key>file.txt</Key>"));
+}
+
+#[tokio::test]
+async fn test_virtual_hosted_unknown_bucket() {
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let app = create_virtual_hosted_app(storage);
+
+    let host = format!("other-bucket.{}", BASE_DOMAIN);
+    let request = signed_request(
+        "HEAD",
+        &host,
+        "/",
+        "",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_path_style_still_works_with_base_domain() {
+    let storage: Arc<dyn StorageBackend> = Arc::new(InMemoryStorage::new());
+    let app = create_virtual_hosted_app(storage);
+
+    let request = signed_request(
+        "HEAD",
+        BASE_DOMAIN,
+        &format!("/{}", TEST_BUCKET),
+        "",
+        TEST_ACCESS_KEY_ID,
+        TEST_SECRET_ACCESS_KEY,
+        "us-east-1",
+    );
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,38 @@
+//! Tests of the WebDAV backend against a WebDAV server
+//!
+//! Serve an empty directory over WebDAV, then run the ignored tests:
+//!
+//! ```text
+//! mkdir -p /tmp/replicat4-webdav
+//! rclone serve webdav /tmp/replicat4-webdav --addr localhost:8080 --user replicat4 --pass secret
+//! cargo test --test test_webdav_backend -- --ignored
+//! ```
+//!
+//! `WEBDAV_URL`, `WEBDAV_USERNAME` and `WEBDAV_PASSWORD` point the tests at another server.
+
+mod helpers;
+
+use helpers::backend_suite::{env_or, run_backend_suite};
+use replicat4::StorageBackend;
+use replicat4::storage::WebdavBackend;
+use std::sync::Arc;
+
+fn webdav_server() -> Arc<dyn StorageBackend> {
+    let backend = WebdavBackend::new(
+        "local-webdav".to_string(),
+        &env_or("WEBDAV_URL", "http://localhost:8080/"),
+        Some((
+            env_or("WEBDAV_USERNAME", "replicat4"),
+            env_or("WEBDAV_PASSWORD", "secret"),
+        )),
+    )
+    .unwrap();
+    Arc::new(backend)
+}
+
+#[tokio::test]
+#[ignore = "needs a WebDAV server"]
+async fn test_webdav_backend_against_server() {
+    // Content types are whatever the server reports, usually guessed from the extension
+    run_backend_suite(webdav_server(), false).await;
+}